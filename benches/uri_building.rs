@@ -0,0 +1,124 @@
+//! Compares the URI building of the code generated by `ruma_api!` with the previous approach of
+//! going through `url::Url`, by counting allocations and measuring the time per conversion.
+//!
+//! Run with `cargo bench --bench uri_building`.
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    convert::TryFrom,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+use ruma_api::{
+    exports::{http, serde_urlencoded, url::Url},
+    ruma_api,
+};
+use serde::Serialize;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+ruma_api! {
+    metadata {
+        description: "Get a state event.",
+        method: GET,
+        name: "get_state_event",
+        path: "/_matrix/client/r0/rooms/:room_id/state/:event_type/:state_key",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+        #[ruma_api(path)]
+        pub event_type: String,
+        #[ruma_api(path)]
+        pub state_key: String,
+        #[ruma_api(query)]
+        pub format: String,
+    }
+
+    response {}
+}
+
+#[derive(Serialize)]
+struct Query<'a> {
+    format: &'a str,
+}
+
+/// The URI building of previous versions of `ruma_api!`.
+fn url_based_uri(request: &Request) -> http::Uri {
+    let mut url = Url::parse("http://invalid-host-please-change/").unwrap();
+    {
+        let mut path_segments = url.path_segments_mut().unwrap();
+        path_segments.push("_matrix");
+        path_segments.push("client");
+        path_segments.push("r0");
+        path_segments.push("rooms");
+        path_segments.push(&request.room_id.to_string());
+        path_segments.push("state");
+        path_segments.push(&request.event_type.to_string());
+        path_segments.push(&request.state_key.to_string());
+    }
+    url.set_query(Some(&serde_urlencoded::to_string(Query { format: &request.format }).unwrap()));
+    url.as_str().parse().unwrap()
+}
+
+fn measure(name: &str, iterations: u32, mut f: impl FnMut()) {
+    let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for _ in 0..iterations {
+        f();
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations_before;
+
+    println!(
+        "{:<40} {:>8} ns/iter {:>6.1} allocations/iter",
+        name,
+        elapsed.as_nanos() / u128::from(iterations),
+        allocations as f64 / f64::from(iterations),
+    );
+}
+
+fn main() {
+    let request = Request {
+        room_id: "!room:example.org".into(),
+        event_type: "m.room.name".into(),
+        state_key: "".into(),
+        format: "content".into(),
+    };
+
+    let generated = http::Request::<Vec<u8>>::try_from(request.clone()).unwrap();
+    assert_eq!(*generated.uri(), url_based_uri(&request));
+
+    let iterations = 200_000;
+    measure("url::Url (URI only)", iterations, || {
+        url_based_uri(&request);
+    });
+    measure("ruma_api! (whole http::Request)", iterations, || {
+        http::Request::<Vec<u8>>::try_from(request.clone()).unwrap();
+    });
+}
@@ -0,0 +1,841 @@
+//! The analysis stage of the `ruma_api` macro: interpreting the parsed input, validating it and
+//! resolving the kind of every field.
+//!
+//! The types in this module are the intermediate representation that code generation works with.
+//! Everything that can be wrong with the macro input is reported here, so code generation can
+//! rely on the invariants of these types.
+
+use std::convert::{TryFrom, TryInto as _};
+
+use proc_macro2::Span;
+use syn::{parse_quote, Attribute, Field, Ident, Item, Lit, LitStr, NestedMeta, Type};
+
+use super::{
+    attribute::Meta,
+    cfg_attrs,
+    parse::{ModuleDecl, RawApi},
+    unraw,
+};
+
+mod compat;
+mod metadata;
+mod request;
+mod response;
+
+pub use self::{
+    metadata::Metadata,
+    request::{serde_default, FieldLimit, Request, RequestField, RequestFieldKind},
+    response::{Response, ResponseField},
+};
+
+/// The result of analyzing the `ruma_api` macro input, ready for output back to source code.
+pub struct Api {
+    /// Whether to generate round-trip tests for the endpoint.
+    pub generate_tests: bool,
+    /// The module to generate the endpoint in, if any.
+    pub module: Option<ModuleDecl>,
+    /// The `metadata` section of the macro.
+    pub metadata: Metadata,
+    /// The `request` section of the macro.
+    pub request: Request,
+    /// The `response` section of the macro.
+    pub response: Response,
+    /// Additional items after the `response` section, emitted verbatim.
+    pub items: Vec<Item>,
+}
+
+impl TryFrom<RawApi> for Api {
+    type Error = syn::Error;
+
+    fn try_from(raw_api: RawApi) -> syn::Result<Self> {
+        let mut generate_tests = false;
+        for attr in &raw_api.attrs {
+            match Meta::from_attribute(attr)? {
+                Some(Meta::Word(ident)) if ident == "generate_tests" => generate_tests = true,
+                Some(_) => {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "unsupported ruma_api attribute, expected `generate_tests`",
+                    ))
+                }
+                None => {
+                    return Err(syn::Error::new_spanned(
+                        attr,
+                        "only `#[ruma_api(...)]` attributes are allowed here",
+                    ))
+                }
+            }
+        }
+
+        let res = Self {
+            generate_tests,
+            module: raw_api.module,
+            metadata: raw_api.metadata.try_into()?,
+            request: raw_api.request.try_into()?,
+            response: raw_api.response.try_into()?,
+            items: raw_api.items,
+        };
+
+        let newtype_body_field = res.request.newtype_body_field();
+        // Endpoints that also accept other methods send the same body with all of them.
+        if res.metadata.method == "GET"
+            && res.metadata.other_methods.is_empty()
+            && (res.request.has_body_fields() || newtype_body_field.is_some())
+        {
+            let mut combined_error: Option<syn::Error> = None;
+            let mut add_error = |field| {
+                let error = syn::Error::new_spanned(field, "GET endpoints can't have body fields");
+                if let Some(combined_error_ref) = &mut combined_error {
+                    combined_error_ref.combine(error);
+                } else {
+                    combined_error = Some(error);
+                }
+            };
+
+            for field in res.request.body_fields() {
+                add_error(field);
+            }
+
+            if let Some(field) = newtype_body_field {
+                add_error(field);
+            }
+
+            return Err(combined_error.unwrap());
+        }
+
+        // Cache keys are derived from the path and query string only, so requests that differ in
+        // their body would share one.
+        if res.metadata.cacheable {
+            let body_fields = res
+                .request
+                .body_fields()
+                .chain(newtype_body_field)
+                .chain(res.request.newtype_raw_body_field());
+            let mut combined_error: Option<syn::Error> = None;
+            for field in body_fields {
+                let error =
+                    syn::Error::new_spanned(field, "cacheable endpoints can't have body fields");
+                if let Some(combined_error_ref) = &mut combined_error {
+                    combined_error_ref.combine(error);
+                } else {
+                    combined_error = Some(error);
+                }
+            }
+
+            if let Some(error) = combined_error {
+                return Err(error);
+            }
+        }
+
+        if res.request.has_path_fields() {
+            let path = &res.metadata.path;
+            let path_str = path.value();
+
+            if !path_str.starts_with('/') {
+                return Err(syn::Error::new_spanned(path, "path needs to start with '/'"));
+            }
+
+            // Checked first, since a repeated placeholder also makes the counts differ.
+            check_unique_placeholders(path)?;
+
+            if path_str.chars().filter(|c| *c == ':').count() != res.request.path_field_count() {
+                return Err(syn::Error::new_spanned(
+                    path,
+                    "number of declared path parameters needs to match amount of placeholders in \
+                     path",
+                ));
+            }
+        }
+
+        check_path_params(&res.metadata.path, &res.request)?;
+        check_wire_names(res.request.fields.iter().filter_map(RequestField::wire_name))?;
+        check_wire_names(res.response.fields.iter().filter_map(ResponseField::wire_name))?;
+
+        Ok(res)
+    }
+}
+
+/// Checks that no placeholder appears more than once in the path.
+///
+/// A path field is sent in exactly one segment, and an incoming request with different values in
+/// the segments of a repeated placeholder couldn't be represented, so endpoints that need the same
+/// value in two segments have to declare a path field for each of them.
+fn check_unique_placeholders(path: &LitStr) -> syn::Result<()> {
+    let path_str = path.value();
+    let mut params: Vec<&str> = Vec::new();
+
+    for param in path_str.split('/').filter(|s| s.starts_with(':')).map(|s| &s[1..]) {
+        if params.contains(&param) {
+            return Err(syn::Error::new_spanned(
+                path,
+                format!(
+                    "path placeholder `:{}` appears more than once, repeated path parameters are \
+                     not supported; declare a separate path field for each segment",
+                    param
+                ),
+            ));
+        }
+        params.push(param);
+    }
+
+    Ok(())
+}
+
+/// Checks that the placeholders in the path are well-formed and declared as path fields, and that
+/// no query parameter has the name of one of them.
+fn check_path_params(path: &LitStr, request: &Request) -> syn::Result<()> {
+    let path_str = path.value();
+    let mut params: Vec<&str> = Vec::new();
+
+    for segment in path_str.split('/') {
+        if segment.contains(':') && !segment.starts_with(':') {
+            return Err(syn::Error::new_spanned(
+                path,
+                format!("path segment `{}` needs to be a placeholder or not contain ':'", segment),
+            ));
+        }
+        if !segment.starts_with(':') {
+            continue;
+        }
+
+        let param = &segment[1..];
+        let is_identifier = param.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+            && param.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !is_identifier {
+            return Err(syn::Error::new_spanned(
+                path,
+                format!("path placeholder `{}` needs to be ':' followed by an identifier", segment),
+            ));
+        }
+        if request.path_field(param).is_none() {
+            return Err(syn::Error::new_spanned(
+                path,
+                format!("path placeholder `:{}` has no path field with the same name", param),
+            ));
+        }
+        params.push(param);
+    }
+
+    for param in params {
+        if let Some(field) = request.query_field(param) {
+            return Err(syn::Error::new_spanned(
+                field,
+                format!("query parameter `{}` has the name of a path placeholder", param),
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that no two fields are sent under the same name in the same part of a request or
+/// response, given the wire names of the fields as returned by `RequestField::wire_name` and
+/// `ResponseField::wire_name`.
+///
+/// Fields with `cfg` attributes are skipped, since only one of several fields with the same name
+/// may be enabled.
+fn check_wire_names<'a>(
+    fields: impl Iterator<Item = (&'a Field, &'static str, String)>,
+) -> syn::Result<()> {
+    let mut seen: Vec<(&Field, &str, String)> = Vec::new();
+
+    for (field, location, name) in fields.filter(|(field, ..)| cfg_attrs(field).is_empty()) {
+        let previous = seen.iter().find(|(_, l, n)| *l == location && *n == name);
+        if let Some((previous, ..)) = previous {
+            let ident = field.ident.as_ref().expect("expected field to have an identifier");
+            let previous_ident =
+                previous.ident.as_ref().expect("expected field to have an identifier");
+            let mut error = syn::Error::new_spanned(
+                field,
+                format!(
+                    "field `{}` is sent as the {} `{}`, like the field `{}`",
+                    ident, location, name, previous_ident
+                ),
+            );
+            error.combine(syn::Error::new_spanned(previous, "previous field with that name"));
+            return Err(error);
+        }
+        seen.push((field, location, name));
+    }
+
+    Ok(())
+}
+
+/// Adds `#[serde(rename = "...")]` with the name without the `r#` prefix to a body or query field
+/// with a raw identifier like `r#type`, unless it already has a `rename`.
+///
+/// serde strips the prefix itself, but with an explicit `rename` the name is the same in all
+/// generated code that refers to the field by name, like the alias of
+/// `#[ruma_api(renamed_from = "...")]` and the other derives on the generated types.
+fn rename_raw_identifier(field: &mut Field) {
+    let ident = field.ident.as_ref().expect("expected field to have an identifier");
+    if !ident.to_string().starts_with("r#") || has_serde_rename(field) {
+        return;
+    }
+
+    let name = unraw(ident);
+    field.attrs.push(parse_quote!(#[serde(rename = #name)]));
+}
+
+/// Whether the field has a `#[serde(rename = "...")]` attribute.
+fn has_serde_rename(field: &Field) -> bool {
+    field.attrs.iter().filter(|attr| attr.path.is_ident("serde")).any(|attr| {
+        match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list.nested.iter().any(|nested| match nested {
+                NestedMeta::Meta(syn::Meta::NameValue(name_value)) => {
+                    name_value.path.is_ident("rename")
+                }
+                _ => false,
+            }),
+            _ => false,
+        }
+    })
+}
+
+/// The name a body or query field is sent under: the one given with `#[serde(rename = "...")]`,
+/// or its identifier. `None` for flattened fields, whose names are only known at runtime.
+fn serde_key(field: &Field) -> Option<String> {
+    let mut rename = None;
+    for attr in field.attrs.iter().filter(|attr| attr.path.is_ident("serde")) {
+        let list = match attr.parse_meta() {
+            Ok(syn::Meta::List(list)) => list,
+            _ => continue,
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("flatten") => {
+                    return None;
+                }
+                NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: Lit::Str(name),
+                    ..
+                })) if path.is_ident("rename") => rename = Some(name.value()),
+                _ => {}
+            }
+        }
+    }
+
+    rename.or_else(|| {
+        Some(unraw(field.ident.as_ref().expect("expected field to have an identifier")))
+    })
+}
+
+/// Checks that the name given with `#[ruma_api(cookie = "...")]` is a valid cookie name, which
+/// can't be empty or contain whitespace, separators like `=` and `;`, or control characters.
+fn check_cookie_name(name: &LitStr) -> syn::Result<()> {
+    let value = name.value();
+    let is_token = !value.is_empty()
+        && value.chars().all(|c| c.is_ascii_graphic() && !"()<>@,;:\\\"/[]?={}".contains(c));
+
+    if !is_token {
+        return Err(syn::Error::new_spanned(
+            name,
+            format!("cookie name `{}` needs to be a token without separators", value),
+        ));
+    }
+
+    Ok(())
+}
+
+/// A field with a `#[ruma_api(example = "...")]` attribute.
+pub struct FieldExample {
+    /// The `cfg` attributes of the field.
+    pub cfg_attrs: Vec<Attribute>,
+    /// The name of the field.
+    pub ident: Ident,
+    /// The `ruma_api::examples::FieldLocation` variant for the field kind.
+    pub location: Ident,
+    /// The example value.
+    pub example: LitStr,
+}
+
+impl FieldExample {
+    /// Creates a new `FieldExample` for the given field, sent in the given `FieldLocation`.
+    pub fn new(field: &Field, location: &str, example: LitStr) -> Self {
+        Self {
+            cfg_attrs: cfg_attrs(field).into_iter().cloned().collect(),
+            ident: field.ident.clone().expect("expected field to have an identifier"),
+            location: Ident::new(location, Span::call_site()),
+            example,
+        }
+    }
+}
+
+/// A body field with a `#[ruma_api(renamed_from = "...")]` attribute.
+pub struct RenamedField {
+    /// The `cfg` attributes of the field.
+    pub cfg_attrs: Vec<Attribute>,
+    /// The name of the field.
+    pub ident: Ident,
+    /// The type of the field.
+    pub ty: Type,
+    /// The name the field is sent under.
+    pub name: String,
+    /// The name this field had in a previous version of the spec.
+    pub old_name: LitStr,
+}
+
+impl RenamedField {
+    /// Creates a new `RenamedField`, adding a serde alias for the old name to the given field so
+    /// incoming bodies are accepted with either name.
+    pub fn new(field: &mut Field, old_name: LitStr) -> Self {
+        field.attrs.push(parse_quote!(#[serde(alias = #old_name)]));
+
+        let ident = field.ident.clone().expect("expected field to have an identifier");
+        Self {
+            cfg_attrs: cfg_attrs(field).into_iter().cloned().collect(),
+            name: serde_key(field).unwrap_or_else(|| unraw(&ident)),
+            ident,
+            ty: field.ty.clone(),
+            old_name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use proc_macro2::TokenStream;
+    use quote::quote;
+
+    use super::{Api, RequestFieldKind, ResponseField};
+
+    fn endpoint(
+        method: &str,
+        path: &str,
+        request: TokenStream,
+        response: TokenStream,
+    ) -> TokenStream {
+        let method: TokenStream = method.parse().unwrap();
+
+        quote! {
+            metadata {
+                description: "Does something.",
+                method: #method,
+                name: "endpoint",
+                path: #path,
+                rate_limited: false,
+                requires_authentication: false,
+            }
+
+            request { #request }
+            response { #response }
+        }
+    }
+
+    fn analyze(input: TokenStream) -> syn::Result<Api> {
+        Api::try_from(syn::parse2::<super::RawApi>(input)?)
+    }
+
+    fn analysis_error(input: TokenStream) -> String {
+        match analyze(input) {
+            Ok(_) => panic!("expected the analysis to fail"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    #[test]
+    fn resolves_request_field_kinds() {
+        let api = analyze(endpoint(
+            "POST",
+            "/rooms/:room_id/send",
+            quote! {
+                #[ruma_api(path)]
+                pub room_id: String,
+                #[ruma_api(query)]
+                pub limit: Option<u32>,
+                #[ruma_api(header = CONTENT_TYPE)]
+                pub content_type: String,
+                pub body: String,
+            },
+            quote! {},
+        ))
+        .unwrap();
+
+        let kinds: Vec<_> = api.request.fields.iter().map(|field| field.kind()).collect();
+        assert_eq!(
+            kinds,
+            [
+                RequestFieldKind::Path,
+                RequestFieldKind::Query,
+                RequestFieldKind::Header,
+                RequestFieldKind::Body,
+            ]
+        );
+        assert_eq!(api.request.path_field_count(), 1);
+        assert!(api.response.fields.is_empty());
+    }
+
+    #[test]
+    fn resolves_response_field_kinds() {
+        let api = analyze(endpoint(
+            "GET",
+            "/media",
+            quote! {},
+            quote! {
+                #[ruma_api(header = CONTENT_TYPE)]
+                pub content_type: String,
+                #[ruma_api(raw_body)]
+                pub file: Vec<u8>,
+            },
+        ))
+        .unwrap();
+
+        match &api.response.fields[..] {
+            [ResponseField::Header(_, header), ResponseField::NewtypeRawBody(_)] => {
+                assert_eq!(header, "CONTENT_TYPE");
+            }
+            _ => panic!("unexpected response fields"),
+        }
+        assert!(!api.response.is_header_only());
+    }
+
+    #[test]
+    fn collects_examples_and_encode_sets() {
+        let api = analyze(endpoint(
+            "PUT",
+            "/rooms/:room_id",
+            quote! {
+                #[ruma_api(path, encode = STRICT, example = "!room:example.org")]
+                pub room_id: String,
+                #[ruma_api(example = "\"hello\"")]
+                pub body: String,
+            },
+            quote! {},
+        ))
+        .unwrap();
+
+        assert_eq!(api.request.path_encode_set("room_id").unwrap(), "STRICT");
+        assert!(api.request.path_encode_set("body").is_none());
+
+        let examples: Vec<_> = api
+            .request
+            .examples()
+            .iter()
+            .map(|example| (example.ident.to_string(), example.location.to_string()))
+            .collect();
+        assert_eq!(
+            examples,
+            [("room_id".to_owned(), "Path".to_owned()), ("body".to_owned(), "Body".to_owned())]
+        );
+    }
+
+    #[test]
+    fn rejects_body_fields_on_get() {
+        let error = analysis_error(endpoint("GET", "/", quote! { pub body: String }, quote! {}));
+        assert_eq!(error, "GET endpoints can't have body fields");
+    }
+
+    #[test]
+    fn allows_body_fields_with_several_methods() {
+        let api = analyze(endpoint("GET | POST", "/", quote! { pub body: String }, quote! {}))
+            .unwrap_or_else(|err| panic!("analysis failed: {}", err));
+        assert_eq!(api.metadata.method, "GET");
+        assert_eq!(api.metadata.other_methods, ["POST"]);
+
+        let error = analysis_error(endpoint(
+            "GET | POST | GET",
+            "/",
+            quote! { pub body: String },
+            quote! {},
+        ));
+        assert_eq!(error, "the method `GET` is listed more than once");
+    }
+
+    #[test]
+    fn rejects_removal_before_deprecation() {
+        let error = analysis_error(quote! {
+            metadata {
+                description: "Does something.",
+                method: GET,
+                name: "endpoint",
+                path: "/",
+                rate_limited: false,
+                requires_authentication: false,
+                deprecated: R0_6_0,
+                removed: R0_4_0,
+            }
+
+            request {}
+            response {}
+        });
+        assert_eq!(error, "an endpoint can't be removed before it is deprecated");
+    }
+
+    #[test]
+    fn resolves_cookie_fields() {
+        let api = analyze(endpoint(
+            "POST",
+            "/login",
+            quote! {
+                #[ruma_api(cookie = "session")]
+                pub session: String,
+            },
+            quote! {
+                #[ruma_api(cookie = "session")]
+                pub session: Option<String>,
+            },
+        ))
+        .unwrap();
+
+        assert_eq!(api.request.fields[0].kind(), RequestFieldKind::Cookie);
+        assert!(api.request.has_header_fields());
+        assert!(api.response.is_header_only());
+    }
+
+    #[test]
+    fn rejects_invalid_cookie_name() {
+        let error = analysis_error(endpoint(
+            "GET",
+            "/",
+            quote! {
+                #[ruma_api(cookie = "session id")]
+                pub session: String,
+            },
+            quote! {},
+        ));
+        assert_eq!(error, "cookie name `session id` needs to be a token without separators");
+    }
+
+    #[test]
+    fn rejects_path_placeholder_mismatch() {
+        let error = analysis_error(endpoint(
+            "GET",
+            "/rooms/:room_id/:event_id",
+            quote! {
+                #[ruma_api(path)]
+                pub room_id: String,
+            },
+            quote! {},
+        ));
+        assert_eq!(
+            error,
+            "number of declared path parameters needs to match amount of placeholders in path"
+        );
+
+        let error = analysis_error(endpoint(
+            "GET",
+            "rooms/:room_id",
+            quote! {
+                #[ruma_api(path)]
+                pub room_id: String,
+            },
+            quote! {},
+        ));
+        assert_eq!(error, "path needs to start with '/'");
+    }
+
+    #[test]
+    fn rejects_conflicting_path_placeholders() {
+        let error = analysis_error(endpoint(
+            "GET",
+            "/rooms/:room_id/:room_id",
+            quote! {
+                #[ruma_api(path)]
+                pub room_id: String,
+                #[ruma_api(path)]
+                pub event_id: String,
+            },
+            quote! {},
+        ));
+        assert_eq!(
+            error,
+            "path placeholder `:room_id` appears more than once, repeated path parameters are not \
+             supported; declare a separate path field for each segment"
+        );
+
+        let error = analysis_error(endpoint(
+            "GET",
+            "/rooms/:room_id/alias/:room_id",
+            quote! {
+                #[ruma_api(path)]
+                pub room_id: String,
+            },
+            quote! {},
+        ));
+        assert!(error.starts_with("path placeholder `:room_id` appears more than once"));
+
+        let error = analysis_error(endpoint(
+            "GET",
+            "/rooms/:room-id",
+            quote! {
+                #[ruma_api(path)]
+                pub room_id: String,
+            },
+            quote! {},
+        ));
+        assert_eq!(error, "path placeholder `:room-id` needs to be ':' followed by an identifier");
+
+        let error = analysis_error(endpoint(
+            "GET",
+            "/rooms/!:room_id",
+            quote! {
+                #[ruma_api(path)]
+                pub room_id: String,
+            },
+            quote! {},
+        ));
+        assert_eq!(error, "path segment `!:room_id` needs to be a placeholder or not contain ':'");
+
+        let error = analysis_error(endpoint("GET", "/rooms/:room_id", quote! {}, quote! {}));
+        assert_eq!(error, "path placeholder `:room_id` has no path field with the same name");
+
+        let error = analysis_error(endpoint(
+            "GET",
+            "/rooms/:room_id",
+            quote! {
+                #[ruma_api(path)]
+                pub room_id: String,
+                #[ruma_api(query)]
+                #[serde(rename = "room_id")]
+                pub room: Option<String>,
+            },
+            quote! {},
+        ));
+        assert_eq!(error, "query parameter `room_id` has the name of a path placeholder");
+    }
+
+    #[test]
+    fn rejects_several_field_kinds() {
+        let error = analysis_error(endpoint(
+            "GET",
+            "/rooms",
+            quote! {
+                #[ruma_api(query)]
+                #[ruma_api(path)]
+                pub room_id: String,
+            },
+            quote! {},
+        ));
+        assert_eq!(error, "field `room_id` can't be both a `query` field and a `path` field");
+
+        let error = analysis_error(endpoint(
+            "GET",
+            "/",
+            quote! {},
+            quote! {
+                #[ruma_api(header = LOCATION, body)]
+                pub location: String,
+            },
+        ));
+        assert_eq!(error, "field `location` can't be both a `header` field and a `body` field");
+    }
+
+    #[test]
+    fn rejects_duplicate_wire_names() {
+        let error = analysis_error(endpoint(
+            "GET",
+            "/",
+            quote! {
+                #[ruma_api(query)]
+                pub limit: Option<u32>,
+                #[ruma_api(query)]
+                #[serde(rename = "limit")]
+                pub max: Option<u32>,
+            },
+            quote! {},
+        ));
+        assert_eq!(
+            error,
+            "field `max` is sent as the query parameter `limit`, like the field `limit`"
+        );
+
+        let error = analysis_error(endpoint(
+            "POST",
+            "/",
+            quote! {},
+            quote! {
+                #[ruma_api(header = CONTENT_TYPE)]
+                pub content_type: String,
+                #[ruma_api(header = CONTENT_TYPE)]
+                pub mime_type: String,
+            },
+        ));
+        assert_eq!(
+            error,
+            "field `mime_type` is sent as the header `CONTENT_TYPE`, like the field `content_type`"
+        );
+
+        // The same name in different parts, and in fields of which only one is enabled.
+        analyze(endpoint(
+            "POST",
+            "/",
+            quote! {
+                #[ruma_api(query)]
+                pub limit: u32,
+                #[serde(rename = "limit")]
+                pub body_limit: u32,
+                #[cfg(feature = "a")]
+                pub filter: String,
+                #[cfg(not(feature = "a"))]
+                pub filter: u32,
+            },
+            quote! {},
+        ))
+        .unwrap();
+    }
+
+    #[test]
+    fn rejects_newtype_and_regular_body_fields() {
+        let error = analysis_error(endpoint(
+            "POST",
+            "/",
+            quote! {
+                #[ruma_api(body)]
+                pub content: Vec<String>,
+                pub other: String,
+            },
+            quote! {},
+        ));
+        assert_eq!(error, "Can't have both a newtype body field and regular body fields");
+    }
+
+    #[test]
+    fn rejects_attributes_on_the_wrong_field_kind() {
+        let error = analysis_error(endpoint(
+            "POST",
+            "/",
+            quote! {
+                #[ruma_api(as_str)]
+                pub dir: String,
+            },
+            quote! {},
+        ));
+        assert_eq!(error, "`as_str` is only supported on query fields");
+
+        let error = analysis_error(endpoint(
+            "POST",
+            "/",
+            quote! {},
+            quote! {
+                #[ruma_api(header = LOCATION, renamed_from = "loc")]
+                pub location: String,
+            },
+        ));
+        assert_eq!(error, "`renamed_from` is only supported on body fields");
+
+        let error = analysis_error(endpoint(
+            "POST",
+            "/",
+            quote! {
+                #[ruma_api(query, batch)]
+                pub ids: Vec<String>,
+            },
+            quote! {},
+        ));
+        assert_eq!(error, "`batch` is only supported on body fields");
+
+        let error = analysis_error(endpoint(
+            "POST",
+            "/",
+            quote! {},
+            quote! {
+                #[ruma_api(batch)]
+                pub pdus: BTreeMap<String, Pdu>,
+            },
+        ));
+        assert_eq!(error, "`batch` fields need to be of type `Vec<T>`");
+    }
+}
@@ -0,0 +1,60 @@
+//! Relaxed deserialization of body fields for the `compat` feature.
+
+use syn::{parse_quote, Field, Meta, NestedMeta};
+
+use crate::api::{is_integer, is_option};
+
+/// Adds serde attributes to a body field that relax its deserialization in the ways documented in
+/// `ruma_api::compat`. Does nothing if the `compat` feature is disabled.
+pub fn relax_body_field(field: &mut Field) {
+    if !cfg!(feature = "compat") || has_custom_deserialization(field) {
+        return;
+    }
+
+    let has_default = serde_meta_items(field).any(|meta| meta.path().is_ident("default"));
+
+    if is_option(&field.ty) {
+        if has_default {
+            field.attrs.push(parse_quote! {
+                #[serde(deserialize_with = "ruma_api::compat::ignore_invalid")]
+            });
+        } else {
+            field.attrs.push(parse_quote! {
+                #[serde(default, deserialize_with = "ruma_api::compat::ignore_invalid")]
+            });
+        }
+    } else if is_integer(&field.ty) {
+        field.attrs.push(parse_quote! {
+            #[serde(deserialize_with = "ruma_api::compat::int_or_string")]
+        });
+    } else if has_default {
+        field.attrs.push(parse_quote! {
+            #[serde(deserialize_with = "ruma_api::compat::null_as_default")]
+        });
+    }
+}
+
+/// Whether the field uses a custom deserializer or `#[wrap_incoming]`, in which case it is left
+/// untouched.
+pub fn has_custom_deserialization(field: &Field) -> bool {
+    field.attrs.iter().any(|attr| attr.path.is_ident("wrap_incoming"))
+        || serde_meta_items(field)
+            .any(|meta| meta.path().is_ident("with") || meta.path().is_ident("deserialize_with"))
+}
+
+/// The items of all `#[serde(...)]` attributes on the field.
+fn serde_meta_items(field: &Field) -> impl Iterator<Item = Meta> + '_ {
+    field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("serde"))
+        .filter_map(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested| match nested {
+            NestedMeta::Meta(meta) => Some(meta),
+            NestedMeta::Lit(_) => None,
+        })
+}
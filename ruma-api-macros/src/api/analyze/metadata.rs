@@ -0,0 +1,361 @@
+//! Analysis of the `metadata` section of the procedural macro.
+
+use std::convert::TryFrom;
+
+use syn::{
+    BinOp, Expr, ExprBinary, ExprCall, ExprLit, ExprPath, Ident, Lit, LitBool, LitInt, LitStr,
+    Member,
+};
+
+use crate::api::parse::RawMetadata;
+
+/// The result of processing the `metadata` section of the macro.
+pub struct Metadata {
+    /// The description field.
+    pub description: LitStr,
+    /// The method field, or the first of its methods if it has several.
+    pub method: Ident,
+    /// The methods after the first one in a method field like `GET | POST`.
+    pub other_methods: Vec<Ident>,
+    /// The name field.
+    pub name: LitStr,
+    /// The path field.
+    pub path: LitStr,
+    /// The rate_limited field.
+    pub rate_limited: LitBool,
+    /// The description field.
+    pub requires_authentication: LitBool,
+    /// The optional uiaa field.
+    pub uiaa: bool,
+    /// The optional canonical_json field.
+    pub canonical_json: bool,
+    /// The optional unstable field.
+    pub unstable: bool,
+    /// The optional request_content_type field.
+    pub request_content_type: Option<LitStr>,
+    /// The optional cacheable field.
+    pub cacheable: bool,
+    /// The optional validate field.
+    pub validate: bool,
+    /// The optional direction field.
+    pub direction: Option<Ident>,
+    /// The optional server_kind field.
+    pub server_kind: Option<Ident>,
+    /// The optional duplicate_query_parameters field, `Reject` or `LastWins`.
+    pub duplicate_query_parameters: Option<Ident>,
+    /// The optional suggested_timeout field, as the `Duration` constructor and its argument.
+    pub suggested_timeout: Option<(Ident, LitInt)>,
+    /// The optional deprecated field, a `MatrixVersion` variant.
+    pub deprecated: Option<Ident>,
+    /// The optional removed field, a `MatrixVersion` variant.
+    pub removed: Option<Ident>,
+}
+
+impl TryFrom<RawMetadata> for Metadata {
+    type Error = syn::Error;
+
+    fn try_from(raw: RawMetadata) -> syn::Result<Self> {
+        let mut description = None;
+        let mut method = None;
+        let mut other_methods = Vec::new();
+        let mut name = None;
+        let mut path = None;
+        let mut rate_limited = None;
+        let mut requires_authentication = None;
+        let mut uiaa = None;
+        let mut canonical_json = None;
+        let mut unstable = None;
+        let mut request_content_type = None;
+        let mut cacheable = None;
+        let mut validate = None;
+        let mut direction = None;
+        let mut server_kind = None;
+        let mut duplicate_query_parameters = None;
+        let mut suggested_timeout = None;
+        let mut deprecated = None;
+        let mut removed = None;
+
+        for field_value in raw.field_values {
+            let identifier = match field_value.member.clone() {
+                Member::Named(identifier) => identifier,
+                _ => panic!("expected Member::Named"),
+            };
+            let expr = field_value.expr.clone();
+
+            match &identifier.to_string()[..] {
+                "description" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Str(literal), .. }) => {
+                        description = Some(literal);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a string literal")),
+                },
+                "method" => {
+                    let mut methods = Vec::new();
+                    if !method_list(&expr, &mut methods) {
+                        return Err(syn::Error::new_spanned(
+                            expr,
+                            "expected an identifier or identifiers separated by `|`",
+                        ));
+                    }
+                    for (i, method) in methods.iter().enumerate() {
+                        if methods[..i].contains(method) {
+                            return Err(syn::Error::new_spanned(
+                                method,
+                                format!("the method `{}` is listed more than once", method),
+                            ));
+                        }
+                    }
+                    let mut methods = methods.into_iter();
+                    method = methods.next();
+                    other_methods = methods.collect();
+                }
+                "name" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Str(literal), .. }) => {
+                        name = Some(literal);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a string literal")),
+                },
+                "path" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Str(literal), .. }) => {
+                        path = Some(literal);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a string literal")),
+                },
+                "rate_limited" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        rate_limited = Some(literal);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "requires_authentication" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        requires_authentication = Some(literal);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "uiaa" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        uiaa = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "canonical_json" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        canonical_json = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "unstable" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        unstable = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "request_content_type" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Str(literal), .. }) => {
+                        // The value ends up in `HeaderValue::from_static`, which panics on
+                        // anything but visible ASCII characters and spaces.
+                        if !literal.value().bytes().all(|b| b == b' ' || b.is_ascii_graphic()) {
+                            return Err(syn::Error::new_spanned(
+                                literal,
+                                "expected a valid header value",
+                            ));
+                        }
+                        request_content_type = Some(literal);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a string literal")),
+                },
+                "cacheable" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        cacheable = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "validate" => match expr {
+                    Expr::Lit(ExprLit { lit: Lit::Bool(literal), .. }) => {
+                        validate = Some(literal.value);
+                    }
+                    _ => return Err(syn::Error::new_spanned(expr, "expected a bool literal")),
+                },
+                "deprecated" | "removed" => match matrix_version(&expr) {
+                    Some(version) if identifier == "deprecated" => deprecated = Some(version),
+                    Some(version) => removed = Some(version),
+                    None => {
+                        return Err(syn::Error::new_spanned(
+                            expr,
+                            "expected a `MatrixVersion` variant like `R0_6_0`",
+                        ))
+                    }
+                },
+                "direction" => match expr {
+                    Expr::Path(ExprPath { ref path, .. })
+                        if path.segments.len() == 1
+                            && (path.segments[0].ident == "ClientToServer"
+                                || path.segments[0].ident == "ServerToClient") =>
+                    {
+                        direction = Some(path.segments[0].ident.clone());
+                    }
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            expr,
+                            "expected `ClientToServer` or `ServerToClient`",
+                        ))
+                    }
+                },
+                "server_kind" => match expr {
+                    Expr::Path(ExprPath { ref path, .. })
+                        if path.segments.len() == 1
+                            && (path.segments[0].ident == "Homeserver"
+                                || path.segments[0].ident == "IdentityServer"
+                                || path.segments[0].ident == "PushGateway") =>
+                    {
+                        server_kind = Some(path.segments[0].ident.clone());
+                    }
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            expr,
+                            "expected `Homeserver`, `IdentityServer` or `PushGateway`",
+                        ))
+                    }
+                },
+                "duplicate_query_parameters" => match expr {
+                    Expr::Path(ExprPath { ref path, .. })
+                        if path.segments.len() == 1
+                            && (path.segments[0].ident == "Reject"
+                                || path.segments[0].ident == "LastWins") =>
+                    {
+                        duplicate_query_parameters = Some(path.segments[0].ident.clone());
+                    }
+                    _ => {
+                        return Err(syn::Error::new_spanned(
+                            expr,
+                            "expected `Reject` or `LastWins`",
+                        ))
+                    }
+                },
+                "suggested_timeout" => {
+                    match duration_constructor(&expr) {
+                        Some(constructor) => suggested_timeout = Some(constructor),
+                        None => return Err(syn::Error::new_spanned(
+                            expr,
+                            "expected `Duration::from_secs` or `Duration::from_millis` with an \
+                             integer literal",
+                        )),
+                    }
+                }
+                _ => return Err(syn::Error::new_spanned(field_value, "unexpected field")),
+            }
+        }
+
+        let metadata_kw = raw.metadata_kw;
+        let missing_field =
+            |name| syn::Error::new_spanned(metadata_kw, format!("missing field `{}`", name));
+
+        let uiaa = uiaa.unwrap_or(false);
+        if let Some(direction) = &direction {
+            if uiaa && direction == "ServerToClient" {
+                return Err(syn::Error::new_spanned(
+                    direction,
+                    "endpoints with the `ServerToClient` direction can't use `uiaa`",
+                ));
+            }
+        }
+
+        if let (Some(deprecated), Some(removed)) = (&deprecated, &removed) {
+            if version_numbers(removed) < version_numbers(deprecated) {
+                return Err(syn::Error::new_spanned(
+                    removed,
+                    "an endpoint can't be removed before it is deprecated",
+                ));
+            }
+        }
+
+        Ok(Self {
+            description: description.ok_or_else(|| missing_field("description"))?,
+            method: method.ok_or_else(|| missing_field("method"))?,
+            other_methods,
+            name: name.ok_or_else(|| missing_field("name"))?,
+            path: path.ok_or_else(|| missing_field("path"))?,
+            rate_limited: rate_limited.ok_or_else(|| missing_field("rate_limited"))?,
+            requires_authentication: requires_authentication
+                .ok_or_else(|| missing_field("requires_authentication"))?,
+            uiaa,
+            canonical_json: canonical_json.unwrap_or(false),
+            unstable: unstable.unwrap_or(false),
+            request_content_type,
+            cacheable: cacheable.unwrap_or(false),
+            validate: validate.unwrap_or(false),
+            direction,
+            server_kind,
+            duplicate_query_parameters,
+            suggested_timeout,
+            deprecated,
+            removed,
+        })
+    }
+}
+
+/// The identifier of a `MatrixVersion` variant like `R0_6_0`.
+fn matrix_version(expr: &Expr) -> Option<Ident> {
+    match expr {
+        Expr::Path(ExprPath { path, .. }) if path.segments.len() == 1 => {
+            let ident = &path.segments[0].ident;
+            version_numbers(ident).map(|_| ident.clone())
+        }
+        _ => None,
+    }
+}
+
+/// The numbers of a `MatrixVersion` variant like `R0_6_0`, for comparing versions.
+fn version_numbers(ident: &Ident) -> Option<Vec<u32>> {
+    let ident = ident.to_string();
+    if !ident.starts_with('R') {
+        return None;
+    }
+
+    let numbers = ident[1..].split('_').map(|n| n.parse().ok()).collect::<Option<Vec<_>>>()?;
+    if numbers.len() == 3 {
+        Some(numbers)
+    } else {
+        None
+    }
+}
+
+/// Collects the methods of a method field like `GET | POST` in order, returning whether it has
+/// the expected form.
+fn method_list(expr: &Expr, methods: &mut Vec<Ident>) -> bool {
+    match expr {
+        Expr::Path(ExprPath { path, .. }) if path.segments.len() == 1 => {
+            methods.push(path.segments[0].ident.clone());
+            true
+        }
+        Expr::Binary(ExprBinary { left, op: BinOp::BitOr(_), right, .. }) => {
+            method_list(left, methods) && method_list(right, methods)
+        }
+        _ => false,
+    }
+}
+
+/// Splits a call like `Duration::from_secs(30)` into the name of the constructor and its argument.
+fn duration_constructor(expr: &Expr) -> Option<(Ident, LitInt)> {
+    let (func, args) = match expr {
+        Expr::Call(ExprCall { func, args, .. }) => (func, args),
+        _ => return None,
+    };
+    let segments = match &**func {
+        Expr::Path(ExprPath { path, .. }) => &path.segments,
+        _ => return None,
+    };
+    let mut segments = segments.iter().rev();
+    let constructor = &segments.next()?.ident;
+    if segments.next()?.ident != "Duration"
+        || (constructor != "from_secs" && constructor != "from_millis")
+    {
+        return None;
+    }
+
+    match args.iter().collect::<Vec<_>>()[..] {
+        [Expr::Lit(ExprLit { lit: Lit::Int(int), .. })] => Some((constructor.clone(), int.clone())),
+        _ => None,
+    }
+}
@@ -0,0 +1,857 @@
+//! Analysis of the `request` section of the procedural macro.
+
+use std::{convert::TryFrom, mem};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{parse_quote, Attribute, Field, Ident, Lit, LitStr, NestedMeta};
+
+use super::{
+    check_cookie_name, compat, rename_raw_identifier, serde_key, FieldExample, RenamedField,
+};
+use crate::api::{
+    attribute::{Meta, MetaNameValue},
+    cfg_attrs, is_identifier_type, is_integer, is_option, option_inner,
+    parse::RawRequest,
+    unraw, vec_inner,
+};
+
+/// The analyzed `request` section of the macro.
+pub struct Request {
+    /// The fields of the request.
+    pub fields: Vec<RequestField>,
+    /// The body fields with a `#[ruma_api(renamed_from = "...")]` attribute.
+    pub renamed_fields: Vec<RenamedField>,
+    /// The fields with a `#[ruma_api(example = "...")]` attribute.
+    pub examples: Vec<FieldExample>,
+    /// The path fields with a `#[ruma_api(encode = ...)]` attribute, with the name of the encode
+    /// set.
+    pub path_encode_sets: Vec<(Ident, Ident)>,
+    /// The path and query fields with a `#[ruma_api(decode = ...)]` attribute, with the decoding
+    /// mode.
+    pub decode_modes: Vec<(Ident, Ident)>,
+    /// The limits given with `#[ruma_api(max_len = ...)]` and `#[ruma_api(max_items = ...)]`.
+    pub field_limits: Vec<FieldLimit>,
+    /// The path fields with a `#[ruma_api(identifier)]` attribute.
+    pub identifier_fields: Vec<Ident>,
+}
+
+impl Request {
+    /// Whether or not this request has any data in the HTTP body.
+    pub fn has_body_fields(&self) -> bool {
+        self.fields.iter().any(|field| field.is_body())
+    }
+
+    /// Whether or not this request has any data in HTTP headers.
+    pub fn has_header_fields(&self) -> bool {
+        self.fields.iter().any(|field| field.is_header() || field.is_cookie())
+            || self.header_map_field().is_some()
+    }
+
+    /// Whether or not this request has any data in the URL path.
+    pub fn has_path_fields(&self) -> bool {
+        self.fields.iter().any(|field| field.is_path())
+    }
+
+    /// Whether or not this request has any data in the query string.
+    pub fn has_query_fields(&self) -> bool {
+        self.fields.iter().any(|field| field.is_query())
+    }
+
+    /// Produces an iterator over all the body fields.
+    pub fn body_fields(&self) -> impl Iterator<Item = &Field> {
+        self.fields.iter().filter_map(|field| field.as_body_field())
+    }
+
+    /// Whether any field has a #[wrap_incoming] attribute.
+    pub fn uses_wrap_incoming(&self) -> bool {
+        self.fields.iter().any(|f| f.has_wrap_incoming_attr())
+    }
+
+    /// Produces an iterator over all the header fields.
+    pub fn header_fields(&self) -> impl Iterator<Item = &RequestField> {
+        self.fields.iter().filter(|field| field.is_header())
+    }
+
+    /// Produces an iterator over all the cookie fields.
+    pub fn cookie_fields(&self) -> impl Iterator<Item = &RequestField> {
+        self.fields.iter().filter(|field| field.is_cookie())
+    }
+
+    /// Gets the number of path fields.
+    pub fn path_field_count(&self) -> usize {
+        self.fields.iter().filter(|field| field.is_path()).count()
+    }
+
+    /// The path field with the given name, if any.
+    pub fn path_field(&self, name: &str) -> Option<&Field> {
+        self.fields
+            .iter()
+            .filter(|field| field.is_path())
+            .map(RequestField::field)
+            .find(|field| field.ident.iter().any(|ident| unraw(ident) == name))
+    }
+
+    /// The identifier of the path field with the given name, e.g. `r#type` for `type`.
+    pub fn path_field_ident(&self, name: &str) -> &Ident {
+        self.path_field(name)
+            .and_then(|field| field.ident.as_ref())
+            .expect("expected a path field for every path parameter")
+    }
+
+    /// The query field sent under the given key, if any.
+    pub fn query_field(&self, key: &str) -> Option<&Field> {
+        self.fields
+            .iter()
+            .filter_map(RequestField::as_query_field)
+            .find(|field| query_key(field) == key)
+    }
+
+    /// Returns the body field.
+    pub fn newtype_body_field(&self) -> Option<&Field> {
+        self.fields.iter().find_map(RequestField::as_newtype_body_field)
+    }
+
+    /// Returns the body field.
+    pub fn newtype_raw_body_field(&self) -> Option<&Field> {
+        self.fields.iter().find_map(RequestField::as_newtype_raw_body_field)
+    }
+
+    /// Returns the query map field.
+    pub fn query_map_field(&self) -> Option<&Field> {
+        self.fields.iter().find_map(RequestField::as_query_map_field)
+    }
+
+    /// Returns the header map field.
+    pub fn header_map_field(&self) -> Option<&Field> {
+        self.fields.iter().find_map(RequestField::as_header_map_field)
+    }
+
+    /// The keys of the typed query fields in the query string, honoring `#[serde(rename)]`.
+    ///
+    /// Used to keep these parameters out of the query map field when a request has both.
+    pub fn query_field_keys(&self) -> Vec<String> {
+        self.fields.iter().filter_map(RequestField::as_query_field).map(query_key).collect()
+    }
+
+    /// The keys of the query fields with `#[ruma_api(decode = STRICT)]`, whose values have to be
+    /// valid UTF-8 after percent-decoding.
+    pub fn strict_query_keys(&self) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter_map(RequestField::as_query_field)
+            .filter(|field| {
+                let ident = field.ident.as_ref().expect("expected field to have an identifier");
+                match self.decode_mode(&unraw(ident)) {
+                    Some(mode) => mode == "STRICT",
+                    None => false,
+                }
+            })
+            .map(query_key)
+            .collect()
+    }
+
+    /// The fields with a `#[ruma_api(example = "...")]` attribute.
+    pub fn examples(&self) -> &[FieldExample] {
+        &self.examples
+    }
+
+    /// The name of the `ruma_api::path` encode set given with `#[ruma_api(encode = ...)]` for the
+    /// path field with the given name, if any.
+    pub fn path_encode_set(&self, name: &str) -> Option<&Ident> {
+        self.path_encode_sets.iter().find(|(ident, _)| unraw(ident) == name).map(|(_, set)| set)
+    }
+
+    /// Whether the path field with the given name has a `#[ruma_api(identifier)]` attribute.
+    pub fn is_identifier(&self, name: &str) -> bool {
+        self.identifier_fields.iter().any(|ident| unraw(ident) == name)
+    }
+
+    /// The decoding mode given with `#[ruma_api(decode = ...)]` for the path or query field with
+    /// the given name, if any: `LOSSY`, `STRICT` or `RAW`.
+    pub fn decode_mode(&self, name: &str) -> Option<&Ident> {
+        self.decode_modes.iter().find(|(ident, _)| unraw(ident) == name).map(|(_, mode)| mode)
+    }
+}
+
+impl TryFrom<RawRequest> for Request {
+    type Error = syn::Error;
+
+    fn try_from(raw: RawRequest) -> syn::Result<Self> {
+        let mut newtype_body_field = None;
+        let mut query_map_field = None;
+        let mut header_map_field = None;
+        let mut renamed_fields = Vec::new();
+        let mut examples = Vec::new();
+        let mut path_encode_sets = Vec::new();
+        let mut decode_modes = Vec::new();
+        let mut field_limits = Vec::new();
+        let mut identifier_fields = Vec::new();
+
+        let fields = raw
+            .fields
+            .into_iter()
+            .map(|mut field| {
+                let mut field_kind = None;
+                let mut kind_attr: Option<Ident> = None;
+                let mut header = None;
+                let mut cookie = None;
+                let mut renamed_from = None;
+                let mut example = None;
+                let mut encode = None;
+                let mut decode = None;
+                let mut limits = Vec::new();
+                let mut identifier = None;
+                let mut partial = false;
+                let mut batch = None;
+                let mut as_str = None;
+
+                for attr in mem::replace(&mut field.attrs, Vec::new()) {
+                    let metas = match Meta::list_from_attribute(&attr)? {
+                        Some(metas) => metas,
+                        None => {
+                            field.attrs.push(attr);
+                            continue;
+                        }
+                    };
+
+                    for meta in metas {
+                        let meta = match meta {
+                            Meta::NameValue(MetaNameValue { name, value }) if name == "renamed_from" => {
+                                renamed_from = Some(value.into_lit_str()?);
+                                continue;
+                            }
+                            Meta::NameValue(MetaNameValue { name, value }) if name == "example" => {
+                                example = Some(value.into_lit_str()?);
+                                continue;
+                            }
+                            Meta::NameValue(MetaNameValue { name, value }) if name == "encode" => {
+                                encode = Some(value.into_ident()?);
+                                continue;
+                            }
+                            Meta::NameValue(MetaNameValue { name, value }) if name == "decode" => {
+                                decode = Some(value.into_ident()?);
+                                continue;
+                            }
+                            Meta::NameValue(MetaNameValue { name, value })
+                                if name == "max_len" || name == "max_items" =>
+                            {
+                                limits.push((name, value.into_usize()?));
+                                continue;
+                            }
+                            Meta::Word(ident) if ident == "partial" => {
+                                partial = true;
+                                continue;
+                            }
+                            Meta::Word(ident) if ident == "batch" => {
+                                batch = Some(ident);
+                                continue;
+                            }
+                            Meta::Word(ident) if ident == "as_str" => {
+                                as_str = Some(ident);
+                                continue;
+                            }
+                            Meta::Word(ident) if ident == "identifier" => {
+                                identifier = Some(ident);
+                                continue;
+                            }
+                            meta => meta,
+                        };
+
+                        if let Some(previous) = &kind_attr {
+                            let ident =
+                                field.ident.as_ref().expect("expected field to have an identifier");
+                            return Err(syn::Error::new_spanned(
+                                attr,
+                                format!(
+                                    "field `{}` can't be both a `{}` field and a `{}` field",
+                                    ident,
+                                    previous,
+                                    meta.name(),
+                                ),
+                            ));
+                        }
+                        kind_attr = Some(meta.name().clone());
+
+                        field_kind = Some(match meta {
+                            Meta::Word(ident) => {
+                                match &ident.to_string()[..] {
+                                    s @ "body" | s @ "raw_body" => {
+                                        if let Some(f) = &newtype_body_field {
+                                            let mut error = syn::Error::new_spanned(
+                                                field,
+                                                "There can only be one newtype body field",
+                                            );
+                                            error.combine(syn::Error::new_spanned(
+                                                f,
+                                                "Previous newtype body field",
+                                            ));
+                                            return Err(error);
+                                        }
+
+                                        newtype_body_field = Some(field.clone());
+                                        match s {
+                                            "body" => RequestFieldKind::NewtypeBody,
+                                            "raw_body" => RequestFieldKind::NewtypeRawBody,
+                                            _ => unreachable!(),
+                                        }
+                                    }
+                                    "path" => RequestFieldKind::Path,
+                                    "query" => RequestFieldKind::Query,
+                                    "query_map" => {
+                                        if let Some(f) = &query_map_field {
+                                            let mut error = syn::Error::new_spanned(
+                                                field,
+                                                "There can only be one query map field",
+                                            );
+                                            error.combine(syn::Error::new_spanned(
+                                                f,
+                                                "Previous query map field",
+                                            ));
+                                            return Err(error);
+                                        }
+
+                                        query_map_field = Some(field.clone());
+                                        RequestFieldKind::QueryMap
+                                    },
+                                    "header_map" => {
+                                        if let Some(f) = &header_map_field {
+                                            let mut error = syn::Error::new_spanned(
+                                                field,
+                                                "There can only be one header map field",
+                                            );
+                                            error.combine(syn::Error::new_spanned(
+                                                f,
+                                                "Previous header map field",
+                                            ));
+                                            return Err(error);
+                                        }
+
+                                        header_map_field = Some(field.clone());
+                                        RequestFieldKind::HeaderMap
+                                    },
+                                    _ => {
+                                        return Err(syn::Error::new_spanned(
+                                            ident,
+                                            "Invalid #[ruma_api] argument, expected one of `body`, `raw_body`, `path`, \
+                                             `query`, `query_map`, `header_map`, `partial`, `batch`, \
+                                             `as_str`, `identifier`",
+                                        ));
+                                    }
+                                }
+                            }
+                            Meta::NameValue(MetaNameValue { name, value }) if name == "cookie" => {
+                                let name = value.into_lit_str()?;
+                                check_cookie_name(&name)?;
+                                cookie = Some(name);
+                                RequestFieldKind::Cookie
+                            }
+                            Meta::NameValue(MetaNameValue { name, value }) => {
+                                if name != "header" {
+                                    return Err(syn::Error::new_spanned(
+                                        name,
+                                        "Invalid #[ruma_api] argument with value, expected `header`, \
+                                         `cookie`, `renamed_from`, `example`, `encode`, `decode`, \
+                                         `max_len` or `max_items`"
+                                    ));
+                                }
+
+                                header = Some(value.into_ident()?);
+                                RequestFieldKind::Header
+                            }
+                        });
+                    }
+                }
+
+                let field_kind = field_kind.unwrap_or(RequestFieldKind::Body);
+
+                match field_kind {
+                    RequestFieldKind::Body | RequestFieldKind::Header | RequestFieldKind::Query => {}
+                    _ => {
+                        if let Some(attr) = cfg_attrs(&field).first() {
+                            return Err(syn::Error::new_spanned(
+                                attr,
+                                "`cfg` attributes are only supported on body, header and query fields",
+                            ));
+                        }
+                    }
+                }
+
+                if let Some(old_name) = renamed_from {
+                    if field_kind != RequestFieldKind::Body {
+                        return Err(syn::Error::new_spanned(
+                            old_name,
+                            "`renamed_from` is only supported on body fields",
+                        ));
+                    }
+
+                    renamed_fields.push(RenamedField::new(&mut field, old_name));
+                }
+
+                if partial {
+                    if field_kind != RequestFieldKind::Body {
+                        return Err(syn::Error::new_spanned(
+                            field,
+                            "`partial` is only supported on body fields",
+                        ));
+                    }
+
+                    field.attrs.push(parse_quote!(#[wrap_incoming(with ruma_api::partial::Partial)]));
+                }
+
+                if let Some(batch) = batch {
+                    if field_kind != RequestFieldKind::Body {
+                        return Err(syn::Error::new_spanned(
+                            batch,
+                            "`batch` is only supported on body fields",
+                        ));
+                    }
+
+                    if partial {
+                        return Err(syn::Error::new_spanned(
+                            batch,
+                            "`batch` can't be combined with `partial`",
+                        ));
+                    }
+
+                    let item = vec_inner(&field.ty).cloned().ok_or_else(|| {
+                        syn::Error::new_spanned(
+                            &field.ty,
+                            "`batch` fields need to be of type `Vec<T>`",
+                        )
+                    })?;
+                    field
+                        .attrs
+                        .push(parse_quote!(#[wrap_incoming(#item with ruma_api::partial::Partial)]));
+                }
+
+                if let Some(as_str) = as_str {
+                    if field_kind != RequestFieldKind::Query {
+                        return Err(syn::Error::new_spanned(
+                            as_str,
+                            "`as_str` is only supported on query fields",
+                        ));
+                    }
+
+                    if is_option(&field.ty) {
+                        if !has_serde_default(&field) {
+                            field.attrs.push(parse_quote!(#[serde(default)]));
+                        }
+                        field.attrs.push(parse_quote!(#[serde(with = "ruma_api::query::opt_as_str")]));
+                    } else {
+                        field.attrs.push(parse_quote!(#[serde(with = "ruma_api::query::as_str")]));
+                    }
+                } else if field_kind == RequestFieldKind::Query
+                    && !compat::has_custom_deserialization(&field)
+                {
+                    // Integers and identifiers get the same checks and errors as in the path,
+                    // instead of the messages of `serde_urlencoded`.
+                    let (is_optional_integer, is_optional_identifier) =
+                        match option_inner(&field.ty) {
+                            Some(ty) => (is_integer(ty), is_identifier_type(ty)),
+                            None => (false, false),
+                        };
+                    if is_integer(&field.ty) {
+                        field.attrs.push(parse_quote! {
+                            #[serde(deserialize_with = "ruma_api::query::integer")]
+                        });
+                    } else if is_optional_integer {
+                        if !has_serde_default(&field) {
+                            field.attrs.push(parse_quote!(#[serde(default)]));
+                        }
+                        field.attrs.push(parse_quote! {
+                            #[serde(deserialize_with = "ruma_api::query::opt_integer")]
+                        });
+                    } else if is_identifier_type(&field.ty) {
+                        field.attrs.push(parse_quote! {
+                            #[serde(deserialize_with = "ruma_api::query::identifier")]
+                        });
+                    } else if is_optional_identifier {
+                        if !has_serde_default(&field) {
+                            field.attrs.push(parse_quote!(#[serde(default)]));
+                        }
+                        field.attrs.push(parse_quote! {
+                            #[serde(deserialize_with = "ruma_api::query::opt_identifier")]
+                        });
+                    }
+                }
+
+                if let Some(encode) = encode {
+                    if field_kind != RequestFieldKind::Path {
+                        return Err(syn::Error::new_spanned(
+                            encode,
+                            "`encode` is only supported on path fields",
+                        ));
+                    }
+
+                    let ident = field.ident.clone().expect("expected field to have an identifier");
+                    path_encode_sets.push((ident, encode));
+                }
+
+                if let Some(identifier) = identifier {
+                    if field_kind != RequestFieldKind::Path {
+                        return Err(syn::Error::new_spanned(
+                            identifier,
+                            "`identifier` is only supported on path fields",
+                        ));
+                    }
+                    if let Some(decode) = decode.as_ref().filter(|decode| *decode == "RAW") {
+                        return Err(syn::Error::new_spanned(
+                            decode,
+                            "`identifier` can't be combined with `decode = RAW`",
+                        ));
+                    }
+
+                    let ident = field.ident.clone().expect("expected field to have an identifier");
+                    identifier_fields.push(ident);
+                }
+
+                if let Some(decode) = decode {
+                    match (&decode.to_string()[..], field_kind) {
+                        ("LOSSY", RequestFieldKind::Path)
+                        | ("LOSSY", RequestFieldKind::Query)
+                        | ("STRICT", RequestFieldKind::Path)
+                        | ("STRICT", RequestFieldKind::Query)
+                        | ("RAW", RequestFieldKind::Path) => {}
+                        ("RAW", RequestFieldKind::Query) => {
+                            return Err(syn::Error::new_spanned(
+                                decode,
+                                "`decode = RAW` is only supported on path fields",
+                            ));
+                        }
+                        ("LOSSY", _) | ("STRICT", _) | ("RAW", _) => {
+                            return Err(syn::Error::new_spanned(
+                                decode,
+                                "`decode` is only supported on path and query fields",
+                            ));
+                        }
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                decode,
+                                "expected one of `LOSSY`, `STRICT` or `RAW`",
+                            ));
+                        }
+                    }
+
+                    let ident = field.ident.clone().expect("expected field to have an identifier");
+                    decode_modes.push((ident, decode));
+                }
+
+                for (kind, max) in limits {
+                    if field_kind == RequestFieldKind::NewtypeRawBody {
+                        return Err(syn::Error::new_spanned(
+                            kind,
+                            "limits are not supported on raw body fields",
+                        ));
+                    }
+
+                    field_limits.push(FieldLimit {
+                        cfg_attrs: cfg_attrs(&field).into_iter().cloned().collect(),
+                        ident: field.ident.clone().expect("expected field to have an identifier"),
+                        optional: is_option(&field.ty),
+                        kind,
+                        max,
+                    });
+                }
+
+                if let Some(example) = example {
+                    let location = match field_kind {
+                        RequestFieldKind::Body
+                        | RequestFieldKind::NewtypeBody
+                        | RequestFieldKind::NewtypeRawBody => "Body",
+                        RequestFieldKind::Header
+                        | RequestFieldKind::HeaderMap
+                        | RequestFieldKind::Cookie => "Header",
+                        RequestFieldKind::Path => "Path",
+                        RequestFieldKind::Query | RequestFieldKind::QueryMap => "Query",
+                    };
+                    examples.push(FieldExample::new(&field, location, example));
+                }
+
+                if field_kind == RequestFieldKind::Body || field_kind == RequestFieldKind::Query {
+                    rename_raw_identifier(&mut field);
+                }
+
+                if field_kind == RequestFieldKind::Body {
+                    compat::relax_body_field(&mut field);
+                }
+
+                Ok(RequestField::new(field_kind, field, header, cookie))
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        if newtype_body_field.is_some() && fields.iter().any(|f| f.is_body()) {
+            // TODO: highlight conflicting fields,
+            return Err(syn::Error::new_spanned(
+                raw.request_kw,
+                "Can't have both a newtype body field and regular body fields",
+            ));
+        }
+
+        Ok(Self {
+            fields,
+            renamed_fields,
+            examples,
+            path_encode_sets,
+            decode_modes,
+            field_limits,
+            identifier_fields,
+        })
+    }
+}
+
+/// A field with a `#[ruma_api(max_len = ...)]` or `#[ruma_api(max_items = ...)]` attribute.
+pub struct FieldLimit {
+    /// The `cfg` attributes of the field.
+    pub cfg_attrs: Vec<Attribute>,
+    /// The name of the field.
+    pub ident: Ident,
+    /// Whether the field is an `Option`, which is only checked if it is `Some`.
+    pub optional: bool,
+    /// `max_len` or `max_items`.
+    pub kind: Ident,
+    /// The largest allowed length or number of items.
+    pub max: usize,
+}
+
+/// The types of fields that a request can have.
+pub enum RequestField {
+    /// JSON data in the body of the request.
+    Body(Field),
+    /// Data in an HTTP header.
+    Header(Field, Ident),
+    /// All HTTP headers that don't belong to a header field.
+    HeaderMap(Field),
+    /// Data in a cookie of the `Cookie` header, with the name of the cookie.
+    Cookie(Field, LitStr),
+    /// A specific data type in the body of the request.
+    NewtypeBody(Field),
+    /// Arbitrary bytes in the body of the request.
+    NewtypeRawBody(Field),
+    /// Data that appears in the URL path.
+    Path(Field),
+    /// Data that appears in the query string.
+    Query(Field),
+    /// Data that appears in the query string as dynamic key-value pairs.
+    QueryMap(Field),
+}
+
+impl RequestField {
+    /// Creates a new `RequestField`.
+    fn new(
+        kind: RequestFieldKind,
+        field: Field,
+        header: Option<Ident>,
+        cookie: Option<LitStr>,
+    ) -> Self {
+        match kind {
+            RequestFieldKind::Body => RequestField::Body(field),
+            RequestFieldKind::Header => {
+                RequestField::Header(field, header.expect("missing header name"))
+            }
+            RequestFieldKind::HeaderMap => RequestField::HeaderMap(field),
+            RequestFieldKind::Cookie => {
+                RequestField::Cookie(field, cookie.expect("missing cookie name"))
+            }
+            RequestFieldKind::NewtypeBody => RequestField::NewtypeBody(field),
+            RequestFieldKind::NewtypeRawBody => RequestField::NewtypeRawBody(field),
+            RequestFieldKind::Path => RequestField::Path(field),
+            RequestFieldKind::Query => RequestField::Query(field),
+            RequestFieldKind::QueryMap => RequestField::QueryMap(field),
+        }
+    }
+
+    /// Gets the kind of the request field.
+    pub fn kind(&self) -> RequestFieldKind {
+        match self {
+            RequestField::Body(..) => RequestFieldKind::Body,
+            RequestField::Header(..) => RequestFieldKind::Header,
+            RequestField::HeaderMap(..) => RequestFieldKind::HeaderMap,
+            RequestField::Cookie(..) => RequestFieldKind::Cookie,
+            RequestField::NewtypeBody(..) => RequestFieldKind::NewtypeBody,
+            RequestField::NewtypeRawBody(..) => RequestFieldKind::NewtypeRawBody,
+            RequestField::Path(..) => RequestFieldKind::Path,
+            RequestField::Query(..) => RequestFieldKind::Query,
+            RequestField::QueryMap(..) => RequestFieldKind::QueryMap,
+        }
+    }
+
+    /// The field with the part of the request it is sent in and the name it is sent under, for
+    /// body, query, header and cookie fields with a name that is known at compile time.
+    pub fn wire_name(&self) -> Option<(&Field, &'static str, String)> {
+        match self {
+            RequestField::Body(field) => Some((field, "body property", serde_key(field)?)),
+            RequestField::Query(field) => Some((field, "query parameter", serde_key(field)?)),
+            RequestField::Header(field, header) => Some((field, "header", header.to_string())),
+            RequestField::Cookie(field, cookie) => Some((field, "cookie", cookie.value())),
+            _ => None,
+        }
+    }
+
+    /// Whether or not this request field is a body kind.
+    pub fn is_body(&self) -> bool {
+        self.kind() == RequestFieldKind::Body
+    }
+
+    /// Whether or not this request field is a header kind.
+    pub fn is_header(&self) -> bool {
+        self.kind() == RequestFieldKind::Header
+    }
+
+    /// Whether or not this request field is a cookie kind.
+    pub fn is_cookie(&self) -> bool {
+        self.kind() == RequestFieldKind::Cookie
+    }
+
+    /// Whether or not this request field is a newtype body kind.
+    pub fn is_newtype_body(&self) -> bool {
+        self.kind() == RequestFieldKind::NewtypeBody
+    }
+
+    /// Whether or not this request field is a path kind.
+    pub fn is_path(&self) -> bool {
+        self.kind() == RequestFieldKind::Path
+    }
+
+    /// Whether or not this request field is a query string kind.
+    pub fn is_query(&self) -> bool {
+        self.kind() == RequestFieldKind::Query
+    }
+
+    /// Return the contained field if this request field is a body kind.
+    pub fn as_body_field(&self) -> Option<&Field> {
+        self.field_of_kind(RequestFieldKind::Body)
+    }
+
+    /// Return the contained field if this request field is a body kind.
+    pub fn as_newtype_body_field(&self) -> Option<&Field> {
+        self.field_of_kind(RequestFieldKind::NewtypeBody)
+    }
+
+    /// Return the contained field if this request field is a raw body kind.
+    pub fn as_newtype_raw_body_field(&self) -> Option<&Field> {
+        self.field_of_kind(RequestFieldKind::NewtypeRawBody)
+    }
+
+    /// Return the contained field if this request field is a query kind.
+    pub fn as_query_field(&self) -> Option<&Field> {
+        self.field_of_kind(RequestFieldKind::Query)
+    }
+
+    /// Return the contained field if this request field is a query map kind.
+    pub fn as_query_map_field(&self) -> Option<&Field> {
+        self.field_of_kind(RequestFieldKind::QueryMap)
+    }
+
+    /// Return the contained field if this request field is a header map kind.
+    pub fn as_header_map_field(&self) -> Option<&Field> {
+        self.field_of_kind(RequestFieldKind::HeaderMap)
+    }
+
+    /// Gets the inner `Field` value.
+    pub fn field(&self) -> &Field {
+        match self {
+            RequestField::Body(field)
+            | RequestField::Header(field, _)
+            | RequestField::HeaderMap(field)
+            | RequestField::Cookie(field, _)
+            | RequestField::NewtypeBody(field)
+            | RequestField::NewtypeRawBody(field)
+            | RequestField::Path(field)
+            | RequestField::Query(field)
+            | RequestField::QueryMap(field) => field,
+        }
+    }
+
+    /// Gets the inner `Field` value if it's of the provided kind.
+    pub fn field_of_kind(&self, kind: RequestFieldKind) -> Option<&Field> {
+        if self.kind() == kind {
+            Some(self.field())
+        } else {
+            None
+        }
+    }
+
+    /// Whether or not the request field has a #[wrap_incoming] attribute.
+    pub fn has_wrap_incoming_attr(&self) -> bool {
+        self.field().attrs.iter().any(|attr| {
+            attr.path.segments.len() == 1 && attr.path.segments[0].ident == "wrap_incoming"
+        })
+    }
+}
+
+/// The types of fields that a request can have, without their values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RequestFieldKind {
+    /// See the similarly named variant of `RequestField`.
+    Body,
+    /// See the similarly named variant of `RequestField`.
+    Header,
+    /// See the similarly named variant of `RequestField`.
+    HeaderMap,
+    /// See the similarly named variant of `RequestField`.
+    Cookie,
+    /// See the similarly named variant of `RequestField`.
+    NewtypeBody,
+    /// See the similarly named variant of `RequestField`.
+    NewtypeRawBody,
+    /// See the similarly named variant of `RequestField`.
+    Path,
+    /// See the similarly named variant of `RequestField`.
+    Query,
+    /// See the similarly named variant of `RequestField`.
+    QueryMap,
+}
+
+/// Whether the field has a `#[serde(default)]` or `#[serde(default = "...")]` attribute.
+fn has_serde_default(field: &Field) -> bool {
+    serde_default(field).is_some()
+}
+
+/// The expression for the default value of a field with `#[serde(default)]` or
+/// `#[serde(default = "...")]`, if any.
+pub fn serde_default(field: &Field) -> Option<TokenStream> {
+    field.attrs.iter().filter(|attr| attr.path.is_ident("serde")).find_map(|attr| {
+        match attr.parse_meta().ok()? {
+            syn::Meta::List(list) => list.nested.into_iter().find_map(|nested| match nested {
+                NestedMeta::Meta(syn::Meta::Path(path)) if path.is_ident("default") => {
+                    Some(quote!(std::default::Default::default()))
+                }
+                NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: Lit::Str(default_fn),
+                    ..
+                })) if path.is_ident("default") => {
+                    let default_fn = default_fn.parse::<syn::ExprPath>().ok()?;
+                    Some(quote!(#default_fn()))
+                }
+                _ => None,
+            }),
+            _ => None,
+        }
+    })
+}
+
+/// The key of a query field in the query string, honoring `#[serde(rename)]`.
+fn query_key(field: &Field) -> String {
+    serde_rename(field).unwrap_or_else(|| {
+        unraw(field.ident.as_ref().expect("expected field to have an identifier"))
+    })
+}
+
+/// The name given to a field with `#[serde(rename = "...")]`, if any.
+fn serde_rename(field: &Field) -> Option<String> {
+    field.attrs.iter().filter(|attr| attr.path.is_ident("serde")).find_map(|attr| {
+        match attr.parse_meta().ok()? {
+            syn::Meta::List(list) => list.nested.into_iter().find_map(|nested| match nested {
+                NestedMeta::Meta(syn::Meta::NameValue(syn::MetaNameValue {
+                    path,
+                    lit: Lit::Str(name),
+                    ..
+                })) if path.is_ident("rename") => Some(name.value()),
+                _ => None,
+            }),
+            _ => None,
+        }
+    })
+}
@@ -0,0 +1,496 @@
+//! Analysis of the `response` section of the procedural macro.
+
+use std::{convert::TryFrom, mem};
+
+use syn::{parse_quote, Field, Ident, LitStr};
+
+use super::{
+    check_cookie_name, compat, rename_raw_identifier, serde_key, FieldExample, RenamedField,
+};
+use crate::api::{
+    attribute::{Meta, MetaNameValue},
+    cfg_attrs,
+    parse::RawResponse,
+    vec_inner,
+};
+
+/// The analyzed `response` section of the macro.
+pub struct Response {
+    /// The fields of the response.
+    pub fields: Vec<ResponseField>,
+    /// The body fields with a `#[ruma_api(renamed_from = "...")]` attribute.
+    pub renamed_fields: Vec<RenamedField>,
+    /// The fields with a `#[ruma_api(example = "...")]` attribute.
+    pub examples: Vec<FieldExample>,
+}
+
+impl Response {
+    /// The fields with a `#[ruma_api(example = "...")]` attribute.
+    pub fn examples(&self) -> &[FieldExample] {
+        &self.examples
+    }
+
+    /// Whether or not this response has any data in the HTTP body.
+    pub fn has_body_fields(&self) -> bool {
+        self.fields.iter().any(|field| field.is_body())
+    }
+
+    /// Whether or not this response has any data in HTTP headers.
+    pub fn has_header_fields(&self) -> bool {
+        self.fields.iter().any(|field| field.is_header() || field.is_cookie())
+            || self.header_map_field().is_some()
+    }
+
+    /// Returns the header map field.
+    pub fn header_map_field(&self) -> Option<&Field> {
+        self.fields.iter().find_map(ResponseField::as_header_map_field)
+    }
+
+    /// Whether this response only has header fields, so its HTTP body is empty.
+    pub fn is_header_only(&self) -> bool {
+        self.has_header_fields()
+            && !self.has_body_fields()
+            && self.newtype_body_field().is_none()
+            && self.newtype_raw_body_field().is_none()
+    }
+
+    /// Whether any field has a #[wrap_incoming] attribute.
+    pub fn uses_wrap_incoming(&self) -> bool {
+        self.fields.iter().any(|f| f.has_wrap_incoming_attr())
+    }
+
+    /// Gets the newtype body field, if this response has one.
+    pub fn newtype_body_field(&self) -> Option<&Field> {
+        self.fields.iter().find_map(ResponseField::as_newtype_body_field)
+    }
+
+    /// Gets the newtype raw body field, if this response has one.
+    pub fn newtype_raw_body_field(&self) -> Option<&Field> {
+        self.fields.iter().find_map(ResponseField::as_newtype_raw_body_field)
+    }
+}
+
+impl TryFrom<RawResponse> for Response {
+    type Error = syn::Error;
+
+    fn try_from(raw: RawResponse) -> syn::Result<Self> {
+        let mut newtype_body_field = None;
+        let mut header_map_field = None;
+        let mut renamed_fields = Vec::new();
+        let mut examples = Vec::new();
+
+        let fields = raw
+            .fields
+            .into_iter()
+            .map(|mut field| {
+                let mut field_kind = None;
+                let mut kind_attr: Option<Ident> = None;
+                let mut header = None;
+                let mut cookie = None;
+                let mut renamed_from = None;
+                let mut example = None;
+                let mut partial = false;
+                let mut batch = None;
+                let mut deferred = None;
+                let mut fallback = None;
+
+                for attr in mem::replace(&mut field.attrs, Vec::new()) {
+                    let metas = match Meta::list_from_attribute(&attr)? {
+                        Some(metas) => metas,
+                        None => {
+                            field.attrs.push(attr);
+                            continue;
+                        }
+                    };
+
+                    for meta in metas {
+                        let meta = match meta {
+                            Meta::NameValue(MetaNameValue { name, value })
+                                if name == "renamed_from" =>
+                            {
+                                renamed_from = Some(value.into_lit_str()?);
+                                continue;
+                            }
+                            Meta::NameValue(MetaNameValue { name, value }) if name == "example" => {
+                                example = Some(value.into_lit_str()?);
+                                continue;
+                            }
+                            Meta::NameValue(MetaNameValue { name, value })
+                                if name == "fallback" =>
+                            {
+                                fallback = Some(value.into_ident()?);
+                                continue;
+                            }
+                            Meta::Word(ident) if ident == "partial" => {
+                                partial = true;
+                                continue;
+                            }
+                            Meta::Word(ident) if ident == "batch" => {
+                                batch = Some(ident);
+                                continue;
+                            }
+                            Meta::Word(ident) if ident == "deferred" => {
+                                deferred = Some(ident);
+                                continue;
+                            }
+                            meta => meta,
+                        };
+
+                        if let Some(previous) = &kind_attr {
+                            let ident =
+                                field.ident.as_ref().expect("expected field to have an identifier");
+                            return Err(syn::Error::new_spanned(
+                                attr,
+                                format!(
+                                    "field `{}` can't be both a `{}` field and a `{}` field",
+                                    ident,
+                                    previous,
+                                    meta.name(),
+                                ),
+                            ));
+                        }
+                        kind_attr = Some(meta.name().clone());
+
+                        field_kind = Some(match meta {
+                            Meta::Word(ident) => match &ident.to_string()[..] {
+                                s @ "body" | s @ "raw_body" => {
+                                    if let Some(f) = &newtype_body_field {
+                                        let mut error = syn::Error::new_spanned(
+                                            field,
+                                            "There can only be one newtype body field",
+                                        );
+                                        error.combine(syn::Error::new_spanned(
+                                            f,
+                                            "Previous newtype body field",
+                                        ));
+                                        return Err(error);
+                                    }
+
+                                    newtype_body_field = Some(field.clone());
+                                    match s {
+                                        "body" => ResponseFieldKind::NewtypeBody,
+                                        "raw_body" => ResponseFieldKind::NewtypeRawBody,
+                                        _ => unreachable!(),
+                                    }
+                                }
+                                "header_map" => {
+                                    if let Some(f) = &header_map_field {
+                                        let mut error = syn::Error::new_spanned(
+                                            field,
+                                            "There can only be one header map field",
+                                        );
+                                        error.combine(syn::Error::new_spanned(
+                                            f,
+                                            "Previous header map field",
+                                        ));
+                                        return Err(error);
+                                    }
+
+                                    header_map_field = Some(field.clone());
+                                    ResponseFieldKind::HeaderMap
+                                }
+                                _ => {
+                                    return Err(syn::Error::new_spanned(
+                                        ident,
+                                        "Invalid #[ruma_api] argument, expected one of `body`, `raw_body`, \
+                                         `header_map`, `partial`, `batch`, `deferred`",
+                                    ));
+                                }
+                            },
+                            Meta::NameValue(MetaNameValue { name, value }) if name == "cookie" => {
+                                let name = value.into_lit_str()?;
+                                check_cookie_name(&name)?;
+                                cookie = Some(name);
+                                ResponseFieldKind::Cookie
+                            }
+                            Meta::NameValue(MetaNameValue { name, value }) => {
+                                if name != "header" {
+                                    return Err(syn::Error::new_spanned(
+                                        name,
+                                        "Invalid #[ruma_api] argument with value, expected `header`, \
+                                         `cookie`, `renamed_from`, `example` or `fallback`",
+                                    ));
+                                }
+
+                                header = Some(value.into_ident()?);
+                                ResponseFieldKind::Header
+                            }
+                        });
+                    }
+                }
+
+                let field_kind = field_kind.unwrap_or(ResponseFieldKind::Body);
+
+                match field_kind {
+                    ResponseFieldKind::Body | ResponseFieldKind::Header => {}
+                    _ => {
+                        if let Some(attr) = cfg_attrs(&field).first() {
+                            return Err(syn::Error::new_spanned(
+                                attr,
+                                "`cfg` attributes are only supported on body and header fields",
+                            ));
+                        }
+                    }
+                }
+
+                if let Some(old_name) = renamed_from {
+                    if field_kind != ResponseFieldKind::Body {
+                        return Err(syn::Error::new_spanned(
+                            old_name,
+                            "`renamed_from` is only supported on body fields",
+                        ));
+                    }
+
+                    renamed_fields.push(RenamedField::new(&mut field, old_name));
+                }
+
+                if partial {
+                    if field_kind != ResponseFieldKind::Body {
+                        return Err(syn::Error::new_spanned(
+                            field,
+                            "`partial` is only supported on body fields",
+                        ));
+                    }
+
+                    field
+                        .attrs
+                        .push(parse_quote!(#[wrap_incoming(with ruma_api::partial::Partial)]));
+                }
+
+                if let Some(batch) = batch {
+                    if field_kind != ResponseFieldKind::Body {
+                        return Err(syn::Error::new_spanned(
+                            batch,
+                            "`batch` is only supported on body fields",
+                        ));
+                    }
+
+                    if partial {
+                        return Err(syn::Error::new_spanned(
+                            batch,
+                            "`batch` can't be combined with `partial`",
+                        ));
+                    }
+
+                    if deferred.is_some() {
+                        return Err(syn::Error::new_spanned(
+                            batch,
+                            "`batch` can't be combined with `deferred`",
+                        ));
+                    }
+
+                    let item = vec_inner(&field.ty).cloned().ok_or_else(|| {
+                        syn::Error::new_spanned(
+                            &field.ty,
+                            "`batch` fields need to be of type `Vec<T>`",
+                        )
+                    })?;
+                    field
+                        .attrs
+                        .push(parse_quote!(#[wrap_incoming(#item with ruma_api::partial::Partial)]));
+                }
+
+                if let Some(deferred) = deferred {
+                    if field_kind != ResponseFieldKind::Body {
+                        return Err(syn::Error::new_spanned(
+                            deferred,
+                            "`deferred` is only supported on body fields",
+                        ));
+                    }
+
+                    if partial {
+                        return Err(syn::Error::new_spanned(
+                            deferred,
+                            "`deferred` can't be combined with `partial`",
+                        ));
+                    }
+
+                    field
+                        .attrs
+                        .push(parse_quote!(#[wrap_incoming(with ruma_api::deferred::Deferred)]));
+                }
+
+                if let Some(legacy) = fallback {
+                    if field_kind != ResponseFieldKind::NewtypeBody {
+                        return Err(syn::Error::new_spanned(
+                            legacy,
+                            "`fallback` is only supported on newtype body fields",
+                        ));
+                    }
+
+                    field.attrs.push(
+                        parse_quote!(#[wrap_incoming(with ruma_api::fallback::Fallback<#legacy>)]),
+                    );
+                }
+
+                if let Some(example) = example {
+                    let location = match field_kind {
+                        ResponseFieldKind::Body
+                        | ResponseFieldKind::NewtypeBody
+                        | ResponseFieldKind::NewtypeRawBody => "Body",
+                        ResponseFieldKind::Header
+                        | ResponseFieldKind::HeaderMap
+                        | ResponseFieldKind::Cookie => "Header",
+                    };
+                    examples.push(FieldExample::new(&field, location, example));
+                }
+
+                if field_kind == ResponseFieldKind::Body {
+                    rename_raw_identifier(&mut field);
+                    compat::relax_body_field(&mut field);
+                }
+
+                Ok(match field_kind {
+                    ResponseFieldKind::Body => ResponseField::Body(field),
+                    ResponseFieldKind::Header => {
+                        ResponseField::Header(field, header.expect("missing header name"))
+                    }
+                    ResponseFieldKind::HeaderMap => ResponseField::HeaderMap(field),
+                    ResponseFieldKind::Cookie => {
+                        ResponseField::Cookie(field, cookie.expect("missing cookie name"))
+                    }
+                    ResponseFieldKind::NewtypeBody => ResponseField::NewtypeBody(field),
+                    ResponseFieldKind::NewtypeRawBody => ResponseField::NewtypeRawBody(field),
+                })
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        if newtype_body_field.is_some() && fields.iter().any(|f| f.is_body()) {
+            // TODO: highlight conflicting fields,
+            return Err(syn::Error::new_spanned(
+                raw.response_kw,
+                "Can't have both a newtype body field and regular body fields",
+            ));
+        }
+
+        Ok(Self { fields, renamed_fields, examples })
+    }
+}
+
+/// The types of fields that a response can have.
+pub enum ResponseField {
+    /// JSON data in the body of the response.
+    Body(Field),
+    /// Data in an HTTP header.
+    Header(Field, Ident),
+    /// All HTTP headers that don't belong to a header field.
+    HeaderMap(Field),
+    /// Data in a `Set-Cookie` header, with the name of the cookie.
+    Cookie(Field, LitStr),
+    /// A specific data type in the body of the response.
+    NewtypeBody(Field),
+    /// Arbitrary bytes in the body of the response.
+    NewtypeRawBody(Field),
+}
+
+impl ResponseField {
+    /// Gets the inner `Field` value.
+    pub fn field(&self) -> &Field {
+        match self {
+            ResponseField::Body(field)
+            | ResponseField::Header(field, _)
+            | ResponseField::HeaderMap(field)
+            | ResponseField::Cookie(field, _)
+            | ResponseField::NewtypeBody(field)
+            | ResponseField::NewtypeRawBody(field) => field,
+        }
+    }
+
+    /// The field with the part of the response it is sent in and the name it is sent under, for
+    /// body, header and cookie fields with a name that is known at compile time.
+    pub fn wire_name(&self) -> Option<(&Field, &'static str, String)> {
+        match self {
+            ResponseField::Body(field) => Some((field, "body property", serde_key(field)?)),
+            ResponseField::Header(field, header) => Some((field, "header", header.to_string())),
+            ResponseField::Cookie(field, cookie) => Some((field, "cookie", cookie.value())),
+            _ => None,
+        }
+    }
+
+    /// Whether or not this response field is a body kind.
+    pub fn is_body(&self) -> bool {
+        self.as_body_field().is_some()
+    }
+
+    /// Whether or not this response field is a header kind.
+    pub fn is_header(&self) -> bool {
+        match self {
+            ResponseField::Header(..) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether or not this response field is a cookie kind.
+    pub fn is_cookie(&self) -> bool {
+        self.as_cookie_field().is_some()
+    }
+
+    /// Whether or not this response field is a newtype body kind.
+    pub fn is_newtype_body(&self) -> bool {
+        self.as_newtype_body_field().is_some()
+    }
+
+    /// Return the contained field if this response field is a body kind.
+    pub fn as_body_field(&self) -> Option<&Field> {
+        match self {
+            ResponseField::Body(field) => Some(field),
+            _ => None,
+        }
+    }
+
+    /// Return the contained field if this response field is a newtype body kind.
+    pub fn as_newtype_body_field(&self) -> Option<&Field> {
+        match self {
+            ResponseField::NewtypeBody(field) => Some(field),
+            _ => None,
+        }
+    }
+
+    /// Return the contained field if this response field is a header map kind.
+    pub fn as_header_map_field(&self) -> Option<&Field> {
+        match self {
+            ResponseField::HeaderMap(field) => Some(field),
+            _ => None,
+        }
+    }
+
+    /// Return the contained field if this response field is a cookie kind.
+    pub fn as_cookie_field(&self) -> Option<&Field> {
+        match self {
+            ResponseField::Cookie(field, _) => Some(field),
+            _ => None,
+        }
+    }
+
+    /// Return the contained field if this response field is a newtype raw body kind.
+    pub fn as_newtype_raw_body_field(&self) -> Option<&Field> {
+        match self {
+            ResponseField::NewtypeRawBody(field) => Some(field),
+            _ => None,
+        }
+    }
+
+    /// Whether or not the reponse field has a #[wrap_incoming] attribute.
+    pub fn has_wrap_incoming_attr(&self) -> bool {
+        self.field().attrs.iter().any(|attr| {
+            attr.path.segments.len() == 1 && attr.path.segments[0].ident == "wrap_incoming"
+        })
+    }
+}
+
+/// The types of fields that a response can have, without their values.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ResponseFieldKind {
+    /// See the similarly named variant of `ResponseField`.
+    Body,
+    /// See the similarly named variant of `ResponseField`.
+    Header,
+    /// See the similarly named variant of `ResponseField`.
+    HeaderMap,
+    /// See the similarly named variant of `ResponseField`.
+    Cookie,
+    /// See the similarly named variant of `ResponseField`.
+    NewtypeBody,
+    /// See the similarly named variant of `ResponseField`.
+    NewtypeRawBody,
+}
@@ -2,16 +2,75 @@
 
 use syn::{
     parse::{Parse, ParseStream},
-    Ident, Token,
+    punctuated::Punctuated,
+    Ident, LitInt, LitStr, Token,
 };
 
-/// Like syn::MetaNameValue, but expects an identifier as the value. Also, we don't care about the
-/// the span of the equals sign, so we don't have the `eq_token` field from syn::MetaNameValue.
+/// Like syn::MetaNameValue, but expects an identifier, a string literal or an integer literal as the
+/// value. Also, we
+/// don't care about the the span of the equals sign, so we don't have the `eq_token` field from
+/// syn::MetaNameValue.
 pub struct MetaNameValue {
     /// The part left of the equals sign
     pub name: Ident,
     /// The part right of the equals sign
-    pub value: Ident,
+    pub value: MetaValue,
+}
+
+/// The value of a `MetaNameValue`.
+pub enum MetaValue {
+    /// An identifier, like `CONTENT_TYPE` in `#[ruma_api(header = CONTENT_TYPE)]`
+    Ident(Ident),
+    /// A string literal, like `"chunk"` in `#[ruma_api(renamed_from = "chunk")]`
+    Str(LitStr),
+    /// An integer literal, like `255` in `#[ruma_api(max_len = 255)]`
+    Int(LitInt),
+}
+
+impl MetaValue {
+    /// Returns the identifier, or an error if the value is a literal.
+    pub fn into_ident(self) -> syn::Result<Ident> {
+        match self {
+            MetaValue::Ident(ident) => Ok(ident),
+            MetaValue::Str(lit) => Err(syn::Error::new_spanned(lit, "expected an identifier")),
+            MetaValue::Int(lit) => Err(syn::Error::new_spanned(lit, "expected an identifier")),
+        }
+    }
+
+    /// Returns the string literal, or an error if the value is something else.
+    pub fn into_lit_str(self) -> syn::Result<LitStr> {
+        match self {
+            MetaValue::Str(lit) => Ok(lit),
+            MetaValue::Ident(ident) => {
+                Err(syn::Error::new_spanned(ident, "expected a string literal"))
+            }
+            MetaValue::Int(lit) => Err(syn::Error::new_spanned(lit, "expected a string literal")),
+        }
+    }
+
+    /// Returns the value of the integer literal, or an error if the value is something else or
+    /// doesn't fit in a `usize`.
+    pub fn into_usize(self) -> syn::Result<usize> {
+        match self {
+            MetaValue::Int(lit) => lit.base10_parse(),
+            MetaValue::Ident(ident) => {
+                Err(syn::Error::new_spanned(ident, "expected an integer literal"))
+            }
+            MetaValue::Str(lit) => Err(syn::Error::new_spanned(lit, "expected an integer literal")),
+        }
+    }
+}
+
+impl Parse for MetaValue {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        if input.peek(LitStr) {
+            input.parse().map(MetaValue::Str)
+        } else if input.peek(LitInt) {
+            input.parse().map(MetaValue::Int)
+        } else {
+            input.parse().map(MetaValue::Ident)
+        }
+    }
 }
 
 /// Like syn::Meta, but only parses ruma_api attributes
@@ -37,6 +96,27 @@ impl Meta {
     }
 }
 
+impl Meta {
+    /// The word, or the name of the name-value pair.
+    pub fn name(&self) -> &Ident {
+        match self {
+            Meta::Word(ident) => ident,
+            Meta::NameValue(MetaNameValue { name, .. }) => name,
+        }
+    }
+
+    /// Like `from_attribute`, but parses a comma-separated list of arguments, like
+    /// `#[ruma_api(path, encode = STRICT)]`.
+    pub fn list_from_attribute(attr: &syn::Attribute) -> syn::Result<Option<Vec<Self>>> {
+        if attr.path.is_ident("ruma_api") {
+            attr.parse_args_with(Punctuated::<Self, Token![,]>::parse_terminated)
+                .map(|metas| Some(metas.into_iter().collect()))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
 impl Parse for Meta {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let ident = input.parse()?;
@@ -0,0 +1,1430 @@
+//! The code generation stage of the `ruma_api` macro: producing the endpoint's types and trait
+//! implementations from the analyzed input.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use syn::{spanned::Spanned, Field, Ident};
+
+use super::{
+    analyze::{serde_default, Api, FieldExample, RenamedField},
+    cfg_attrs, is_identifier_type, is_integer, is_option,
+    parse::ModuleDecl,
+    unraw,
+};
+
+mod request;
+mod response;
+
+impl ToTokens for Api {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let description = &self.metadata.description;
+        let method = &self.metadata.method;
+        // We don't (currently) use this literal as a literal in the generated code. Instead we just
+        // put it into doc comments, for which the span information is irrelevant. So we can work
+        // with only the literal's value from here on.
+        let name = &self.metadata.name.value();
+        let path = &self.metadata.path;
+        let rate_limited = &self.metadata.rate_limited;
+        let requires_authentication = &self.metadata.requires_authentication;
+
+        let request_type = &self.request;
+        let response_type = &self.response;
+        let request_examples = FieldExample::to_vec(self.request.examples());
+        let response_examples = FieldExample::to_vec(self.response.examples());
+        let items = &self.items;
+
+        let request_try_from_type = if self.request.uses_wrap_incoming() {
+            quote!(IncomingRequest)
+        } else {
+            quote!(Request)
+        };
+
+        let response_try_from_type = if self.response.uses_wrap_incoming() {
+            quote!(IncomingResponse)
+        } else {
+            quote!(Response)
+        };
+
+        let request_from_incoming = if self.request.uses_wrap_incoming() {
+            from_incoming(quote!(Request), quote!(IncomingRequest))
+        } else {
+            TokenStream::new()
+        };
+
+        let response_from_incoming = if self.response.uses_wrap_incoming() {
+            from_incoming(quote!(Response), quote!(IncomingResponse))
+        } else {
+            TokenStream::new()
+        };
+
+        let (extract_request_path, url_set_path, parse_request_path, check_request_path) =
+            if self.request.has_path_fields() {
+                let path_str = path.value();
+
+                let path_segments = path_str[1..].split('/');
+
+                // Only the segments that hold path parameters are extracted, in a single pass
+                // over the request path without allocating. Each one is found by skipping the
+                // segments after the previous parameter.
+                let mut next_index = 0;
+                let path_param_segments = path_segments
+                    .clone()
+                    .enumerate()
+                    .filter(|(_, segment)| segment.starts_with(':'))
+                    .map(|(i, _)| {
+                        let skip = i - next_index;
+                        next_index = i + 1;
+                        if skip == 0 {
+                            quote!(path_segments.next().unwrap_or(""))
+                        } else {
+                            quote!(path_segments.nth(#skip).unwrap_or(""))
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                let path_param_count = path_param_segments.len();
+
+                let extract_tokens = quote! {
+                    let path_params: [&str; #path_param_count] = {
+                        let mut path_segments =
+                            request.uri().path().get(1..).unwrap_or("").split('/');
+                        [#(#path_param_segments),*]
+                    };
+                };
+                let path_segment_push = path_segments.clone().map(|segment| {
+                    if segment.starts_with(':') {
+                        let path_var = &segment[1..];
+                        let path_var_ident = self.request.path_field_ident(path_var);
+                        let is_raw = match self.request.decode_mode(path_var) {
+                            Some(mode) => mode == "RAW",
+                            None => false,
+                        };
+                        if is_raw {
+                            let encode_set =
+                                self.request.path_encode_set(path_var).cloned().unwrap_or_else(
+                                    || Ident::new("PATH_SEGMENT", Span::call_site()),
+                                );
+                            return quote! {
+                                ruma_api::path::push_bytes_segment_with(
+                                    &mut uri,
+                                    std::convert::AsRef::<[u8]>::as_ref(&request.#path_var_ident),
+                                    ruma_api::path::#encode_set,
+                                );
+                            };
+                        }
+
+                        match self.request.path_encode_set(path_var) {
+                            Some(encode_set) => quote! {
+                                ruma_api::path::push_segment_with(
+                                    &mut uri,
+                                    &request.#path_var_ident,
+                                    ruma_api::path::#encode_set,
+                                );
+                            },
+                            None => quote! {
+                                ruma_api::path::push_segment(&mut uri, &request.#path_var_ident);
+                            },
+                        }
+                    } else {
+                        let segment = format!("/{}", segment);
+                        quote! {
+                            uri.push_str(#segment);
+                        }
+                    }
+                });
+
+                let set_tokens = quote! {
+                    #(#path_segment_push)*
+                };
+
+                let path_fields =
+                path_segments.filter(|s| s.starts_with(':')).enumerate().map(|(i, segment)| {
+                    let path_var = &segment[1..];
+                    let path_var_ident = self.request.path_field_ident(path_var);
+
+                    // `RAW` fields get the percent-decoded bytes, `STRICT` fields reject
+                    // segments that aren't valid UTF-8 and by default, invalid sequences are
+                    // replaced with U+FFFD.
+                    let decode = match self.request.decode_mode(path_var) {
+                        Some(mode) if mode == "RAW" => quote! {
+                            let decoded: std::borrow::Cow<'_, [u8]> =
+                                ruma_api::exports::percent_encoding::percent_decode(segment)
+                                    .into();
+                        },
+                        Some(mode) if mode == "STRICT" => quote! {
+                            let decoded =
+                                match ruma_api::exports::percent_encoding::percent_decode(segment)
+                                    .decode_utf8()
+                                {
+                                    Ok(decoded) => decoded,
+                                    Err(err) => {
+                                        return Err(
+                                            RequestDeserializationError::new(err, &request)
+                                                .into()
+                                        );
+                                    }
+                                };
+                        },
+                        _ => quote! {
+                            let decoded =
+                                ruma_api::exports::percent_encoding::percent_decode(segment)
+                                .decode_utf8_lossy();
+                        },
+                    };
+
+                    let check_identifier = if self.request.is_identifier(path_var) {
+                        quote! {
+                            if let Err(err) = ruma_api::path::check_identifier(&decoded) {
+                                return Err(RequestDeserializationError::new(err, &request).into());
+                            }
+                        }
+                    } else {
+                        TokenStream::new()
+                    };
+
+                    let ty = &self
+                        .request
+                        .path_field(path_var)
+                        .expect("expected a path field for every path parameter")
+                        .ty;
+
+                    // Integers don't implement `TryFrom<&str>`, and `FromStr` doesn't tell
+                    // overflows apart from invalid digits. The errors of the identifiers'
+                    // `TryFrom<&str>` don't say which kind of identifier was expected.
+                    let convert = if is_integer(ty) {
+                        quote!(ruma_api::path::parse_integer(decoded.deref()))
+                    } else if is_identifier_type(ty) {
+                        quote!(ruma_api::path::parse_identifier(decoded.deref()))
+                    } else {
+                        quote!(std::convert::TryFrom::try_from(decoded.deref()))
+                    };
+
+                    let value = quote! {
+                        {
+                            use std::ops::Deref as _;
+                            use ruma_api::error::RequestDeserializationError;
+
+                            let segment = path_params[#i].as_bytes();
+                            #decode
+                            #check_identifier
+                            match #convert {
+                                Ok(val) => val,
+                                Err(err) => {
+                                    return Err(
+                                        RequestDeserializationError::new(err, &request).into()
+                                    );
+                                }
+                            }
+                        }
+                    };
+                    (quote!(#path_var_ident: #value,), quote!(let _: #ty = #value;))
+                });
+                let (parse_fields, check_fields): (Vec<_>, Vec<_>) = path_fields.unzip();
+
+                let parse_tokens = quote! {
+                    #(#parse_fields)*
+                };
+                let check_tokens = quote! {
+                    #(#check_fields)*
+                };
+
+                (extract_tokens, set_tokens, parse_tokens, check_tokens)
+            } else {
+                let set_tokens = quote! {
+                    uri.push_str(metadata.path());
+                };
+                (TokenStream::new(), set_tokens, TokenStream::new(), TokenStream::new())
+            };
+
+        // Room for the placeholder host, the path and some parameters. This is only an estimate,
+        // the string grows if it isn't enough.
+        let uri_capacity = "http://invalid-host-please-change".len()
+            + path.value().len()
+            + 32 * (self.request.path_field_count() + self.request.has_query_fields() as usize);
+
+        // The query string and the JSON body are serialized before anything else, from values that
+        // the request can be reassembled from if that fails.
+        let request_query = if let Some(field) = self.request.query_map_field() {
+            if self.request.has_query_fields() {
+                let request_query_init_fields = self.request.request_query_init_fields();
+                Some(quote!(RequestQuery { #request_query_init_fields }))
+            } else {
+                let field_name = field.ident.as_ref().expect("expected field to have identifier");
+                Some(quote!(RequestQuery(request.#field_name)))
+            }
+        } else if self.request.has_query_fields() {
+            let request_query_init_fields = self.request.request_query_init_fields();
+            Some(quote!(RequestQuery { #request_query_init_fields }))
+        } else {
+            None
+        };
+
+        let url_set_querystring = if let Some(field) = self.request.query_map_field() {
+            let field_name = field.ident.as_ref().expect("expected field to have identifier");
+            let field_type = &field.ty;
+
+            let extend_query_str = if self.request.has_query_fields() {
+                quote! {
+                    let query_map: Vec<(std::string::String, std::string::String)> =
+                        request.#field_name.into_iter().collect();
+                    if !query_map.is_empty() {
+                        if !query_str.is_empty() {
+                            query_str.push('&');
+                        }
+                        query_str.push_str(
+                            &ruma_api::manual::serialize_query(&query_map)
+                                .expect("string pairs to be serializable as a query string"),
+                        );
+                    }
+                }
+            } else {
+                TokenStream::new()
+            };
+
+            quote! {
+                // This function exists so that the compiler will throw an
+                // error when the type of the field with the query_map
+                // attribute doesn't implement IntoIterator<Item = (String, String)>
+                //
+                // This is necessary because the serde_urlencoded::to_string
+                // call will result in a runtime error when the type cannot be
+                // encoded as a list key-value pairs (?key1=value1&key2=value2)
+                //
+                // By asserting that it implements the iterator trait, we can
+                // ensure that it won't fail.
+                fn assert_trait_impl<T>()
+                where
+                    T: std::iter::IntoIterator<Item = (std::string::String, std::string::String)>,
+                {}
+                assert_trait_impl::<#field_type>();
+
+                let mut query_str = query_str;
+                #extend_query_str
+
+                if !query_str.is_empty() {
+                    uri.push('?');
+                    uri.push_str(&query_str);
+                }
+            }
+        } else if self.request.has_query_fields() {
+            quote! {
+                uri.push('?');
+                uri.push_str(&query_str);
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let strict_query_keys = self.request.strict_query_keys();
+        let check_strict_query = if strict_query_keys.is_empty() {
+            TokenStream::new()
+        } else {
+            quote! {
+                for key in &[#(#strict_query_keys),*] {
+                    if let Err(err) =
+                        ruma_api::query::check_utf8(request.uri().query().unwrap_or(""), key)
+                    {
+                        return Err(
+                            ruma_api::error::RequestDeserializationError::new(err, &request).into()
+                        );
+                    }
+                }
+            }
+        };
+
+        // serde would report a duplicate key as a "duplicate field" error at best, so duplicates
+        // of the query fields' keys are rejected with a dedicated error or dropped up front.
+        let query_field_keys = self.request.query_field_keys();
+        let last_wins = match &self.metadata.duplicate_query_parameters {
+            Some(mode) => mode == "LastWins",
+            None => false,
+        };
+        let (check_duplicate_query, request_query_str) = if last_wins {
+            (
+                TokenStream::new(),
+                quote! {
+                    ruma_api::query::keep_last(
+                        request.uri().query().unwrap_or(""),
+                        &[#(#query_field_keys),*],
+                    )
+                },
+            )
+        } else {
+            (
+                quote! {
+                    if let Err(err) = ruma_api::query::check_unique(
+                        request.uri().query().unwrap_or(""),
+                        &[#(#query_field_keys),*],
+                    ) {
+                        return Err(
+                            ruma_api::error::RequestDeserializationError::new(err, &request).into()
+                        );
+                    }
+                },
+                quote!(request.uri().query().unwrap_or("")),
+            )
+        };
+
+        let extract_typed_request_query = quote! {
+            #check_strict_query
+            #check_duplicate_query
+
+            let request_query: RequestQuery =
+                match ruma_api::exports::serde_urlencoded::from_str(&#request_query_str) {
+                    Ok(query) => query,
+                    Err(err) => {
+                        return Err(
+                            ruma_api::error::RequestDeserializationError::new(err, &request)
+                                .into()
+                        );
+                    }
+                };
+        };
+
+        let extract_request_query = if let Some(field) = self.request.query_map_field() {
+            if self.request.has_query_fields() {
+                quote! {
+                    #extract_typed_request_query
+
+                    let request_query_map: Vec<(std::string::String, std::string::String)> =
+                        match ruma_api::exports::serde_urlencoded::from_str(
+                            &request.uri().query().unwrap_or("")
+                        ) {
+                            Ok(query) => query,
+                            Err(err) => {
+                                return Err(
+                                    ruma_api::error::RequestDeserializationError::new(err, &request)
+                                        .into()
+                                );
+                            }
+                        };
+                }
+            } else {
+                let field_type = &field.ty;
+                quote! {
+                    let request_query: #field_type = match ruma_api::exports::serde_urlencoded::from_str(
+                        &request.uri().query().unwrap_or("")
+                    ) {
+                        Ok(query) => query,
+                        Err(err) => {
+                            return Err(
+                                ruma_api::error::RequestDeserializationError::new(err, &request)
+                                    .into()
+                            );
+                        }
+                    };
+                }
+            }
+        } else if self.request.has_query_fields() {
+            extract_typed_request_query
+        } else {
+            TokenStream::new()
+        };
+        let extract_request_query = if extract_request_query.is_empty() {
+            extract_request_query
+        } else {
+            quote! {
+                if let Err(err) =
+                    ruma_api::query::check_encoding(request.uri().query().unwrap_or(""))
+                {
+                    return Err(
+                        ruma_api::error::RequestDeserializationError::new(err, &request).into()
+                    );
+                }
+
+                #extract_request_query
+            }
+        };
+
+        let parse_request_query = if let Some(field) = self.request.query_map_field() {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+
+            if self.request.has_query_fields() {
+                let request_init_query_fields = self.request.request_init_query_fields();
+                let query_field_keys = self.request.query_field_keys();
+
+                quote! {
+                    #request_init_query_fields
+                    #field_name: request_query_map
+                        .into_iter()
+                        .filter(|(key, _)| ![#(#query_field_keys),*].contains(&key.as_str()))
+                        .collect(),
+                }
+            } else {
+                quote! {
+                    #field_name: request_query,
+                }
+            }
+        } else {
+            self.request.request_init_query_fields()
+        };
+
+        let add_headers_to_request = if self.request.has_header_fields() {
+            let add_headers = self.request.add_headers_to_request();
+            quote! {
+                let headers = http_request.headers_mut();
+                #add_headers
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let extract_request_headers = if self.request.has_header_fields() {
+            quote! {
+                let headers = request.headers();
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        // Requests with a method like GET that usually has no body can leave out the body of an
+        // endpoint that accepts several methods, which is then parsed as an empty object.
+        let request_body_slice = if self.metadata.other_methods.is_empty() {
+            quote!(request.body().as_slice())
+        } else {
+            quote! {
+                if request.body().is_empty() { b"{}" } else { request.body().as_slice() }
+            }
+        };
+
+        let request_body_slice =
+            RenamedField::prefer_new_names(&self.request.renamed_fields, request_body_slice);
+        let extract_request_body =
+            if self.request.has_body_fields() || self.request.newtype_body_field().is_some() {
+                quote! {
+                    let request_body: <RequestBody as ruma_api::Outgoing>::Incoming =
+                        match ruma_api::error::from_json_slice(#request_body_slice) {
+                            Ok(body) => body,
+                            Err(err) => {
+                                return Err(
+                                    ruma_api::error::RequestDeserializationError::new(err, &request)
+                                        .into()
+                                );
+                            }
+                        };
+                }
+            } else {
+                TokenStream::new()
+            };
+
+        let parse_request_headers = if self.request.has_header_fields() {
+            self.request.parse_headers_from_request()
+        } else {
+            TokenStream::new()
+        };
+        let check_request_headers = self.request.check_headers_in_request();
+
+        // The module used to serialize JSON bodies, which has `to_vec` and `to_writer` functions.
+        let json = if self.metadata.canonical_json {
+            quote!(ruma_api::canonical_json)
+        } else {
+            quote!(ruma_api::exports::serde_json)
+        };
+
+        let request_body_value = if self.request.has_body_fields()
+            || self.request.newtype_body_field().is_some()
+        {
+            let request_body_initializers = if let Some(field) = self.request.newtype_body_field() {
+                let field_name =
+                    field.ident.as_ref().expect("expected field to have an identifier");
+                quote! { (request.#field_name) }
+            } else {
+                let initializers = self.request.request_body_init_fields();
+                quote! { { #initializers } }
+            };
+
+            Some(quote!(RequestBody #request_body_initializers))
+        } else {
+            None
+        };
+
+        let request_body = if let Some(field) = self.request.newtype_raw_body_field() {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let span = field.ty.span();
+            quote_spanned!(span=> std::convert::Into::<Vec<u8>>::into(request.#field_name))
+        } else if request_body_value.is_some() {
+            quote!(body)
+        } else {
+            quote!(Vec::new())
+        };
+
+        // An explicit content type is also required on incoming requests, the one derived from a
+        // JSON body is only set on outgoing ones.
+        let request_content_type = match &self.metadata.request_content_type {
+            Some(content_type) => Some(content_type.value()),
+            None if request_body_value.is_some() => Some("application/json".to_owned()),
+            None => None,
+        };
+
+        let set_request_content_type = request_content_type.map(|content_type| {
+            quote! {
+                let headers = http_request.headers_mut();
+                if !headers.contains_key(ruma_api::exports::http::header::CONTENT_TYPE) {
+                    headers.insert(
+                        ruma_api::exports::http::header::CONTENT_TYPE,
+                        ruma_api::exports::http::header::HeaderValue::from_static(#content_type),
+                    );
+                }
+            }
+        });
+
+        let check_request_content_type =
+            self.metadata.request_content_type.as_ref().map(|content_type| {
+                quote! {
+                    ruma_api::options::check_content_type(&request, #content_type)?;
+                }
+            });
+
+        let check_field_limits = self.request.check_field_limits();
+
+        let validate_request = if self.metadata.validate {
+            quote! {
+                ruma_api::Validate::validate(&incoming)?;
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let set_request_content_length =
+            if request_body_value.is_some() || self.request.newtype_raw_body_field().is_some() {
+                quote! {
+                    let content_length = http_request.body().len();
+                    http_request.headers_mut().insert(
+                        ruma_api::exports::http::header::CONTENT_LENGTH,
+                        ruma_api::exports::http::header::HeaderValue::from(content_length),
+                    );
+                }
+            } else {
+                TokenStream::new()
+            };
+
+        let serialize_request = {
+            let reassemble = self.request.reassemble();
+            let return_err = quote! {
+                return Err(ruma_api::error::IntoHttpRequestError::new(
+                    #reassemble,
+                    std::convert::From::from(err),
+                ))
+            };
+
+            let build_query = request_query.map(|value| quote!(let request_query = #value;));
+            let build_body =
+                request_body_value.as_ref().map(|value| quote!(let request_body = #value;));
+            let serialize_query = build_query.as_ref().map(|_| {
+                quote! {
+                    let query_str = match ruma_api::manual::serialize_query(&request_query) {
+                        Ok(query_str) => query_str,
+                        Err(err) => #return_err,
+                    };
+                }
+            });
+            let serialize_body = build_body.as_ref().map(|_| {
+                quote! {
+                    let body = match #json::to_vec(&request_body) {
+                        Ok(body) => body,
+                        Err(err) => #return_err,
+                    };
+                }
+            });
+
+            let check_identifiers = self.request.identifier_fields.iter().map(|field_name| {
+                quote! {
+                    if let Err(err) = ruma_api::path::check_identifier(
+                        &std::string::ToString::to_string(&request.#field_name),
+                    ) {
+                        #return_err
+                    }
+                }
+            });
+
+            quote! {
+                #build_query
+                #build_body
+                #serialize_query
+                #serialize_body
+                #(#check_identifiers)*
+            }
+        };
+
+        let parse_request_body = if let Some(field) = self.request.newtype_body_field() {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            quote! {
+                #field_name: request_body.0,
+            }
+        } else if let Some(field) = self.request.newtype_raw_body_field() {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let span = field.ty.span();
+            quote_spanned! {span=>
+                #field_name: std::convert::From::<Vec<u8>>::from(request.into_body()),
+            }
+        } else {
+            self.request.request_init_body_fields()
+        };
+
+        let extract_response_headers = if self.response.has_header_fields() {
+            // Only typed header fields are removed from the headers.
+            let mutability = if self.response.fields.iter().any(|field| field.is_header()) {
+                quote!(mut)
+            } else {
+                TokenStream::new()
+            };
+            quote! {
+                let #mutability headers = response.headers().clone();
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let typed_response_body_decl = if self.response.has_body_fields()
+            || self.response.newtype_body_field().is_some()
+        {
+            let response_body_slice = RenamedField::prefer_new_names(
+                &self.response.renamed_fields,
+                quote!(response.body().as_slice()),
+            );
+            quote! {
+                let response_body: <ResponseBody as ruma_api::Outgoing>::Incoming =
+                    match ruma_api::error::from_json_slice(#response_body_slice) {
+                        Ok(body) => body,
+                        Err(err) => {
+                            return Err(
+                                ruma_api::error::ResponseDeserializationError::new(err, response)
+                                    .into()
+                            );
+                        }
+                    };
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let response_init_fields = self.response.init_fields();
+
+        let serialize_response_headers = self.response.apply_header_fields();
+
+        let body = self.response.to_body(&json);
+        let write_body = self.response.write_body(&json);
+
+        // Responses with only header fields have an empty body and responses with a raw body set
+        // their own content type, so neither gets the JSON content type.
+        let response_content_type =
+            if self.response.is_header_only() || self.response.newtype_raw_body_field().is_some() {
+                TokenStream::new()
+            } else {
+                quote! {
+                    .header(ruma_api::exports::http::header::CONTENT_TYPE, "application/json")
+                }
+            };
+
+        // The `Content-Length` header field of a header-only response, e.g. to a `HEAD` request,
+        // describes a body that isn't sent, so it's kept as is.
+        let build_response = if self.response.is_header_only() {
+            quote! {
+                let response = builder.body(#body).unwrap();
+            }
+        } else {
+            quote! {
+                let mut response = builder.body(#body).unwrap();
+                let content_length = response.body().len();
+                response.headers_mut().insert(
+                    ruma_api::exports::http::header::CONTENT_LENGTH,
+                    ruma_api::exports::http::header::HeaderValue::from(content_length),
+                );
+            }
+        };
+
+        let handler_trait = if cfg!(feature = "handler") {
+            let handler_doc = format!(
+                "A handler for the `{}` API endpoint, for use in server implementations.",
+                name
+            );
+
+            quote! {
+                #[doc = #handler_doc]
+                #[ruma_api::exports::async_trait::async_trait]
+                pub trait Handler: Send + Sync {
+                    /// The error returned when handling a request fails.
+                    type Error;
+
+                    /// Handles a request to this endpoint.
+                    async fn handle(
+                        &self,
+                        request: #request_try_from_type,
+                    ) -> Result<Response, Self::Error>;
+                }
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let endpoint_fields = if cfg!(feature = "spec-check") {
+            let request_fields = self.request.field_infos();
+            let response_fields = self.response.field_infos();
+
+            quote! {
+                impl ruma_api::spec_check::EndpointFields for Request {
+                    fn request_fields() -> Vec<ruma_api::spec_check::FieldInfo> {
+                        #request_fields
+                    }
+
+                    fn response_fields() -> Vec<ruma_api::spec_check::FieldInfo> {
+                        #response_fields
+                    }
+                }
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        // `const fn`s with control flow and panics need a nightly compiler for now, so this check
+        // would break the minimum supported Rust version without the feature.
+        let const_path_check = if cfg!(feature = "nightly") {
+            quote! {
+                const _: () = ruma_api::path::assert_valid_path(#path);
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let generated_tests =
+            if self.generate_tests && cfg!(feature = "client") && cfg!(feature = "server") {
+                quote! {
+                    #[cfg(test)]
+                    mod ruma_api_generated_tests {
+                        use std::convert::TryFrom as _;
+
+                        use super::{Request, Response};
+
+                        #[test]
+                        fn request_round_trip() {
+                            let request: Request = Default::default();
+                            let http_request =
+                                ruma_api::exports::http::Request::<Vec<u8>>::try_from(request)
+                                    .expect("request to convert to an http::Request");
+                            assert_eq!(
+                                *http_request.method(),
+                                ruma_api::exports::http::Method::#method,
+                            );
+
+                            <Request as ruma_api::Outgoing>::Incoming::try_from(http_request)
+                                .expect("http::Request to convert back to a request");
+                        }
+
+                        #[test]
+                        fn malformed_requests_do_not_panic() {
+                            let request: Request = Default::default();
+                            let http_request =
+                                ruma_api::exports::http::Request::<Vec<u8>>::try_from(request)
+                                    .expect("request to convert to an http::Request");
+
+                            ruma_api::malformed::assert_no_panics::<Request>(&http_request);
+                        }
+
+                        #[test]
+                        fn response_round_trip() {
+                            let response: Response = Default::default();
+                            let http_response =
+                                ruma_api::exports::http::Response::<Vec<u8>>::try_from(response)
+                                    .expect("response to convert to an http::Response");
+
+                            <Response as ruma_api::Outgoing>::Incoming::try_from(http_response)
+                                .expect("http::Response to convert back to a response");
+                        }
+                    }
+                }
+            } else {
+                TokenStream::new()
+            };
+
+        // Clients only need to convert outgoing requests and incoming responses, servers only
+        // need the other direction.
+        let parse_uiaa_response = if self.metadata.uiaa {
+            quote! {
+                if response.status().as_u16() == 401 {
+                    if let Ok(info) = ruma_api::exports::serde_json::from_slice::<ruma_api::uiaa::UiaaInfo>(
+                        response.body(),
+                    ) {
+                        return Err(ruma_api::error::FromHttpResponseError::Uiaa(info));
+                    }
+                }
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let request_with_method = if self.metadata.other_methods.is_empty() {
+            TokenStream::new()
+        } else {
+            quote! {
+                impl Request {
+                    /// Converts this request into an `http::Request` with the given method, which
+                    /// has to be one of the methods of this endpoint.
+                    ///
+                    /// The request is the same as with the default method otherwise.
+                    pub fn try_into_http_request_with_method(
+                        self,
+                        method: ruma_api::exports::http::Method,
+                    ) -> Result<
+                        ruma_api::exports::http::Request<Vec<u8>>,
+                        ruma_api::error::IntoHttpError,
+                    > {
+                        if !Request::METADATA.allows_method(&method) {
+                            return Err(ruma_api::error::UnsupportedMethod::new(
+                                method,
+                                Request::METADATA.name(),
+                            )
+                            .into());
+                        }
+
+                        let mut http_request = self.try_into_http_request_or_return()?;
+                        *http_request.method_mut() = method;
+                        Ok(http_request)
+                    }
+                }
+            }
+        };
+
+        let client_conversions = if cfg!(feature = "client") {
+            quote! {
+                impl Request {
+                    /// Converts this request into an `http::Request`, handing it back together
+                    /// with the error if that fails.
+                    #[allow(clippy::useless_conversion, unused_mut, unused_variables)]
+                    pub fn try_into_http_request_or_return(
+                        self,
+                    ) -> Result<
+                        ruma_api::exports::http::Request<Vec<u8>>,
+                        ruma_api::error::IntoHttpRequestError<Self>,
+                    > {
+                        let request = self;
+                        let metadata = Request::METADATA;
+
+                        #serialize_request
+
+                        // Use dummy homeserver url which has to be overwritten in
+                        // the calling code. The whole URI is written to a single
+                        // string, which is only parsed once at the end.
+                        let mut uri = String::with_capacity(#uri_capacity);
+                        uri.push_str("http://invalid-host-please-change");
+
+                        { #url_set_path }
+                        { #url_set_querystring }
+
+                        let mut http_request = ruma_api::exports::http::Request::new(#request_body);
+
+                        *http_request.method_mut() = ruma_api::exports::http::Method::#method;
+                        *http_request.uri_mut() = uri.parse().unwrap();
+
+                        { #add_headers_to_request }
+                        { #set_request_content_type }
+                        { #set_request_content_length }
+
+                        Ok(http_request)
+                    }
+                }
+
+                impl std::convert::TryFrom<Request> for ruma_api::exports::http::Request<Vec<u8>> {
+                    type Error = ruma_api::error::IntoHttpError;
+
+                    fn try_from(request: Request) -> Result<Self, Self::Error> {
+                        Ok(request.try_into_http_request_or_return()?)
+                    }
+                }
+
+                #request_with_method
+
+                impl std::convert::TryFrom<ruma_api::exports::http::Response<Vec<u8>>> for #response_try_from_type {
+                    type Error = ruma_api::error::FromHttpResponseError;
+
+                    #[allow(clippy::useless_conversion, unused_variables)]
+                    fn try_from(
+                        response: ruma_api::exports::http::Response<Vec<u8>>,
+                    ) -> Result<Self, Self::Error> {
+                        if response.status().as_u16() < 400 {
+                            #extract_response_headers
+
+                            #typed_response_body_decl
+
+                            Ok(Self {
+                                #response_init_fields
+                            })
+                        } else {
+                            #parse_uiaa_response
+                            Err(ruma_api::error::FromHttpResponseError::from_error_response(response))
+                        }
+                    }
+                }
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let server_conversions = if cfg!(feature = "server") {
+            quote! {
+                impl std::convert::TryFrom<ruma_api::exports::http::Request<Vec<u8>>> for #request_try_from_type {
+                    type Error = ruma_api::error::FromHttpRequestError;
+
+                    #[allow(clippy::useless_conversion, unused_variables)]
+                    fn try_from(request: ruma_api::exports::http::Request<Vec<u8>>) -> Result<Self, Self::Error> {
+                        ruma_api::options::check_content_length(&request)?;
+                        #check_request_content_type
+                        #extract_request_path
+                        #extract_request_query
+                        #extract_request_headers
+                        #extract_request_body
+
+                        let incoming = Self {
+                            #parse_request_path
+                            #parse_request_query
+                            #parse_request_headers
+                            #parse_request_body
+                        };
+                        #check_field_limits
+                        #validate_request
+
+                        Ok(incoming)
+                    }
+                }
+
+                impl Request {
+                    /// Whether the given request path matches the path of this endpoint, with any
+                    /// value for the path parameters.
+                    pub fn matches_path(path: &str) -> bool {
+                        ruma_api::path::matches(Request::METADATA.path(), path)
+                    }
+
+                    /// Checks the path, query string and headers of an incoming request before its
+                    /// body is read, e.g. to reject it early.
+                    ///
+                    /// The request is expected to be routed to this endpoint already, see
+                    /// `matches_path`. The returned `PartialIncoming` is completed with the body.
+                    #[allow(clippy::useless_conversion, unused_variables)]
+                    pub fn try_from_parts(
+                        parts: ruma_api::exports::http::request::Parts,
+                    ) -> Result<
+                        ruma_api::conversion::PartialIncoming<#request_try_from_type>,
+                        ruma_api::error::FromHttpRequestError,
+                    > {
+                        let request =
+                            ruma_api::exports::http::Request::from_parts(parts, Vec::<u8>::new());
+                        #check_request_content_type
+                        #extract_request_path
+                        #extract_request_query
+                        #extract_request_headers
+                        #check_request_path
+                        #check_request_headers
+
+                        Ok(ruma_api::conversion::PartialIncoming::new(request.into_parts().0))
+                    }
+                }
+
+                impl std::convert::TryFrom<Response> for ruma_api::exports::http::Response<Vec<u8>> {
+                    type Error = ruma_api::error::IntoHttpError;
+
+                    #[allow(clippy::useless_conversion, unused_variables)]
+                    fn try_from(response: Response) -> Result<Self, Self::Error> {
+                        let builder = ruma_api::exports::http::Response::builder()#response_content_type;
+                        #serialize_response_headers
+                        #build_response
+                        Ok(response)
+                    }
+                }
+
+                impl Response {
+                    /// Converts this response into an `http::Response`, writing the body to the given
+                    /// writer instead of a new `Vec<u8>`.
+                    ///
+                    /// The writer is used as the body of the returned `http::Response`. Since the
+                    /// length of the body isn't known, setting the `Content-Length` header is left
+                    /// to the transport.
+                    #[allow(clippy::useless_conversion, unused_mut, unused_variables)]
+                    pub fn try_into_http_response_with_writer<W: std::io::Write>(
+                        self,
+                        mut writer: W,
+                    ) -> Result<ruma_api::exports::http::Response<W>, ruma_api::error::IntoHttpError> {
+                        let response = self;
+                        let builder = ruma_api::exports::http::Response::builder()#response_content_type;
+                        #serialize_response_headers
+                        #write_body
+                        Ok(builder.body(writer).unwrap())
+                    }
+                }
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let stability_marker = if self.metadata.unstable {
+            quote!(ruma_api::UnstableEndpoint)
+        } else {
+            quote!(ruma_api::StableEndpoint)
+        };
+
+        let cache_key = if self.metadata.cacheable && cfg!(feature = "client") {
+            quote! {
+                fn cache_key(&self) -> Option<ruma_api::cache::CacheKey> {
+                    let http_request = self.clone().try_into_http_request_or_return().ok()?;
+                    Some(ruma_api::cache::CacheKey::from_http_request(
+                        Self::METADATA.name(),
+                        &http_request,
+                    ))
+                }
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let with_direction = match &self.metadata.direction {
+            Some(direction) => quote!(.with_direction(ruma_api::Direction::#direction)),
+            None => TokenStream::new(),
+        };
+        let with_server_kind = match &self.metadata.server_kind {
+            Some(server_kind) => quote!(.with_server_kind(ruma_api::ServerKind::#server_kind)),
+            None => TokenStream::new(),
+        };
+
+        let other_methods = &self.metadata.other_methods;
+        let with_other_methods = if other_methods.is_empty() {
+            TokenStream::new()
+        } else {
+            quote! {
+                .with_other_methods(&[#(ruma_api::exports::http::Method::#other_methods),*])
+            }
+        };
+
+        let with_deprecated = match &self.metadata.deprecated {
+            Some(version) => {
+                quote!(.with_deprecated(ruma_api::versions::MatrixVersion::#version))
+            }
+            None => TokenStream::new(),
+        };
+        let with_removed = match &self.metadata.removed {
+            Some(version) => quote!(.with_removed(ruma_api::versions::MatrixVersion::#version)),
+            None => TokenStream::new(),
+        };
+
+        let with_suggested_timeout = match &self.metadata.suggested_timeout {
+            Some((constructor, value)) => {
+                quote!(.with_suggested_timeout(std::time::Duration::#constructor(#value)))
+            }
+            None => TokenStream::new(),
+        };
+
+        let request_doc = format!(
+            "Data for a request to the `{}` API endpoint.\n\n{}",
+            name,
+            description.value()
+        );
+        let response_doc = format!("Data in the response from the `{}` API endpoint.", name);
+
+        let api = quote! {
+            use ruma_api::exports::serde::de::Error as _;
+            use ruma_api::exports::serde::Deserialize as _;
+            use ruma_api::Endpoint as _;
+
+            use std::convert::TryInto as _;
+
+            #[doc = #request_doc]
+            #request_type
+
+            #[doc = #response_doc]
+            #response_type
+
+            #client_conversions
+
+            #server_conversions
+
+            impl ruma_api::Endpoint for Request {
+                type Response = Response;
+
+                /// Metadata for the `#name` endpoint.
+                const METADATA: ruma_api::Metadata = ruma_api::Metadata::new(
+                    #description,
+                    &ruma_api::exports::http::Method::#method,
+                    #name,
+                    #path,
+                )
+                .with_rate_limited(#rate_limited)
+                .with_requires_authentication(#requires_authentication)
+                #with_direction
+                #with_server_kind
+                #with_other_methods
+                #with_suggested_timeout
+                #with_deprecated
+                #with_removed;
+
+                #cache_key
+            }
+
+            impl #stability_marker for Request {}
+
+            impl ruma_api::examples::EndpointExamples for Request {
+                fn request_examples() -> Vec<ruma_api::examples::FieldExample> {
+                    #request_examples
+                }
+
+                fn response_examples() -> Vec<ruma_api::examples::FieldExample> {
+                    #response_examples
+                }
+            }
+
+            #request_from_incoming
+
+            #response_from_incoming
+
+            #endpoint_fields
+
+            #handler_trait
+
+            #const_path_check
+
+            #generated_tests
+
+            #(#items)*
+        };
+
+        let api = if let Some(ModuleDecl { vis, ident }) = &self.module {
+            let module_doc = format!("The `{}` API endpoint.\n\n{}", name, description.value());
+
+            quote! {
+                #[doc = #module_doc]
+                #vis mod #ident {
+                    use super::*;
+
+                    #api
+                }
+            }
+        } else {
+            api
+        };
+
+        api.to_tokens(tokens);
+    }
+}
+
+/// Produces a conversion from the 'Incoming' variant of a request or response back to the type
+/// itself, failing if any of the wrapped fields couldn't be deserialized.
+fn from_incoming(ty: TokenStream, incoming_ty: TokenStream) -> TokenStream {
+    quote! {
+        // Like the bounds of the `TryIntoOutgoing` implementation from `#[derive(Outgoing)]`, this
+        // bound is only checked when the conversion is used.
+        impl std::convert::TryFrom<#incoming_ty> for #ty
+        where
+            for<'a> #incoming_ty: ruma_api::TryIntoOutgoing<#ty>,
+        {
+            type Error = ruma_api::partial::PartialError;
+
+            fn try_from(incoming: #incoming_ty) -> Result<Self, Self::Error> {
+                ruma_api::TryIntoOutgoing::try_into_outgoing(incoming)
+            }
+        }
+    }
+}
+
+/// Where a field is sent and under which name, for `ruma_api::spec_check::FieldInfo`.
+pub enum WireName<'a> {
+    /// Under the serde name of the field, in the given `FieldLocation`.
+    Field(&'static str),
+    /// In the header with the given name.
+    Header(&'a Ident),
+    /// In the given `FieldLocation`, under names that are only known at runtime, like those of
+    /// newtype bodies and flattened fields.
+    Dynamic(&'static str),
+}
+
+/// Produces a statement pushing the `ruma_api::spec_check::FieldInfo` for the given field to a
+/// `fields` vector.
+pub fn field_info(field: &Field, wire_name: WireName<'_>) -> TokenStream {
+    let cfg_attrs = cfg_attrs(field);
+    let ident = field.ident.as_ref().expect("expected field to have an identifier");
+    let field_name = unraw(ident);
+    let optional = is_option(&field.ty) || serde_default(field).is_some();
+    let required = !optional;
+
+    let (location, name) = match wire_name {
+        WireName::Field(location) => {
+            let location = Ident::new(location, Span::call_site());
+            if has_serde_word(field, "flatten") {
+                (location, quote!(None))
+            } else {
+                let name = serde_rename(field).unwrap_or_else(|| field_name.clone());
+                (location, quote!(Some(#name.to_owned())))
+            }
+        }
+        WireName::Header(header_name) => (
+            Ident::new("Header", Span::call_site()),
+            quote!(Some(ruma_api::exports::http::header::#header_name.as_str().to_owned())),
+        ),
+        WireName::Dynamic(location) => (Ident::new(location, Span::call_site()), quote!(None)),
+    };
+
+    quote! {
+        #(#cfg_attrs)*
+        fields.push(ruma_api::spec_check::FieldInfo {
+            field: #field_name,
+            name: #name,
+            location: ruma_api::examples::FieldLocation::#location,
+            required: #required,
+        });
+    }
+}
+
+/// The value of a field that isn't given to `Request::new` or set by `Default`: the default of
+/// `Option`al and header map fields, or the one given with `#[serde(default)]`.
+///
+/// `None` for fields that don't have a default.
+pub fn default_value(field: &Field, is_header_map: bool) -> Option<TokenStream> {
+    if is_option(&field.ty) || is_header_map {
+        Some(quote!(std::default::Default::default()))
+    } else {
+        serde_default(field)
+    }
+}
+
+/// `mut` if the given statements, which modify a binding, aren't empty. Bindings that are never
+/// modified would trigger `unused_mut` in the calling crate.
+pub fn mutability(modifications: &TokenStream) -> TokenStream {
+    if modifications.is_empty() {
+        TokenStream::new()
+    } else {
+        quote!(mut)
+    }
+}
+
+/// The name given with `#[serde(rename = "...")]`, if any.
+fn serde_rename(field: &Field) -> Option<String> {
+    serde_metas(field).into_iter().find_map(|meta| match meta {
+        syn::Meta::NameValue(syn::MetaNameValue { path, lit: syn::Lit::Str(name), .. })
+            if path.is_ident("rename") =>
+        {
+            Some(name.value())
+        }
+        _ => None,
+    })
+}
+
+/// Whether the field has a `#[serde(...)]` attribute with the given word, like `flatten`.
+fn has_serde_word(field: &Field, word: &str) -> bool {
+    serde_metas(field).into_iter().any(|meta| match meta {
+        syn::Meta::Path(path) => path.is_ident(word),
+        _ => false,
+    })
+}
+
+fn serde_metas(field: &Field) -> Vec<syn::Meta> {
+    field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("serde"))
+        .filter_map(|attr| match attr.parse_meta().ok()? {
+            syn::Meta::List(list) => Some(list.nested),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|nested| match nested {
+            syn::NestedMeta::Meta(meta) => Some(meta),
+            _ => None,
+        })
+        .collect()
+}
+
+impl FieldExample {
+    /// Produces an expression evaluating to a `Vec` of the given examples.
+    pub fn to_vec(examples: &[Self]) -> TokenStream {
+        let pushes = examples.iter().map(|example| {
+            let cfg_attrs = &example.cfg_attrs;
+            let field = unraw(&example.ident);
+            let location = &example.location;
+            let value = &example.example;
+
+            quote! {
+                #(#cfg_attrs)*
+                examples.push(ruma_api::examples::FieldExample {
+                    field: #field,
+                    location: ruma_api::examples::FieldLocation::#location,
+                    example: #value,
+                });
+            }
+        });
+
+        quote! {{
+            #[allow(unused_mut)]
+            let mut examples = Vec::new();
+            #(#pushes)*
+            examples
+        }}
+    }
+}
+
+impl RenamedField {
+    /// The name of the additional body struct field that holds a copy of the value under the old
+    /// name.
+    fn compat_ident(&self) -> Ident {
+        format_ident!("{}_renamed_from", self.ident)
+    }
+
+    /// Produces the additional body struct fields that emit renamed fields under their old names
+    /// too. These are only generated with the `compat` feature.
+    pub fn compat_field_defs(renamed_fields: &[Self]) -> TokenStream {
+        if !cfg!(feature = "compat") {
+            return TokenStream::new();
+        }
+
+        let defs = renamed_fields.iter().map(|renamed| {
+            let cfg_attrs = &renamed.cfg_attrs;
+            let compat_ident = renamed.compat_ident();
+            let ty = &renamed.ty;
+            let old_name = &renamed.old_name;
+
+            quote! {
+                #(#cfg_attrs)*
+                #[serde(
+                    rename = #old_name,
+                    skip_deserializing,
+                    skip_serializing_if = "Option::is_none"
+                )]
+                #compat_ident: Option<#ty>
+            }
+        });
+
+        quote! { #(#defs,)* }
+    }
+
+    /// Produces an expression for the given JSON body without the old names of renamed fields
+    /// that it also contains under their new names, which the serde aliases for the old names
+    /// would reject as duplicates.
+    pub fn prefer_new_names(renamed_fields: &[Self], body: TokenStream) -> TokenStream {
+        if renamed_fields.is_empty() {
+            return body;
+        }
+
+        let names = renamed_fields.iter().map(|renamed| {
+            let name = &renamed.name;
+            let old_name = &renamed.old_name;
+            quote!((#name, #old_name))
+        });
+
+        quote!(&ruma_api::error::prefer_new_names(#body, &[#(#names),*]))
+    }
+
+    /// Produces the struct initializers for the fields from `compat_field_defs`, cloning the
+    /// values from the given variable. These need to come before the regular initializers, which
+    /// move out of that variable.
+    pub fn compat_init_fields(renamed_fields: &[Self], src: TokenStream) -> TokenStream {
+        if !cfg!(feature = "compat") {
+            return TokenStream::new();
+        }
+
+        let inits = renamed_fields.iter().map(|renamed| {
+            let cfg_attrs = &renamed.cfg_attrs;
+            let compat_ident = renamed.compat_ident();
+            let ident = &renamed.ident;
+
+            quote! {
+                #(#cfg_attrs)*
+                #compat_ident: Some(#src.#ident.clone())
+            }
+        });
+
+        quote! { #(#inits,)* }
+    }
+}
@@ -0,0 +1,542 @@
+//! Code generation for the `request` section of the procedural macro.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned, ToTokens};
+use syn::{spanned::Spanned, Field};
+
+use super::{default_value, field_info, mutability, WireName};
+use crate::api::{
+    analyze::{FieldLimit, RenamedField, Request, RequestField, RequestFieldKind},
+    cfg_attrs, is_option, strip_serde_attrs, unraw,
+};
+
+impl Request {
+    /// Produces an expression evaluating to a `Vec` of the `ruma_api::spec_check::FieldInfo`s of
+    /// the request fields.
+    pub fn field_infos(&self) -> TokenStream {
+        let pushes = self.fields.iter().map(|request_field| match request_field {
+            RequestField::Body(field) => field_info(field, WireName::Field("Body")),
+            RequestField::Header(field, header_name) => {
+                field_info(field, WireName::Header(header_name))
+            }
+            RequestField::HeaderMap(field) => field_info(field, WireName::Dynamic("Header")),
+            // The OpenAPI definitions of the spec have no cookie parameters to compare with.
+            RequestField::Cookie(..) => TokenStream::new(),
+            RequestField::NewtypeBody(field) | RequestField::NewtypeRawBody(field) => {
+                field_info(field, WireName::Dynamic("Body"))
+            }
+            RequestField::Path(field) => field_info(field, WireName::Field("Path")),
+            RequestField::Query(field) => field_info(field, WireName::Field("Query")),
+            RequestField::QueryMap(field) => field_info(field, WireName::Dynamic("Query")),
+        });
+
+        quote! {{
+            #[allow(unused_mut)]
+            let mut fields = Vec::new();
+            #(#pushes)*
+            fields
+        }}
+    }
+
+    /// Produces code to add necessary HTTP headers to an `http::Request`.
+    pub fn add_headers_to_request(&self) -> TokenStream {
+        let append_stmts = self.header_fields().map(|request_field| {
+            let (field, header_name) = match request_field {
+                RequestField::Header(field, header_name) => (field, header_name),
+                _ => unreachable!("expected request field to be header variant"),
+            };
+
+            let field_name = &field.ident;
+            let cfg_attrs = cfg_attrs(field);
+
+            if is_option(&field.ty) {
+                quote! {
+                    #(#cfg_attrs)*
+                    {
+                        if let Some(value) = request.#field_name {
+                            headers.append(
+                                ruma_api::exports::http::header::#header_name,
+                                <ruma_api::exports::http::header::HeaderValue as std::convert::TryFrom<_>>::try_from(value)
+                                    .expect("failed to convert value into HeaderValue"),
+                            );
+                        }
+                    }
+                }
+            } else {
+                quote! {
+                    #(#cfg_attrs)*
+                    headers.append(
+                        ruma_api::exports::http::header::#header_name,
+                        ruma_api::exports::http::header::HeaderValue::from_str(request.#field_name.as_ref())
+                            .expect("failed to convert value into HeaderValue"),
+                    );
+                }
+            }
+        });
+
+        let append_cookies = self.cookie_fields().map(|request_field| {
+            let (field, cookie_name) = match request_field {
+                RequestField::Cookie(field, cookie_name) => (field, cookie_name),
+                _ => unreachable!("expected request field to be cookie variant"),
+            };
+
+            let field_name = &field.ident;
+            let cfg_attrs = cfg_attrs(field);
+
+            if is_option(&field.ty) {
+                quote! {
+                    #(#cfg_attrs)*
+                    {
+                        if let Some(value) = request.#field_name {
+                            ruma_api::cookie::append_request_cookie(
+                                headers,
+                                #cookie_name,
+                                value.as_ref(),
+                            );
+                        }
+                    }
+                }
+            } else {
+                quote! {
+                    #(#cfg_attrs)*
+                    ruma_api::cookie::append_request_cookie(
+                        headers,
+                        #cookie_name,
+                        request.#field_name.as_ref(),
+                    );
+                }
+            }
+        });
+
+        let extend_headers = self.header_map_field().map(|field| {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let remove_typed_headers = self.remove_typed_headers(quote!(header_map));
+            let mutability = mutability(&remove_typed_headers);
+
+            quote! {
+                let #mutability header_map = request.#field_name;
+                #remove_typed_headers
+                headers.extend(header_map);
+            }
+        });
+
+        quote! {
+            #extend_headers
+            #(#append_stmts)*
+            #(#append_cookies)*
+        }
+    }
+
+    /// Produces code to extract fields from the HTTP headers in an `http::Request`.
+    pub fn parse_headers_from_request(&self) -> TokenStream {
+        let fields = self.header_field_values().map(|(field, value)| {
+            let field_name = &field.ident;
+            let cfg_attrs = cfg_attrs(field);
+            quote! {
+                #(#cfg_attrs)*
+                #field_name: #value
+            }
+        });
+
+        let header_map = self.header_map_field().map(|field| {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let remove_typed_headers = self.remove_typed_headers(quote!(header_map));
+            if remove_typed_headers.is_empty() {
+                return quote! { #field_name: headers.clone(), };
+            }
+
+            quote! {
+                #field_name: {
+                    let mut header_map = headers.clone();
+                    #remove_typed_headers
+                    header_map
+                },
+            }
+        });
+
+        quote! {
+            #(#fields,)*
+            #header_map
+        }
+    }
+
+    /// Produces statements that fail like [`parse_headers_from_request`] if a required header is
+    /// missing, without using the values.
+    ///
+    /// [`parse_headers_from_request`]: #method.parse_headers_from_request
+    pub fn check_headers_in_request(&self) -> TokenStream {
+        let checks = self.header_field_values().filter(|(field, _)| !is_option(&field.ty)).map(
+            |(field, value)| {
+                let ty = &field.ty;
+                let cfg_attrs = cfg_attrs(field);
+                quote! {
+                    #(#cfg_attrs)*
+                    let _: #ty = #value;
+                }
+            },
+        );
+
+        quote! { #(#checks)* }
+    }
+
+    /// Produces the expressions extracting the header and cookie fields from the HTTP headers in
+    /// an `http::Request`, together with the fields.
+    fn header_field_values(&self) -> impl Iterator<Item = (&Field, TokenStream)> {
+        let cookie_values = self.cookie_fields().map(|request_field| {
+            let (field, cookie_name) = match request_field {
+                RequestField::Cookie(field, cookie_name) => (field, cookie_name),
+                _ => panic!("expected request field to be cookie variant"),
+            };
+
+            if is_option(&field.ty) {
+                let convert = quote! { std::convert::TryFrom::try_from(value).ok() };
+                let value = quote! {
+                    ruma_api::cookie::request_cookie(headers, #cookie_name)
+                        .and_then(|value| #convert)
+                };
+                return (field, value);
+            }
+
+            let value = quote! {
+                match ruma_api::cookie::request_cookie(headers, #cookie_name) {
+                    Some(cookie) => cookie,
+                    None => {
+                        return Err(
+                            ruma_api::error::RequestDeserializationError::new(
+                                ruma_api::exports::serde_json::Error::missing_field(#cookie_name),
+                                &request,
+                            )
+                            .into()
+                        );
+                    }
+                }
+            };
+            (field, value)
+        });
+
+        self.header_fields()
+            .map(|request_field| {
+                let (field, header_name) = match request_field {
+                    RequestField::Header(field, header_name) => (field, header_name),
+                    _ => panic!("expected request field to be header variant"),
+                };
+
+                let header_name_string = header_name.to_string();
+
+                if is_option(&field.ty) {
+                    // Not spanned to the field, the conversion is infallible for some types which
+                    // would otherwise be linted in the calling crate.
+                    let convert = quote! { std::convert::TryFrom::try_from(value).ok() };
+                    let value = quote! {
+                        headers.get(ruma_api::exports::http::header::#header_name)
+                            .and_then(|value| {
+                                let value = value.to_str().ok()?;
+                                #convert
+                            })
+                    };
+                    return (field, value);
+                }
+
+                let value = quote! {
+                    match headers.get(ruma_api::exports::http::header::#header_name)
+                        .and_then(|v| v.to_str().ok()) {
+                            Some(header) => header.to_owned(),
+                            None => {
+                                return Err(
+                                    ruma_api::error::RequestDeserializationError::new(
+                                        ruma_api::exports::serde_json::Error::missing_field(
+                                            #header_name_string
+                                        ),
+                                        &request,
+                                    )
+                                    .into()
+                                );
+                            }
+                        }
+                };
+                (field, value)
+            })
+            .chain(cookie_values)
+    }
+
+    /// Produces statements removing the headers of the typed header fields from the `HeaderMap`
+    /// with the given name.
+    fn remove_typed_headers(&self, header_map: TokenStream) -> TokenStream {
+        let removals = self.header_fields().map(|request_field| {
+            let (field, header_name) = match request_field {
+                RequestField::Header(field, header_name) => (field, header_name),
+                _ => unreachable!("expected request field to be header variant"),
+            };
+            let cfg_attrs = cfg_attrs(field);
+
+            quote! {
+                #(#cfg_attrs)*
+                #header_map.remove(ruma_api::exports::http::header::#header_name);
+            }
+        });
+
+        quote! { #(#removals)* }
+    }
+    /// Produces code that checks the `max_len` and `max_items` limits of the fields of a converted
+    /// incoming request in a variable named `incoming`.
+    pub fn check_field_limits(&self) -> TokenStream {
+        let checks = self.field_limits.iter().map(|limit| {
+            let FieldLimit { cfg_attrs, ident, optional, kind, max } = limit;
+            let name = unraw(ident);
+            let check = quote!(ruma_api::limits::#kind(#name, value, #max)?;);
+
+            if *optional {
+                quote! {
+                    #(#cfg_attrs)*
+                    {
+                        if let Some(value) = &incoming.#ident {
+                            #check
+                        }
+                    }
+                }
+            } else {
+                quote! {
+                    #(#cfg_attrs)*
+                    {
+                        let value = &incoming.#ident;
+                        #check
+                    }
+                }
+            }
+        });
+
+        quote! {
+            #(#checks)*
+        }
+    }
+
+    /// Produces code for a struct initializer for body fields on a variable named `request`.
+    pub fn request_body_init_fields(&self) -> TokenStream {
+        let renamed_fields =
+            RenamedField::compat_init_fields(&self.renamed_fields, quote!(request));
+        let fields = self.struct_init_fields(RequestFieldKind::Body, quote!(request));
+        quote! { #renamed_fields #fields }
+    }
+
+    /// Produces code for a struct initializer for query string fields on a variable named `request`.
+    pub fn request_query_init_fields(&self) -> TokenStream {
+        self.struct_init_fields(RequestFieldKind::Query, quote!(request))
+    }
+
+    /// Produces code for a struct initializer for body fields on a variable named `request_body`.
+    pub fn request_init_body_fields(&self) -> TokenStream {
+        self.struct_init_fields(RequestFieldKind::Body, quote!(request_body))
+    }
+
+    /// Produces code for a struct initializer for query string fields on a variable named
+    /// `request_query`.
+    pub fn request_init_query_fields(&self) -> TokenStream {
+        self.struct_init_fields(RequestFieldKind::Query, quote!(request_query))
+    }
+
+    /// Produces code for a struct initializer for the given field kind to be accessed through the
+    /// given variable name.
+    fn struct_init_fields(
+        &self,
+        request_field_kind: RequestFieldKind,
+        src: TokenStream,
+    ) -> TokenStream {
+        let fields = self.fields.iter().filter_map(|f| {
+            f.field_of_kind(request_field_kind).map(|field| {
+                let field_name =
+                    field.ident.as_ref().expect("expected field to have an identifier");
+                let span = field.span();
+                let cfg_attrs = cfg_attrs(field);
+
+                quote_spanned! {span=>
+                    #(#cfg_attrs)*
+                    #field_name: #src.#field_name
+                }
+            })
+        });
+
+        quote! { #(#fields,)* }
+    }
+
+    /// Produces a `Request` struct expression that moves the fields back out of the
+    /// `request_query` and `request_body` values built from a variable named `request`, for
+    /// handing the request back when serializing them fails.
+    pub fn reassemble(&self) -> TokenStream {
+        if self.fields.is_empty() {
+            return quote!(Request);
+        }
+
+        let has_query_fields = self.has_query_fields();
+        let fields = self.fields.iter().map(|request_field| {
+            let field = request_field.field();
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let cfg_attrs = cfg_attrs(field);
+
+            let value = match request_field {
+                RequestField::Query(_) => quote!(request_query.#field_name),
+                RequestField::QueryMap(_) if !has_query_fields => quote!(request_query.0),
+                RequestField::Body(_) => quote!(request_body.#field_name),
+                RequestField::NewtypeBody(_) => quote!(request_body.0),
+                _ => quote!(request.#field_name),
+            };
+
+            quote! { #(#cfg_attrs)* #field_name: #value }
+        });
+
+        quote! { Request { #(#fields,)* } }
+    }
+
+    /// Produces a `Request::new` constructor that takes the required fields in declaration order
+    /// and fills the `Option`al, `#[serde(default)]` and header map fields with their defaults, and
+    /// a `Default` implementation if there are no required fields.
+    fn constructor(&self) -> TokenStream {
+        let mut params = Vec::new();
+        let mut inits = Vec::new();
+
+        for request_field in &self.fields {
+            let field = request_field.field();
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let ty = &field.ty;
+            let cfg_attrs = cfg_attrs(field);
+
+            match default_value(field, request_field.as_header_map_field().is_some()) {
+                Some(default) => inits.push(quote! { #(#cfg_attrs)* #field_name: #default }),
+                None => {
+                    // Not spanned to the field, so lints on the field name aren't repeated for the
+                    // parameter.
+                    let mut param = field_name.clone();
+                    param.set_span(Span::call_site());
+                    params.push(quote! { #(#cfg_attrs)* #param: #ty });
+                    inits.push(quote! { #(#cfg_attrs)* #field_name: #param });
+                }
+            }
+        }
+
+        let init =
+            if self.fields.is_empty() { TokenStream::new() } else { quote!({ #(#inits),* }) };
+
+        // Requests without required fields, like most of the ones of `/sync`, can be created with
+        // `Request { since: Some(token), ..Default::default() }`.
+        let default_impl = if params.is_empty() {
+            quote! {
+                impl std::default::Default for Request {
+                    fn default() -> Self {
+                        Self::new()
+                    }
+                }
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        quote! {
+            #[allow(clippy::new_without_default, clippy::too_many_arguments)]
+            impl Request {
+                /// Creates a new `Request` with the given required fields, and the default values for
+                /// all other fields.
+                pub fn new(#(#params),*) -> Self {
+                    Self #init
+                }
+            }
+
+            #default_impl
+        }
+    }
+}
+
+impl ToTokens for Request {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let request_def = if self.fields.is_empty() {
+            quote!(;)
+        } else {
+            let fields =
+                self.fields.iter().map(|request_field| strip_serde_attrs(request_field.field()));
+            quote! { { #(#fields),* } }
+        };
+
+        let request_body_struct =
+            if let Some(body_field) = self.fields.iter().find(|f| f.is_newtype_body()) {
+                let field = Field { ident: None, colon_token: None, ..body_field.field().clone() };
+                let derive_deserialize = if body_field.has_wrap_incoming_attr() {
+                    TokenStream::new()
+                } else {
+                    quote!(ruma_api::exports::serde::Deserialize)
+                };
+
+                Some((derive_deserialize, quote! { (#field); }))
+            } else if self.has_body_fields() {
+                let fields = self.fields.iter().filter(|f| f.is_body());
+                let derive_deserialize = if fields.clone().any(|f| f.has_wrap_incoming_attr()) {
+                    TokenStream::new()
+                } else {
+                    quote!(ruma_api::exports::serde::Deserialize)
+                };
+                let fields = fields.map(RequestField::field);
+                let renamed_fields = RenamedField::compat_field_defs(&self.renamed_fields);
+
+                Some((derive_deserialize, quote! { { #(#fields,)* #renamed_fields } }))
+            } else {
+                None
+            }
+            .map(|(derive_deserialize, def)| {
+                quote! {
+                    /// Data in the request body.
+                    #[derive(
+                        Debug,
+                        ruma_api::Outgoing,
+                        ruma_api::exports::serde::Serialize,
+                        #derive_deserialize
+                    )]
+                    #[serde(crate = "ruma_api::exports::serde")]
+                    struct RequestBody #def
+                }
+            });
+
+        let request_query_struct = if self.has_query_fields() {
+            let fields = self.fields.iter().filter_map(RequestField::as_query_field);
+
+            quote! {
+                /// Data in the request's query string.
+                #[derive(
+                    Debug,
+                    ruma_api::exports::serde::Deserialize,
+                    ruma_api::exports::serde::Serialize,
+                )]
+                #[serde(crate = "ruma_api::exports::serde")]
+                struct RequestQuery {
+                    #(#fields),*
+                }
+            }
+        } else if let Some(f) = self.query_map_field() {
+            let field = Field { ident: None, colon_token: None, ..f.clone() };
+
+            quote! {
+                /// Data in the request's query string.
+                #[derive(
+                    Debug,
+                    ruma_api::exports::serde::Deserialize,
+                    ruma_api::exports::serde::Serialize,
+                )]
+                #[serde(crate = "ruma_api::exports::serde")]
+                struct RequestQuery(#field);
+            }
+        } else {
+            TokenStream::new()
+        };
+
+        let constructor = self.constructor();
+
+        let request = quote! {
+            #[derive(Debug, Clone, ruma_api::Outgoing)]
+            #[incoming_no_deserialize]
+            pub struct Request #request_def
+
+            #constructor
+
+            #request_body_struct
+            #request_query_struct
+        };
+
+        request.to_tokens(tokens);
+    }
+}
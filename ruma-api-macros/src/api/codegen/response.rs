@@ -0,0 +1,418 @@
+//! Code generation for the `response` section of the procedural macro.
+
+use proc_macro2::TokenStream;
+use quote::{quote, quote_spanned, ToTokens};
+use syn::{spanned::Spanned, Field};
+
+use super::{default_value, field_info, mutability, WireName};
+use crate::api::{
+    analyze::{RenamedField, Response, ResponseField},
+    cfg_attrs, is_option, strip_serde_attrs,
+};
+
+impl Response {
+    /// Produces an expression evaluating to a `Vec` of the `ruma_api::spec_check::FieldInfo`s of
+    /// the response fields.
+    pub fn field_infos(&self) -> TokenStream {
+        let pushes = self.fields.iter().map(|response_field| match response_field {
+            ResponseField::Body(field) => field_info(field, WireName::Field("Body")),
+            ResponseField::Header(field, header_name) => {
+                field_info(field, WireName::Header(header_name))
+            }
+            ResponseField::HeaderMap(field) => field_info(field, WireName::Dynamic("Header")),
+            // The OpenAPI definitions of the spec have no cookie parameters to compare with.
+            ResponseField::Cookie(..) => TokenStream::new(),
+            ResponseField::NewtypeBody(field) | ResponseField::NewtypeRawBody(field) => {
+                field_info(field, WireName::Dynamic("Body"))
+            }
+        });
+
+        quote! {{
+            #[allow(unused_mut)]
+            let mut fields = Vec::new();
+            #(#pushes)*
+            fields
+        }}
+    }
+
+    /// Produces statements removing the headers of the typed header fields from the `HeaderMap`
+    /// with the given name.
+    fn remove_typed_headers(&self, header_map: TokenStream) -> TokenStream {
+        let removals = self.fields.iter().filter_map(|response_field| match response_field {
+            ResponseField::Header(field, header_name) => {
+                let cfg_attrs = cfg_attrs(field);
+
+                Some(quote! {
+                    #(#cfg_attrs)*
+                    #header_map.remove(ruma_api::exports::http::header::#header_name);
+                })
+            }
+            _ => None,
+        });
+
+        quote! { #(#removals)* }
+    }
+
+    /// Produces code for a response struct initializer.
+    pub fn init_fields(&self) -> TokenStream {
+        let fields = self.fields.iter().map(|response_field| {
+            let field = response_field.field();
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let span = field.span();
+            let cfg_attrs = cfg_attrs(field);
+
+            let init = match response_field {
+                ResponseField::Body(_) => {
+                    quote_spanned! {span=>
+                        #field_name: response_body.#field_name
+                    }
+                }
+                ResponseField::Header(_, header_name) if is_option(&field.ty) => {
+                    // Not spanned to the field, the conversion is infallible for some types which
+                    // would otherwise be linted in the calling crate.
+                    let convert = quote! { std::convert::TryFrom::try_from(value).ok() };
+                    quote_spanned! {span=>
+                        #field_name: headers.remove(ruma_api::exports::http::header::#header_name)
+                            .and_then(|value| {
+                                let value = value.to_str().ok()?;
+                                #convert
+                            })
+                    }
+                }
+                ResponseField::Header(_, header_name) => {
+                    quote_spanned! {span=>
+                        #field_name: headers.remove(ruma_api::exports::http::header::#header_name)
+                            .expect("response missing expected header")
+                            .to_str()
+                            .expect("failed to convert HeaderValue to str")
+                            .to_owned()
+                    }
+                }
+                ResponseField::Cookie(_, cookie_name) if is_option(&field.ty) => {
+                    let convert = quote! { std::convert::TryFrom::try_from(value).ok() };
+                    quote_spanned! {span=>
+                        #field_name: ruma_api::cookie::response_cookie(&headers, #cookie_name)
+                            .and_then(|value| #convert)
+                    }
+                }
+                ResponseField::Cookie(_, cookie_name) => {
+                    quote_spanned! {span=>
+                        #field_name: match ruma_api::cookie::response_cookie(&headers, #cookie_name) {
+                            Some(cookie) => cookie,
+                            None => {
+                                return Err(
+                                    ruma_api::error::ResponseDeserializationError::new(
+                                        ruma_api::exports::serde_json::Error::missing_field(
+                                            #cookie_name
+                                        ),
+                                        response,
+                                    )
+                                    .into()
+                                );
+                            }
+                        }
+                    }
+                }
+                ResponseField::HeaderMap(_) => {
+                    let remove_typed_headers = self.remove_typed_headers(quote!(header_map));
+
+                    if remove_typed_headers.is_empty() {
+                        quote_spanned! {span=>
+                            #field_name: headers.clone()
+                        }
+                    } else {
+                        quote_spanned! {span=>
+                            #field_name: {
+                                let mut header_map = headers.clone();
+                                #remove_typed_headers
+                                header_map
+                            }
+                        }
+                    }
+                }
+                ResponseField::NewtypeBody(_) => {
+                    quote_spanned! {span=>
+                        #field_name: response_body.0
+                    }
+                }
+                ResponseField::NewtypeRawBody(field) => {
+                    let span = field.ty.span();
+                    quote_spanned! {span=>
+                        #field_name: std::convert::From::<Vec<u8>>::from(response.into_body())
+                    }
+                }
+            };
+
+            quote! {
+                #(#cfg_attrs)*
+                #init
+            }
+        });
+
+        quote! {
+            #(#fields,)*
+        }
+    }
+
+    /// Produces code to add necessary HTTP headers to an `http::response::Builder` named
+    /// `builder`.
+    pub fn apply_header_fields(&self) -> TokenStream {
+        let header_calls = self.fields.iter().filter_map(|response_field| {
+            if let ResponseField::Header(ref field, ref header_name) = *response_field {
+                let field_name =
+                    field.ident.as_ref().expect("expected field to have an identifier");
+                let span = field.span();
+                let cfg_attrs = cfg_attrs(field);
+
+                // A response with a `Content-Range` header only has part of the content.
+                let set_status = if header_name == "CONTENT_RANGE" {
+                    quote!(.status(ruma_api::exports::http::StatusCode::PARTIAL_CONTENT))
+                } else {
+                    TokenStream::new()
+                };
+
+                Some(if is_option(&field.ty) {
+                    quote_spanned! {span=>
+                        #(#cfg_attrs)*
+                        let builder = match response.#field_name {
+                            Some(value) => {
+                                builder
+                                    .header(ruma_api::exports::http::header::#header_name, value)
+                                    #set_status
+                            }
+                            None => builder,
+                        };
+                    }
+                } else {
+                    quote_spanned! {span=>
+                        #(#cfg_attrs)*
+                        let builder = builder.header(
+                            ruma_api::exports::http::header::#header_name,
+                            response.#field_name,
+                        )#set_status;
+                    }
+                })
+            } else {
+                None
+            }
+        });
+
+        let extend_headers = self.header_map_field().map(|field| {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let remove_typed_headers = self.remove_typed_headers(quote!(header_map));
+            let mutability = mutability(&remove_typed_headers);
+
+            quote! {
+                let mut builder = builder;
+                if let Some(headers) = builder.headers_mut() {
+                    let #mutability header_map = response.#field_name;
+                    #remove_typed_headers
+                    headers.extend(header_map);
+                }
+            }
+        });
+
+        let cookie_calls = self.fields.iter().filter_map(|response_field| {
+            if let ResponseField::Cookie(ref field, ref cookie_name) = *response_field {
+                let field_name =
+                    field.ident.as_ref().expect("expected field to have an identifier");
+                let span = field.span();
+                let cfg_attrs = cfg_attrs(field);
+
+                Some(if is_option(&field.ty) {
+                    quote_spanned! {span=>
+                        #(#cfg_attrs)*
+                        let builder = match response.#field_name {
+                            Some(value) => builder.header(
+                                ruma_api::exports::http::header::SET_COOKIE,
+                                ruma_api::cookie::set_cookie(#cookie_name, value.as_ref()),
+                            ),
+                            None => builder,
+                        };
+                    }
+                } else {
+                    quote_spanned! {span=>
+                        #(#cfg_attrs)*
+                        let builder = builder.header(
+                            ruma_api::exports::http::header::SET_COOKIE,
+                            ruma_api::cookie::set_cookie(#cookie_name, response.#field_name.as_ref()),
+                        );
+                    }
+                })
+            } else {
+                None
+            }
+        });
+
+        quote! {
+            #extend_headers
+            #(#header_calls)*
+            #(#cookie_calls)*
+        }
+    }
+
+    /// Produces code to initialize the struct that will be used to create the response body,
+    /// serializing JSON with the `to_vec` function of the given module.
+    pub fn to_body(&self, json: &TokenStream) -> TokenStream {
+        if let Some(field) = self.newtype_raw_body_field() {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let span = field.ty.span();
+            return quote_spanned!(span=> std::convert::Into::<Vec<u8>>::into(response.#field_name));
+        }
+
+        match self.json_body() {
+            Some(body) => quote!(#json::to_vec(&#body)?),
+            None => quote!(Vec::new()),
+        }
+    }
+
+    /// Produces code that writes the response body to an `io::Write` named `writer`, serializing
+    /// JSON with the `to_writer` function of the given module.
+    pub fn write_body(&self, json: &TokenStream) -> TokenStream {
+        if let Some(field) = self.newtype_raw_body_field() {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let span = field.ty.span();
+            return quote_spanned! {span=>
+                std::io::Write::write_all(
+                    &mut writer,
+                    &std::convert::Into::<Vec<u8>>::into(response.#field_name),
+                )?;
+            };
+        }
+
+        match self.json_body() {
+            Some(body) => quote! {
+                #json::to_writer(&mut writer, &#body)?;
+            },
+            None => TokenStream::new(),
+        }
+    }
+
+    /// Produces an expression for the value that is serialized as the JSON body, if any.
+    fn json_body(&self) -> Option<TokenStream> {
+        if let Some(field) = self.newtype_body_field() {
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let span = field.span();
+            return Some(quote_spanned!(span=> response.#field_name));
+        }
+
+        if !self.has_body_fields() {
+            return None;
+        }
+
+        let fields = self.fields.iter().filter_map(|response_field| {
+            if let ResponseField::Body(ref field) = *response_field {
+                let field_name =
+                    field.ident.as_ref().expect("expected field to have an identifier");
+                let span = field.span();
+                let cfg_attrs = cfg_attrs(field);
+
+                Some(quote_spanned! {span=>
+                    #(#cfg_attrs)*
+                    #field_name: response.#field_name
+                })
+            } else {
+                None
+            }
+        });
+
+        let renamed_fields =
+            RenamedField::compat_init_fields(&self.renamed_fields, quote!(response));
+
+        Some(quote! {
+            ResponseBody { #renamed_fields #(#fields),* }
+        })
+    }
+
+    /// Produces a `Default` implementation for responses whose fields all have defaults, i.e. are
+    /// `Option`al, `#[serde(default)]` or header map fields.
+    fn default_impl(&self) -> TokenStream {
+        let mut inits = Vec::new();
+        for response_field in &self.fields {
+            let field = response_field.field();
+            let field_name = field.ident.as_ref().expect("expected field to have an identifier");
+            let cfg_attrs = cfg_attrs(field);
+
+            match default_value(field, response_field.as_header_map_field().is_some()) {
+                Some(default) => inits.push(quote! { #(#cfg_attrs)* #field_name: #default }),
+                None => return TokenStream::new(),
+            }
+        }
+
+        let init =
+            if self.fields.is_empty() { TokenStream::new() } else { quote!({ #(#inits),* }) };
+
+        quote! {
+            impl std::default::Default for Response {
+                fn default() -> Self {
+                    Self #init
+                }
+            }
+        }
+    }
+}
+
+impl ToTokens for Response {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let response_def = if self.fields.is_empty() {
+            quote!(;)
+        } else {
+            let fields =
+                self.fields.iter().map(|response_field| strip_serde_attrs(response_field.field()));
+
+            quote! { { #(#fields),* } }
+        };
+
+        let response_body_struct =
+            if let Some(body_field) = self.fields.iter().find(|f| f.is_newtype_body()) {
+                let field = Field { ident: None, colon_token: None, ..body_field.field().clone() };
+                let derive_deserialize = if body_field.has_wrap_incoming_attr() {
+                    TokenStream::new()
+                } else {
+                    quote!(ruma_api::exports::serde::Deserialize)
+                };
+
+                Some((derive_deserialize, quote! { (#field); }))
+            } else if self.has_body_fields() {
+                let fields = self.fields.iter().filter(|f| f.is_body());
+                let derive_deserialize = if fields.clone().any(|f| f.has_wrap_incoming_attr()) {
+                    TokenStream::new()
+                } else {
+                    quote!(ruma_api::exports::serde::Deserialize)
+                };
+                let fields = fields.map(ResponseField::field);
+                let renamed_fields = RenamedField::compat_field_defs(&self.renamed_fields);
+
+                Some((derive_deserialize, quote!({ #(#fields,)* #renamed_fields })))
+            } else {
+                None
+            }
+            .map(|(derive_deserialize, def)| {
+                quote! {
+                    /// Data in the response body.
+                    #[derive(
+                        Debug,
+                        ruma_api::Outgoing,
+                        ruma_api::exports::serde::Serialize,
+                        #derive_deserialize
+                    )]
+                    #[serde(crate = "ruma_api::exports::serde")]
+                    struct ResponseBody #def
+                }
+            });
+
+        let default_impl = self.default_impl();
+
+        let response = quote! {
+            #[derive(Debug, Clone, ruma_api::Outgoing)]
+            #[incoming_no_deserialize]
+            pub struct Response #response_def
+
+            #default_impl
+
+            #response_body_struct
+        };
+
+        response.to_tokens(tokens);
+    }
+}
@@ -0,0 +1,146 @@
+//! The parsing stage of the `ruma_api` macro: the syntax of the macro input, before any of it is
+//! interpreted.
+
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    Attribute, Field, FieldValue, Ident, Item, Token, Visibility,
+};
+
+/// Custom keyword macros for syn.
+mod kw {
+    use syn::custom_keyword;
+
+    custom_keyword!(metadata);
+    custom_keyword!(request);
+    custom_keyword!(response);
+}
+
+/// The entire `ruma_api!` macro structure directly as it appears in the source code.
+pub struct RawApi {
+    /// Attributes at the start of the macro, like `#[ruma_api(generate_tests)]`.
+    pub attrs: Vec<Attribute>,
+    /// The optional `mod name;` declaration at the start of the macro.
+    pub module: Option<ModuleDecl>,
+    /// The `metadata` section of the macro.
+    pub metadata: RawMetadata,
+    /// The `request` section of the macro.
+    pub request: RawRequest,
+    /// The `response` section of the macro.
+    pub response: RawResponse,
+    /// Additional items after the `response` section.
+    pub items: Vec<Item>,
+}
+
+impl Parse for RawApi {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let module = if input.peek(Token![mod]) || input.peek(Token![pub]) {
+            Some(input.parse()?)
+        } else {
+            None
+        };
+        let metadata = input.parse()?;
+        let request = input.parse()?;
+        let response = input.parse()?;
+
+        let mut items = Vec::new();
+        while !input.is_empty() {
+            items.push(input.parse()?);
+        }
+
+        Ok(Self { attrs, module, metadata, request, response, items })
+    }
+}
+
+/// A `mod name;` declaration, which makes `ruma_api!` generate the endpoint inside a module of that
+/// name.
+pub struct ModuleDecl {
+    /// The visibility of the module.
+    pub vis: Visibility,
+    /// The name of the module.
+    pub ident: Ident,
+}
+
+impl Parse for ModuleDecl {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let vis = input.parse()?;
+        input.parse::<Token![mod]>()?;
+        let ident = input.parse()?;
+        input.parse::<Token![;]>()?;
+
+        Ok(Self { vis, ident })
+    }
+}
+
+/// The `metadata` section of the macro.
+pub struct RawMetadata {
+    /// The `metadata` keyword.
+    pub metadata_kw: kw::metadata,
+    /// The `name: value` pairs of the section.
+    pub field_values: Vec<FieldValue>,
+}
+
+impl Parse for RawMetadata {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let metadata_kw = input.parse::<kw::metadata>()?;
+        let field_values;
+        braced!(field_values in input);
+
+        Ok(Self {
+            metadata_kw,
+            field_values: field_values
+                .parse_terminated::<FieldValue, Token![,]>(FieldValue::parse)?
+                .into_iter()
+                .collect(),
+        })
+    }
+}
+
+/// The `request` section of the macro.
+pub struct RawRequest {
+    /// The `request` keyword.
+    pub request_kw: kw::request,
+    /// The fields of the section, with their attributes.
+    pub fields: Vec<Field>,
+}
+
+impl Parse for RawRequest {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let request_kw = input.parse::<kw::request>()?;
+        let fields;
+        braced!(fields in input);
+
+        Ok(Self {
+            request_kw,
+            fields: fields
+                .parse_terminated::<Field, Token![,]>(Field::parse_named)?
+                .into_iter()
+                .collect(),
+        })
+    }
+}
+
+/// The `response` section of the macro.
+pub struct RawResponse {
+    /// The `response` keyword.
+    pub response_kw: kw::response,
+    /// The fields of the section, with their attributes.
+    pub fields: Vec<Field>,
+}
+
+impl Parse for RawResponse {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let response_kw = input.parse::<kw::response>()?;
+        let fields;
+        braced!(fields in input);
+
+        Ok(Self {
+            response_kw,
+            fields: fields
+                .parse_terminated::<Field, Token![,]>(Field::parse_named)?
+                .into_iter()
+                .collect(),
+        })
+    }
+}
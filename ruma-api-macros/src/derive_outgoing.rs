@@ -4,7 +4,7 @@ use proc_macro2::{Ident, Span, TokenStream};
 use quote::{format_ident, quote, ToTokens};
 use syn::{
     parse_quote, punctuated::Pair, spanned::Spanned, Attribute, Data, DeriveInput, Fields,
-    GenericArgument, Path, PathArguments, Type, TypePath,
+    GenericArgument, Index, Path, PathArguments, Type, TypePath,
 };
 
 mod wrap_incoming;
@@ -24,10 +24,15 @@ pub fn expand_derive_outgoing(input: DeriveInput) -> syn::Result<TokenStream> {
         ));
     }
 
-    let derive_deserialize = if no_deserialize_in_attrs(&input.attrs) {
-        TokenStream::new()
+    // The crate attribute makes the derive use the serde of ruma-api, so crates that use the
+    // macros don't need a direct dependency on serde.
+    let (derive_deserialize, serde_crate) = if no_deserialize_in_attrs(&input.attrs) {
+        (TokenStream::new(), TokenStream::new())
     } else {
-        quote!(ruma_api::exports::serde::Deserialize)
+        (
+            quote!(ruma_api::exports::serde::Deserialize),
+            quote!(#[serde(crate = "ruma_api::exports::serde")]),
+        )
     };
 
     let (mut fields, struct_kind): (Vec<_>, _) = match input.data {
@@ -46,8 +51,10 @@ pub fn expand_derive_outgoing(input: DeriveInput) -> syn::Result<TokenStream> {
     };
 
     let mut any_attribute = false;
+    // The original types of the wrapped fields, by field index.
+    let mut original_types = vec![None; fields.len()];
 
-    for field in &mut fields {
+    for (field, original_type) in fields.iter_mut().zip(&mut original_types) {
         let mut field_meta = None;
 
         let mut remaining_attrs = Vec::new();
@@ -68,6 +75,7 @@ pub fn expand_derive_outgoing(input: DeriveInput) -> syn::Result<TokenStream> {
         field.attrs = remaining_attrs;
 
         if let Some(attr) = field_meta {
+            *original_type = Some(field.ty.clone());
             if let Some(type_to_wrap) = attr.type_to_wrap {
                 wrap_generic_arg(&type_to_wrap, &mut field.ty, attr.wrapper_type.as_ref())?;
             } else {
@@ -90,14 +98,54 @@ pub fn expand_derive_outgoing(input: DeriveInput) -> syn::Result<TokenStream> {
         StructKind::Tuple => quote! { ( #(#fields,)* ); },
     };
 
+    let bounds = fields.iter().zip(&original_types).filter_map(|(field, original_type)| {
+        let incoming_type = &field.ty;
+        original_type
+            .as_ref()
+            .map(|ty| quote! { for<'a> #incoming_type: ruma_api::TryIntoOutgoing<#ty> })
+    });
+    let conversions =
+        fields.iter().zip(&original_types).enumerate().map(|(i, (field, original_type))| {
+            let cfg_attrs = field.attrs.iter().filter(|attr| attr.path.is_ident("cfg"));
+            let member = match &field.ident {
+                Some(ident) => quote!(#ident),
+                None => {
+                    let index = Index::from(i);
+                    quote!(#index)
+                }
+            };
+            let value = if original_type.is_some() {
+                quote! { ruma_api::TryIntoOutgoing::try_into_outgoing(self.#member)? }
+            } else {
+                quote! { self.#member }
+            };
+
+            quote! { #(#cfg_attrs)* #member: #value }
+        });
+
     Ok(quote! {
         #[doc = #doc]
         #[derive(Debug, #derive_deserialize)]
+        #serde_crate
         #vis struct #incoming_ident #struct_def
 
         impl ruma_api::Outgoing for #original_ident {
             type Incoming = #incoming_ident;
         }
+
+        // The bounds are higher-ranked so they are only checked when the conversion is used.
+        // Otherwise, wrapper types without a `TryIntoOutgoing` implementation would make the
+        // derive fail.
+        impl ruma_api::TryIntoOutgoing<#original_ident> for #incoming_ident
+        where
+            #(#bounds,)*
+        {
+            fn try_into_outgoing(
+                self,
+            ) -> Result<#original_ident, ruma_api::partial::PartialError> {
+                Ok(#original_ident { #(#conversions,)* })
+            }
+        }
     })
 }
 
@@ -126,7 +174,7 @@ fn impl_outgoing_with_incoming_self(ident: Ident) -> TokenStream {
 
 fn wrap_ty(ty: &mut Type, path: Option<Path>) -> syn::Result<()> {
     if let Some(wrap_ty) = path {
-        *ty = parse_quote!(#wrap_ty<#ty>);
+        *ty = wrap_with(&wrap_ty, ty);
     } else {
         match ty {
             Type::Path(TypePath { path, .. }) => {
@@ -141,6 +189,21 @@ fn wrap_ty(ty: &mut Type, path: Option<Path>) -> syn::Result<()> {
     Ok(())
 }
 
+/// Wraps `ty` in `wrapper_type`. If the wrapper type already has generic arguments, like
+/// `Fallback<LegacyBody>`, `ty` is inserted as the first one.
+fn wrap_with(wrapper_type: &Path, ty: &Type) -> Type {
+    let mut wrapper_type = wrapper_type.clone();
+    let last_segment = wrapper_type.segments.last_mut().unwrap();
+
+    match &mut last_segment.arguments {
+        PathArguments::AngleBracketed(ab) => {
+            ab.args.insert(0, GenericArgument::Type(ty.clone()));
+            parse_quote!(#wrapper_type)
+        }
+        _ => parse_quote!(#wrapper_type<#ty>),
+    }
+}
+
 fn wrap_generic_arg(type_to_wrap: &Type, of: &mut Type, with: Option<&Path>) -> syn::Result<()> {
     let mut span = None;
     wrap_generic_arg_impl(type_to_wrap, of, with, &mut span)?;
@@ -197,7 +260,7 @@ fn wrap_generic_arg_impl(
             *span = Some(ty.span());
 
             if let Some(wrapper_type) = with {
-                *ty = parse_quote!(#wrapper_type<#ty>);
+                *ty = wrap_with(wrapper_type, ty);
             } else if let Type::Path(TypePath { path, .. }) = ty {
                 let ty_ident = &mut path.segments.last_mut().unwrap().ident;
                 let ident = format_ident!("Incoming{}", ty_ident, span = Span::call_site());
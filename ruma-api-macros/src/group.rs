@@ -0,0 +1,116 @@
+//! Details of the `ruma_api_group` procedural macro.
+
+use proc_macro2::TokenStream;
+use quote::{quote, ToTokens};
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    Attribute, Ident, Path, Token, Visibility,
+};
+
+/// The input of the `ruma_api_group` macro: a trait declaration listing endpoint modules.
+pub struct ApiGroup {
+    /// Attributes on the trait, like doc comments.
+    attrs: Vec<Attribute>,
+    /// The visibility of the trait.
+    vis: Visibility,
+    /// The name of the trait.
+    ident: Ident,
+    /// The endpoints in the group.
+    endpoints: Vec<GroupEndpoint>,
+}
+
+/// One endpoint in a `ruma_api_group!` invocation.
+struct GroupEndpoint {
+    /// Attributes on the endpoint, like doc comments.
+    attrs: Vec<Attribute>,
+    /// The name of the generated method.
+    method: Ident,
+    /// The path to the module containing the endpoint's `Request` and `Response` types.
+    module: Path,
+}
+
+impl Parse for ApiGroup {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let vis = input.parse()?;
+        input.parse::<Token![trait]>()?;
+        let ident = input.parse()?;
+
+        let content;
+        braced!(content in input);
+        let endpoints = content
+            .parse_terminated::<GroupEndpoint, Token![,]>(GroupEndpoint::parse)?
+            .into_iter()
+            .collect();
+
+        Ok(Self { attrs, vis, ident, endpoints })
+    }
+}
+
+impl Parse for GroupEndpoint {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let method: Ident = input.parse()?;
+        let module = if input.peek(Token![:]) {
+            input.parse::<Token![:]>()?;
+            input.parse()?
+        } else {
+            Path::from(method.clone())
+        };
+
+        Ok(Self { attrs, method, module })
+    }
+}
+
+impl ToTokens for ApiGroup {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let attrs = &self.attrs;
+        let vis = &self.vis;
+        let ident = &self.ident;
+
+        let methods = self.endpoints.iter().map(|endpoint| {
+            let attrs = &endpoint.attrs;
+            let method = &endpoint.method;
+            let module = &endpoint.module;
+            let doc = format!(
+                "Sends a request to the `{}` endpoint.",
+                module.segments.iter().map(|s| s.ident.to_string()).collect::<Vec<_>>().join("::")
+            );
+            let doc_attr = if attrs.iter().any(|attr| attr.path.is_ident("doc")) {
+                TokenStream::new()
+            } else {
+                quote! { #[doc = #doc] }
+            };
+
+            quote! {
+                #doc_attr
+                #(#attrs)*
+                fn #method(
+                    &self,
+                    request: #module::Request,
+                ) -> ruma_api::client::ResponseFuture<
+                    '_,
+                    <#module::Response as ruma_api::Outgoing>::Incoming,
+                    Self::Error,
+                >
+                where
+                    Self::Error: Send,
+                {
+                    ruma_api::client::send(self, request)
+                }
+            }
+        });
+
+        let group = quote! {
+            #(#attrs)*
+            #vis trait #ident: ruma_api::client::Transport {
+                #(#methods)*
+            }
+
+            impl<T: ruma_api::client::Transport + ?Sized> #ident for T {}
+        };
+
+        group.to_tokens(tokens);
+    }
+}
@@ -3,7 +3,10 @@
 //!
 //! This crate should never be used directly; instead, use it through the
 //! re-exports in ruma-api. Also note that for technical reasons, the
-//! `ruma_api!` macro is only documented in ruma-api, not here.
+//! `ruma_api!` and `ruma_api_group!` macros are only documented in ruma-api, not here.
+//!
+//! The generated code has to compile on the minimum Rust version of ruma-api, see its README.
+//! Anything that needs a newer compiler has to be gated behind the `nightly` feature.
 
 #![deny(missing_copy_implementations, missing_debug_implementations)]
 #![allow(clippy::cognitive_complexity)]
@@ -20,10 +23,12 @@ use syn::{parse_macro_input, DeriveInput};
 use self::{
     api::{Api, RawApi},
     derive_outgoing::expand_derive_outgoing,
+    group::ApiGroup,
 };
 
 mod api;
 mod derive_outgoing;
+mod group;
 
 #[proc_macro]
 pub fn ruma_api(input: TokenStream) -> TokenStream {
@@ -34,6 +39,11 @@ pub fn ruma_api(input: TokenStream) -> TokenStream {
     }
 }
 
+#[proc_macro]
+pub fn ruma_api_group(input: TokenStream) -> TokenStream {
+    parse_macro_input!(input as ApiGroup).into_token_stream().into()
+}
+
 /// Derive the `Outgoing` trait, possibly generating an 'Incoming' version of the struct this
 /// derive macro is used on. Specifically, if no `#[wrap_incoming]` attribute is used on any of the
 /// fields of the struct, this simple implementation will be generated:
@@ -60,6 +70,8 @@ pub fn ruma_api(input: TokenStream) -> TokenStream {
 ///     pub x: XEvent,
 ///     #[wrap_incoming(YEvent with EventResult)]
 ///     pub ys: Vec<YEvent>,
+///     #[wrap_incoming(with Fallback<LegacyZ>)]
+///     pub z: Z,
 /// }
 ///
 /// // generated
@@ -69,8 +81,16 @@ pub fn ruma_api(input: TokenStream) -> TokenStream {
 ///     pub baz: Option<IncomingBaz>,
 ///     pub x: EventResult<XEvent>,
 ///     pub ys: Vec<EventResult<YEvent>>,
+///     pub z: Fallback<Z, LegacyZ>,
 /// }
 /// ```
+///
+/// As shown for the last field, the wrapped type is inserted as the first generic argument of
+/// wrapper types that already have generic arguments.
+///
+/// The generated struct also implements `ruma_api::TryIntoOutgoing<MyType>`, which converts it
+/// back to `MyType` as long as all the wrapped field types implement `TryIntoOutgoing` for the
+/// original field types.
 // TODO: Make it clear that `#[wrap_incoming]` and `#[wrap_incoming(Type)]` without the "with" part
 // are (only) useful for fallible deserialization of nested structures.
 #[proc_macro_derive(Outgoing, attributes(wrap_incoming, incoming_no_deserialize))]
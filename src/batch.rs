@@ -0,0 +1,43 @@
+//! Per-item fallible deserialization of arrays in request and response bodies.
+//!
+//! Some bodies carry a batch of independent items, like the PDUs and EDUs of a federation
+//! transaction sent to `/_matrix/federation/v1/send/:txn_id`. One invalid item shouldn't make the
+//! whole batch fail to deserialize there, so it can be reported and the other items processed.
+//!
+//! In `ruma_api!`, body fields of type `Vec<T>` can use `#[ruma_api(batch)]` for this, which is a
+//! shorthand for `#[wrap_incoming(T with ruma_api::partial::Partial)]`:
+//!
+//! ```text
+//! request {
+//!     pub origin: String,
+//!     #[ruma_api(batch)]
+//!     pub pdus: Vec<Pdu>,
+//! }
+//! ```
+//!
+//! The field of the 'Incoming' type is then a `Vec<Partial<Pdu>>`. The array is still parsed in
+//! one pass, but each item is deserialized on its own and keeps its position in the array, so the
+//! functions of this module can tell which items failed.
+
+use crate::partial::{Partial, PartialError};
+
+/// The errors of the items of a batch that failed to deserialize, with their positions in the
+/// batch.
+pub fn errors<T>(items: &[Partial<T>]) -> impl Iterator<Item = (usize, &PartialError)> {
+    items.iter().enumerate().filter_map(|(i, item)| item.err().map(|err| (i, err)))
+}
+
+/// Splits a batch into the items that were deserialized successfully, in their original order,
+/// and the errors of the other items, with their positions in the batch.
+pub fn partition<T>(items: Vec<Partial<T>>) -> (Vec<T>, Vec<(usize, PartialError)>) {
+    let mut values = Vec::new();
+    let mut errors = Vec::new();
+    for (i, item) in items.into_iter().enumerate() {
+        match item {
+            Partial::Ok(value) => values.push(value),
+            Partial::Err(err) => errors.push((i, err)),
+        }
+    }
+
+    (values, errors)
+}
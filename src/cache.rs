@@ -0,0 +1,109 @@
+//! Keys that identify equivalent requests, for HTTP-level caching and request deduplication.
+//!
+//! Endpoints opt in with `cacheable: true` in the metadata section of `ruma_api!`, which makes
+//! [`Endpoint::cache_key`] return a key for their requests. Two requests have the same key if they
+//! are sent to the same endpoint with the same path and query string, no matter the order of the
+//! query parameters, so clients can answer one of them from a cache or coalesce them while the
+//! first is still in flight:
+//!
+//! ```
+//! # use std::collections::HashMap;
+//! # use ruma_api::{ruma_api, Endpoint};
+//! #
+//! # ruma_api! {
+//! #     mod get_profile;
+//! #
+//! #     metadata {
+//! #         description: "Get the profile of a user.",
+//! #         method: GET,
+//! #         name: "get_profile",
+//! #         path: "/_matrix/client/r0/profile/:user_id",
+//! #         rate_limited: false,
+//! #         requires_authentication: false,
+//! #         cacheable: true,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(path)]
+//! #         pub user_id: String,
+//! #     }
+//! #
+//! #     response {
+//! #         pub displayname: Option<String>,
+//! #     }
+//! # }
+//! #
+//! let mut cache = HashMap::new();
+//! let request = get_profile::Request { user_id: "@alice:example.org".to_owned() };
+//! let response = get_profile::Response { displayname: Some("Alice".to_owned()) };
+//! cache.insert(request.cache_key().unwrap(), response);
+//!
+//! // An identical request can be answered from the cache instead of being sent again.
+//! let request = get_profile::Request { user_id: "@alice:example.org".to_owned() };
+//! let cached = request.cache_key().and_then(|key| cache.get(&key));
+//! assert_eq!(cached.unwrap().displayname.as_deref(), Some("Alice"));
+//! ```
+//!
+//! Headers and the body are not part of the key, which is why only endpoints whose responses are
+//! fully determined by their path and query string should be marked as cacheable.
+//!
+//! [`Endpoint::cache_key`]: ../trait.Endpoint.html#method.cache_key
+
+use std::fmt::{self, Display, Formatter};
+
+/// A key that is equal for requests to the same endpoint with the same path and query string.
+///
+/// Its `Display` implementation joins the endpoint name, the path and the normalized query string,
+/// e.g. `get_room_event /_matrix/client/r0/rooms/!a:b/event/$c?format=full&limit=10`.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CacheKey {
+    name: &'static str,
+    path: String,
+    query: Vec<String>,
+}
+
+impl CacheKey {
+    /// Creates the key for an `http::Request` to the endpoint with the given name.
+    ///
+    /// The query string is normalized by sorting its `key=value` pairs and removing empty ones. The
+    /// pairs are compared as they appear in the URI, so the same value has to be percent-encoded
+    /// the same way for two keys to be equal, which is the case for generated requests.
+    pub fn from_http_request<T>(name: &'static str, http_request: &http::Request<T>) -> Self {
+        let uri = http_request.uri();
+        let mut query: Vec<String> = uri
+            .query()
+            .unwrap_or("")
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(ToOwned::to_owned)
+            .collect();
+        query.sort();
+
+        Self { name, path: uri.path().to_owned(), query }
+    }
+
+    /// The name of the endpoint.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The path of the request, with all path parameters filled in.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The sorted `key=value` pairs of the query string, still percent-encoded.
+    pub fn query(&self) -> &[String] {
+        &self.query
+    }
+}
+
+impl Display for CacheKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.name, self.path)?;
+        if !self.query.is_empty() {
+            write!(f, "?{}", self.query.join("&"))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+//! Validation of JSON bodies against the rules of Matrix canonical JSON.
+//!
+//! Bodies of federation requests and responses are signed, which is only possible if they can be
+//! represented as canonical JSON. Most notably, canonical JSON doesn't allow floats, and integers
+//! have to be in the range `[-(2^53) + 1, 2^53 - 1]`.
+//!
+//! Endpoints with `canonical_json: true` in the `metadata` section of `ruma_api!` serialize their
+//! bodies with [`to_vec`](fn.to_vec.html) and [`to_writer`](fn.to_writer.html), which fail with
+//! an `IntoHttpError` for which
+//! [`non_canonical_json`](../error/struct.IntoHttpError.html#method.non_canonical_json) returns
+//! the offending value instead of producing a payload that can't be signed.
+
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+};
+
+use serde::Serialize;
+use serde_json::{Number, Value as JsonValue};
+
+use crate::error::IntoHttpError;
+
+/// The largest integer allowed in canonical JSON, `2^53 - 1`.
+pub const MAX_SAFE_INTEGER: i64 = 9_007_199_254_740_991;
+
+/// The smallest integer allowed in canonical JSON, `-(2^53) + 1`.
+pub const MIN_SAFE_INTEGER: i64 = -MAX_SAFE_INTEGER;
+
+/// Serializes the given value as JSON, failing if it isn't valid canonical JSON.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, IntoHttpError> {
+    let json = serde_json::to_value(value)?;
+    validate(&json)?;
+    Ok(serde_json::to_vec(&json)?)
+}
+
+/// Serializes the given value as JSON into the given writer, failing without writing anything if
+/// it isn't valid canonical JSON.
+pub fn to_writer<W: io::Write, T: Serialize + ?Sized>(
+    writer: W,
+    value: &T,
+) -> Result<(), IntoHttpError> {
+    let json = serde_json::to_value(value)?;
+    validate(&json)?;
+    Ok(serde_json::to_writer(writer, &json)?)
+}
+
+/// Checks that all numbers in the given JSON value are allowed in canonical JSON.
+pub fn validate(json: &JsonValue) -> Result<(), NonCanonicalJson> {
+    let mut path = String::new();
+    validate_at(json, &mut path)
+}
+
+fn validate_at(json: &JsonValue, path: &mut String) -> Result<(), NonCanonicalJson> {
+    let len = path.len();
+    let result = match json {
+        JsonValue::Number(number) if !is_canonical(number) => {
+            Err(NonCanonicalJson { path: path.clone(), number: number.clone() })
+        }
+        JsonValue::Array(values) => values.iter().enumerate().try_for_each(|(i, value)| {
+            path.truncate(len);
+            path.push_str(&format!("[{}]", i));
+            validate_at(value, path)
+        }),
+        JsonValue::Object(map) => map.iter().try_for_each(|(key, value)| {
+            path.truncate(len);
+            if !path.is_empty() {
+                path.push('.');
+            }
+            path.push_str(key);
+            validate_at(value, path)
+        }),
+        _ => Ok(()),
+    };
+
+    path.truncate(len);
+    result
+}
+
+fn is_canonical(number: &Number) -> bool {
+    match number.as_i64() {
+        Some(int) => (MIN_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&int),
+        None => false,
+    }
+}
+
+/// An error when a JSON value contains a number that is not allowed in canonical JSON.
+#[derive(Clone, Debug)]
+pub struct NonCanonicalJson {
+    path: String,
+    number: Number,
+}
+
+impl NonCanonicalJson {
+    /// The path of the offending number in the JSON value, like `content.ratio` or `pdus[0].depth`.
+    ///
+    /// Empty if the value itself is the number.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The offending number.
+    pub fn number(&self) -> &Number {
+        &self.number
+    }
+
+    /// Whether the number is a float, as opposed to an integer outside of the allowed range.
+    pub fn is_float(&self) -> bool {
+        self.number.is_f64()
+    }
+}
+
+impl Display for NonCanonicalJson {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let reason =
+            if self.is_float() { "floats are not allowed" } else { "integer out of range" };
+
+        if self.path.is_empty() {
+            write!(f, "{}: `{}`", reason, self.number)
+        } else {
+            write!(f, "{} at `{}`: `{}`", reason, self.path, self.number)
+        }
+    }
+}
+
+impl std::error::Error for NonCanonicalJson {}
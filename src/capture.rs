@@ -0,0 +1,314 @@
+//! A portable JSON format for recorded requests and responses, which can be replayed through the
+//! incoming conversions.
+//!
+//! A [`Capture`] holds the exchanges with a server, e.g. recorded from live homeserver traffic,
+//! as [`CaptureEntry`]s. Each entry keeps the raw HTTP request and response together with the
+//! name of the endpoint, and, for JSON bodies, the parsed body for reading and diffing captures.
+//! Replaying an entry converts it to the endpoint's incoming types again, so a capture works as a
+//! regression suite for the conversions:
+//!
+//! ```no_run
+//! # use std::fs;
+//! # use ruma_api::{capture::Capture, ruma_api};
+//! #
+//! # ruma_api! {
+//! #     mod sync_events;
+//! #
+//! #     metadata {
+//! #         description: "Get the latest events.",
+//! #         method: GET,
+//! #         name: "sync",
+//! #         path: "/_matrix/client/r0/sync",
+//! #         rate_limited: false,
+//! #         requires_authentication: true,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(query)]
+//! #         pub since: Option<String>,
+//! #     }
+//! #
+//! #     response {
+//! #         pub next_batch: String,
+//! #     }
+//! # }
+//! #
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let capture: Capture = serde_json::from_str(&fs::read_to_string("sync.capture.json")?)?;
+//! for entry in capture.entries_for::<sync_events::Request>() {
+//!     entry.replay_response::<sync_events::Request>()?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Credentials are redacted when an entry is recorded, like in
+//! [request previews](../preview/index.html), so captures can be shared.
+//!
+//! [`Capture`]: struct.Capture.html
+//! [`CaptureEntry`]: struct.CaptureEntry.html
+
+#[cfg(any(feature = "client", feature = "server"))]
+use std::convert::TryFrom;
+use std::fmt::{self, Display, Formatter};
+
+use http::{
+    header::{HeaderName, HeaderValue},
+    HeaderMap, Method, StatusCode,
+};
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "server")]
+use crate::error::FromHttpRequestError;
+#[cfg(feature = "client")]
+use crate::error::FromHttpResponseError;
+use crate::{
+    conversion::{TryFromHttpRequest, TryFromHttpResponse},
+    options::{redact_access_token, redact_credential_headers},
+    Endpoint, Outgoing,
+};
+
+/// A recording of exchanges with a server.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Capture {
+    /// The recorded exchanges, in the order they happened in.
+    pub entries: Vec<CaptureEntry>,
+}
+
+impl Capture {
+    /// Creates an empty capture.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The entries for the endpoint `E`.
+    pub fn entries_for<E>(&self) -> impl Iterator<Item = &CaptureEntry>
+    where
+        E: Endpoint,
+        <E as Outgoing>::Incoming: TryFromHttpRequest,
+        <E::Response as Outgoing>::Incoming: TryFromHttpResponse,
+    {
+        self.entries.iter().filter(|entry| entry.endpoint == E::METADATA.name())
+    }
+}
+
+/// A request to an endpoint and the response to it.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct CaptureEntry {
+    endpoint: String,
+    request: CapturedRequest,
+    response: CapturedResponse,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CapturedRequest {
+    #[serde(with = "crate::method_serde")]
+    method: Method,
+    uri: String,
+    headers: Vec<(String, String)>,
+    body: CapturedBody,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    json: Option<serde_json::Value>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct CapturedResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: CapturedBody,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    json: Option<serde_json::Value>,
+}
+
+/// A body as text if it is valid UTF-8, and as bytes otherwise.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum CapturedBody {
+    Text(String),
+    Bytes(Vec<u8>),
+}
+
+impl CaptureEntry {
+    /// Records a request to the endpoint `E` and the response to it.
+    pub fn new<E>(
+        http_request: &http::Request<Vec<u8>>,
+        http_response: &http::Response<Vec<u8>>,
+    ) -> Self
+    where
+        E: Endpoint,
+        <E as Outgoing>::Incoming: TryFromHttpRequest,
+        <E::Response as Outgoing>::Incoming: TryFromHttpResponse,
+    {
+        let mut request_headers = http_request.headers().clone();
+        redact_credential_headers(&mut request_headers);
+
+        Self {
+            endpoint: E::METADATA.name().to_owned(),
+            request: CapturedRequest {
+                method: http_request.method().clone(),
+                uri: redact_access_token(http_request.uri()).to_string(),
+                headers: capture_headers(&request_headers),
+                body: CapturedBody::new(http_request.body()),
+                json: serde_json::from_slice(http_request.body()).ok(),
+            },
+            response: CapturedResponse {
+                status: http_response.status().as_u16(),
+                headers: capture_headers(http_response.headers()),
+                body: CapturedBody::new(http_response.body()),
+                json: serde_json::from_slice(http_response.body()).ok(),
+            },
+        }
+    }
+
+    /// The name of the endpoint the request was sent to.
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// The parsed body of the request, if it is JSON.
+    pub fn request_json(&self) -> Option<&serde_json::Value> {
+        self.request.json.as_ref()
+    }
+
+    /// The parsed body of the response, if it is JSON.
+    pub fn response_json(&self) -> Option<&serde_json::Value> {
+        self.response.json.as_ref()
+    }
+
+    /// Recreates the recorded `http::Request`.
+    pub fn to_http_request(&self) -> Result<http::Request<Vec<u8>>, ReplayError> {
+        let mut http_request = http::Request::new(self.request.body.to_bytes());
+        *http_request.method_mut() = self.request.method.clone();
+        *http_request.uri_mut() =
+            self.request.uri.parse().map_err(|_| ReplayError::InvalidCapture("uri"))?;
+        *http_request.headers_mut() = replay_headers(&self.request.headers)?;
+        Ok(http_request)
+    }
+
+    /// Recreates the recorded `http::Response`.
+    pub fn to_http_response(&self) -> Result<http::Response<Vec<u8>>, ReplayError> {
+        let mut http_response = http::Response::new(self.response.body.to_bytes());
+        *http_response.status_mut() = StatusCode::from_u16(self.response.status)
+            .map_err(|_| ReplayError::InvalidCapture("status"))?;
+        *http_response.headers_mut() = replay_headers(&self.response.headers)?;
+        Ok(http_response)
+    }
+
+    /// Converts the recorded request to the incoming request type of the endpoint `E`.
+    #[cfg(feature = "server")]
+    pub fn replay_request<E>(&self) -> Result<<E as Outgoing>::Incoming, ReplayError>
+    where
+        E: Endpoint,
+        <E as Outgoing>::Incoming: TryFromHttpRequest,
+        <E::Response as Outgoing>::Incoming: TryFromHttpResponse,
+    {
+        self.check_endpoint(E::METADATA.name())?;
+        <E as Outgoing>::Incoming::try_from(self.to_http_request()?).map_err(ReplayError::Request)
+    }
+
+    /// Converts the recorded response to the incoming response type of the endpoint `E`.
+    ///
+    /// Error responses are returned as `ReplayError::Response`, like they are by the conversion.
+    #[cfg(feature = "client")]
+    pub fn replay_response<E>(&self) -> Result<<E::Response as Outgoing>::Incoming, ReplayError>
+    where
+        E: Endpoint,
+        <E as Outgoing>::Incoming: TryFromHttpRequest,
+        <E::Response as Outgoing>::Incoming: TryFromHttpResponse,
+    {
+        self.check_endpoint(E::METADATA.name())?;
+        <E::Response as Outgoing>::Incoming::try_from(self.to_http_response()?)
+            .map_err(|err| ReplayError::Response(Box::new(err)))
+    }
+
+    #[cfg_attr(not(any(feature = "client", feature = "server")), allow(dead_code))]
+    fn check_endpoint(&self, expected: &'static str) -> Result<(), ReplayError> {
+        if self.endpoint == expected {
+            Ok(())
+        } else {
+            Err(ReplayError::EndpointMismatch { expected, found: self.endpoint.clone() })
+        }
+    }
+}
+
+impl CapturedBody {
+    fn new(body: &[u8]) -> Self {
+        match String::from_utf8(body.to_owned()) {
+            Ok(text) => Self::Text(text),
+            Err(err) => Self::Bytes(err.into_bytes()),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            Self::Text(text) => text.as_bytes().to_owned(),
+            Self::Bytes(bytes) => bytes.clone(),
+        }
+    }
+}
+
+/// The headers in their recorded form. Values that aren't valid UTF-8 are converted lossily.
+fn capture_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            (name.as_str().to_owned(), String::from_utf8_lossy(value.as_bytes()).into_owned())
+        })
+        .collect()
+}
+
+fn replay_headers(headers: &[(String, String)]) -> Result<HeaderMap, ReplayError> {
+    let mut header_map = HeaderMap::with_capacity(headers.len());
+    for (name, value) in headers {
+        header_map.append(
+            HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| ReplayError::InvalidCapture("header name"))?,
+            HeaderValue::from_str(value)
+                .map_err(|_| ReplayError::InvalidCapture("header value"))?,
+        );
+    }
+    Ok(header_map)
+}
+
+/// An error when replaying a [`CaptureEntry`](struct.CaptureEntry.html).
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ReplayError {
+    /// The entry was recorded for another endpoint.
+    EndpointMismatch {
+        /// The name of the endpoint the entry was replayed for.
+        expected: &'static str,
+        /// The name of the endpoint the entry was recorded for.
+        found: String,
+    },
+
+    /// A part of the recorded request or response, like its URI, is invalid.
+    InvalidCapture(&'static str),
+
+    /// Converting the recorded request failed.
+    #[cfg(feature = "server")]
+    Request(FromHttpRequestError),
+
+    /// Converting the recorded response failed, or it is an error response.
+    ///
+    /// The error is boxed since it is much larger than the other variants.
+    #[cfg(feature = "client")]
+    Response(Box<FromHttpResponseError>),
+}
+
+impl Display for ReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EndpointMismatch { expected, found } => {
+                write!(f, "expected an entry for `{}`, found one for `{}`", expected, found)
+            }
+            Self::InvalidCapture(part) => write!(f, "the captured {} is invalid", part),
+            #[cfg(feature = "server")]
+            Self::Request(err) => write!(f, "failed to convert the request: {}", err),
+            #[cfg(feature = "client")]
+            Self::Response(err) => write!(f, "failed to convert the response: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
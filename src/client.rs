@@ -0,0 +1,361 @@
+//! Sending requests to endpoints over a generic transport.
+//!
+//! This is the runtime support for traits generated by
+//! [`ruma_api_group!`](../macro.ruma_api_group.html), but `send` can also be used on its own.
+
+use std::{
+    cmp,
+    convert::TryFrom,
+    fmt::{self, Debug, Display, Formatter},
+    future::Future,
+    io::{self, Read},
+    mem,
+    pin::Pin,
+};
+
+use crate::{
+    conversion::TryFromHttpRequest,
+    error::{FromHttpResponseError, IntoHttpError},
+    options::RequestOptions,
+    Endpoint, Outgoing,
+};
+
+/// A boxed future resolving to the result of sending a request.
+pub type ResponseFuture<'a, T, E> =
+    Pin<Box<dyn Future<Output = Result<T, SendError<E>>> + Send + 'a>>;
+
+/// A boxed future resolving to the HTTP response of a [`Transport`](trait.Transport.html).
+pub type HttpResponseFuture<'a, E> =
+    Pin<Box<dyn Future<Output = Result<http::Response<Vec<u8>>, E>> + Send + 'a>>;
+
+/// Something that can send HTTP requests, like an HTTP client.
+///
+/// Requests created by ruma-api use a placeholder host in their URI, so implementations have to
+/// replace the scheme and authority of the request's URI with the ones of the server they are
+/// sending to. The exception are requests converted with
+/// [`RequestOptions::base_url`](../options/struct.RequestOptions.html#structfield.base_url) set,
+/// like the ones sent by [`discover_homeserver`](../well_known/fn.discover_homeserver.html), which
+/// already have the right scheme and authority. If
+/// [`RequestOptions::connect_to`](../options/struct.RequestOptions.html#structfield.connect_to)
+/// was set, the authority of the URI is the address to connect to, and
+/// [`tls_server_name`](../options/fn.tls_server_name.html) gives the name to verify the server's
+/// certificate for.
+///
+/// Transports need to be `Sync` so the futures returned by [`send`](fn.send.html) are `Send`.
+pub trait Transport: Sync {
+    /// The error returned when sending a request fails.
+    type Error;
+
+    /// The options used to convert requests before they are passed to `send_http`.
+    ///
+    /// This can be used to set options like the `User-Agent` for all requests sent through this
+    /// transport.
+    fn request_options(&self) -> RequestOptions {
+        RequestOptions::default()
+    }
+
+    /// Sends the given HTTP request.
+    fn send_http(&self, request: http::Request<Vec<u8>>) -> HttpResponseFuture<'_, Self::Error>;
+
+    /// Sends the given HTTP request, reporting the progress of sending its body through the
+    /// [`ProgressBody`](struct.ProgressBody.html).
+    ///
+    /// Transports that can send request bodies in pieces should override this and read the body
+    /// in chunks, through its `Iterator` or `io::Read` implementation, as they send it. The
+    /// default implementation passes the whole body to `send_http`, so it is reported as sent
+    /// completely right away.
+    fn send_http_with_progress(
+        &self,
+        request: http::Request<ProgressBody>,
+    ) -> HttpResponseFuture<'_, Self::Error> {
+        self.send_http(request.map(ProgressBody::into_bytes))
+    }
+}
+
+/// Sends a request to its endpoint using the given transport.
+///
+/// The request is converted with the transport's
+/// [`request_options`](trait.Transport.html#method.request_options), adjusted to the endpoint
+/// with [`RequestOptions::for_endpoint`](../options/struct.RequestOptions.html#method.for_endpoint).
+pub fn send<'a, T, E>(
+    transport: &'a T,
+    request: E,
+) -> ResponseFuture<'a, <E::Response as Outgoing>::Incoming, T::Error>
+where
+    T: Transport + ?Sized,
+    E: Endpoint,
+    <E as Outgoing>::Incoming: TryFromHttpRequest,
+    <E::Response as Outgoing>::Incoming:
+        TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError> + Send,
+    T::Error: Send,
+{
+    send_with_options(transport, request, &transport.request_options())
+}
+
+/// Sends a request to its endpoint using the given transport, converting it with the given
+/// options instead of the transport's.
+///
+/// Like with [`send`](fn.send.html), the options are adjusted to the endpoint first.
+pub fn send_with_options<'a, T, E>(
+    transport: &'a T,
+    request: E,
+    options: &RequestOptions,
+) -> ResponseFuture<'a, <E::Response as Outgoing>::Incoming, T::Error>
+where
+    T: Transport + ?Sized,
+    E: Endpoint,
+    <E as Outgoing>::Incoming: TryFromHttpRequest,
+    <E::Response as Outgoing>::Incoming:
+        TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError> + Send,
+    T::Error: Send,
+{
+    send_converted(
+        transport,
+        request.try_into_http_request_with(&options.for_endpoint(&E::METADATA)),
+    )
+}
+
+/// Sends a request to its endpoint using the given transport, changing the generated
+/// `http::Request` with the given closure before it is sent.
+///
+/// The closure runs after the transport's
+/// [`request_options`](trait.Transport.html#method.request_options) were applied, e.g. to add
+/// query parameters for application services or experimental headers.
+pub fn send_customized<'a, T, E, F>(
+    transport: &'a T,
+    request: E,
+    customize: F,
+) -> ResponseFuture<'a, <E::Response as Outgoing>::Incoming, T::Error>
+where
+    T: Transport + ?Sized,
+    E: Endpoint,
+    <E as Outgoing>::Incoming: TryFromHttpRequest,
+    <E::Response as Outgoing>::Incoming:
+        TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError> + Send,
+    T::Error: Send,
+    F: FnOnce(&mut http::Request<Vec<u8>>),
+{
+    let options = transport.request_options().for_endpoint(&E::METADATA);
+    let http_request = request.try_into_http_request_with(&options).map(|mut http_request| {
+        customize(&mut http_request);
+        http_request
+    });
+
+    send_converted(transport, http_request)
+}
+
+/// Sends a request to its endpoint using the given transport, calling `on_progress` as its body
+/// is sent.
+///
+/// This is meant for large uploads like media, to display their progress. How often `on_progress`
+/// is called depends on the transport, see
+/// [`Transport::send_http_with_progress`](trait.Transport.html#method.send_http_with_progress).
+pub fn send_with_progress<'a, T, E, F>(
+    transport: &'a T,
+    request: E,
+    on_progress: F,
+) -> ResponseFuture<'a, <E::Response as Outgoing>::Incoming, T::Error>
+where
+    T: Transport + ?Sized,
+    E: Endpoint,
+    <E as Outgoing>::Incoming: TryFromHttpRequest,
+    <E::Response as Outgoing>::Incoming:
+        TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError> + Send,
+    T::Error: Send,
+    F: FnMut(UploadProgress) + Send + 'static,
+{
+    let http_request = request
+        .try_into_http_request_with(&transport.request_options().for_endpoint(&E::METADATA))
+        .map(|http_request| http_request.map(|body| ProgressBody::new(body, on_progress)));
+
+    Box::pin(async move {
+        let http_response = transport
+            .send_http_with_progress(http_request.map_err(SendError::IntoHttp)?)
+            .await
+            .map_err(SendError::Transport)?;
+
+        TryFrom::try_from(http_response).map_err(SendError::FromHttpResponse)
+    })
+}
+
+/// Sends an already converted request.
+fn send_converted<'a, T, R>(
+    transport: &'a T,
+    http_request: Result<http::Request<Vec<u8>>, IntoHttpError>,
+) -> ResponseFuture<'a, R, T::Error>
+where
+    T: Transport + ?Sized,
+    R: TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError> + Send,
+    T::Error: Send,
+{
+    Box::pin(async move {
+        let http_response = transport
+            .send_http(http_request.map_err(SendError::IntoHttp)?)
+            .await
+            .map_err(SendError::Transport)?;
+
+        TryFrom::try_from(http_response).map_err(SendError::FromHttpResponse)
+    })
+}
+
+/// An error when sending a request with [`send`](fn.send.html).
+#[derive(Debug)]
+pub enum SendError<E> {
+    /// Converting the request to an HTTP request failed.
+    IntoHttp(IntoHttpError),
+    /// The transport failed to send the request.
+    Transport(E),
+    /// Converting the HTTP response failed, or the server returned an error.
+    FromHttpResponse(FromHttpResponseError),
+}
+
+impl<E: Display> Display for SendError<E> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IntoHttp(err) => write!(f, "failed to create the HTTP request: {}", err),
+            Self::Transport(err) => write!(f, "failed to send the request: {}", err),
+            Self::FromHttpResponse(err) => write!(f, "failed to convert the response: {}", err),
+        }
+    }
+}
+
+impl<E: std::error::Error> std::error::Error for SendError<E> {}
+
+/// How much of a request body was sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UploadProgress {
+    /// The number of bytes sent so far.
+    pub sent: usize,
+    /// The size of the body in bytes.
+    pub total: usize,
+}
+
+/// A request body that reports how much of it was read, for displaying the progress of uploads.
+///
+/// The body is read in chunks of at most [`chunk_size`](#method.chunk_size) bytes, through its
+/// `Iterator` or `io::Read` implementation. The progress callback is called after every chunk.
+pub struct ProgressBody {
+    /// The body.
+    data: Vec<u8>,
+    /// The number of bytes read so far.
+    position: usize,
+    /// The maximum number of bytes read at once.
+    chunk_size: usize,
+    /// The progress callback.
+    on_progress: Box<dyn FnMut(UploadProgress) + Send>,
+}
+
+impl ProgressBody {
+    /// The default chunk size, 64 KiB.
+    pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+    /// Creates a new `ProgressBody` that calls `on_progress` whenever a chunk of `data` was read.
+    pub fn new<F>(data: Vec<u8>, on_progress: F) -> Self
+    where
+        F: FnMut(UploadProgress) + Send + 'static,
+    {
+        Self {
+            data,
+            position: 0,
+            chunk_size: Self::DEFAULT_CHUNK_SIZE,
+            on_progress: Box::new(on_progress),
+        }
+    }
+
+    /// Sets the maximum number of bytes read at once.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is zero.
+    pub fn with_chunk_size(mut self, chunk_size: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must not be zero");
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    /// The maximum number of bytes read at once.
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    /// The size of the body in bytes, e.g. for the `Content-Length` header.
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Whether the body is empty.
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// How much of the body was read so far.
+    pub fn progress(&self) -> UploadProgress {
+        UploadProgress { sent: self.position, total: self.data.len() }
+    }
+
+    /// Returns the rest of the body in one piece and reports it as read.
+    ///
+    /// This is for transports that can't send bodies in pieces.
+    pub fn into_bytes(mut self) -> Vec<u8> {
+        let mut data = mem::take(&mut self.data);
+        if self.position < data.len() {
+            let progress = UploadProgress { sent: data.len(), total: data.len() };
+            data.drain(..self.position);
+            (self.on_progress)(progress);
+        }
+
+        data
+    }
+
+    /// Marks the next `len` bytes as read and reports the progress. Returns the range of the
+    /// chunk.
+    fn advance(&mut self, len: usize) -> (usize, usize) {
+        let start = self.position;
+        self.position += len;
+        let progress = self.progress();
+        (self.on_progress)(progress);
+        (start, self.position)
+    }
+
+    /// The length of the next chunk, at most `max` bytes.
+    fn next_chunk_len(&self, max: usize) -> usize {
+        cmp::min(cmp::min(self.chunk_size, max), self.data.len() - self.position)
+    }
+}
+
+impl Iterator for ProgressBody {
+    type Item = Vec<u8>;
+
+    fn next(&mut self) -> Option<Vec<u8>> {
+        let len = self.next_chunk_len(self.chunk_size);
+        if len == 0 {
+            return None;
+        }
+
+        let (start, end) = self.advance(len);
+        Some(self.data[start..end].to_vec())
+    }
+}
+
+impl Read for ProgressBody {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.next_chunk_len(buf.len());
+        if len == 0 {
+            return Ok(0);
+        }
+
+        let (start, end) = self.advance(len);
+        buf[..len].copy_from_slice(&self.data[start..end]);
+        Ok(len)
+    }
+}
+
+impl Debug for ProgressBody {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProgressBody")
+            .field("len", &self.data.len())
+            .field("position", &self.position)
+            .field("chunk_size", &self.chunk_size)
+            .finish()
+    }
+}
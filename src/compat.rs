@@ -0,0 +1,50 @@
+//! Relaxed deserialization used by `ruma_api!` generated code when the `compat` feature is enabled.
+//!
+//! Some homeserver implementations deviate from the spec in small ways that would otherwise make
+//! whole responses fail to parse. With the `compat` feature, body fields of generated request and
+//! response types are deserialized using the functions in this module:
+//!
+//! * fields of type `Option<_>` fall back to `None` if their value is invalid
+//! * integer fields (`u8` through `u64`, `i8` through `i64`, `usize`, `isize`, `UInt` and `Int`)
+//!   also accept numbers encoded as strings, like `"10"`
+//! * fields with `#[serde(default)]` use the default value if their value is `null`
+//!
+//! Fields that already have a `#[serde(with = "...")]` or `#[serde(deserialize_with = "...")]`
+//! attribute and fields with `#[wrap_incoming]` are left as they are.
+
+use std::{fmt::Display, str::FromStr};
+
+use serde::{de::Error as _, Deserialize, Deserializer};
+use serde_json::Value as JsonValue;
+
+/// Deserializes an `Option<T>`, using `None` if the value isn't a valid `T`.
+pub fn ignore_invalid<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: for<'a> Deserialize<'a>,
+{
+    let value = JsonValue::deserialize(deserializer)?;
+    Ok(serde_json::from_value(value).ok())
+}
+
+/// Deserializes an integer, also accepting it encoded as a string.
+pub fn int_or_string<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: for<'a> Deserialize<'a> + FromStr,
+    T::Err: Display,
+{
+    match JsonValue::deserialize(deserializer)? {
+        JsonValue::String(s) => s.parse().map_err(D::Error::custom),
+        value => serde_json::from_value(value).map_err(D::Error::custom),
+    }
+}
+
+/// Deserializes a `T`, using `T::default()` if the value is `null`.
+pub fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + Default,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
@@ -0,0 +1,243 @@
+//! Types for conditional requests, as defined in RFC 7232.
+//!
+//! Endpoints with optimistic concurrency control, like ones that write account data or state,
+//! return the entity tag of a resource in the `ETag` header and accept the tag the client last
+//! saw in the `If-Match` header of writes:
+//!
+//! ```text
+//! request {
+//!     #[ruma_api(header = IF_MATCH)]
+//!     pub if_match: Option<IfMatch>,
+//! }
+//!
+//! response {
+//!     #[ruma_api(header = ETAG)]
+//!     pub etag: Option<EntityTag>,
+//! }
+//! ```
+//!
+//! If the resource was changed by someone else in the meantime, the server rejects the write with
+//! `412 Precondition Failed`, which is classified as
+//! [`ErrorClassification::PreconditionFailed`].
+//!
+//! [`ErrorClassification::PreconditionFailed`]:
+//! ../error/enum.ErrorClassification.html#variant.PreconditionFailed
+
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+};
+
+use http::header::{HeaderValue, InvalidHeaderValue};
+
+/// An entity tag, the value of an `ETag` header.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct EntityTag {
+    /// Whether this is a weak tag, which only identifies semantically equivalent content.
+    weak: bool,
+
+    /// The opaque tag, without quotes.
+    tag: String,
+}
+
+impl EntityTag {
+    /// Creates a strong entity tag, which changes whenever the content changes.
+    ///
+    /// Fails if the tag contains characters that are not allowed in entity tags, like `"`.
+    pub fn strong(tag: impl Into<String>) -> Result<Self, InvalidEntityTag> {
+        Self::new(tag.into(), false)
+    }
+
+    /// Creates a weak entity tag, which only changes when the content changes semantically.
+    ///
+    /// Fails if the tag contains characters that are not allowed in entity tags, like `"`.
+    pub fn weak(tag: impl Into<String>) -> Result<Self, InvalidEntityTag> {
+        Self::new(tag.into(), true)
+    }
+
+    fn new(tag: String, weak: bool) -> Result<Self, InvalidEntityTag> {
+        if tag.chars().all(is_etag_char) {
+            Ok(Self { weak, tag })
+        } else {
+            Err(InvalidEntityTag)
+        }
+    }
+
+    /// The opaque tag, without quotes and the `W/` prefix of weak tags.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// Whether this is a weak tag.
+    pub fn is_weak(&self) -> bool {
+        self.weak
+    }
+
+    /// The strong comparison of RFC 7232: both tags are strong and have the same opaque tag.
+    pub fn strong_eq(&self, other: &EntityTag) -> bool {
+        !self.weak && !other.weak && self.tag == other.tag
+    }
+
+    /// The weak comparison of RFC 7232: both tags have the same opaque tag.
+    pub fn weak_eq(&self, other: &EntityTag) -> bool {
+        self.tag == other.tag
+    }
+}
+
+impl Display for EntityTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.weak {
+            f.write_str("W/")?;
+        }
+
+        write!(f, "\"{}\"", self.tag)
+    }
+}
+
+impl TryFrom<&str> for EntityTag {
+    type Error = InvalidEntityTag;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let mut parser = Parser { rest: s.trim() };
+        let entity_tag = parser.entity_tag()?;
+        if !parser.rest.is_empty() {
+            return Err(InvalidEntityTag);
+        }
+
+        Ok(entity_tag)
+    }
+}
+
+impl TryFrom<EntityTag> for HeaderValue {
+    type Error = InvalidHeaderValue;
+
+    fn try_from(entity_tag: EntityTag) -> Result<Self, Self::Error> {
+        HeaderValue::from_str(&entity_tag.to_string())
+    }
+}
+
+/// The value of an `If-Match` header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum IfMatch {
+    /// `*`: the resource exists, whatever its current entity tag.
+    Any,
+
+    /// The resource has one of the given entity tags.
+    Tags(Vec<EntityTag>),
+}
+
+impl IfMatch {
+    /// Whether a resource with the given current entity tag fulfills the condition, `None`
+    /// meaning that the resource doesn't exist.
+    ///
+    /// As required by RFC 7232, tags are compared with the strong comparison, so weak tags never
+    /// match.
+    pub fn matches(&self, current: Option<&EntityTag>) -> bool {
+        match (self, current) {
+            (_, None) => false,
+            (IfMatch::Any, Some(_)) => true,
+            (IfMatch::Tags(tags), Some(current)) => tags.iter().any(|tag| tag.strong_eq(current)),
+        }
+    }
+}
+
+impl From<EntityTag> for IfMatch {
+    fn from(entity_tag: EntityTag) -> Self {
+        IfMatch::Tags(vec![entity_tag])
+    }
+}
+
+impl Display for IfMatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            IfMatch::Any => f.write_str("*"),
+            IfMatch::Tags(tags) => {
+                for (i, tag) in tags.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", tag)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+impl TryFrom<&str> for IfMatch {
+    type Error = InvalidEntityTag;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let s = s.trim();
+        if s == "*" {
+            return Ok(IfMatch::Any);
+        }
+
+        // Entity tags can contain commas, so the list can't simply be split at them.
+        let mut parser = Parser { rest: s };
+        let mut tags = Vec::new();
+        loop {
+            tags.push(parser.entity_tag()?);
+
+            parser.rest = parser.rest.trim_start();
+            if parser.rest.is_empty() {
+                return Ok(IfMatch::Tags(tags));
+            }
+            if !parser.rest.starts_with(',') {
+                return Err(InvalidEntityTag);
+            }
+            parser.rest = parser.rest[1..].trim_start();
+        }
+    }
+}
+
+impl TryFrom<IfMatch> for HeaderValue {
+    type Error = InvalidHeaderValue;
+
+    fn try_from(if_match: IfMatch) -> Result<Self, Self::Error> {
+        HeaderValue::from_str(&if_match.to_string())
+    }
+}
+
+/// An error when creating or parsing an entity tag.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidEntityTag;
+
+impl Display for InvalidEntityTag {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid entity tag")
+    }
+}
+
+impl std::error::Error for InvalidEntityTag {}
+
+/// A minimal parser for lists of entity tags.
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    /// Parses an entity tag like `"abc"` or `W/"abc"`.
+    fn entity_tag(&mut self) -> Result<EntityTag, InvalidEntityTag> {
+        let weak = self.rest.starts_with("W/");
+        if weak {
+            self.rest = &self.rest[2..];
+        }
+
+        if !self.rest.starts_with('"') {
+            return Err(InvalidEntityTag);
+        }
+        let end = self.rest[1..].find('"').ok_or(InvalidEntityTag)? + 1;
+        let tag = &self.rest[1..end];
+        self.rest = &self.rest[end + 1..];
+
+        EntityTag::new(tag.to_owned(), weak)
+    }
+}
+
+/// Whether the character is allowed in an opaque tag, as defined in RFC 7232, except for the
+/// obsolete non-ASCII characters.
+fn is_etag_char(c: char) -> bool {
+    c == '!' || ('#'..='~').contains(&c)
+}
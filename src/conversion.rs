@@ -0,0 +1,105 @@
+//! The HTTP conversions of requests and responses, depending on the `client` and `server` features.
+//!
+//! Clients only convert requests into `http::Request`s and `http::Response`s into responses,
+//! servers only need the opposite direction. `ruma_api!` only generates the conversions for the
+//! enabled features, and the traits in this module are what [`Endpoint`](../trait.Endpoint.html)
+//! requires of its request and response types:
+//!
+//! * With the feature that needs a conversion, the trait is implemented for exactly the types that
+//!   have it.
+//! * Without that feature, the trait has no requirements and is implemented for all types.
+//!
+//! Servers that route requests before reading their bodies can also convert an incoming request in
+//! two steps, with the `try_from_parts` function `ruma_api!` generates for request types and
+//! [`PartialIncoming`](struct.PartialIncoming.html).
+
+#[cfg(feature = "server")]
+use std::{convert::TryFrom, marker::PhantomData};
+
+#[cfg(feature = "server")]
+use crate::error::FromHttpRequestError;
+
+macro_rules! conversion_trait {
+    ($feature:literal, $(#[$attr:meta])* $name:ident: $($bound:tt)+) => {
+        $(#[$attr])*
+        #[cfg(feature = $feature)]
+        pub trait $name: $($bound)+ {}
+
+        #[cfg(feature = $feature)]
+        impl<T: $($bound)+> $name for T {}
+
+        $(#[$attr])*
+        #[cfg(not(feature = $feature))]
+        pub trait $name {}
+
+        #[cfg(not(feature = $feature))]
+        impl<T> $name for T {}
+    };
+}
+
+conversion_trait! {
+    "client",
+    /// Conversion of an outgoing request into an `http::Request`, used by clients.
+    TryIntoHttpRequest:
+        std::convert::TryInto<http::Request<Vec<u8>>, Error = crate::error::IntoHttpError>
+}
+
+conversion_trait! {
+    "server",
+    /// Conversion of an `http::Request` into an incoming request, used by servers.
+    TryFromHttpRequest: std::convert::TryFrom<
+        http::Request<Vec<u8>>,
+        Error = crate::error::FromHttpRequestError,
+    >
+}
+
+conversion_trait! {
+    "server",
+    /// Conversion of an outgoing response into an `http::Response`, used by servers.
+    TryIntoHttpResponse:
+        std::convert::TryInto<http::Response<Vec<u8>>, Error = crate::error::IntoHttpError>
+}
+
+conversion_trait! {
+    "client",
+    /// Conversion of an `http::Response` into an incoming response, used by clients.
+    TryFromHttpResponse: std::convert::TryFrom<
+        http::Response<Vec<u8>>,
+        Error = crate::error::FromHttpResponseError,
+    >
+}
+
+/// An incoming request whose path, query string and headers were checked before its body was
+/// read.
+///
+/// Returned by the `try_from_parts` function `ruma_api!` generates for request types, and
+/// converted into the incoming request type `T` with the body by [`complete`](#method.complete).
+#[cfg(feature = "server")]
+#[derive(Debug)]
+pub struct PartialIncoming<T> {
+    parts: http::request::Parts,
+    incoming: PhantomData<fn() -> T>,
+}
+
+#[cfg(feature = "server")]
+impl<T> PartialIncoming<T>
+where
+    T: TryFrom<http::Request<Vec<u8>>, Error = FromHttpRequestError>,
+{
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn new(parts: http::request::Parts) -> Self {
+        Self { parts, incoming: PhantomData }
+    }
+
+    /// The method, URI and headers of the request.
+    pub fn parts(&self) -> &http::request::Parts {
+        &self.parts
+    }
+
+    /// Converts the request into the incoming request type, now that its body was read.
+    pub fn complete(self, body: Vec<u8>) -> Result<T, FromHttpRequestError> {
+        T::try_from(http::Request::from_parts(self.parts, body))
+    }
+}
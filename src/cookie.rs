@@ -0,0 +1,78 @@
+//! Cookies in request and response headers.
+//!
+//! Fields with `#[ruma_api(cookie = "name")]` are sent as a `name=value` pair in the `Cookie`
+//! header of a request, or in a `Set-Cookie` header of a response. The functions in this module
+//! are used by the generated code, and can be used for the header map fields of endpoints that
+//! need more than the value of a cookie, like its attributes.
+//!
+//! Cookie values are sent as they are, without quoting or escaping, so they must not contain
+//! whitespace, `"`, `,`, `;` or `\`.
+
+use http::header::{HeaderMap, HeaderValue, COOKIE, SET_COOKIE};
+
+/// Gets the value of the cookie with the given name from the `Cookie` headers of a request.
+///
+/// Clients usually send all cookies in one header, but HTTP/2 allows splitting them over several,
+/// so all of them are searched. If the cookie is sent more than once, the first value is returned.
+pub fn request_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| cookie_value(pair, name))
+}
+
+/// Gets the value of the cookie with the given name from the `Set-Cookie` headers of a response.
+///
+/// Attributes like `Path` or `HttpOnly` are ignored. If the cookie is set more than once, the last
+/// value is returned, like a client would keep it.
+pub fn response_cookie(headers: &HeaderMap, name: &str) -> Option<String> {
+    headers
+        .get_all(SET_COOKIE)
+        .iter()
+        .rev()
+        .filter_map(|value| value.to_str().ok())
+        .find_map(|value| cookie_value(value.split(';').next().unwrap_or_default(), name))
+}
+
+/// Adds the cookie with the given name and value to the `Cookie` header of a request, creating
+/// the header if there is none.
+///
+/// # Panics
+///
+/// Panics if the cookie isn't valid in a header value.
+pub fn append_request_cookie(headers: &mut HeaderMap, name: &str, value: &str) {
+    let cookie = match headers.get(COOKIE).and_then(|cookies| cookies.to_str().ok()) {
+        Some(cookies) => format!("{}; {}={}", cookies, name, value),
+        None => format!("{}={}", name, value),
+    };
+
+    headers.insert(
+        COOKIE,
+        HeaderValue::from_str(&cookie).expect("failed to convert value into HeaderValue"),
+    );
+}
+
+/// The value of a `Set-Cookie` header setting the cookie with the given name and value.
+pub fn set_cookie(name: &str, value: &str) -> String {
+    format!("{}={}", name, value)
+}
+
+/// Gets the value of a `name=value` pair if it has the given name, without the optional quotes
+/// around it.
+fn cookie_value(pair: &str, name: &str) -> Option<String> {
+    let mut parts = pair.splitn(2, '=');
+    if parts.next()?.trim() != name {
+        return None;
+    }
+
+    let value = parts.next()?.trim();
+    let value = if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        &value[1..value.len() - 1]
+    } else {
+        value
+    };
+
+    Some(value.to_owned())
+}
@@ -0,0 +1,284 @@
+//! Typed credentials of incoming requests.
+//!
+//! Clients authenticate with an access token, sent as `Authorization: Bearer <token>` or in the
+//! `access_token` query parameter, and homeservers sign federation requests with an
+//! `Authorization: X-Matrix …` header. [`Credentials::from_request`] parses either into
+//! [`Credentials`], and
+//! [`Endpoint::try_from_http_request_authenticated`](../trait.Endpoint.html#method.try_from_http_request_authenticated)
+//! returns them together with the incoming request, rejecting requests to endpoints that require
+//! authentication if they have none:
+//!
+//! ```
+//! # use std::collections::HashMap;
+//! # use ruma_api::{
+//! #     credentials::{Authenticated, Credentials},
+//! #     deps::http,
+//! #     error::FromHttpRequestError,
+//! #     options::IncomingRequestOptions,
+//! #     ruma_api, Endpoint,
+//! # };
+//! #
+//! # ruma_api! {
+//! #     mod set_topic;
+//! #
+//! #     metadata {
+//! #         description: "Set the topic of a room.",
+//! #         method: PUT,
+//! #         name: "set_topic",
+//! #         path: "/_matrix/client/r0/rooms/:room_id/state/m.room.topic",
+//! #         rate_limited: false,
+//! #         requires_authentication: true,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(path)]
+//! #         pub room_id: String,
+//! #         pub topic: String,
+//! #     }
+//! #
+//! #     response {}
+//! # }
+//! #
+//! # enum Error {
+//! #     Forbidden,
+//! #     Request(FromHttpRequestError),
+//! # }
+//! #
+//! # impl From<FromHttpRequestError> for Error {
+//! #     fn from(err: FromHttpRequestError) -> Self {
+//! #         Error::Request(err)
+//! #     }
+//! # }
+//! #
+//! # struct Users(HashMap<String, String>);
+//! #
+//! # impl Users {
+//! #     fn by_access_token(&self, token: &str) -> Result<&str, Error> {
+//! #         self.0.get(token).map(String::as_str).ok_or(Error::Forbidden)
+//! #     }
+//! # }
+//! #
+//! # fn handle(
+//! #     http_request: http::Request<Vec<u8>>,
+//! #     options: IncomingRequestOptions,
+//! #     users: &Users,
+//! # ) -> Result<(), Error> {
+//! let Authenticated { credentials, request } =
+//!     set_topic::Request::try_from_http_request_authenticated(http_request, &options)?;
+//! let user = match credentials.as_ref().and_then(Credentials::access_token) {
+//!     Some(token) => users.by_access_token(token)?,
+//!     None => return Err(Error::Forbidden),
+//! };
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Only the format of the credentials is checked here. Looking up access tokens and verifying
+//! `X-Matrix` signatures is up to the server.
+//!
+//! [`Credentials::from_request`]: enum.Credentials.html#method.from_request
+//! [`Credentials`]: enum.Credentials.html
+
+use std::fmt::{self, Display, Formatter};
+
+use http::header::AUTHORIZATION;
+
+use crate::options::query_access_token;
+
+/// The credentials of an incoming request.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Credentials {
+    /// An access token, from the `Authorization: Bearer` header or the `access_token` query
+    /// parameter.
+    AccessToken(String),
+
+    /// The parts of an `Authorization: X-Matrix` header of a federation request.
+    XMatrix(XMatrix),
+}
+
+impl Credentials {
+    /// Gets the credentials of an incoming request, from its `Authorization` header or, without
+    /// one, its `access_token` query parameter.
+    ///
+    /// Returns `Ok(None)` if the request has neither.
+    pub fn from_request<T>(request: &http::Request<T>) -> Result<Option<Self>, CredentialsError> {
+        let value = match request.headers().get(AUTHORIZATION) {
+            Some(value) => value.to_str().map_err(|_| CredentialsError::NotAscii)?,
+            None => return Ok(query_access_token(request).map(Self::AccessToken)),
+        };
+
+        let (scheme, params) = match value.find(' ') {
+            Some(index) => (&value[..index], value[index + 1..].trim()),
+            None => (value, ""),
+        };
+
+        if scheme.eq_ignore_ascii_case("Bearer") {
+            if params.is_empty() || params.contains(' ') {
+                return Err(CredentialsError::MalformedAccessToken);
+            }
+            Ok(Some(Self::AccessToken(params.to_owned())))
+        } else if scheme.eq_ignore_ascii_case("X-Matrix") {
+            XMatrix::parse(params).map(|x_matrix| Some(Self::XMatrix(x_matrix)))
+        } else {
+            Err(CredentialsError::UnknownScheme(scheme.to_owned()))
+        }
+    }
+
+    /// The access token, if these are the credentials of a client.
+    pub fn access_token(&self) -> Option<&str> {
+        match self {
+            Self::AccessToken(token) => Some(token),
+            Self::XMatrix(_) => None,
+        }
+    }
+
+    /// The `X-Matrix` header parts, if these are the credentials of a homeserver.
+    pub fn x_matrix(&self) -> Option<&XMatrix> {
+        match self {
+            Self::AccessToken(_) => None,
+            Self::XMatrix(x_matrix) => Some(x_matrix),
+        }
+    }
+}
+
+/// The parts of an `Authorization: X-Matrix` header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct XMatrix {
+    /// The server name of the sending homeserver.
+    pub origin: String,
+
+    /// The server name of the receiving homeserver, if the sender included it.
+    pub destination: Option<String>,
+
+    /// The ID of the key the request was signed with, e.g. `ed25519:key1`.
+    pub key: String,
+
+    /// The signature of the request, in unpadded base64.
+    pub sig: String,
+}
+
+impl XMatrix {
+    /// Parses the comma-separated `name=value` parameters after `X-Matrix`. Values can be quoted,
+    /// and unknown parameters are ignored.
+    fn parse(params: &str) -> Result<Self, CredentialsError> {
+        let mut origin = None;
+        let mut destination = None;
+        let mut key = None;
+        let mut sig = None;
+
+        for param in split_params(params) {
+            let mut parts = param.splitn(2, '=');
+            let name = parts.next().unwrap_or_default().trim();
+            let value = match parts.next() {
+                Some(value) => unquote(value.trim()),
+                None => return Err(CredentialsError::MalformedXMatrix),
+            };
+
+            match &*name.to_ascii_lowercase() {
+                "origin" => origin = Some(value),
+                "destination" => destination = Some(value),
+                "key" => key = Some(value),
+                "sig" => sig = Some(value),
+                _ => {}
+            }
+        }
+
+        Ok(Self {
+            origin: origin.ok_or(CredentialsError::MissingXMatrixParameter("origin"))?,
+            destination,
+            key: key.ok_or(CredentialsError::MissingXMatrixParameter("key"))?,
+            sig: sig.ok_or(CredentialsError::MissingXMatrixParameter("sig"))?,
+        })
+    }
+}
+
+/// Splits `X-Matrix` parameters at the commas that aren't in a quoted value.
+fn split_params(params: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    let mut quoted = false;
+    let mut escaped = false;
+
+    for (index, c) in params.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(&params[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&params[start..]);
+
+    parts.into_iter().filter(|part| !part.trim().is_empty()).collect()
+}
+
+/// Removes the quotes and escapes of a quoted value.
+fn unquote(value: &str) -> String {
+    if value.len() < 2 || !value.starts_with('"') || !value.ends_with('"') {
+        return value.to_owned();
+    }
+
+    let mut unquoted = String::with_capacity(value.len() - 2);
+    let mut escaped = false;
+    for c in value[1..value.len() - 1].chars() {
+        if c == '\\' && !escaped {
+            escaped = true;
+        } else {
+            unquoted.push(c);
+            escaped = false;
+        }
+    }
+    unquoted
+}
+
+/// An incoming request together with its credentials.
+#[derive(Clone, Debug)]
+pub struct Authenticated<R> {
+    /// The credentials of the request, `None` if it had none.
+    pub credentials: Option<Credentials>,
+
+    /// The incoming request.
+    pub request: R,
+}
+
+/// Why the `Authorization` header of a request couldn't be parsed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CredentialsError {
+    /// The header isn't valid ASCII.
+    NotAscii,
+
+    /// The header uses a scheme other than `Bearer` and `X-Matrix`.
+    UnknownScheme(String),
+
+    /// The `Bearer` header has no token or more than one.
+    MalformedAccessToken,
+
+    /// A parameter of the `X-Matrix` header isn't of the form `name=value`.
+    MalformedXMatrix,
+
+    /// The `X-Matrix` header lacks the given parameter.
+    MissingXMatrixParameter(&'static str),
+}
+
+impl Display for CredentialsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAscii => write!(f, "the Authorization header isn't valid ASCII"),
+            Self::UnknownScheme(scheme) => {
+                write!(f, "the Authorization header has the unknown scheme `{}`", scheme)
+            }
+            Self::MalformedAccessToken => write!(f, "the Bearer token is malformed"),
+            Self::MalformedXMatrix => write!(f, "the X-Matrix parameters are malformed"),
+            Self::MissingXMatrixParameter(name) => {
+                write!(f, "the X-Matrix header has no `{}` parameter", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CredentialsError {}
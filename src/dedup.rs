@@ -0,0 +1,273 @@
+//! Sharing the response of a request with identical requests that are sent while it is in flight.
+//!
+//! Clients often request the same data from several places at once, e.g. the profile of a user
+//! whose avatar is shown in many places. A [`RequestDeduplicator`] sends only the first of these
+//! requests and hands its response to all of them:
+//!
+//! ```
+//! # use ruma_api::{client::Transport, dedup::RequestDeduplicator, ruma_api};
+//! #
+//! # ruma_api! {
+//! #     mod get_profile;
+//! #
+//! #     metadata {
+//! #         description: "Get the profile of a user.",
+//! #         method: GET,
+//! #         name: "get_profile",
+//! #         path: "/_matrix/client/r0/profile/:user_id",
+//! #         rate_limited: false,
+//! #         requires_authentication: false,
+//! #         cacheable: true,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(path)]
+//! #         pub user_id: String,
+//! #     }
+//! #
+//! #     response {
+//! #         pub displayname: Option<String>,
+//! #     }
+//! # }
+//! #
+//! # async fn example<T>(transport: T, alice: String)
+//! # where
+//! #     T: Transport,
+//! #     T::Error: Send + Sync + 'static,
+//! # {
+//! let deduplicator = RequestDeduplicator::new(transport);
+//!
+//! // Only one request is sent, both futures resolve to the same response.
+//! let a = deduplicator.send(get_profile::Request { user_id: alice.clone() });
+//! let b = deduplicator.send(get_profile::Request { user_id: alice.clone() });
+//! assert_eq!(deduplicator.in_flight(), 1);
+//! let (a, b) = (a.await, b.await);
+//! # }
+//! ```
+//!
+//! Requests are identified by their [cache key](../cache/index.html), so only requests to
+//! endpoints with `cacheable: true` in their metadata are deduplicated; all others are sent as
+//! usual. A deduplicator owns the transport it sends requests through, which holds the access
+//! token and the server the requests go to, so every account or client needs its own
+//! deduplicator. Responses are not kept after the request completes, a request that is sent
+//! afterwards is sent again.
+//!
+//! [`RequestDeduplicator`]: struct.RequestDeduplicator.html
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    convert::TryFrom,
+    fmt::{self, Debug, Formatter},
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+};
+
+use crate::{
+    cache::CacheKey,
+    client::{self, SendError, Transport},
+    conversion::TryFromHttpRequest,
+    error::FromHttpResponseError,
+    Endpoint, Outgoing,
+};
+
+/// The result of a request sent through a [`RequestDeduplicator`], which is shared by all
+/// identical requests.
+///
+/// [`RequestDeduplicator`]: struct.RequestDeduplicator.html
+pub type SharedResult<T, E> = Result<Arc<T>, Arc<SendError<E>>>;
+
+/// A boxed future resolving to the shared result of a request.
+pub type SharedResponseFuture<'a, T, E> =
+    Pin<Box<dyn Future<Output = SharedResult<T, E>> + Send + 'a>>;
+
+/// Sends requests through a transport, ensuring that only one request per [`CacheKey`] is in
+/// flight at a time.
+///
+/// A single deduplicator can be used for requests to any number of endpoints.
+///
+/// [`CacheKey`]: ../cache/struct.CacheKey.html
+pub struct RequestDeduplicator<T> {
+    transport: T,
+    in_flight: InFlight,
+}
+
+/// The requests in flight, by their cache key.
+type InFlight = Mutex<HashMap<CacheKey, Arc<dyn Any + Send + Sync>>>;
+
+impl<T: Transport> RequestDeduplicator<T> {
+    /// Creates a deduplicator that sends requests through the given transport.
+    pub fn new(transport: T) -> Self {
+        Self { transport, in_flight: Mutex::default() }
+    }
+
+    /// The transport requests are sent through.
+    pub fn transport(&self) -> &T {
+        &self.transport
+    }
+
+    /// Returns the transport requests are sent through.
+    pub fn into_transport(self) -> T {
+        self.transport
+    }
+
+    /// The number of distinct requests that are currently in flight.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.lock().unwrap().len()
+    }
+
+    /// Sends a request with [`client::send`], unless an identical request is already in flight,
+    /// in which case its result is returned instead.
+    ///
+    /// If the future of the request that is in flight is dropped before it completes, the
+    /// requests waiting for it are sent on their own.
+    ///
+    /// [`client::send`]: ../client/fn.send.html
+    pub fn send<'a, E>(
+        &'a self,
+        request: E,
+    ) -> SharedResponseFuture<'a, <E::Response as Outgoing>::Incoming, T::Error>
+    where
+        E: Endpoint + Send + 'a,
+        <E as Outgoing>::Incoming: TryFromHttpRequest,
+        <E::Response as Outgoing>::Incoming:
+            TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError> + Send + Sync + 'static,
+        T::Error: Send + Sync + 'static,
+    {
+        let transport = &self.transport;
+        let key = match request.cache_key() {
+            Some(key) => key,
+            None => return Box::pin(send_shared(transport, request)),
+        };
+
+        let slot = {
+            let mut in_flight = self.in_flight.lock().unwrap();
+            match in_flight.get(&key).map(|slot| Arc::clone(slot).downcast()) {
+                Some(Ok(slot)) => Err(slot),
+                // Another endpoint with the same name, which can't share the response.
+                Some(Err(_)) => return Box::pin(send_shared(transport, request)),
+                None => {
+                    let slot = Arc::new(Slot::default());
+                    in_flight.insert(key.clone(), Arc::clone(&slot) as Arc<dyn Any + Send + Sync>);
+                    Ok(slot)
+                }
+            }
+        };
+
+        match slot {
+            Ok(slot) => {
+                // Created outside of the future so the request is removed even if the future is
+                // dropped before it is polled.
+                let guard = InFlightGuard { in_flight: &self.in_flight, key, slot };
+                Box::pin(async move {
+                    let result = send_shared(transport, request).await;
+                    guard.slot.complete(result.clone());
+                    result
+                })
+            }
+            Err(slot) => Box::pin(async move {
+                match (Wait { slot }).await {
+                    Some(result) => result,
+                    None => send_shared(transport, request).await,
+                }
+            }),
+        }
+    }
+}
+
+impl<T: Transport + Debug> Debug for RequestDeduplicator<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RequestDeduplicator")
+            .field("transport", &self.transport)
+            .field("in_flight", &self.in_flight())
+            .finish()
+    }
+}
+
+/// Sends a request on its own.
+async fn send_shared<T, E>(
+    transport: &T,
+    request: E,
+) -> SharedResult<<E::Response as Outgoing>::Incoming, T::Error>
+where
+    T: Transport + ?Sized,
+    E: Endpoint,
+    <E as Outgoing>::Incoming: TryFromHttpRequest,
+    <E::Response as Outgoing>::Incoming:
+        TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError> + Send,
+    T::Error: Send,
+{
+    client::send(transport, request).await.map(Arc::new).map_err(Arc::new)
+}
+
+/// The result of a request in flight, once it is available.
+struct Slot<T> {
+    state: Mutex<SlotState<T>>,
+}
+
+struct SlotState<T> {
+    result: Option<T>,
+    /// Whether the request was dropped before it completed.
+    abandoned: bool,
+    wakers: Vec<Waker>,
+}
+
+impl<T> Default for Slot<T> {
+    fn default() -> Self {
+        Self { state: Mutex::new(SlotState { result: None, abandoned: false, wakers: Vec::new() }) }
+    }
+}
+
+impl<T> Slot<T> {
+    fn complete(&self, result: T) {
+        self.state.lock().unwrap().result = Some(result);
+    }
+}
+
+/// Removes a request from the requests in flight when it completes or is dropped, and wakes the
+/// requests waiting for it.
+struct InFlightGuard<'a, T> {
+    in_flight: &'a InFlight,
+    key: CacheKey,
+    slot: Arc<Slot<T>>,
+}
+
+impl<T> Drop for InFlightGuard<'_, T> {
+    fn drop(&mut self) {
+        self.in_flight.lock().unwrap().remove(&self.key);
+
+        let wakers = {
+            let mut state = self.slot.state.lock().unwrap();
+            state.abandoned = state.result.is_none();
+            std::mem::take(&mut state.wakers)
+        };
+        for waker in wakers {
+            waker.wake();
+        }
+    }
+}
+
+/// Waits for the result of a request in flight. Resolves to `None` if the request was dropped.
+struct Wait<T> {
+    slot: Arc<Slot<T>>,
+}
+
+impl<T: Clone> Future for Wait<T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<T>> {
+        let mut state = self.slot.state.lock().unwrap();
+        if let Some(result) = &state.result {
+            Poll::Ready(Some(result.clone()))
+        } else if state.abandoned {
+            Poll::Ready(None)
+        } else {
+            if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                state.wakers.push(cx.waker().clone());
+            }
+            Poll::Pending
+        }
+    }
+}
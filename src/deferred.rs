@@ -0,0 +1,116 @@
+//! Lazy deserialization of parts of large response bodies.
+//!
+//! Responses like the one of `/sync` can be several megabytes of JSON, most of which a client
+//! might not need right away. Wrapping a field in [`Deferred`](struct.Deferred.html) only checks
+//! that its value is valid JSON when the body is parsed and keeps the raw JSON, so the field can
+//! be deserialized later, when it is needed, or on another thread.
+//!
+//! In `ruma_api!`, response body fields can use `#[ruma_api(deferred)]` to get this behavior for
+//! the field's value in the 'Incoming' type:
+//!
+//! ```text
+//! response {
+//!     #[ruma_api(deferred)]
+//!     pub rooms: Rooms,
+//!     pub next_batch: String,
+//! }
+//! ```
+//!
+//! This requires the `deferred` feature. Since the raw JSON is borrowed from the body as it is
+//! parsed, deferred fields can't be combined with serde attributes that buffer the value first,
+//! like `#[serde(flatten)]`.
+
+use std::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+};
+
+use serde::{
+    de::{Deserialize, DeserializeOwned, Deserializer},
+    ser::{Serialize, Serializer},
+};
+use serde_json::value::RawValue;
+
+use crate::{partial::PartialError, TryIntoOutgoing};
+
+/// The raw JSON of a value of type `T` that is yet to be deserialized.
+///
+/// `Deferred` is `Send` and `Sync` regardless of `T`, so it can be handed to worker threads.
+pub struct Deferred<T> {
+    json: Box<RawValue>,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T> Deferred<T> {
+    /// Wraps raw JSON, which is expected to be a value of type `T`.
+    pub fn from_json(json: Box<RawValue>) -> Self {
+        Self { json, value: PhantomData }
+    }
+
+    /// The raw JSON of the value.
+    pub fn json(&self) -> &RawValue {
+        &self.json
+    }
+
+    /// Consumes the `Deferred`, returning the raw JSON of the value.
+    pub fn into_json(self) -> Box<RawValue> {
+        self.json
+    }
+}
+
+impl<T: Serialize> Deferred<T> {
+    /// Serializes the given value.
+    pub fn new(value: &T) -> Result<Self, serde_json::Error> {
+        Ok(Self::from_json(serde_json::value::to_raw_value(value)?))
+    }
+}
+
+impl<T: DeserializeOwned> Deferred<T> {
+    /// Deserializes the value.
+    ///
+    /// This can be called any number of times, each call deserializes the raw JSON again.
+    pub fn deserialize(&self) -> Result<T, serde_json::Error> {
+        serde_json::from_str(self.json.get())
+    }
+}
+
+impl<T> Clone for Deferred<T> {
+    fn clone(&self) -> Self {
+        Self::from_json(self.json.clone())
+    }
+}
+
+impl<T> Debug for Deferred<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Deferred").field(&self.json).finish()
+    }
+}
+
+impl<T: DeserializeOwned> TryIntoOutgoing<T> for Deferred<T> {
+    fn try_into_outgoing(self) -> Result<T, PartialError> {
+        self.deserialize().map_err(|err| {
+            PartialError::new(
+                err.to_string(),
+                serde_json::from_str(self.json.get()).unwrap_or_default(),
+            )
+        })
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Deferred<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Box::<RawValue>::deserialize(deserializer).map(Self::from_json)
+    }
+}
+
+impl<T> Serialize for Deferred<T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.json.serialize(serializer)
+    }
+}
@@ -0,0 +1,224 @@
+//! Generating a Markdown reference for endpoints.
+//!
+//! With the `docs` feature, [`render`] describes endpoints generated by `ruma_api!` in Markdown:
+//! their method, path, authentication and rate limiting, a table of the parameters of the request
+//! and the response, and example bodies built from the `#[ruma_api(example = "...")]` attributes
+//! of their body fields. This is meant for APIs defined with `ruma_api!` that aren't part of the
+//! specification, like the admin API of a homeserver:
+//!
+//! ```
+//! # use ruma_api::{
+//! #     docs::{render, EndpointDoc},
+//! #     ruma_api,
+//! # };
+//! #
+//! # ruma_api! {
+//! #     mod list_users;
+//! #
+//! #     metadata {
+//! #         description: "List the users of the server.",
+//! #         method: GET,
+//! #         name: "list_users",
+//! #         path: "/_synapse/admin/v2/users",
+//! #         rate_limited: false,
+//! #         requires_authentication: true,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(query)]
+//! #         pub from: Option<String>,
+//! #     }
+//! #
+//! #     response {
+//! #         #[ruma_api(example = r#"["@alice:example.org"]"#)]
+//! #         pub users: Vec<String>,
+//! #     }
+//! # }
+//! #
+//! # ruma_api! {
+//! #     mod deactivate_user;
+//! #
+//! #     metadata {
+//! #         description: "Deactivate a user.",
+//! #         method: POST,
+//! #         name: "deactivate_user",
+//! #         path: "/_synapse/admin/v1/deactivate/:user_id",
+//! #         rate_limited: false,
+//! #         requires_authentication: true,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(path)]
+//! #         pub user_id: String,
+//! #         #[ruma_api(example = "true")]
+//! #         pub erase: bool,
+//! #     }
+//! #
+//! #     response {}
+//! # }
+//! #
+//! let reference = render(&[
+//!     EndpointDoc::of::<list_users::Request>(),
+//!     EndpointDoc::of::<deactivate_user::Request>(),
+//! ]);
+//! assert!(reference.starts_with("## `list_users`\n\nList the users of the server.\n"));
+//! ```
+//!
+//! [`render`]: fn.render.html
+
+use std::fmt::Write as _;
+
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::{
+    conversion::{TryFromHttpRequest, TryFromHttpResponse},
+    examples::{EndpointExamples, FieldExample, FieldLocation},
+    spec_check::{EndpointFields, FieldInfo},
+    Endpoint, Metadata, Outgoing,
+};
+
+/// The information about an endpoint that [`render`](fn.render.html) documents.
+#[derive(Clone, Debug)]
+pub struct EndpointDoc {
+    /// The metadata of the endpoint.
+    pub metadata: Metadata,
+
+    /// The request fields.
+    pub request_fields: Vec<FieldInfo>,
+
+    /// The response fields.
+    pub response_fields: Vec<FieldInfo>,
+
+    /// The examples of the request fields.
+    pub request_examples: Vec<FieldExample>,
+
+    /// The examples of the response fields.
+    pub response_examples: Vec<FieldExample>,
+}
+
+impl EndpointDoc {
+    /// The information about the endpoint `E`.
+    pub fn of<E>() -> Self
+    where
+        E: Endpoint + EndpointFields + EndpointExamples,
+        <E as Outgoing>::Incoming: TryFromHttpRequest,
+        <E::Response as Outgoing>::Incoming: TryFromHttpResponse,
+    {
+        Self {
+            metadata: E::METADATA,
+            request_fields: E::request_fields(),
+            response_fields: E::response_fields(),
+            request_examples: E::request_examples(),
+            response_examples: E::response_examples(),
+        }
+    }
+}
+
+/// Renders a Markdown reference of the given endpoints, in the given order.
+pub fn render(endpoints: &[EndpointDoc]) -> String {
+    let mut out = String::new();
+    for (i, endpoint) in endpoints.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        render_endpoint(&mut out, endpoint);
+    }
+    out
+}
+
+fn render_endpoint(out: &mut String, endpoint: &EndpointDoc) {
+    let metadata = &endpoint.metadata;
+
+    // Writing to a `String` can't fail.
+    let _ = writeln!(out, "## `{}`\n", metadata.name());
+    let _ = writeln!(out, "{}\n", metadata.description());
+
+    let methods = std::iter::once(metadata.method())
+        .chain(metadata.other_methods())
+        .map(|method| method.as_str())
+        .collect::<Vec<_>>()
+        .join(" | ");
+    let _ = writeln!(out, "`{} {}`\n", methods, metadata.path());
+
+    let _ = writeln!(
+        out,
+        "* Requires authentication: {}",
+        if metadata.requires_authentication() { "yes" } else { "no" }
+    );
+    let _ = writeln!(out, "* Rate-limited: {}", if metadata.rate_limited() { "yes" } else { "no" });
+    if let Some(version) = metadata.deprecated() {
+        let _ = writeln!(out, "* Deprecated in: {}", version);
+    }
+    if let Some(version) = metadata.removed() {
+        let _ = writeln!(out, "* Removed in: {}", version);
+    }
+
+    render_part(out, "Request", &endpoint.request_fields, &endpoint.request_examples);
+    render_part(out, "Response", &endpoint.response_fields, &endpoint.response_examples);
+}
+
+fn render_part(out: &mut String, title: &str, fields: &[FieldInfo], examples: &[FieldExample]) {
+    let _ = writeln!(out, "\n### {}\n", title);
+
+    if fields.is_empty() {
+        let _ = writeln!(out, "No parameters.");
+        return;
+    }
+
+    let _ = writeln!(out, "| Name | Location | Required | Example |");
+    let _ = writeln!(out, "| --- | --- | --- | --- |");
+    for field in fields {
+        let example = example_of(examples, field).map_or_else(String::new, |example| {
+            format!("`{}`", example.replace('|', "\\|").replace('\n', " "))
+        });
+        let _ = writeln!(
+            out,
+            "| `{}` | {} | {} | {} |",
+            field.name.as_deref().unwrap_or(field.field),
+            location_name(field.location),
+            if field.required { "yes" } else { "no" },
+            example,
+        );
+    }
+
+    if let Some(body) = example_body(fields, examples) {
+        let _ = writeln!(out, "\nExample body:\n\n```json\n{}\n```", body);
+    }
+}
+
+/// The example of the given field, if any.
+fn example_of<'a>(examples: &'a [FieldExample], field: &FieldInfo) -> Option<&'a str> {
+    examples
+        .iter()
+        .find(|example| example.field == field.field && example.location == field.location)
+        .map(|example| example.example)
+}
+
+/// The JSON object made of the examples of the named body fields, or `None` if there are no such
+/// examples.
+fn example_body(fields: &[FieldInfo], examples: &[FieldExample]) -> Option<String> {
+    let mut body = JsonMap::new();
+    for field in fields.iter().filter(|field| field.location == FieldLocation::Body) {
+        let (name, example) = match (&field.name, example_of(examples, field)) {
+            (Some(name), Some(example)) => (name, example),
+            _ => continue,
+        };
+        let value =
+            serde_json::from_str(example).unwrap_or_else(|_| JsonValue::String(example.to_owned()));
+        body.insert(name.clone(), value);
+    }
+
+    if body.is_empty() {
+        return None;
+    }
+    serde_json::to_string_pretty(&JsonValue::Object(body)).ok()
+}
+
+fn location_name(location: FieldLocation) -> &'static str {
+    match location {
+        FieldLocation::Body => "body",
+        FieldLocation::Header => "header",
+        FieldLocation::Path => "path",
+        FieldLocation::Query => "query",
+    }
+}
@@ -2,7 +2,17 @@
 //! converting between http requests / responses and ruma's representation of
 //! matrix API requests / responses.
 
-use std::fmt::{self, Display, Formatter};
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+#[cfg(feature = "serde")]
+use std::borrow::Cow;
+
+#[cfg(feature = "serde")]
+use crate::canonical_json::NonCanonicalJson;
+use crate::credentials::CredentialsError;
 
 /// An error when converting one of ruma's endpoint-specific request or response
 /// types to the corresponding http type.
@@ -23,19 +33,150 @@ impl From<serde_urlencoded::ser::Error> for IntoHttpError {
     }
 }
 
+#[doc(hidden)]
+impl From<std::io::Error> for IntoHttpError {
+    fn from(err: std::io::Error) -> Self {
+        Self(SerializationError::Io(err))
+    }
+}
+
+#[doc(hidden)]
+impl From<InvalidPathSegment> for IntoHttpError {
+    fn from(err: InvalidPathSegment) -> Self {
+        Self(SerializationError::InvalidPathSegment(err))
+    }
+}
+
+#[doc(hidden)]
+impl From<UnsupportedMethod> for IntoHttpError {
+    fn from(err: UnsupportedMethod) -> Self {
+        Self(SerializationError::UnsupportedMethod(err))
+    }
+}
+
+#[cfg(feature = "cbor")]
+#[doc(hidden)]
+impl From<serde_cbor::Error> for IntoHttpError {
+    fn from(err: serde_cbor::Error) -> Self {
+        Self(SerializationError::Cbor(err))
+    }
+}
+
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+impl From<NonCanonicalJson> for IntoHttpError {
+    fn from(err: NonCanonicalJson) -> Self {
+        Self(SerializationError::NonCanonicalJson(err))
+    }
+}
+
+impl IntoHttpError {
+    /// If the body of an endpoint with `canonical_json: true` wasn't valid canonical JSON, the
+    /// offending value.
+    #[cfg(feature = "serde")]
+    pub fn non_canonical_json(&self) -> Option<&NonCanonicalJson> {
+        match &self.0 {
+            SerializationError::NonCanonicalJson(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// If a path parameter of a `#[ruma_api(path, identifier)]` field wasn't a valid identifier,
+    /// why it wasn't.
+    pub fn invalid_path_segment(&self) -> Option<&InvalidPathSegment> {
+        match &self.0 {
+            SerializationError::InvalidPathSegment(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// If the request was converted with a method its endpoint doesn't accept, which method.
+    pub fn unsupported_method(&self) -> Option<&UnsupportedMethod> {
+        match &self.0 {
+            SerializationError::UnsupportedMethod(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
 impl Display for IntoHttpError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match &self.0 {
             SerializationError::Json(err) => write!(f, "JSON serialization failed: {}", err),
+            #[cfg(feature = "cbor")]
+            SerializationError::Cbor(err) => write!(f, "CBOR serialization failed: {}", err),
             SerializationError::Query(err) => {
                 write!(f, "Query parameter serialization failed: {}", err)
             }
+            SerializationError::Io(err) => write!(f, "Writing the body failed: {}", err),
+            SerializationError::InvalidPathSegment(err) => {
+                write!(f, "Invalid path parameter: {}", err)
+            }
+            SerializationError::UnsupportedMethod(err) => write!(f, "{}", err),
+            #[cfg(feature = "serde")]
+            SerializationError::NonCanonicalJson(err) => {
+                write!(f, "The body is not canonical JSON: {}", err)
+            }
         }
     }
 }
 
 impl std::error::Error for IntoHttpError {}
 
+/// An error when converting a request to an `http::Request`, together with the request that
+/// couldn't be converted.
+///
+/// This is returned by the `try_into_http_request_or_return` method that `ruma_api!` generates for
+/// requests, so callers like retry queues can keep the request without cloning it up front.
+#[derive(Debug)]
+pub struct IntoHttpRequestError<R> {
+    request: R,
+    error: IntoHttpError,
+}
+
+impl<R> IntoHttpRequestError<R> {
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn new(request: R, error: IntoHttpError) -> Self {
+        Self { request, error }
+    }
+
+    /// The request that couldn't be converted.
+    pub fn request(&self) -> &R {
+        &self.request
+    }
+
+    /// The reason the conversion failed.
+    pub fn error(&self) -> &IntoHttpError {
+        &self.error
+    }
+
+    /// Consumes the error, returning the request that couldn't be converted.
+    pub fn into_request(self) -> R {
+        self.request
+    }
+
+    /// Consumes the error, returning the request and the reason the conversion failed.
+    pub fn into_parts(self) -> (R, IntoHttpError) {
+        (self.request, self.error)
+    }
+}
+
+impl<R> From<IntoHttpRequestError<R>> for IntoHttpError {
+    fn from(err: IntoHttpRequestError<R>) -> Self {
+        err.error
+    }
+}
+
+impl<R> Display for IntoHttpRequestError<R> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl<R: fmt::Debug> std::error::Error for IntoHttpRequestError<R> {}
+
 /// An error when converting a http request to one of ruma's endpoint-specific
 /// request types.
 #[derive(Debug)]
@@ -43,12 +184,65 @@ impl std::error::Error for IntoHttpError {}
 pub enum FromHttpRequestError {
     /// Deserialization failed
     Deserialization(RequestDeserializationError),
+    /// The request was sent to an authority that isn't one of the expected server names, see
+    /// [`IncomingRequestOptions`](../options/struct.IncomingRequestOptions.html)
+    UnexpectedAuthority {
+        /// The authority of the request URI, or the `Host` header. `None` if the request has
+        /// neither.
+        authority: Option<String>,
+    },
+    /// The request doesn't have the `Content-Type` the endpoint requires, see
+    /// [`options::check_content_type`](../options/fn.check_content_type.html)
+    UnexpectedContentType {
+        /// The `Content-Type` of the request. `None` if it has none.
+        content_type: Option<String>,
+        /// The `Content-Type` the endpoint requires.
+        expected: String,
+    },
+    /// The `Content-Length` header of the request doesn't match the length of its body, see
+    /// [`options::check_content_length`](../options/fn.check_content_length.html)
+    ContentLengthMismatch {
+        /// The value of the `Content-Length` header.
+        content_length: String,
+        /// The length of the received body.
+        body_length: usize,
+    },
+    /// The request was deserialized, but violates a constraint of the endpoint, see
+    /// [`Validate`](../trait.Validate.html)
+    Validation(ValidationError),
+    /// The `Authorization` header of the request is malformed, see
+    /// [`Credentials::from_request`](../credentials/enum.Credentials.html#method.from_request)
+    InvalidCredentials(CredentialsError),
+    /// The endpoint requires authentication, but the request has no credentials
+    MissingCredentials,
 }
 
 impl Display for FromHttpRequestError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             Self::Deserialization(err) => write!(f, "deserialization failed: {}", err),
+            Self::UnexpectedAuthority { authority: Some(authority) } => {
+                write!(f, "the request was sent to the unexpected authority `{}`", authority)
+            }
+            Self::UnexpectedAuthority { authority: None } => {
+                write!(f, "the request has neither an authority nor a `Host` header")
+            }
+            Self::UnexpectedContentType { content_type: Some(content_type), expected } => write!(
+                f,
+                "the request has the Content-Type `{}` instead of `{}`",
+                content_type, expected
+            ),
+            Self::UnexpectedContentType { content_type: None, expected } => {
+                write!(f, "the request has no Content-Type, expected `{}`", expected)
+            }
+            Self::ContentLengthMismatch { content_length, body_length } => write!(
+                f,
+                "the request has the Content-Length `{}`, but a body of {} bytes",
+                content_length, body_length
+            ),
+            Self::Validation(err) => write!(f, "validation failed: {}", err),
+            Self::InvalidCredentials(err) => write!(f, "invalid credentials: {}", err),
+            Self::MissingCredentials => write!(f, "the request has no credentials"),
         }
     }
 }
@@ -59,13 +253,315 @@ impl From<RequestDeserializationError> for FromHttpRequestError {
     }
 }
 
+impl From<ValidationError> for FromHttpRequestError {
+    fn from(err: ValidationError) -> Self {
+        Self::Validation(err)
+    }
+}
+
+impl From<CredentialsError> for FromHttpRequestError {
+    fn from(err: CredentialsError) -> Self {
+        Self::InvalidCredentials(err)
+    }
+}
+
 impl std::error::Error for FromHttpRequestError {}
 
+/// Why a path parameter doesn't follow the grammar of Matrix identifiers, see
+/// [`path::check_identifier`](../path/fn.check_identifier.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvalidPathSegment {
+    /// The parameter is empty.
+    Empty,
+    /// The parameter is longer than 255 bytes.
+    TooLong {
+        /// The length of the parameter in bytes.
+        length: usize,
+    },
+    /// The parameter is `.` or `..`, which would be resolved as a relative path.
+    DotSegment,
+    /// The parameter contains a `/` or a control character.
+    InvalidCharacter(char),
+}
+
+impl Display for InvalidPathSegment {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "it is empty"),
+            Self::TooLong { length } => {
+                write!(f, "it is {} bytes long, at most 255 bytes are allowed", length)
+            }
+            Self::DotSegment => write!(f, "`.` and `..` are not allowed"),
+            Self::InvalidCharacter(c) => write!(f, "it contains the character {:?}", c),
+        }
+    }
+}
+
+impl std::error::Error for InvalidPathSegment {}
+
+/// Why a path or query parameter of an integer field isn't a valid integer, see
+/// [`path::parse_integer`](../path/fn.parse_integer.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvalidInteger {
+    /// The parameter is empty, or only a `-`.
+    Empty,
+    /// The parameter contains a character that isn't a decimal digit, after the optional leading
+    /// `-`.
+    InvalidCharacter(char),
+    /// The value is larger than the maximum of the field's type.
+    Overflow,
+    /// The value is smaller than the minimum of the field's type, e.g. negative for an unsigned
+    /// type.
+    Underflow,
+}
+
+impl Display for InvalidInteger {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the integer is empty"),
+            Self::InvalidCharacter(c) => {
+                write!(f, "the integer contains the character {:?}, expected a digit", c)
+            }
+            Self::Overflow => write!(f, "the integer is too large"),
+            Self::Underflow => write!(f, "the integer is too small"),
+        }
+    }
+}
+
+impl std::error::Error for InvalidInteger {}
+
+/// A kind of Matrix identifier that `ruma_api!` parses from path and query parameters, see
+/// [`path::Identifier`](../path/trait.Identifier.html).
+#[cfg(feature = "ruma-identifiers")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum IdentifierKind {
+    /// A `RoomId`, like `!n8f893n9:example.com`.
+    RoomId,
+    /// A `RoomAliasId`, like `#ruma:example.com`.
+    RoomAliasId,
+    /// A `UserId`, like `@carl:example.com`.
+    UserId,
+    /// An `EventId`, like `$h29iv0s8:example.com`.
+    EventId,
+}
+
+#[cfg(feature = "ruma-identifiers")]
+impl Display for IdentifierKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let kind = match self {
+            Self::RoomId => "room ID",
+            Self::RoomAliasId => "room alias",
+            Self::UserId => "user ID",
+            Self::EventId => "event ID",
+        };
+        f.write_str(kind)
+    }
+}
+
+/// A path or query parameter of a `RoomId`, `RoomAliasId`, `UserId` or `EventId` field that isn't
+/// a valid identifier of that kind, see
+/// [`path::parse_identifier`](../path/fn.parse_identifier.html).
+#[cfg(feature = "ruma-identifiers")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidIdentifier {
+    kind: IdentifierKind,
+    error: ruma_identifiers::Error,
+}
+
+#[cfg(feature = "ruma-identifiers")]
+impl InvalidIdentifier {
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn new(kind: IdentifierKind, error: ruma_identifiers::Error) -> Self {
+        Self { kind, error }
+    }
+
+    /// The kind of identifier the parameter should have been.
+    pub fn kind(&self) -> IdentifierKind {
+        self.kind
+    }
+
+    /// Why the parameter isn't a valid identifier.
+    pub fn error(&self) -> &ruma_identifiers::Error {
+        &self.error
+    }
+}
+
+#[cfg(feature = "ruma-identifiers")]
+impl Display for InvalidIdentifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "the {} is invalid: {}", self.kind, self.error)
+    }
+}
+
+#[cfg(feature = "ruma-identifiers")]
+impl std::error::Error for InvalidIdentifier {}
+
+/// A query parameter of an incoming request that appears more than once, see
+/// [`query::check_unique`](../query/fn.check_unique.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DuplicateQueryParameter {
+    name: String,
+}
+
+impl DuplicateQueryParameter {
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    #[doc(hidden)]
+    pub fn new(name: String) -> Self {
+        Self { name }
+    }
+
+    /// The percent-decoded name of the query parameter.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl Display for DuplicateQueryParameter {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "the query parameter `{}` appears more than once", self.name)
+    }
+}
+
+impl std::error::Error for DuplicateQueryParameter {}
+
+/// Why the query string of an incoming request is malformed, see
+/// [`query::check_encoding`](../query/fn.check_encoding.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum InvalidQueryString {
+    /// A `%` isn't followed by two hexadecimal digits.
+    InvalidPercentEncoding {
+        /// The byte offset of the `%` in the query string.
+        position: usize,
+    },
+    /// The percent-decoded name of a parameter isn't valid UTF-8.
+    InvalidUtf8Name {
+        /// The byte offset of the parameter in the query string.
+        position: usize,
+    },
+    /// The percent-decoded value of a parameter of a `#[ruma_api(query, decode = STRICT)]` field
+    /// isn't valid UTF-8.
+    InvalidUtf8Value {
+        /// The name of the parameter.
+        name: String,
+    },
+}
+
+impl Display for InvalidQueryString {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPercentEncoding { position } => {
+                write!(f, "invalid percent-encoding at byte {} of the query string", position)
+            }
+            Self::InvalidUtf8Name { position } => {
+                write!(f, "the name of the query parameter at byte {} isn't valid UTF-8", position)
+            }
+            Self::InvalidUtf8Value { name } => {
+                write!(f, "the value of the query parameter `{}` isn't valid UTF-8", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InvalidQueryString {}
+
+/// A request was converted with an HTTP method that its endpoint doesn't accept, see
+/// [`Metadata::allows_method`](../struct.Metadata.html#method.allows_method).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnsupportedMethod {
+    method: http::Method,
+    endpoint: &'static str,
+}
+
+impl UnsupportedMethod {
+    #[doc(hidden)]
+    pub fn new(method: http::Method, endpoint: &'static str) -> Self {
+        Self { method, endpoint }
+    }
+
+    /// The method the request was converted with.
+    pub fn method(&self) -> &http::Method {
+        &self.method
+    }
+
+    /// The name of the endpoint.
+    pub fn endpoint(&self) -> &'static str {
+        self.endpoint
+    }
+}
+
+impl Display for UnsupportedMethod {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "The endpoint `{}` doesn't accept the method {}", self.endpoint, self.method)
+    }
+}
+
+impl std::error::Error for UnsupportedMethod {}
+
+/// A constraint of an endpoint that an incoming request violates, returned by
+/// [`Validate::validate`](../trait.Validate.html#tymethod.validate).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError {
+    field: Option<&'static str>,
+    message: String,
+}
+
+impl ValidationError {
+    /// Creates an error for a constraint that involves the whole request.
+    pub fn new(message: impl Into<String>) -> Self {
+        Self { field: None, message: message.into() }
+    }
+
+    /// Creates an error for a constraint on the field with the given name.
+    pub fn field(field: &'static str, message: impl Into<String>) -> Self {
+        Self { field: Some(field), message: message.into() }
+    }
+
+    /// The name of the field that violates the constraint, if it is about a single field.
+    pub fn field_name(&self) -> Option<&'static str> {
+        self.field
+    }
+
+    /// A description of the violated constraint.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.field {
+            Some(field) => write!(f, "`{}` {}", field, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
 /// An error that occurred when trying to deserialize a request.
+///
+/// Keeps a copy of the method, URI, headers and body of the request, so servers can report what
+/// they failed to parse.
 #[derive(Debug)]
 pub struct RequestDeserializationError {
     inner: DeserializationError,
-    http_request: http::Request<Vec<u8>>,
+    // Boxed to keep `FromHttpRequestError`, and the results of request conversions, small.
+    parts: Box<RequestParts>,
+}
+
+#[derive(Debug)]
+struct RequestParts {
+    method: http::Method,
+    uri: http::Uri,
+    headers: http::HeaderMap,
+    body: Vec<u8>,
 }
 
 impl RequestDeserializationError {
@@ -74,9 +570,84 @@ impl RequestDeserializationError {
     #[doc(hidden)]
     pub fn new(
         inner: impl Into<DeserializationError>,
-        http_request: http::Request<Vec<u8>>,
+        http_request: &http::Request<Vec<u8>>,
     ) -> Self {
-        Self { inner: inner.into(), http_request }
+        Self {
+            inner: inner.into(),
+            parts: Box::new(RequestParts {
+                method: http_request.method().clone(),
+                uri: http_request.uri().clone(),
+                headers: http_request.headers().clone(),
+                body: http_request.body().clone(),
+            }),
+        }
+    }
+
+    /// The method of the request that failed to deserialize.
+    pub fn method(&self) -> &http::Method {
+        &self.parts.method
+    }
+
+    /// The URI of the request that failed to deserialize.
+    pub fn uri(&self) -> &http::Uri {
+        &self.parts.uri
+    }
+
+    /// The headers of the request that failed to deserialize.
+    pub fn headers(&self) -> &http::HeaderMap {
+        &self.parts.headers
+    }
+
+    /// The body of the request that failed to deserialize.
+    pub fn body(&self) -> &[u8] {
+        &self.parts.body
+    }
+
+    /// The value of the `X-Request-Id` header of the request that failed to deserialize, if any.
+    pub fn request_id(&self) -> Option<&str> {
+        crate::options::request_id(&self.parts.headers)
+    }
+
+    /// If a path parameter of an integer or `MilliSecondsSinceUnixEpoch` field wasn't a valid
+    /// integer, why it wasn't.
+    pub fn invalid_integer(&self) -> Option<InvalidInteger> {
+        match &self.inner {
+            DeserializationError::InvalidInteger(err) => Some(*err),
+            _ => None,
+        }
+    }
+
+    /// If a path parameter of a `RoomId`, `RoomAliasId`, `UserId` or `EventId` field wasn't a
+    /// valid identifier, which kind of identifier was expected and why it wasn't one.
+    #[cfg(feature = "ruma-identifiers")]
+    pub fn invalid_identifier(&self) -> Option<&InvalidIdentifier> {
+        match &self.inner {
+            DeserializationError::InvalidIdentifier(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// The path to the value in the JSON body that failed to deserialize, like
+    /// `content.membership`, if the body was the problem and the value wasn't the body itself.
+    #[cfg(feature = "path-to-error")]
+    pub fn json_path(&self) -> Option<&str> {
+        self.inner.json_path()
+    }
+
+    /// If the query string was malformed, why it was.
+    pub fn invalid_query_string(&self) -> Option<&InvalidQueryString> {
+        match &self.inner {
+            DeserializationError::InvalidQueryString(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    /// If a query parameter of a query field appeared more than once, which one.
+    pub fn duplicate_query_parameter(&self) -> Option<&DuplicateQueryParameter> {
+        match &self.inner {
+            DeserializationError::DuplicateQueryParameter(err) => Some(err),
+            _ => None,
+        }
     }
 }
 
@@ -97,6 +668,60 @@ pub enum FromHttpResponseError {
     Deserialization(ResponseDeserializationError),
     /// The server returned a non-success status
     Http(ServerError),
+    /// The server returned a non-success status with a body that isn't JSON, e.g. an HTML error
+    /// page of a reverse proxy
+    NonMatrixError {
+        /// The HTTP status code of the response.
+        status: http::StatusCode,
+        /// The `Content-Type` of the response.
+        content_type: String,
+        /// The body of the response.
+        body: Vec<u8>,
+    },
+    /// The server requires User-Interactive Authentication for an endpoint declared with
+    /// `uiaa: true`
+    #[cfg(feature = "serde")]
+    Uiaa(crate::uiaa::UiaaInfo),
+}
+
+impl FromHttpResponseError {
+    /// Whether the server rejected the access token but allows a soft logout, see
+    /// [`ServerError::requires_refresh`](struct.ServerError.html#method.requires_refresh).
+    pub fn requires_refresh(&self) -> bool {
+        match self {
+            Self::Http(err) => err.requires_refresh(),
+            _ => false,
+        }
+    }
+
+    /// This method is public so it is accessible from `ruma_api!` generated
+    /// code. It is not considered part of ruma-api's public API.
+    ///
+    /// Classifies a response with a non-success status.
+    #[doc(hidden)]
+    pub fn from_error_response(http_response: http::Response<Vec<u8>>) -> Self {
+        let content_type = http_response
+            .headers()
+            .get(http::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .filter(|content_type| !is_json(content_type))
+            .map(ToOwned::to_owned);
+
+        match content_type {
+            Some(content_type) => Self::NonMatrixError {
+                status: http_response.status(),
+                content_type,
+                body: http_response.into_body(),
+            },
+            None => Self::Http(ServerError::new(http_response)),
+        }
+    }
+}
+
+/// Whether the given `Content-Type` is `application/json` or a `+json` type.
+fn is_json(content_type: &str) -> bool {
+    let essence = content_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase();
+    essence == "application/json" || essence.ends_with("+json")
 }
 
 impl Display for FromHttpResponseError {
@@ -104,6 +729,14 @@ impl Display for FromHttpResponseError {
         match self {
             Self::Deserialization(err) => write!(f, "deserialization failed: {}", err),
             Self::Http(err) => write!(f, "the server returned an error: {}", err),
+            Self::NonMatrixError { status, content_type, .. } => write!(
+                f,
+                "the server returned a non-Matrix error: HTTP status {} with Content-Type {}",
+                status.as_str(),
+                content_type,
+            ),
+            #[cfg(feature = "serde")]
+            Self::Uiaa(_) => write!(f, "the server requires user-interactive authentication"),
         }
     }
 }
@@ -120,6 +753,8 @@ impl From<ResponseDeserializationError> for FromHttpResponseError {
     }
 }
 
+impl std::error::Error for FromHttpResponseError {}
+
 /// An error that occurred when trying to deserialize a response.
 #[derive(Debug)]
 pub struct ResponseDeserializationError {
@@ -137,6 +772,20 @@ impl ResponseDeserializationError {
     ) -> Self {
         Self { inner: inner.into(), http_response }
     }
+
+    /// The value of the `X-Request-Id` header of the response that failed to deserialize, if
+    /// any.
+    pub fn request_id(&self) -> Option<&str> {
+        crate::options::request_id(self.http_response.headers())
+    }
+
+    /// The path to the value in the JSON body that failed to deserialize, like
+    /// `rooms.join.!room:example.org.timeline.events[3].type`, if the body was the problem and the
+    /// value wasn't the body itself.
+    #[cfg(feature = "path-to-error")]
+    pub fn json_path(&self) -> Option<&str> {
+        self.inner.json_path()
+    }
 }
 
 impl Display for ResponseDeserializationError {
@@ -161,10 +810,48 @@ impl ServerError {
         Self { http_response }
     }
 
+    /// The value of the `X-Request-Id` header of the error response, if any.
+    pub fn request_id(&self) -> Option<&str> {
+        crate::options::request_id(self.http_response.headers())
+    }
+
+    /// The HTTP status code of the error response.
+    pub fn status(&self) -> http::StatusCode {
+        self.http_response.status()
+    }
+
     /// Get the HTTP response without parsing its contents.
     pub fn into_raw_reponse(self) -> http::Response<Vec<u8>> {
         self.http_response
     }
+
+    /// Classifies the error response by its status code, `Retry-After` header and Matrix error
+    /// body, e.g. to decide whether a request should be retried.
+    pub fn classification(&self) -> ErrorClassification {
+        let retry_after = self
+            .http_response
+            .headers()
+            .get(http::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+
+        ErrorClassification::new(self.status(), retry_after, self.http_response.body())
+    }
+
+    /// The `errcode` of the Matrix error body, or `ErrorKind::Unknown` if the response doesn't
+    /// have one.
+    pub fn kind(&self) -> ErrorKind {
+        parse_matrix_error(self.http_response.body()).0
+    }
+
+    /// Whether the access token is no longer valid, but the server allows a soft logout: the
+    /// client should refresh its token or log in again without discarding its data.
+    ///
+    /// This is the case for `M_UNKNOWN_TOKEN` errors with `soft_logout: true`.
+    pub fn requires_refresh(&self) -> bool {
+        self.kind() == ErrorKind::UnknownToken { soft_logout: true }
+    }
 }
 
 impl Display for ServerError {
@@ -180,10 +867,135 @@ impl Display for ServerError {
 
 impl std::error::Error for ServerError {}
 
+/// The classification of an error response, returned by [`ServerError::classification`].
+///
+/// [`ServerError::classification`]: struct.ServerError.html#method.classification
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorClassification {
+    /// The request was rate-limited (HTTP status 429 or `M_LIMIT_EXCEEDED`).
+    RateLimited {
+        /// How long to wait before retrying, from the `Retry-After` header or the
+        /// `retry_after_ms` field of the error body.
+        retry_after: Option<Duration>,
+    },
+    /// The server is temporarily unavailable (HTTP status 502, 503 or 504).
+    Unavailable {
+        /// How long to wait before retrying, from the `Retry-After` header.
+        retry_after: Option<Duration>,
+    },
+    /// The request lacked valid authentication or wasn't permitted (HTTP status 401 or 403).
+    Unauthorized(ErrorKind),
+    /// The resource was changed since the entity tag in the request's `If-Match` header was
+    /// obtained (HTTP status 412). The client should fetch the resource again before retrying.
+    PreconditionFailed(ErrorKind),
+    /// The request was rejected for another reason (any other 4xx HTTP status).
+    ClientError(ErrorKind),
+    /// The server failed to handle the request (any other 5xx HTTP status).
+    Internal(ErrorKind),
+}
+
+impl ErrorClassification {
+    fn new(status: http::StatusCode, retry_after: Option<Duration>, body: &[u8]) -> Self {
+        let (kind, retry_after_ms) = parse_matrix_error(body);
+
+        match status.as_u16() {
+            429 => Self::RateLimited { retry_after: retry_after.or(retry_after_ms) },
+            _ if kind == ErrorKind::LimitExceeded => {
+                Self::RateLimited { retry_after: retry_after.or(retry_after_ms) }
+            }
+            502..=504 => Self::Unavailable { retry_after },
+            401 | 403 => Self::Unauthorized(kind),
+            412 => Self::PreconditionFailed(kind),
+            500..=599 => Self::Internal(kind),
+            _ => Self::ClientError(kind),
+        }
+    }
+
+    /// Whether the request may succeed when it is retried later.
+    ///
+    /// This is the case for rate-limited requests and unavailable servers.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::RateLimited { .. } | Self::Unavailable { .. } => true,
+            Self::Unauthorized(_)
+            | Self::PreconditionFailed(_)
+            | Self::ClientError(_)
+            | Self::Internal(_) => false,
+        }
+    }
+
+    /// How long the server asked to wait before retrying, if it did.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::RateLimited { retry_after } | Self::Unavailable { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+/// The `errcode` of a Matrix error body.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// `M_FORBIDDEN`
+    Forbidden,
+    /// `M_UNKNOWN_TOKEN`
+    UnknownToken {
+        /// Whether the client should perform a soft logout, i.e. log in again without discarding
+        /// its data.
+        soft_logout: bool,
+    },
+    /// `M_MISSING_TOKEN`
+    MissingToken,
+    /// `M_LIMIT_EXCEEDED`
+    LimitExceeded,
+    /// `M_NOT_FOUND`
+    NotFound,
+    /// `M_UNRECOGNIZED`
+    Unrecognized,
+    /// `M_UNKNOWN`, also used for error responses without a Matrix error body.
+    Unknown,
+    /// Any other `errcode`.
+    Other(String),
+}
+
+/// Parses the `errcode` and `retry_after_ms` fields of a Matrix error body.
+fn parse_matrix_error(body: &[u8]) -> (ErrorKind, Option<Duration>) {
+    let json = match serde_json::from_slice::<serde_json::Value>(body) {
+        Ok(serde_json::Value::Object(json)) => json,
+        _ => return (ErrorKind::Unknown, None),
+    };
+
+    let kind = match json.get("errcode").and_then(|errcode| errcode.as_str()) {
+        Some("M_FORBIDDEN") => ErrorKind::Forbidden,
+        Some("M_UNKNOWN_TOKEN") => ErrorKind::UnknownToken {
+            soft_logout: json.get("soft_logout").and_then(|s| s.as_bool()).unwrap_or(false),
+        },
+        Some("M_MISSING_TOKEN") => ErrorKind::MissingToken,
+        Some("M_LIMIT_EXCEEDED") => ErrorKind::LimitExceeded,
+        Some("M_NOT_FOUND") => ErrorKind::NotFound,
+        Some("M_UNRECOGNIZED") => ErrorKind::Unrecognized,
+        Some("M_UNKNOWN") | None => ErrorKind::Unknown,
+        Some(errcode) => ErrorKind::Other(errcode.to_owned()),
+    };
+    let retry_after_ms =
+        json.get("retry_after_ms").and_then(|ms| ms.as_u64()).map(Duration::from_millis);
+
+    (kind, retry_after_ms)
+}
+
 #[derive(Debug)]
 enum SerializationError {
     Json(serde_json::Error),
+    #[cfg(feature = "cbor")]
+    Cbor(serde_cbor::Error),
     Query(serde_urlencoded::ser::Error),
+    Io(std::io::Error),
+    InvalidPathSegment(InvalidPathSegment),
+    UnsupportedMethod(UnsupportedMethod),
+    #[cfg(feature = "serde")]
+    NonCanonicalJson(NonCanonicalJson),
 }
 
 /// This type is public so it is accessible from `ruma_api!` generated code.
@@ -192,22 +1004,148 @@ enum SerializationError {
 #[derive(Debug)]
 pub enum DeserializationError {
     Json(serde_json::Error),
+    // A CBOR body isn't valid CBOR or can't be represented as JSON
+    #[cfg(feature = "cbor")]
+    Cbor(serde_cbor::Error),
+    // A value in a JSON body failed to deserialize, at the given path
+    #[cfg(feature = "path-to-error")]
+    JsonAtPath {
+        path: String,
+        error: serde_json::Error,
+    },
     Query(serde_urlencoded::de::Error),
+    #[cfg(feature = "ruma-identifiers")]
     Ident(ruma_identifiers::Error),
     // String <> Enum conversion failed. This can currently only happen in path
     // segment deserialization
     Strum(strum::ParseError),
+    // A percent-decoded path segment or query value that has to be UTF-8 isn't
+    Utf8(std::str::Utf8Error),
+    // A path segment of a `#[ruma_api(path, identifier)]` field isn't a valid identifier
+    InvalidPathSegment(InvalidPathSegment),
+    // A path segment of an integer field isn't a valid integer
+    InvalidInteger(InvalidInteger),
+    // A path segment of a `RoomId`, `RoomAliasId`, `UserId` or `EventId` field isn't a valid
+    // identifier of that kind
+    #[cfg(feature = "ruma-identifiers")]
+    InvalidIdentifier(InvalidIdentifier),
+    // The query string isn't percent-encoded correctly, or a value that has to be UTF-8 isn't
+    InvalidQueryString(InvalidQueryString),
+    // A query parameter of a query field appears more than once
+    DuplicateQueryParameter(DuplicateQueryParameter),
+}
+
+impl DeserializationError {
+    #[cfg(feature = "path-to-error")]
+    fn json_path(&self) -> Option<&str> {
+        match self {
+            DeserializationError::JsonAtPath { path, .. } => Some(path),
+            _ => None,
+        }
+    }
 }
 
 impl Display for DeserializationError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
             DeserializationError::Json(err) => Display::fmt(err, f),
+            #[cfg(feature = "cbor")]
+            DeserializationError::Cbor(err) => Display::fmt(err, f),
+            #[cfg(feature = "path-to-error")]
+            DeserializationError::JsonAtPath { path, error } => write!(f, "{}: {}", path, error),
             DeserializationError::Query(err) => Display::fmt(err, f),
-            DeserializationError::Ident(err) => Display::fmt(err, f),
+            #[cfg(feature = "ruma-identifiers")]
+            DeserializationError::Ident(err) => write!(f, "invalid identifier: {}", err),
             DeserializationError::Strum(err) => Display::fmt(err, f),
+            DeserializationError::Utf8(err) => Display::fmt(err, f),
+            DeserializationError::InvalidPathSegment(err) => {
+                write!(f, "invalid path parameter: {}", err)
+            }
+            DeserializationError::InvalidInteger(err) => {
+                write!(f, "invalid path parameter: {}", err)
+            }
+            #[cfg(feature = "ruma-identifiers")]
+            DeserializationError::InvalidIdentifier(err) => {
+                write!(f, "invalid path parameter: {}", err)
+            }
+            DeserializationError::InvalidQueryString(err) => Display::fmt(err, f),
+            DeserializationError::DuplicateQueryParameter(err) => Display::fmt(err, f),
+        }
+    }
+}
+
+/// This function is public so it is accessible from `ruma_api!` generated
+/// code. It is not considered part of ruma-api's public API.
+///
+/// Deserializes a JSON body. With the `path-to-error` feature, the error keeps the path to the
+/// value that failed to deserialize.
+#[doc(hidden)]
+#[cfg(feature = "serde")]
+pub fn from_json_slice<'de, T>(body: &'de [u8]) -> Result<T, DeserializationError>
+where
+    T: serde::Deserialize<'de>,
+{
+    #[cfg(feature = "path-to-error")]
+    {
+        let mut deserializer = serde_json::Deserializer::from_slice(body);
+        let value = serde_path_to_error::deserialize(&mut deserializer).map_err(|err| {
+            let path = err.path().to_string();
+            let error = err.into_inner();
+            // The path of the body itself is `.`, which doesn't tell anything.
+            if path == "." {
+                DeserializationError::Json(error)
+            } else {
+                DeserializationError::JsonAtPath { path, error }
+            }
+        })?;
+        deserializer.end()?;
+
+        Ok(value)
+    }
+
+    #[cfg(not(feature = "path-to-error"))]
+    {
+        Ok(serde_json::from_slice(body)?)
+    }
+}
+
+/// This function is public so it is accessible from `ruma_api!` generated
+/// code. It is not considered part of ruma-api's public API.
+///
+/// Removes the old names of renamed body fields, given as `(new, old)` pairs, from a JSON object
+/// that also contains their new names. The fields accept their old names through serde aliases,
+/// which fail on bodies with both names, like the ones sent with the `compat` feature.
+#[doc(hidden)]
+#[cfg(feature = "serde")]
+pub fn prefer_new_names<'a>(body: &'a [u8], renamed: &[(&str, &str)]) -> Cow<'a, [u8]> {
+    // Most bodies only have one of the names, so they are only parsed once.
+    let has_old_name = renamed.iter().any(|(_, old_name)| {
+        let quoted = format!("\"{}\"", old_name);
+        body.windows(quoted.len()).any(|window| window == quoted.as_bytes())
+    });
+    if !has_old_name {
+        return Cow::Borrowed(body);
+    }
+
+    // Invalid bodies are left as they are, deserializing them reports the error.
+    let mut object: serde_json::Map<String, serde_json::Value> = match serde_json::from_slice(body)
+    {
+        Ok(object) => object,
+        Err(_) => return Cow::Borrowed(body),
+    };
+
+    let mut changed = false;
+    for (new_name, old_name) in renamed {
+        if object.contains_key(*new_name) && object.remove(*old_name).is_some() {
+            changed = true;
         }
     }
+
+    if changed {
+        Cow::Owned(serde_json::to_vec(&object).expect("JSON object to serialize"))
+    } else {
+        Cow::Borrowed(body)
+    }
 }
 
 #[doc(hidden)]
@@ -217,6 +1155,14 @@ impl From<serde_json::Error> for DeserializationError {
     }
 }
 
+#[cfg(feature = "cbor")]
+#[doc(hidden)]
+impl From<serde_cbor::Error> for DeserializationError {
+    fn from(err: serde_cbor::Error) -> Self {
+        Self::Cbor(err)
+    }
+}
+
 #[doc(hidden)]
 impl From<serde_urlencoded::de::Error> for DeserializationError {
     fn from(err: serde_urlencoded::de::Error) -> Self {
@@ -224,6 +1170,7 @@ impl From<serde_urlencoded::de::Error> for DeserializationError {
     }
 }
 
+#[cfg(feature = "ruma-identifiers")]
 #[doc(hidden)]
 impl From<ruma_identifiers::Error> for DeserializationError {
     fn from(err: ruma_identifiers::Error) -> Self {
@@ -238,6 +1185,49 @@ impl From<strum::ParseError> for DeserializationError {
     }
 }
 
+#[doc(hidden)]
+impl From<std::str::Utf8Error> for DeserializationError {
+    fn from(err: std::str::Utf8Error) -> Self {
+        Self::Utf8(err)
+    }
+}
+
+#[doc(hidden)]
+impl From<InvalidPathSegment> for DeserializationError {
+    fn from(err: InvalidPathSegment) -> Self {
+        Self::InvalidPathSegment(err)
+    }
+}
+
+#[doc(hidden)]
+impl From<InvalidInteger> for DeserializationError {
+    fn from(err: InvalidInteger) -> Self {
+        Self::InvalidInteger(err)
+    }
+}
+
+#[cfg(feature = "ruma-identifiers")]
+#[doc(hidden)]
+impl From<InvalidIdentifier> for DeserializationError {
+    fn from(err: InvalidIdentifier) -> Self {
+        Self::InvalidIdentifier(err)
+    }
+}
+
+#[doc(hidden)]
+impl From<InvalidQueryString> for DeserializationError {
+    fn from(err: InvalidQueryString) -> Self {
+        Self::InvalidQueryString(err)
+    }
+}
+
+#[doc(hidden)]
+impl From<DuplicateQueryParameter> for DeserializationError {
+    fn from(err: DuplicateQueryParameter) -> Self {
+        Self::DuplicateQueryParameter(err)
+    }
+}
+
 #[doc(hidden)]
 impl From<std::convert::Infallible> for DeserializationError {
     fn from(err: std::convert::Infallible) -> Self {
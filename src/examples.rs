@@ -0,0 +1,49 @@
+//! Example values of request and response fields, for documentation and for generating sample
+//! requests and responses.
+//!
+//! Examples are declared with `#[ruma_api(example = "...")]` on the fields of `ruma_api!`, and
+//! every endpoint generated by the macro implements [`EndpointExamples`].
+//!
+//! [`EndpointExamples`]: trait.EndpointExamples.html
+
+/// Access to the example values of an endpoint's fields, implemented for the endpoint's request
+/// type.
+pub trait EndpointExamples {
+    /// The examples of the request fields, in declaration order.
+    fn request_examples() -> Vec<FieldExample>;
+
+    /// The examples of the response fields, in declaration order.
+    fn response_examples() -> Vec<FieldExample>;
+}
+
+/// The example value of a request or response field.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FieldExample {
+    /// The name of the field in the `Request` or `Response` struct.
+    pub field: &'static str,
+
+    /// Where the field is sent.
+    pub location: FieldLocation,
+
+    /// The example value.
+    ///
+    /// For body fields this is the JSON representation of the value, for other fields the string
+    /// that is sent in the path, query string or header.
+    pub example: &'static str,
+}
+
+/// Where a field of a request or response is sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FieldLocation {
+    /// In the body.
+    Body,
+
+    /// In a header.
+    Header,
+
+    /// In a segment of the path.
+    Path,
+
+    /// In the query string.
+    Query,
+}
@@ -0,0 +1,93 @@
+//! Passing data from middleware to endpoint handlers.
+//!
+//! Middleware like authentication or rate limiting usually runs on the `http::Request` and stores
+//! what it resolved, e.g. the user and device of an access token, in the request's
+//! [`Extensions`]. Converting the request to an endpoint's incoming request type would drop them,
+//! so [`Endpoint::try_from_http_request_with_extensions`] moves them to a [`WithExtensions`]
+//! next to the incoming request instead:
+//!
+//! ```
+//! # use std::convert::TryFrom;
+//! # use ruma_api::{deps::http, options::IncomingRequestOptions, ruma_api, Endpoint};
+//! # use ruma_identifiers::UserId;
+//! #
+//! # ruma_api! {
+//! #     mod set_topic;
+//! #
+//! #     metadata {
+//! #         description: "Set the topic of a room.",
+//! #         method: PUT,
+//! #         name: "set_topic",
+//! #         path: "/_matrix/client/r0/rooms/:room_id/state/m.room.topic",
+//! #         rate_limited: false,
+//! #         requires_authentication: true,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(path)]
+//! #         pub room_id: String,
+//! #         pub topic: String,
+//! #     }
+//! #
+//! #     response {}
+//! # }
+//! #
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! # let options = IncomingRequestOptions::default();
+//! # let mut http_request = http::Request::builder()
+//! #     .method("PUT")
+//! #     .uri("/_matrix/client/r0/rooms/!room:example.org/state/m.room.topic")
+//! #     .body(br#"{"topic":"Hello"}"#.to_vec())?;
+//! http_request.extensions_mut().insert(UserId::try_from("@alice:example.org")?);
+//!
+//! let request =
+//!     set_topic::Request::try_from_http_request_with_extensions(http_request, &options)?;
+//! let user_id = request.extensions.get::<UserId>();
+//! let room_id = &request.room_id;
+//! # assert!(user_id.is_some());
+//! # assert_eq!(room_id, "!room:example.org");
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Extensions`]: struct.Extensions.html
+//! [`Endpoint::try_from_http_request_with_extensions`]: ../trait.Endpoint.html#method.try_from_http_request_with_extensions
+//! [`WithExtensions`]: struct.WithExtensions.html
+
+use std::ops::{Deref, DerefMut};
+
+pub use http::Extensions;
+
+/// An incoming request together with the extensions of the `http::Request` it was converted
+/// from.
+///
+/// Dereferences to the incoming request, so its fields can be accessed directly.
+#[derive(Debug)]
+pub struct WithExtensions<R> {
+    /// The incoming request.
+    pub request: R,
+
+    /// The extensions of the `http::Request`.
+    pub extensions: Extensions,
+}
+
+impl<R> WithExtensions<R> {
+    /// Splits this into the incoming request and the extensions.
+    pub fn into_parts(self) -> (R, Extensions) {
+        (self.request, self.extensions)
+    }
+}
+
+impl<R> Deref for WithExtensions<R> {
+    type Target = R;
+
+    fn deref(&self) -> &R {
+        &self.request
+    }
+}
+
+impl<R> DerefMut for WithExtensions<R> {
+    fn deref_mut(&mut self) -> &mut R {
+        &mut self.request
+    }
+}
@@ -0,0 +1,113 @@
+//! Accepting both the current and a legacy shape of a response body.
+//!
+//! During spec migrations, servers may still send the legacy shape of a response body. In
+//! `ruma_api!`, a newtype body field can use `#[ruma_api(body, fallback = LegacyBody)]` to make the
+//! field's value in the 'Incoming' type a [`Fallback`](enum.Fallback.html), which tries to
+//! deserialize the current shape first and the legacy shape second:
+//!
+//! ```text
+//! response {
+//!     #[ruma_api(body, fallback = LegacyBody)]
+//!     pub body: Body,
+//! }
+//! ```
+//!
+//! Converting the 'Incoming' type back into the outgoing one requires `LegacyBody: Into<Body>`.
+
+use serde::{
+    de::{Deserialize, DeserializeOwned, Deserializer},
+    ser::{Serialize, Serializer},
+};
+use serde_json::Value as JsonValue;
+
+use crate::{partial::PartialError, TryIntoOutgoing};
+
+/// A value that was received in either the current or the legacy shape.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Fallback<T, L> {
+    /// The value was received in the current shape.
+    Current(T),
+    /// The value was received in the legacy shape.
+    Legacy(L),
+}
+
+impl<T, L> Fallback<T, L> {
+    /// Whether the value was received in the legacy shape.
+    pub fn is_legacy(&self) -> bool {
+        match self {
+            Fallback::Current(_) => false,
+            Fallback::Legacy(_) => true,
+        }
+    }
+
+    /// Returns the value if it was received in the current shape.
+    pub fn current(self) -> Option<T> {
+        match self {
+            Fallback::Current(value) => Some(value),
+            Fallback::Legacy(_) => None,
+        }
+    }
+
+    /// Returns the value if it was received in the legacy shape.
+    pub fn legacy(self) -> Option<L> {
+        match self {
+            Fallback::Current(_) => None,
+            Fallback::Legacy(value) => Some(value),
+        }
+    }
+
+    /// Converts the value into the current shape, upgrading it if it was received in the legacy
+    /// shape.
+    pub fn into_current(self) -> T
+    where
+        L: Into<T>,
+    {
+        match self {
+            Fallback::Current(value) => value,
+            Fallback::Legacy(value) => value.into(),
+        }
+    }
+}
+
+impl<T, L: Into<T>> TryIntoOutgoing<T> for Fallback<T, L> {
+    fn try_into_outgoing(self) -> Result<T, PartialError> {
+        Ok(self.into_current())
+    }
+}
+
+impl<'de, T, L> Deserialize<'de> for Fallback<T, L>
+where
+    T: DeserializeOwned,
+    L: DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let json = JsonValue::deserialize(deserializer)?;
+
+        match T::deserialize(&json) {
+            Ok(value) => Ok(Fallback::Current(value)),
+            // If neither shape matches, the error for the current shape is the more useful one.
+            Err(err) => L::deserialize(&json)
+                .map(Fallback::Legacy)
+                .map_err(|_| serde::de::Error::custom(err)),
+        }
+    }
+}
+
+impl<T, L> Serialize for Fallback<T, L>
+where
+    T: Serialize,
+    L: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Fallback::Current(value) => value.serialize(serializer),
+            Fallback::Legacy(value) => value.serialize(serializer),
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! Negotiation of the wire format of request and response bodies.
+//!
+//! The conversions `ruma_api!` generates send and parse JSON bodies. With the `cbor` feature, the
+//! functions of this module convert bodies between JSON and [CBOR] at the HTTP boundary, so the
+//! same endpoint definition can serve clients that prefer the more compact binary format, e.g. on
+//! low-bandwidth connections. The format of a body is given by its `Content-Type`,
+//! `application/json` or `application/cbor`, and the format of a response by the `Accept` header
+//! of the request.
+//!
+//! Servers convert incoming requests to JSON before parsing them, and send the response in the
+//! format the request asked for:
+//!
+//! ```
+//! # use std::{convert::TryFrom, error::Error};
+//! # use ruma_api::{deps::http, format, ruma_api};
+//! #
+//! # ruma_api! {
+//! #     mod set_topic;
+//! #
+//! #     metadata {
+//! #         description: "Set the topic of a room.",
+//! #         method: PUT,
+//! #         name: "set_topic",
+//! #         path: "/_matrix/client/r0/rooms/:room_id/state/m.room.topic",
+//! #         rate_limited: false,
+//! #         requires_authentication: true,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(path)]
+//! #         pub room_id: String,
+//! #         pub topic: String,
+//! #     }
+//! #
+//! #     response {
+//! #         pub event_id: String,
+//! #     }
+//! # }
+//! #
+//! # fn handle(
+//! #     http_request: http::Request<Vec<u8>>,
+//! # ) -> Result<http::Response<Vec<u8>>, Box<dyn Error>> {
+//! let (http_request, format) = format::decode_request(http_request)?;
+//! let request = set_topic::Request::try_from(http_request)?;
+//! // …
+//! # let response = set_topic::Response { event_id: "$event".to_owned() };
+//! let http_response =
+//!     format::encode_response(http::Response::<Vec<u8>>::try_from(response)?, format)?;
+//! # Ok(http_response)
+//! # }
+//! ```
+//!
+//! Clients do the opposite with [`encode_request`], which also asks for a response in the given
+//! format, and [`decode_response`].
+//!
+//! [CBOR]: https://tools.ietf.org/html/rfc7049
+//! [`encode_request`]: fn.encode_request.html
+//! [`decode_response`]: fn.decode_response.html
+
+use http::header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, VARY};
+use serde_json::Value as JsonValue;
+
+use crate::error::{
+    DeserializationError, FromHttpRequestError, FromHttpResponseError, IntoHttpError,
+    RequestDeserializationError, ResponseDeserializationError,
+};
+
+/// The media type of CBOR bodies.
+pub const APPLICATION_CBOR: &str = "application/cbor";
+
+/// The `Accept` header of requests that ask for a CBOR response, which still allows JSON for
+/// servers that don't support CBOR.
+const ACCEPT_CBOR: &str = "application/cbor, application/json;q=0.9";
+
+/// The format of a request or response body.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum BodyFormat {
+    /// JSON, `application/json`.
+    Json,
+
+    /// CBOR, `application/cbor`.
+    Cbor,
+}
+
+impl BodyFormat {
+    /// The media type of the format, used as the `Content-Type` of bodies.
+    pub fn media_type(self) -> &'static str {
+        match self {
+            BodyFormat::Json => "application/json",
+            BodyFormat::Cbor => APPLICATION_CBOR,
+        }
+    }
+
+    /// The format of a body with the given headers: CBOR if its `Content-Type` is
+    /// `application/cbor`, JSON otherwise.
+    pub fn of_body(headers: &HeaderMap) -> Self {
+        match headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok()) {
+            Some(content_type) if essence(content_type) == APPLICATION_CBOR => BodyFormat::Cbor,
+            _ => BodyFormat::Json,
+        }
+    }
+
+    /// The format a response should be sent in for a request with the given headers.
+    ///
+    /// This is CBOR if the `Accept` header prefers `application/cbor` to `application/json`, i.e.
+    /// gives it a higher quality value, and JSON otherwise, including for requests without an
+    /// `Accept` header.
+    pub fn accepted(headers: &HeaderMap) -> Self {
+        let mut json = None;
+        let mut cbor = None;
+        let mut wildcard = None;
+        let ranges = headers
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','));
+        for range in ranges {
+            let mut params = range.split(';');
+            let quality = params
+                .find_map(|param| {
+                    let param = param.trim();
+                    match param.find('=') {
+                        Some(i) if &param[..i] == "q" => param[i + 1..].parse::<f32>().ok(),
+                        _ => None,
+                    }
+                })
+                .unwrap_or(1.0);
+
+            let slot = match essence(range).as_str() {
+                "application/json" => &mut json,
+                "application/cbor" => &mut cbor,
+                "*/*" | "application/*" => &mut wildcard,
+                _ => continue,
+            };
+            *slot = Some(slot.map_or(quality, |previous: f32| previous.max(quality)));
+        }
+
+        let json = json.or(wildcard).unwrap_or(0.0);
+        let cbor = cbor.or(wildcard).unwrap_or(0.0);
+        if cbor > json {
+            BodyFormat::Cbor
+        } else {
+            BodyFormat::Json
+        }
+    }
+}
+
+// `#[derive(Default)]` with `#[default]` needs a newer Rust version than the minimum supported one.
+#[allow(clippy::derivable_impls)]
+impl Default for BodyFormat {
+    fn default() -> Self {
+        BodyFormat::Json
+    }
+}
+
+/// Converts the body of an outgoing request to the given format, and asks for a response in it.
+///
+/// For CBOR, a JSON body is converted and the `Accept` header is set to prefer CBOR responses.
+/// Requests without a JSON body, like those with a raw body, are only changed in their `Accept`
+/// header. For JSON, the request is returned as it is.
+pub fn encode_request(
+    http_request: http::Request<Vec<u8>>,
+    format: BodyFormat,
+) -> Result<http::Request<Vec<u8>>, IntoHttpError> {
+    if format == BodyFormat::Json {
+        return Ok(http_request);
+    }
+
+    let (mut parts, mut body) = http_request.into_parts();
+    parts.headers.insert(ACCEPT, HeaderValue::from_static(ACCEPT_CBOR));
+    if is_json_body(&parts.headers, &body) {
+        body = json_to_cbor(&body)?;
+        set_format(&mut parts.headers, &body, format);
+    }
+
+    Ok(http::Request::from_parts(parts, body))
+}
+
+/// Converts the body of an incoming request to JSON if it is CBOR, so it can be converted to an
+/// endpoint's incoming request type, and returns it with the format the response should be sent
+/// in, see [`BodyFormat::accepted`](enum.BodyFormat.html#method.accepted).
+///
+/// A body that isn't valid CBOR, or can't be represented as JSON, is rejected with
+/// `FromHttpRequestError::Deserialization`.
+pub fn decode_request(
+    http_request: http::Request<Vec<u8>>,
+) -> Result<(http::Request<Vec<u8>>, BodyFormat), FromHttpRequestError> {
+    let response_format = BodyFormat::accepted(http_request.headers());
+    if BodyFormat::of_body(http_request.headers()) == BodyFormat::Json
+        || http_request.body().is_empty()
+    {
+        return Ok((http_request, response_format));
+    }
+
+    let body = match cbor_to_json(http_request.body()) {
+        Ok(body) => body,
+        Err(err) => return Err(RequestDeserializationError::new(err, &http_request).into()),
+    };
+    let (mut parts, _) = http_request.into_parts();
+    set_format(&mut parts.headers, &body, BodyFormat::Json);
+
+    Ok((http::Request::from_parts(parts, body), response_format))
+}
+
+/// Converts the body of an outgoing response to the given format.
+///
+/// For CBOR, a JSON body is converted, including the bodies of error responses. Responses without
+/// a JSON body are left as they are. In both cases, `Vary: Accept` is added, since the format of
+/// the response depends on the `Accept` header of the request.
+pub fn encode_response(
+    http_response: http::Response<Vec<u8>>,
+    format: BodyFormat,
+) -> Result<http::Response<Vec<u8>>, IntoHttpError> {
+    let (mut parts, mut body) = http_response.into_parts();
+    parts.headers.append(VARY, HeaderValue::from_static("Accept"));
+    if format == BodyFormat::Cbor && is_json_body(&parts.headers, &body) {
+        body = json_to_cbor(&body)?;
+        set_format(&mut parts.headers, &body, format);
+    }
+
+    Ok(http::Response::from_parts(parts, body))
+}
+
+/// Converts the body of an incoming response to JSON if it is CBOR, so it can be converted to an
+/// endpoint's incoming response type.
+///
+/// A body that isn't valid CBOR, or can't be represented as JSON, is rejected with
+/// `FromHttpResponseError::Deserialization`, which is boxed to keep the `Result` small.
+pub fn decode_response(
+    http_response: http::Response<Vec<u8>>,
+) -> Result<http::Response<Vec<u8>>, Box<FromHttpResponseError>> {
+    if BodyFormat::of_body(http_response.headers()) == BodyFormat::Json
+        || http_response.body().is_empty()
+    {
+        return Ok(http_response);
+    }
+
+    let body = match cbor_to_json(http_response.body()) {
+        Ok(body) => body,
+        Err(err) => {
+            return Err(Box::new(ResponseDeserializationError::new(err, http_response).into()))
+        }
+    };
+    let (mut parts, _) = http_response.into_parts();
+    set_format(&mut parts.headers, &body, BodyFormat::Json);
+
+    Ok(http::Response::from_parts(parts, body))
+}
+
+fn json_to_cbor(body: &[u8]) -> Result<Vec<u8>, IntoHttpError> {
+    let value: JsonValue = serde_json::from_slice(body)?;
+    Ok(serde_cbor::to_vec(&value)?)
+}
+
+fn cbor_to_json(body: &[u8]) -> Result<Vec<u8>, DeserializationError> {
+    // Going through `serde_json::Value` rejects what JSON can't represent, like byte strings and
+    // map keys that aren't strings.
+    let value: JsonValue = serde_cbor::from_slice(body)?;
+    Ok(serde_json::to_vec(&value)?)
+}
+
+/// Whether the body isn't empty and the headers declare it as JSON.
+fn is_json_body(headers: &HeaderMap, body: &[u8]) -> bool {
+    if body.is_empty() {
+        return false;
+    }
+    match headers.get(CONTENT_TYPE).and_then(|value| value.to_str().ok()) {
+        Some(content_type) => essence(content_type) == "application/json",
+        None => false,
+    }
+}
+
+/// Sets the `Content-Type` and `Content-Length` of a body that was converted to the given format.
+fn set_format(headers: &mut HeaderMap, body: &[u8], format: BodyFormat) {
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(format.media_type()));
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(body.len()));
+}
+
+/// The media type of a `Content-Type` or `Accept` value without its parameters, in lowercase.
+fn essence(media_type: &str) -> String {
+    media_type.split(';').next().unwrap_or_default().trim().to_ascii_lowercase()
+}
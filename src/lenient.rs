@@ -0,0 +1,146 @@
+//! Lenient deserialization helpers for query string parameters.
+//!
+//! `serde_urlencoded` only accepts `true` and `false` for booleans and fails on empty values for
+//! numbers, while some servers and clients send `1` / `0` or leave the value empty. The functions
+//! in this module can be used with serde's `deserialize_with` attribute on `#[ruma_api(query)]`
+//! fields to accept these variants:
+//!
+//! ```
+//! # use std::convert::TryFrom;
+//! # use ruma_api::{deps::http, ruma_api};
+//! #
+//! # ruma_api! {
+//! #     mod sync_events;
+//! #
+//! #     metadata {
+//! #         description: "Get the latest events.",
+//! #         method: GET,
+//! #         name: "sync",
+//! #         path: "/_matrix/client/r0/sync",
+//! #         rate_limited: false,
+//! #         requires_authentication: true,
+//! #     }
+//! #
+//! #     request {
+//! #[ruma_api(query)]
+//! #[serde(default, deserialize_with = "ruma_api::lenient::bool")]
+//! pub full_state: bool,
+//! #     }
+//! #
+//! #     response {}
+//! # }
+//! #
+//! # let http_request =
+//! #     http::Request::builder().uri("/_matrix/client/r0/sync?full_state=1").body(Vec::new());
+//! # assert!(sync_events::Request::try_from(http_request.unwrap()).unwrap().full_state);
+//! ```
+//!
+//! The accepted forms are:
+//!
+//! * for booleans: `true`, `false`, `1`, `0` and the empty string (which means `false`, or `None`
+//!   for the `opt_` variant)
+//! * for integers: anything `FromStr` accepts after trimming surrounding whitespace, and the empty
+//!   string (which means the default value, or `None` for the `opt_` variant)
+
+use std::{
+    fmt::{self, Display, Formatter},
+    marker::PhantomData,
+    str::FromStr,
+};
+
+use serde::de::{self, Deserializer, Visitor};
+
+/// Deserializes a boolean, accepting `1`, `0` and the empty string in addition to `true` and
+/// `false`.
+pub fn bool<'de, D>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    opt_bool(deserializer).map(Option::unwrap_or_default)
+}
+
+/// Like [`bool`](fn.bool.html), but maps the empty string to `None`.
+pub fn opt_bool<'de, D>(deserializer: D) -> Result<Option<bool>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserializer.deserialize_any(LenientVisitor(PhantomData, parse_bool))
+}
+
+/// Deserializes an integer, accepting surrounding whitespace and mapping the empty string to the
+/// default value.
+pub fn int<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr + Default,
+    T::Err: Display,
+{
+    opt_int(deserializer).map(Option::unwrap_or_default)
+}
+
+/// Like [`int`](fn.int.html), but maps the empty string to `None`.
+pub fn opt_int<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    deserializer.deserialize_any(LenientVisitor(PhantomData, parse_int::<T>))
+}
+
+fn parse_bool(s: &str) -> Result<bool, String> {
+    match s {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(format!("invalid boolean `{}`", s)),
+    }
+}
+
+fn parse_int<T>(s: &str) -> Result<T, String>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    s.parse().map_err(|err| format!("invalid integer `{}`: {}", s, err))
+}
+
+/// Visitor that accepts strings, booleans and integers and funnels all of them through the same
+/// string-based parsing function.
+struct LenientVisitor<T>(PhantomData<T>, fn(&str) -> Result<T, String>);
+
+impl<'de, T> Visitor<'de> for LenientVisitor<T> {
+    type Value = Option<T>;
+
+    fn expecting(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("a string, boolean or integer")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        let v = v.trim();
+        if v.is_empty() {
+            Ok(None)
+        } else {
+            (self.1)(v).map(Some).map_err(E::custom)
+        }
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Self::Value, E> {
+        self.visit_str(if v { "true" } else { "false" })
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+        self.visit_str(&v.to_string())
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+        self.visit_str(&v.to_string())
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(None)
+    }
+}
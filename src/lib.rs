@@ -9,11 +9,41 @@
 //! input parameters for requests, and the structure of a successful response.
 //! Such types can then be used by client code to make requests, and by server code to fulfill
 //! those requests.
+//!
+//! The `http::Request` and `http::Response` types come from version 0.2 of the `http` crate with
+//! the default `http02` feature. To use version 1 instead, disable the default features and enable
+//! `http1` (plus `client`, `server` and `with-ruma-api-macros` to keep the other defaults). At
+//! least one of the two features has to be enabled, version 1 is used if both are, and
+//! `ruma_api::deps::http` always refers to the selected version.
+//!
+//! Clients and servers each only need half of the HTTP conversions of requests and responses. The
+//! default `client` and `server` features select which of them `ruma_api!` generates, so a pure
+//! client or server can disable the other one to save build time. The
+//! [`client`](client/index.html) module and `ruma_api_group!` need the `client` feature, the
+//! `handler` feature enables `server`.
 
+#![cfg_attr(feature = "nightly", feature(const_if_match, const_loop, const_panic))]
 #![warn(rust_2018_idioms)]
 #![deny(missing_copy_implementations, missing_debug_implementations, missing_docs)]
 
-use std::convert::{TryFrom, TryInto};
+#[cfg(not(any(feature = "http02", feature = "http1")))]
+compile_error!("one of the `http02` and `http1` features needs to be enabled");
+
+// With the `http1` feature, version 1 of the http crate is used under its usual name, both within
+// this crate and for generated code through `exports::http`. It takes precedence over `http02`, so
+// the crate still builds with all features enabled.
+#[cfg(feature = "http1")]
+extern crate http1 as http;
+
+#[cfg(feature = "server")]
+use std::convert::TryFrom;
+use std::time::Duration;
+#[cfg(feature = "serde")]
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, HashMap},
+    hash::Hash,
+};
 
 use http::Method;
 
@@ -57,6 +87,12 @@ use http::Method;
 /// *   `method`: The HTTP method used for requests to the endpoint.
 ///     It's not necessary to import `http::Method`'s associated constants. Just write
 ///     the value as if it was imported, e.g. `GET`.
+///     Endpoints that accept several methods list them separated by `|`, e.g. `GET | POST`. The
+///     first one is used by default, and the generated
+///     `Request::try_into_http_request_with_method` converts a request with one of the others.
+///     The request is encoded the same way with all methods, so such endpoints can have body
+///     fields even if they accept `GET`, and incoming requests without a body are parsed like ones
+///     with an empty JSON object.
 /// *   `name`: A unique name for the endpoint.
 ///     Generally this will be the same as the containing module.
 /// *   `path`: The path component of the URL for the endpoint, e.g. "/foo/bar".
@@ -66,6 +102,59 @@ use http::Method;
 ///     for details).
 /// *   `rate_limited`: Whether or not the endpoint enforces rate limiting on requests.
 /// *   `requires_authentication`: Whether or not the endpoint requires a valid access token.
+/// *   `uiaa`: Optional, whether the endpoint uses
+///     [User-Interactive Authentication](uiaa/index.html). Defaults to `false`. With `true`, a
+///     `401` response with authentication flows is returned as `FromHttpResponseError::Uiaa`.
+/// *   `canonical_json`: Optional, whether request and response bodies have to be
+///     [canonical JSON](canonical_json/index.html), like those of federation endpoints. Defaults to
+///     `false`. With `true`, converting a request or response with a float or an integer that is
+///     out of range in its body fails instead of producing a payload that can't be signed.
+/// *   `unstable`: Optional, whether the endpoint is not part of a released version of the spec
+///     yet. Defaults to `false`. Decides whether the request type implements
+///     [`StableEndpoint`](trait.StableEndpoint.html) or
+///     [`UnstableEndpoint`](trait.UnstableEndpoint.html).
+/// *   `request_content_type`: Optional, the `Content-Type` of requests, e.g.
+///     `"application/octet-stream"`. Outgoing requests get it unless a header field already sets
+///     the `Content-Type`, and incoming requests without it are rejected with
+///     `FromHttpRequestError::UnexpectedContentType`. Without it, outgoing requests with a JSON
+///     body get `application/json`, which isn't checked on incoming requests.
+/// *   `cacheable`: Optional, whether responses only depend on the path and query string of the
+///     request. Defaults to `false`. With `true`,
+///     [`Endpoint::cache_key`](trait.Endpoint.html#method.cache_key) returns a
+///     [`CacheKey`](cache/struct.CacheKey.html) for requests, which clients can use for caching or
+///     to deduplicate identical requests. Endpoints with body fields can't be cacheable.
+/// *   `validate`: Optional, whether incoming requests are checked with the request type's
+///     implementation of [`Validate`](trait.Validate.html) after they were converted. Defaults to
+///     `false`.
+/// *   `direction`: Optional, which side of the Matrix API sends requests to the endpoint, either
+///     `ClientToServer` or `ServerToClient`. Defaults to `ClientToServer`. Endpoints a homeserver
+///     pushes to an application service, like `/transactions/{txnId}`, use `ServerToClient`: the
+///     client functions send their access token in the query string, and they can't use `uiaa`.
+///     See [`Direction`](enum.Direction.html).
+/// *   `server_kind`: Optional, the kind of server the endpoint belongs to, either `Homeserver`,
+///     `IdentityServer` or `PushGateway`. Defaults to `Homeserver`. The client functions send
+///     requests to the base URL configured for this kind of server, see
+///     [`ServerKind`](enum.ServerKind.html).
+/// *   `duplicate_query_parameters`: Optional, what happens when a parameter of a query field
+///     appears more than once in the query string of an incoming request, either `Reject` or
+///     `LastWins`. Defaults to `Reject`, with which the request is rejected with an
+///     [`error::DuplicateQueryParameter`](error/struct.DuplicateQueryParameter.html). With
+///     `LastWins`, the last value of the parameter is used. Parameters that only go into a
+///     `query_map` field are kept as they are.
+/// *   `suggested_timeout`: Optional, how long clients should wait for a response, written as
+///     `Duration::from_secs(…)` or `Duration::from_millis(…)` with an integer literal, e.g. for
+///     long polling endpoints like `/sync`. Without it, the endpoint has no suggested timeout.
+///     `RequestOptions::for_endpoint` uses it for requests without a timeout of their own.
+/// *   `deprecated` and `removed`: Optional, the versions of the spec that deprecated and removed
+///     the endpoint, as [`MatrixVersion`](versions/enum.MatrixVersion.html) variants like
+///     `R0_6_0`. Available through
+///     [`Metadata::status_for`](struct.Metadata.html#method.status_for). With the `tracing`
+///     feature and a `matrix_version` in the request options, a warning is logged when a request
+///     to a deprecated or removed endpoint is sent or received.
+///
+/// Outgoing requests and responses with a body get a `Content-Length` header that matches it.
+/// Incoming requests with a `Content-Length` header that doesn't match the length of their body
+/// are rejected with `FromHttpRequestError::ContentLengthMismatch`.
 ///
 /// ## Request
 ///
@@ -80,23 +169,133 @@ use http::Method;
 ///     Generally this is a `String`.
 ///     The attribute value shown above as `HEADER_NAME` must be a header name constant from
 ///     `http::header`, e.g. `CONTENT_TYPE`.
+///     Header fields can also be optional, with a type `Option<T>`, like in responses. This
+///     allows typed headers like `ruma_api::media::ByteRange`.
+/// *   `#[ruma_api(cookie = "name")]`: Fields with this attribute are sent as the cookie with
+///     the given name in the `Cookie` header of the request, see [`cookie`](cookie/index.html).
+///     The type must be `String` or `Option<String>`. All cookie fields of a request are sent in
+///     one header, and all `Cookie` headers are searched when receiving one.
+/// *   `#[ruma_api(header_map)]`: One field of type `http::HeaderMap` can collect all headers of
+///     the request that don't belong to a header field, e.g. for proxies that pass on unknown
+///     headers. Its headers are sent as is, except for the ones of header fields.
 /// *   `#[ruma_api(path)]`: Fields with this attribute will be inserted into the matching path
 ///     component of the request URL.
+///     The value must implement `ToString` and `TryFrom<&str>`. The identifier types `RoomId`,
+///     `RoomAliasId`, `UserId` and `EventId` from ruma-identifiers are parsed with
+///     [`path::parse_identifier`](path/fn.parse_identifier.html) instead, so an invalid
+///     identifier is reported as an [`InvalidIdentifier`](error/struct.InvalidIdentifier.html)
+///     that says which kind of identifier was expected.
+///     The value is percent-encoded with [`path::PATH_SEGMENT`](path/constant.PATH_SEGMENT.html),
+///     which escapes characters like `#` and `/`. Another encode set from the
+///     [`path`](path/index.html) module can be selected with
+///     `#[ruma_api(path, encode = STRICT)]`.
+///     Percent-encoded sequences that aren't valid UTF-8 are replaced with U+FFFD when parsing.
+///     With `#[ruma_api(path, decode = STRICT)]`, they fail the conversion instead, and with
+///     `#[ruma_api(path, decode = RAW)]`, the field gets the percent-decoded bytes. Its type then
+///     has to implement `AsRef<[u8]>` and `TryFrom<&[u8]>`, like `Vec<u8>`.
+///     With `#[ruma_api(path, identifier)]`, the value has to follow the grammar of Matrix
+///     identifiers, see [`path::check_identifier`](path/fn.check_identifier.html). Converting a
+///     request that violates it fails, as does converting an incoming request whose decoded
+///     segment violates it, so user input can't add segments to the path.
 /// *   `#[ruma_api(query)]`: Fields with this attribute will be inserting into the URL's query
 ///     string.
-/// *   `#[ruma_api(query_map)]`: Instead of individual query fields, one query_map field, of any
-///     type that implements `IntoIterator<Item = (String, String)>` (e.g.
-///     `HashMap<String, String>`, can be used for cases where an endpoint supports arbitrary query
-///     parameters.
+///     To accept `1` / `0` or empty values for booleans and integers, use the helpers from
+///     [`ruma_api::lenient`](lenient/index.html) with `#[serde(deserialize_with = "...")]`.
+///     With `#[ruma_api(query, as_str)]`, the value is sent as the string of its `Display`
+///     implementation and parsed with `FromStr`, e.g. for enums like `dir=f` / `dir=b`. See
+///     [`ruma_api::query`](query/index.html).
+///     Fields of the identifier types are parsed like path fields of these types, with
+///     [`query::identifier`](query/fn.identifier.html).
+///     Like path fields, query fields can use `#[ruma_api(query, decode = STRICT)]` to reject
+///     values that aren't valid UTF-8 instead of replacing invalid sequences.
+///     Incoming requests whose query string has a `%` that doesn't start a percent-encoded byte,
+///     or a parameter name that isn't valid UTF-8, are rejected with an
+///     [`error::InvalidQueryString`](error/enum.InvalidQueryString.html) before the query fields
+///     are deserialized.
+/// *   `#[ruma_api(query_map)]`: One query_map field, of any type that implements
+///     `IntoIterator<Item = (String, String)>` (e.g. `HashMap<String, String>`), can be used for
+///     cases where an endpoint supports arbitrary query parameters.
+///     It can be combined with `#[ruma_api(query)]` fields. The map's parameters are then appended
+///     after the typed ones, and parsing puts every parameter that isn't a typed query field into
+///     the map, which additionally needs to implement `FromIterator<(String, String)>`.
 ///
 /// Any field that does not include one of these attributes will be part of the request's JSON
 /// body.
 ///
+/// Request fields can limit their size with `#[ruma_api(max_len = 255)]` for strings, counted in
+/// bytes, and `#[ruma_api(max_items = 100)]` for collections. Incoming requests that exceed a limit
+/// are rejected with `FromHttpRequestError::Validation`, naming the field, see
+/// [`ruma_api::limits`](limits/index.html).
+///
+/// Body, header and query fields can be conditionally compiled with `#[cfg(...)]` attributes,
+/// e.g. for fields that are only part of an unstable version of the spec. A disabled field is
+/// removed from the generated types and conversions entirely.
+///
+/// Body fields can additionally use `#[ruma_api(renamed_from = "old_name")]` when a field was
+/// renamed in the spec. Incoming bodies are then accepted with either name, or both, in which
+/// case the value under the new name is used. With the `compat` feature enabled, outgoing bodies
+/// contain the field under both names.
+///
+/// With the `compat` feature enabled, deserialization of body fields is also relaxed to work
+/// around known deviations from the spec in server implementations. See the
+/// [`compat`](compat/index.html) module for details.
+///
+/// With the `path-to-error` feature enabled, errors from deserializing the body of a request or
+/// response also tell where in the body the invalid value is, through `json_path` on
+/// [`RequestDeserializationError`](error/struct.RequestDeserializationError.html) and
+/// [`ResponseDeserializationError`](error/struct.ResponseDeserializationError.html), e.g.
+/// `rooms.join.!room:example.org.timeline.events[3].type`. Their messages start with that path.
+///
+/// The generated conversions send and parse JSON bodies. With the `cbor` feature enabled, the
+/// [`format`](format/index.html) module converts bodies between JSON and CBOR at the HTTP
+/// boundary, picking the format from the `Content-Type` and `Accept` headers.
+///
+/// The generated `Request` has a `new` constructor that takes the required fields in declaration
+/// order. `Option`al fields, fields with `#[serde(default)]` or `#[serde(default = "...")]` and
+/// the header map field are set to their default values.
+///
+/// If all fields of the `Request` or the `Response` have such a default value, it implements
+/// `Default` too, so requests with many optional fields can be created with struct update syntax:
+///
+/// ```
+/// # use ruma_api::ruma_api;
+/// #
+/// # ruma_api! {
+/// #     mod sync_events;
+/// #
+/// #     metadata {
+/// #         description: "Get the latest events.",
+/// #         method: GET,
+/// #         name: "sync",
+/// #         path: "/_matrix/client/r0/sync",
+/// #         rate_limited: false,
+/// #         requires_authentication: true,
+/// #     }
+/// #
+/// #     request {
+/// #         #[ruma_api(query)]
+/// #         pub since: Option<String>,
+/// #         #[ruma_api(query)]
+/// #         pub timeout: Option<u64>,
+/// #         #[ruma_api(query)]
+/// #         #[serde(default)]
+/// #         pub full_state: bool,
+/// #     }
+/// #
+/// #     response {
+/// #         pub next_batch: String,
+/// #     }
+/// # }
+/// #
+/// # let token = "s72594_4483_1934".to_owned();
+/// let request = sync_events::Request { since: Some(token), ..Default::default() };
+/// ```
+///
 /// ## Response
 ///
 /// Like the request block, the response block consists of normal struct field definitions.
 /// Doc comments and attributes are allowed as normal.
-/// There is also a special attribute available to control how the struct is created from a
+/// There are also special attributes available to control how the struct is created from a
 /// `http::Request`:
 ///
 /// *   `#[ruma_api(header = HEADER_NAME)]`: Fields with this attribute will be treated as HTTP
@@ -105,9 +304,92 @@ use http::Method;
 ///     Generally this is a `String`.
 ///     The attribute value shown above as `HEADER_NAME` must be a header name constant from
 ///     `http::header`, e.g. `CONTENT_TYPE`.
+///     Header fields can also be optional, with a type `Option<T>`. For those, `T` must
+///     implement `TryFrom<&str>` and `http::HeaderValue` must implement `TryFrom<T>`, which allows
+///     typed headers like `ruma_api::media::ContentDisposition`. A header that is missing or fails
+///     to parse results in `None`.
+///     A response with a `CONTENT_RANGE` header field is sent with the status
+///     `206 Partial Content` when the header is set.
+/// *   `#[ruma_api(cookie = "name")]`: Fields with this attribute are sent as a `Set-Cookie`
+///     header setting the cookie with the given name, one header per field. The type must be
+///     `String` or `Option<String>`, and the attributes of the cookie are ignored when receiving
+///     it. A missing cookie results in `None`, or in an error if the field isn't optional.
+/// *   `#[ruma_api(header_map)]`: Like in requests, one field of type `http::HeaderMap` can
+///     collect all headers that don't belong to a header field.
 ///
-/// Any field that does not include the above attribute will be expected in the response's JSON
-/// body.
+/// Any field that does not include one of the above attributes will be expected in the response's JSON
+/// body. Response body fields also support `#[ruma_api(renamed_from = "old_name")]`.
+///
+/// If all fields of a response are headers, its body is empty and it doesn't get the
+/// `Content-Type: application/json` header.
+///
+/// ## Examples
+///
+/// Request and response fields of any kind can be annotated with `#[ruma_api(example = "...")]`.
+/// The generated endpoint implements
+/// [`examples::EndpointExamples`](examples/trait.EndpointExamples.html), which lists these
+/// examples, e.g. for generating documentation or sample requests and responses. The examples of
+/// body fields are written in JSON, like `#[ruma_api(example = "\"@alice:example.org\"")]`.
+///
+/// Besides the `TryFrom<Response>` implementation for `http::Response<Vec<u8>>`, the generated
+/// `Response` has a `try_into_http_response_with_writer` method that writes the body to any
+/// `std::io::Write`, like a reused buffer, instead of allocating a new `Vec<u8>`.
+///
+/// ## Modules
+///
+/// The generated types always have the same names, so there can normally only be one endpoint per
+/// module. To define several endpoints in one module, start the macro input with a module
+/// declaration like `pub mod create_alias;`. Everything the macro generates is then put into a
+/// module of that name, which imports all items of its parent module:
+///
+/// ```text
+/// ruma_api! {
+///     pub mod create_alias;
+///
+///     metadata { ... }
+///     request { ... }
+///     response { ... }
+/// }
+///
+/// pub use create_alias::{Request as CreateAliasRequest, Response as CreateAliasResponse};
+/// ```
+///
+/// ## Routing before reading the body
+///
+/// With the `server` feature enabled, the request type also gets two associated functions for
+/// servers that route requests before reading their bodies:
+///
+/// *   `Request::matches_path(path: &str) -> bool` checks whether a request path matches the
+///     endpoint's path, with any value for the path parameters.
+/// *   `Request::try_from_parts(parts: http::request::Parts)` parses and checks the path, query
+///     string and headers, and returns a
+///     [`conversion::PartialIncoming`](conversion/struct.PartialIncoming.html) that is converted
+///     into the incoming request with `complete(body)` once the body was read.
+///
+/// ## Server handlers
+///
+/// With the `handler` feature enabled, `ruma_api!` also generates a `Handler` trait with a single
+/// `async fn handle(&self, request)` method that server implementations can implement for each
+/// endpoint. The request type is the 'Incoming' variant of `Request` and the method returns a
+/// `Result<Response, Self::Error>`. Implementations need to use the `#[async_trait]` attribute
+/// from the `async-trait` crate, which is re-exported as `ruma_api::async_trait`.
+///
+/// ## Additional items
+///
+/// Any items after the `response` block, like `impl Request { ... }` blocks with convenience
+/// constructors, constants or helper functions, are emitted verbatim after the generated code.
+///
+/// ## Generated tests
+///
+/// Starting the macro input with `#[ruma_api(generate_tests)]` (before the module declaration, if
+/// any) generates a `#[cfg(test)]` module with tests that convert `Request::default()` and
+/// `Response::default()` to their HTTP representation and back, and a test that parses
+/// [malformed variants](malformed/index.html) of the default request without panicking.
+/// `Request` and `Response` have to implement `Default` for this, either because all of their
+/// fields have default values or through `impl Default for Request { ... }` blocks in the
+/// additional items. The tests need both
+/// directions of the conversions, so they are only generated when both the `client` and `server`
+/// features are enabled.
 ///
 /// ## Newtype bodies
 ///
@@ -118,9 +400,16 @@ use http::Method;
 /// each struct can be marked with this attribute. It is an error to have a newtype body field and
 /// normal body fields within the same struct.
 ///
+/// The field can have any type that (de)serializes as JSON, not just structs: endpoints whose body
+/// is a map with arbitrary keys, like `BTreeMap<UserId, DeviceKeys>`, or an array use a newtype
+/// body of that map or `Vec` type. A body that is valid JSON of a different kind, for example an
+/// array where a map is expected, fails to deserialize like any other invalid body.
+///
 /// There is another kind of newtype body that is enabled with `#[ruma_api(raw_body)]`. It is used
 /// for endpoints in which the request or response body can be arbitrary bytes instead of a JSON
-/// objects. A field with `#[ruma_api(raw_body)]` needs to have the type `Vec<u8>`.
+/// objects. A field with `#[ruma_api(raw_body)]` can have any type that implements both
+/// `From<Vec<u8>>` and `Into<Vec<u8>>`, like `Vec<u8>` itself or a byte buffer type such as
+/// `bytes::Bytes`.
 ///
 /// # Examples
 ///
@@ -197,28 +486,176 @@ use http::Method;
 /// All request and response types also derive [`Outgoing`][Outgoing]. As such, to allow fallible
 /// deserialization, you can use the `#[wrap_incoming]` attribute. For details, see the
 /// documentation for [the derive macro](derive.Outgoing.html).
+///
+/// Body fields can also use `#[ruma_api(partial)]`, which is a shorthand for
+/// `#[wrap_incoming(with ruma_api::partial::Partial)]`. A value that fails to deserialize is then
+/// reported in that field of the 'Incoming' type instead of failing the whole conversion. See
+/// [`Partial`](partial/enum.Partial.html).
+///
+/// Body fields of type `Vec<T>` can use `#[ruma_api(batch)]`, which is a shorthand for
+/// `#[wrap_incoming(T with ruma_api::partial::Partial)]`. Each item of the array is then
+/// deserialized on its own, so one invalid item, like a malformed PDU in a federation transaction,
+/// doesn't reject the whole batch. See the [`batch`](batch/index.html) module.
+///
+/// Response body fields can use `#[ruma_api(deferred)]`, which is a shorthand for
+/// `#[wrap_incoming(with ruma_api::deferred::Deferred)]`, to keep the field's raw JSON and only
+/// deserialize it when needed, e.g. for the large parts of a `/sync` response. See
+/// [`Deferred`](deferred/struct.Deferred.html).
+///
+/// Similarly, a newtype body field in a response can use `#[ruma_api(body, fallback = LegacyBody)]`
+/// for endpoints whose response body is being migrated to a new shape. The field of the 'Incoming'
+/// type is then a [`Fallback`](fallback/enum.Fallback.html), which holds the body in the declared
+/// shape if it matches and in the legacy shape otherwise.
+///
+/// If a request or response has an 'Incoming' type, it can be converted back with
+/// `Request::try_from(incoming)` (or `Response::try_from(incoming)`), e.g. to forward a request
+/// after validating it. This fails if one of the wrapped fields couldn't be deserialized, and
+/// requires all wrapper types to implement [`TryIntoOutgoing`](trait.TryIntoOutgoing.html).
 // TODO: Explain the concept of fallible deserialization before jumping to `ruma_api::Outgoing`
 #[cfg(feature = "with-ruma-api-macros")]
 pub use ruma_api_macros::ruma_api;
 
+/// Generates a trait with one method per endpoint for sending requests over a
+/// [`client::Transport`](client/trait.Transport.html).
+///
+/// The macro expects a trait declaration whose body is a comma-separated list of endpoint modules,
+/// i.e. modules containing the `Request` and `Response` types generated by `ruma_api!`. Each entry
+/// is either the name of such a module, which is also used as the method name, or a method name
+/// followed by a colon and the path to the module:
+///
+/// ```text
+/// ruma_api_group! {
+///     /// Room directory endpoints.
+///     pub trait DirectoryApi {
+///         create_alias,
+///         delete_alias: r0::alias::delete_alias,
+///     }
+/// }
+/// ```
+///
+/// The generated trait has `client::Transport` as a supertrait and is implemented for all
+/// transports. Each method takes the endpoint's `Request` and returns a future resolving to the
+/// 'Incoming' variant of its `Response`.
+#[cfg(all(feature = "client", feature = "with-ruma-api-macros"))]
+pub use ruma_api_macros::ruma_api_group;
+
 #[cfg(feature = "with-ruma-api-macros")]
 pub use ruma_api_macros::Outgoing;
 
+#[cfg(feature = "handler")]
+pub use async_trait::async_trait;
+
+#[cfg(feature = "serde")]
+pub mod batch;
+#[cfg(feature = "client")]
+pub mod cache;
+#[cfg(feature = "serde")]
+pub mod canonical_json;
+#[cfg(feature = "capture")]
+pub mod capture;
+#[cfg(feature = "client")]
+pub mod client;
+#[cfg(feature = "compat")]
+pub mod compat;
+pub mod conditional;
+pub mod conversion;
+pub mod cookie;
+pub mod credentials;
+#[cfg(feature = "dedup")]
+pub mod dedup;
+#[cfg(feature = "deferred")]
+pub mod deferred;
+#[cfg(feature = "docs")]
+pub mod docs;
 pub mod error;
-/// This module is used to support the generated code from ruma-api-macros.
-/// It is not considered part of ruma-api's public API.
+pub mod examples;
+#[cfg(feature = "server")]
+pub mod extensions;
+#[cfg(feature = "serde")]
+pub mod fallback;
+#[cfg(feature = "cbor")]
+pub mod format;
+#[cfg(feature = "serde")]
+pub mod lenient;
+pub mod limits;
+#[cfg(feature = "server")]
+pub mod malformed;
+#[cfg(all(feature = "percent-encoding", feature = "serde"))]
+pub mod manual;
+#[cfg(feature = "media")]
+pub mod media;
+pub mod options;
+#[cfg(feature = "serde")]
+pub mod partial;
+#[cfg(feature = "percent-encoding")]
+pub mod path;
+pub mod prelude;
+#[cfg(feature = "client")]
+pub mod preview;
+#[cfg(feature = "server")]
+pub mod proxy;
+#[cfg(feature = "serde")]
+pub mod query;
+#[cfg(feature = "queue")]
+pub mod queue;
+#[cfg(feature = "server")]
+pub mod security;
+#[cfg(feature = "spec-check")]
+pub mod spec_check;
+#[cfg(feature = "serde")]
+pub mod time;
+#[cfg(feature = "serde")]
+pub mod uiaa;
+#[cfg(feature = "server")]
+pub mod unknown;
+pub mod versions;
+#[cfg(all(feature = "client", feature = "with-ruma-api-macros"))]
+pub mod well_known;
+
+// Allows using `ruma_api!` within this crate, since the generated code refers to `ruma_api`.
 #[cfg(feature = "with-ruma-api-macros")]
-#[doc(hidden)]
-pub mod exports {
+extern crate self as ruma_api;
+/// The crates in ruma-api's public API, for implementing `Endpoint` manually without having to
+/// keep their versions in sync with ruma-api.
+///
+/// `deps::http` is the version of the `http` crate selected with the `http02` or `http1` feature.
+pub mod deps {
+    #[cfg(all(feature = "http02", not(feature = "http1")))]
     pub use http;
+    #[cfg(feature = "http1")]
+    pub use http1 as http;
+    #[cfg(feature = "percent-encoding")]
     pub use percent_encoding;
+    #[cfg(feature = "serde")]
     pub use serde;
     pub use serde_json;
     pub use serde_urlencoded;
+    #[cfg(feature = "url")]
     pub use url;
 }
 
-use error::{FromHttpRequestError, FromHttpResponseError, IntoHttpError};
+/// This module is used to support the generated code from ruma-api-macros.
+/// It is not considered part of ruma-api's public API, use [`deps`](deps/index.html) instead.
+#[cfg(feature = "with-ruma-api-macros")]
+#[doc(hidden)]
+pub mod exports {
+    #[cfg(feature = "handler")]
+    pub use async_trait;
+
+    pub use crate::deps::*;
+}
+
+use conversion::{
+    TryFromHttpRequest, TryFromHttpResponse, TryIntoHttpRequest, TryIntoHttpResponse,
+};
+#[cfg(feature = "server")]
+use error::FromHttpRequestError;
+#[cfg(feature = "client")]
+use error::IntoHttpError;
+#[cfg(feature = "client")]
+use options::CustomizeRequest;
+#[cfg(feature = "server")]
+use options::{IncomingRequestOptions, ResponseOptions};
 
 /// A type that can be sent to another party that understands the matrix protocol. If any of the
 /// fields of `Self` don't implement serde's `Deserialize`, you can derive this trait to generate a
@@ -232,43 +669,697 @@ pub trait Outgoing {
     type Incoming;
 }
 
+/// Conversion of an 'Incoming' value back into the type it is the 'Incoming' variant of.
+///
+/// This is implemented for 'Incoming' types generated by the [`Outgoing`][doc] derive macro, for
+/// [`Partial<T>`](partial/enum.Partial.html) and for the standard containers of such types. The
+/// conversion fails if any of the wrapped values couldn't be deserialized.
+///
+/// [doc]: derive.Outgoing.html
+#[cfg(feature = "serde")]
+pub trait TryIntoOutgoing<T> {
+    /// Converts `self` into the outgoing type.
+    fn try_into_outgoing(self) -> Result<T, partial::PartialError>;
+}
+
+#[cfg(feature = "serde")]
+impl<T, U: TryIntoOutgoing<T>> TryIntoOutgoing<Option<T>> for Option<U> {
+    fn try_into_outgoing(self) -> Result<Option<T>, partial::PartialError> {
+        self.map(U::try_into_outgoing).transpose()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<T, U: TryIntoOutgoing<T>> TryIntoOutgoing<Vec<T>> for Vec<U> {
+    fn try_into_outgoing(self) -> Result<Vec<T>, partial::PartialError> {
+        self.into_iter().map(U::try_into_outgoing).collect()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, W> TryIntoOutgoing<BTreeMap<K, V>> for BTreeMap<K, W>
+where
+    K: Ord,
+    W: TryIntoOutgoing<V>,
+{
+    fn try_into_outgoing(self) -> Result<BTreeMap<K, V>, partial::PartialError> {
+        self.into_iter().map(|(key, value)| Ok((key, value.try_into_outgoing()?))).collect()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, W> TryIntoOutgoing<HashMap<K, V>> for HashMap<K, W>
+where
+    K: Eq + Hash,
+    W: TryIntoOutgoing<V>,
+{
+    fn try_into_outgoing(self) -> Result<HashMap<K, V>, partial::PartialError> {
+        self.into_iter().map(|(key, value)| Ok((key, value.try_into_outgoing()?))).collect()
+    }
+}
+
+/// Constraints on incoming requests that can't be expressed in their types, like a maximum value
+/// for a `limit` or a non-empty alias.
+///
+/// For endpoints with `validate: true` in the metadata section of `ruma_api!`, `validate` is called
+/// by the conversion from `http::Request` after all fields were extracted, and violations are
+/// returned as `FromHttpRequestError::Validation`. It is implemented for the type incoming requests
+/// are converted to, which is `IncomingRequest` if the request has `#[wrap_incoming]` fields and
+/// `Request` otherwise:
+///
+/// ```
+/// # mod get_public_rooms {
+/// # use ruma_api::{error::ValidationError, ruma_api, Validate};
+/// #
+/// # ruma_api! {
+/// #     metadata {
+/// #         description: "Get public rooms.",
+/// #         method: GET,
+/// #         name: "get_public_rooms",
+/// #         path: "/_matrix/client/r0/publicRooms",
+/// #         rate_limited: false,
+/// #         requires_authentication: false,
+/// #         validate: true,
+/// #     }
+/// #
+/// #     request {
+/// #         #[ruma_api(query)]
+/// #         pub limit: u32,
+/// #     }
+/// #
+/// #     response {}
+/// # }
+/// #
+/// impl Validate for Request {
+///     fn validate(&self) -> Result<(), ValidationError> {
+///         if self.limit > 1000 {
+///             return Err(ValidationError::field("limit", "must be at most 1000"));
+///         }
+///         Ok(())
+///     }
+/// }
+/// # }
+/// #
+/// # use std::convert::TryFrom;
+/// # use ruma_api::{deps::http, error::FromHttpRequestError};
+/// #
+/// # let http_request = http::Request::builder()
+/// #     .uri("/_matrix/client/r0/publicRooms?limit=1001")
+/// #     .body(Vec::new())
+/// #     .unwrap();
+/// # match get_public_rooms::Request::try_from(http_request) {
+/// #     Err(FromHttpRequestError::Validation(err)) => assert_eq!(err.field_name(), Some("limit")),
+/// #     _ => panic!("expected a validation error"),
+/// # }
+/// ```
+pub trait Validate {
+    /// Checks the constraints of the endpoint.
+    fn validate(&self) -> Result<(), error::ValidationError>;
+}
+
 /// A Matrix API endpoint.
 ///
 /// The type implementing this trait contains any data needed to make a request to the endpoint.
-pub trait Endpoint: Outgoing + TryInto<http::Request<Vec<u8>>, Error = IntoHttpError>
+///
+/// Which HTTP conversions the request and response types need depends on the `client` and
+/// `server` features, see the [`conversion`](conversion/index.html) module.
+pub trait Endpoint: Outgoing + TryIntoHttpRequest
 where
-    <Self as Outgoing>::Incoming: TryFrom<http::Request<Vec<u8>>, Error = FromHttpRequestError>,
-    <Self::Response as Outgoing>::Incoming:
-        TryFrom<http::Response<Vec<u8>>, Error = FromHttpResponseError>,
+    <Self as Outgoing>::Incoming: TryFromHttpRequest,
+    <Self::Response as Outgoing>::Incoming: TryFromHttpResponse,
 {
     /// Data returned in a successful response from the endpoint.
-    type Response: Outgoing + TryInto<http::Response<Vec<u8>>, Error = IntoHttpError>;
+    type Response: Outgoing + TryIntoHttpResponse;
 
     /// Metadata about the endpoint.
     const METADATA: Metadata;
+
+    /// Converts this request to an `http::Request`, customizing it with the given
+    /// [`RequestOptions`](options/struct.RequestOptions.html) or closure.
+    ///
+    /// A closure can make any changes to the generated `http::Request`, like adding query
+    /// parameters or experimental headers:
+    ///
+    /// ```
+    /// # use ruma_api::{
+    /// #     deps::http::{self, header::HeaderValue},
+    /// #     ruma_api, Endpoint,
+    /// # };
+    /// #
+    /// # ruma_api! {
+    /// #     mod get_alias;
+    /// #
+    /// #     metadata {
+    /// #         description: "Resolve a room alias to a room ID.",
+    /// #         method: GET,
+    /// #         name: "get_alias",
+    /// #         path: "/_matrix/client/r0/directory/room/:room_alias",
+    /// #         rate_limited: false,
+    /// #         requires_authentication: false,
+    /// #     }
+    /// #
+    /// #     request {
+    /// #         #[ruma_api(path)]
+    /// #         pub room_alias: String,
+    /// #     }
+    /// #
+    /// #     response {
+    /// #         pub room_id: String,
+    /// #     }
+    /// # }
+    /// #
+    /// # fn main() -> Result<(), ruma_api::error::IntoHttpError> {
+    /// # let request = get_alias::Request { room_alias: "#room:example.org".to_owned() };
+    /// let http_request = request.try_into_http_request_with(|req: &mut http::Request<_>| {
+    ///     req.headers_mut().insert("x-experimental", HeaderValue::from_static("1"));
+    /// })?;
+    /// # assert_eq!(http_request.headers()["x-experimental"], "1");
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "client")]
+    fn try_into_http_request_with(
+        self,
+        customizer: impl CustomizeRequest,
+    ) -> Result<http::Request<Vec<u8>>, IntoHttpError> {
+        let mut http_request = self.try_into()?;
+        customizer.customize(&mut http_request);
+        Ok(http_request)
+    }
+
+    /// Describes the `http::Request` this request would be converted to, without sending it.
+    ///
+    /// See the [`preview`](preview/index.html) module for details.
+    #[cfg(feature = "client")]
+    fn preview(&self) -> Result<preview::RequestPreview, IntoHttpError>
+    where
+        Self: Clone,
+    {
+        Ok(preview::RequestPreview::new(&self.clone().try_into()?))
+    }
+
+    /// Like [`preview`](#method.preview), but for the request customized with the given
+    /// [`RequestOptions`](options/struct.RequestOptions.html) or closure, e.g. to see the URL
+    /// with the base URL applied.
+    #[cfg(feature = "client")]
+    fn preview_with(
+        &self,
+        customizer: impl CustomizeRequest,
+    ) -> Result<preview::RequestPreview, IntoHttpError>
+    where
+        Self: Clone,
+    {
+        Ok(preview::RequestPreview::new(&self.clone().try_into_http_request_with(customizer)?))
+    }
+
+    /// A key that is equal for requests that can be answered by the same response, for endpoints
+    /// with `cacheable: true` in their metadata. Returns `None` for all other endpoints.
+    ///
+    /// See the [`cache`](cache/index.html) module for details.
+    #[cfg(feature = "client")]
+    fn cache_key(&self) -> Option<cache::CacheKey> {
+        None
+    }
+
+    /// Converts an `http::Request` to this endpoint's incoming request type, after checking it
+    /// against the given [`IncomingRequestOptions`](options/struct.IncomingRequestOptions.html).
+    #[cfg(feature = "server")]
+    fn try_from_http_request_with(
+        http_request: http::Request<Vec<u8>>,
+        options: &IncomingRequestOptions,
+    ) -> Result<<Self as Outgoing>::Incoming, FromHttpRequestError> {
+        options.check(&http_request)?;
+        options.warn_if_outdated(&Self::METADATA);
+        <Self as Outgoing>::Incoming::try_from(http_request)
+    }
+
+    /// Like [`try_from_http_request_with`](#method.try_from_http_request_with), but also extracts
+    /// the [`Credentials`](credentials/enum.Credentials.html) of the request.
+    ///
+    /// Requests with a malformed `Authorization` header are rejected with
+    /// `FromHttpRequestError::InvalidCredentials`, and requests without credentials to endpoints
+    /// that require authentication with `FromHttpRequestError::MissingCredentials`.
+    #[cfg(feature = "server")]
+    fn try_from_http_request_authenticated(
+        http_request: http::Request<Vec<u8>>,
+        options: &IncomingRequestOptions,
+    ) -> Result<credentials::Authenticated<<Self as Outgoing>::Incoming>, FromHttpRequestError>
+    {
+        let credentials = credentials::Credentials::from_request(&http_request)?;
+        if credentials.is_none() && Self::METADATA.requires_authentication() {
+            return Err(FromHttpRequestError::MissingCredentials);
+        }
+
+        let request = Self::try_from_http_request_with(http_request, options)?;
+        Ok(credentials::Authenticated { credentials, request })
+    }
+
+    /// Like [`try_from_http_request_with`](#method.try_from_http_request_with), but keeps the
+    /// extensions of the `http::Request`, e.g. data attached by middleware.
+    ///
+    /// See the [`extensions`](extensions/index.html) module for details.
+    #[cfg(feature = "server")]
+    fn try_from_http_request_with_extensions(
+        mut http_request: http::Request<Vec<u8>>,
+        options: &IncomingRequestOptions,
+    ) -> Result<extensions::WithExtensions<<Self as Outgoing>::Incoming>, FromHttpRequestError>
+    {
+        let extensions = std::mem::take(http_request.extensions_mut());
+        let request = Self::try_from_http_request_with(http_request, options)?;
+        Ok(extensions::WithExtensions { request, extensions })
+    }
+
+    /// Converts a response of this endpoint to an `http::Response`, adding the headers of the
+    /// given [`ResponseOptions`](options/struct.ResponseOptions.html), like security headers.
+    #[cfg(feature = "server")]
+    fn try_into_http_response_with(
+        response: Self::Response,
+        options: &ResponseOptions,
+    ) -> Result<http::Response<Vec<u8>>, error::IntoHttpError> {
+        let mut http_response = std::convert::TryInto::try_into(response)?;
+        options.apply(&Self::METADATA, &mut http_response);
+        Ok(http_response)
+    }
+}
+
+/// Marker trait for endpoints that are part of a released version of the spec.
+///
+/// `ruma_api!` implements either this trait or [`UnstableEndpoint`](trait.UnstableEndpoint.html)
+/// for every endpoint, depending on the `unstable` field of its metadata. Applications can require
+/// it to make sure at compile time that they only call stable endpoints:
+///
+/// ```
+/// # use ruma_api::{
+/// #     conversion::{TryFromHttpRequest, TryFromHttpResponse},
+/// #     Outgoing, StableEndpoint,
+/// # };
+/// #
+/// async fn send_stable<E>(request: E)
+/// where
+///     E: StableEndpoint,
+///     <E as Outgoing>::Incoming: TryFromHttpRequest,
+///     <E::Response as Outgoing>::Incoming: TryFromHttpResponse,
+/// {
+///     // …
+/// }
+/// ```
+pub trait StableEndpoint: Endpoint
+where
+    <Self as Outgoing>::Incoming: TryFromHttpRequest,
+    <Self::Response as Outgoing>::Incoming: TryFromHttpResponse,
+{
+}
+
+/// Marker trait for endpoints that are not part of a released version of the spec yet, usually
+/// because their path contains an `unstable` prefix.
+///
+/// Generated for endpoints with `unstable: true` in the metadata section of `ruma_api!`.
+pub trait UnstableEndpoint: Endpoint
+where
+    <Self as Outgoing>::Incoming: TryFromHttpRequest,
+    <Self::Response as Outgoing>::Incoming: TryFromHttpResponse,
+{
 }
 
 /// Metadata about an API endpoint.
-#[derive(Clone, Debug)]
+///
+/// The fields are private so new metadata can be added without breaking hand-written `Endpoint`
+/// implementations. Those create the metadata with [`new`](#method.new) and the `with_` methods,
+/// which can all be used in constants:
+///
+/// ```
+/// use ruma_api::{deps::http::Method, Metadata};
+///
+/// const METADATA: Metadata = Metadata::new(
+///     "Add an alias to a room.",
+///     &Method::PUT,
+///     "create_alias",
+///     "/_matrix/client/r0/directory/room/:room_alias",
+/// )
+/// .with_requires_authentication(true);
+///
+/// assert_eq!(METADATA.name(), "create_alias");
+/// assert!(!METADATA.rate_limited());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub struct Metadata {
+    description: &'static str,
+    // A reference, so `Metadata` has no destructor and the `with_` methods can be `const fn`s.
+    method: &'static Method,
+    name: &'static str,
+    path: &'static str,
+    rate_limited: bool,
+    requires_authentication: bool,
+    direction: Direction,
+    server_kind: ServerKind,
+    other_methods: &'static [Method],
+    suggested_timeout: Option<Duration>,
+    deprecated: Option<versions::MatrixVersion>,
+    removed: Option<versions::MatrixVersion>,
+}
+
+impl Metadata {
+    /// Creates metadata for an endpoint that is neither rate limited nor requires authentication.
+    pub const fn new(
+        description: &'static str,
+        method: &'static Method,
+        name: &'static str,
+        path: &'static str,
+    ) -> Self {
+        Self {
+            description,
+            method,
+            name,
+            path,
+            rate_limited: false,
+            requires_authentication: false,
+            direction: Direction::ClientToServer,
+            server_kind: ServerKind::Homeserver,
+            other_methods: &[],
+            suggested_timeout: None,
+            deprecated: None,
+            removed: None,
+        }
+    }
+
+    /// Sets whether this endpoint is rate limited by the server.
+    pub const fn with_rate_limited(self, rate_limited: bool) -> Self {
+        Self { rate_limited, ..self }
+    }
+
+    /// Sets whether the server requires an authenticated user for this endpoint.
+    pub const fn with_requires_authentication(self, requires_authentication: bool) -> Self {
+        Self { requires_authentication, ..self }
+    }
+
+    /// Sets which side of the Matrix API sends requests to this endpoint.
+    pub const fn with_direction(self, direction: Direction) -> Self {
+        Self { direction, ..self }
+    }
+
+    /// Sets the kind of server that requests to this endpoint are sent to.
+    pub const fn with_server_kind(self, server_kind: ServerKind) -> Self {
+        Self { server_kind, ..self }
+    }
+
+    /// Sets the HTTP methods this endpoint accepts besides its default method.
+    pub const fn with_other_methods(self, other_methods: &'static [Method]) -> Self {
+        Self { other_methods, ..self }
+    }
+
+    /// Sets how long clients should wait for a response from this endpoint.
+    pub const fn with_suggested_timeout(self, suggested_timeout: Duration) -> Self {
+        Self { suggested_timeout: Some(suggested_timeout), ..self }
+    }
+
+    /// Sets the version of the spec that deprecated this endpoint.
+    pub const fn with_deprecated(self, version: versions::MatrixVersion) -> Self {
+        Self { deprecated: Some(version), ..self }
+    }
+
+    /// Sets the version of the spec that removed this endpoint.
+    pub const fn with_removed(self, version: versions::MatrixVersion) -> Self {
+        Self { removed: Some(version), ..self }
+    }
+
+    /// A human-readable description of the endpoint.
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// The HTTP method used by this endpoint.
+    ///
+    /// For endpoints that accept several methods, this is the default one.
+    pub fn method(&self) -> &'static Method {
+        self.method
+    }
+
+    /// The HTTP methods this endpoint accepts besides its default method, e.g. `POST` for an
+    /// endpoint declared with `method: GET | POST`.
+    pub fn other_methods(&self) -> &'static [Method] {
+        self.other_methods
+    }
+
+    /// Whether this endpoint accepts requests with the given method.
+    pub fn allows_method(&self, method: &Method) -> bool {
+        self.method == method || self.other_methods.contains(method)
+    }
+
+    /// A unique identifier for this endpoint.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// The path of this endpoint's URL, with variable names where path parameters should be filled
+    /// in during a request.
+    pub fn path(&self) -> &'static str {
+        self.path
+    }
+
+    /// Whether or not this endpoint is rate limited by the server.
+    pub fn rate_limited(&self) -> bool {
+        self.rate_limited
+    }
+
+    /// Whether or not the server requires an authenticated user for this endpoint.
+    pub fn requires_authentication(&self) -> bool {
+        self.requires_authentication
+    }
+
+    /// Which side of the Matrix API sends requests to this endpoint.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    /// The kind of server that requests to this endpoint are sent to.
+    pub fn server_kind(&self) -> ServerKind {
+        self.server_kind
+    }
+
+    /// How long clients should wait for a response from this endpoint, if it differs from usual
+    /// requests, e.g. because the server holds the request open like with long polling.
+    pub fn suggested_timeout(&self) -> Option<Duration> {
+        self.suggested_timeout
+    }
+
+    /// The version of the spec that deprecated this endpoint, if any.
+    pub fn deprecated(&self) -> Option<versions::MatrixVersion> {
+        self.deprecated
+    }
+
+    /// The version of the spec that removed this endpoint, if any.
+    pub fn removed(&self) -> Option<versions::MatrixVersion> {
+        self.removed
+    }
+
+    /// The status of this endpoint in the given version of the spec.
+    pub fn status_for(&self, version: versions::MatrixVersion) -> versions::EndpointStatus {
+        match (self.removed, self.deprecated) {
+            (Some(removed), _) if version >= removed => versions::EndpointStatus::Removed,
+            (_, Some(deprecated)) if version >= deprecated => versions::EndpointStatus::Deprecated,
+            _ => versions::EndpointStatus::Supported,
+        }
+    }
+}
+
+/// Which side of the Matrix API sends requests to an endpoint, and with that, which conventions
+/// for authentication and errors apply.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Direction {
+    /// Requests are sent to a server, e.g. by clients to a homeserver or by one homeserver to
+    /// another. This is the case for almost all endpoints.
+    ClientToServer,
+
+    /// Requests are pushed by a server to its client, like the `/transactions/{txnId}` endpoint a
+    /// homeserver uses to send events to an application service.
+    ///
+    /// The homeserver authenticates with the application service's `hs_token`, which is sent in
+    /// the `access_token` query parameter, and there is no User-Interactive Authentication.
+    ServerToClient,
+}
+
+impl Direction {
+    /// Where the access token of requests in this direction is sent by convention.
+    pub fn access_token_placement(self) -> options::AccessTokenPlacement {
+        match self {
+            Self::ClientToServer => options::AccessTokenPlacement::Header,
+            Self::ServerToClient => options::AccessTokenPlacement::Query,
+        }
+    }
+}
+
+// `#[derive(Default)]` with `#[default]` needs a newer Rust version than the minimum supported one.
+#[allow(clippy::derivable_impls)]
+impl Default for Direction {
+    fn default() -> Self {
+        Self::ClientToServer
+    }
+}
+
+/// The kind of server an endpoint belongs to, which decides the base URL requests to it are sent
+/// to.
+///
+/// See [`RequestOptions::for_endpoint`](options/struct.RequestOptions.html#method.for_endpoint).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Deserialize, serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ServerKind {
+    /// A homeserver, for the client-server, server-server and application service APIs.
+    Homeserver,
+
+    /// An identity server, for the endpoints under `/_matrix/identity`.
+    IdentityServer,
+
+    /// A push gateway, for the endpoints under `/_matrix/push`.
+    PushGateway,
+}
+
+// `#[derive(Default)]` with `#[default]` needs a newer Rust version than the minimum supported one.
+#[allow(clippy::derivable_impls)]
+impl Default for ServerKind {
+    fn default() -> Self {
+        Self::Homeserver
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Metadata {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        MetadataRef::from(self).serialize(serializer)
+    }
+}
+
+/// A borrowed version of [`Metadata`](struct.Metadata.html) that can be deserialized, e.g. from
+/// an endpoint inventory that was dumped to JSON.
+///
+/// `Metadata` itself only contains `&'static str`s, so it can't implement `Deserialize`. The
+/// strings are borrowed from the input where possible, and owned if they contain escape sequences
+/// or the deserializer can't borrow, like `serde_json::from_reader`.
+#[cfg(feature = "serde")]
+#[derive(Clone, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub struct MetadataRef<'a> {
     /// A human-readable description of the endpoint.
-    pub description: &'static str,
+    #[serde(borrow)]
+    pub description: Cow<'a, str>,
 
     /// The HTTP method used by this endpoint.
+    #[serde(with = "method_serde")]
     pub method: Method,
 
     /// A unique identifier for this endpoint.
-    pub name: &'static str,
+    #[serde(borrow)]
+    pub name: Cow<'a, str>,
 
     /// The path of this endpoint's URL, with variable names where path parameters should be filled
     /// in during a request.
-    pub path: &'static str,
+    #[serde(borrow)]
+    pub path: Cow<'a, str>,
 
     /// Whether or not this endpoint is rate limited by the server.
     pub rate_limited: bool,
 
     /// Whether or not the server requires an authenticated user for this endpoint.
     pub requires_authentication: bool,
+
+    /// Which side of the Matrix API sends requests to this endpoint.
+    #[serde(default)]
+    pub direction: Direction,
+
+    /// The kind of server that requests to this endpoint are sent to.
+    #[serde(default)]
+    pub server_kind: ServerKind,
+
+    /// The HTTP methods this endpoint accepts besides its default method.
+    #[serde(default, with = "method_serde::list", skip_serializing_if = "Vec::is_empty")]
+    pub other_methods: Vec<Method>,
+
+    /// How long clients should wait for a response from this endpoint.
+    #[serde(
+        default,
+        rename = "suggested_timeout_ms",
+        with = "time::duration::opt_ms",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub suggested_timeout: Option<Duration>,
+
+    /// The version of the spec that deprecated this endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecated: Option<versions::MatrixVersion>,
+
+    /// The version of the spec that removed this endpoint.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub removed: Option<versions::MatrixVersion>,
+}
+
+#[cfg(feature = "serde")]
+impl<'a> From<&'a Metadata> for MetadataRef<'a> {
+    fn from(metadata: &'a Metadata) -> Self {
+        Self {
+            description: Cow::Borrowed(metadata.description),
+            method: metadata.method.clone(),
+            name: Cow::Borrowed(metadata.name),
+            path: Cow::Borrowed(metadata.path),
+            rate_limited: metadata.rate_limited,
+            requires_authentication: metadata.requires_authentication,
+            direction: metadata.direction,
+            server_kind: metadata.server_kind,
+            other_methods: metadata.other_methods.to_vec(),
+            suggested_timeout: metadata.suggested_timeout,
+            deprecated: metadata.deprecated,
+            removed: metadata.removed,
+        }
+    }
+}
+
+/// De-/serialization of `http::Method` as a string.
+#[cfg(feature = "serde")]
+pub(crate) mod method_serde {
+    use http::Method;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(method: &Method, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(method.as_str())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Method, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Not `&str`, which fails for deserializers that can't borrow, like `serde_json::Value`.
+        let method = String::deserialize(deserializer)?;
+        Method::from_bytes(method.as_bytes()).map_err(de::Error::custom)
+    }
+
+    /// De-/serialization of a list of `http::Method`s as strings.
+    pub mod list {
+        use http::Method;
+        use serde::{de, Deserialize, Deserializer, Serializer};
+
+        pub fn serialize<S>(methods: &[Method], serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serializer.collect_seq(methods.iter().map(Method::as_str))
+        }
+
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Vec<Method>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Vec::<String>::deserialize(deserializer)?
+                .iter()
+                .map(|method| Method::from_bytes(method.as_bytes()).map_err(de::Error::custom))
+                .collect()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -286,6 +1377,8 @@ mod tests {
                 FromHttpRequestError, FromHttpResponseError, IntoHttpError,
                 RequestDeserializationError, ServerError,
             },
+            manual::{encode_path_segment, json_body, parse_path_args},
+            path::parse_identifier,
             Endpoint, Metadata, Outgoing,
         };
 
@@ -303,14 +1396,13 @@ mod tests {
         impl Endpoint for Request {
             type Response = Response;
 
-            const METADATA: Metadata = Metadata {
-                description: "Add an alias to a room.",
-                method: Method::PUT,
-                name: "create_alias",
-                path: "/_matrix/client/r0/directory/room/:room_alias",
-                rate_limited: false,
-                requires_authentication: true,
-            };
+            const METADATA: Metadata = Metadata::new(
+                "Add an alias to a room.",
+                &Method::PUT,
+                "create_alias",
+                "/_matrix/client/r0/directory/room/:room_alias",
+            )
+            .with_requires_authentication(true);
         }
 
         impl TryFrom<Request> for http::Request<Vec<u8>> {
@@ -320,16 +1412,15 @@ mod tests {
                 let metadata = Request::METADATA;
 
                 let path = metadata
-                    .path
-                    .to_string()
-                    .replace(":room_alias", &request.room_alias.to_string());
+                    .path()
+                    .replace(":room_alias", &encode_path_segment(&request.room_alias));
 
                 let request_body = RequestBody { room_id: request.room_id };
 
                 let http_request = http::Request::builder()
-                    .method(metadata.method)
+                    .method(metadata.method())
                     .uri(path)
-                    .body(serde_json::to_vec(&request_body)?)
+                    .body(json_body(&request_body)?)
                     .expect("http request building to succeed");
 
                 Ok(http_request)
@@ -344,19 +1435,19 @@ mod tests {
                     match serde_json::from_slice(request.body().as_slice()) {
                         Ok(body) => body,
                         Err(err) => {
-                            return Err(RequestDeserializationError::new(err, request).into());
+                            return Err(RequestDeserializationError::new(err, &request).into());
                         }
                     };
-                let path_segments: Vec<&str> = request.uri().path()[1..].split('/').collect();
+                let path_args = parse_path_args(Request::METADATA.path(), request.uri().path())
+                    .unwrap_or_default();
                 Ok(Request {
                     room_id: request_body.room_id,
                     room_alias: {
-                        let segment = path_segments.get(5).unwrap().as_bytes();
-                        let decoded = percent_encoding::percent_decode(segment).decode_utf8_lossy();
-                        match serde_json::from_str(decoded.deref()) {
+                        let segment = path_args.first().map(Deref::deref).unwrap_or("");
+                        match parse_identifier(segment) {
                             Ok(id) => id,
                             Err(err) => {
-                                return Err(RequestDeserializationError::new(err, request).into())
+                                return Err(RequestDeserializationError::new(err, &request).into())
                             }
                         }
                     },
@@ -0,0 +1,50 @@
+//! Checks for the `#[ruma_api(max_len = ...)]` and `#[ruma_api(max_items = ...)]` attributes on
+//! request fields.
+//!
+//! Incoming requests with a field that exceeds its limit are rejected with
+//! `FromHttpRequestError::Validation`, whose [`ValidationError`] names the field:
+//!
+//! ```text
+//! request {
+//!     #[ruma_api(max_len = 255)]
+//!     pub room_alias_name: String,
+//!     #[ruma_api(max_items = 100)]
+//!     pub invite: Vec<UserId>,
+//! }
+//! ```
+//!
+//! Fields of type `Option<T>` are only checked if they are `Some`.
+//!
+//! [`ValidationError`]: ../error/struct.ValidationError.html
+
+use crate::error::ValidationError;
+
+/// Checks that a string is at most `max` bytes long.
+pub fn max_len<T>(field: &'static str, value: &T, max: usize) -> Result<(), ValidationError>
+where
+    T: AsRef<str> + ?Sized,
+{
+    if value.as_ref().len() > max {
+        return Err(ValidationError::field(field, format!("must be at most {} bytes long", max)));
+    }
+
+    Ok(())
+}
+
+/// Checks that a collection has at most `max` items.
+pub fn max_items<'a, T>(
+    field: &'static str,
+    value: &'a T,
+    max: usize,
+) -> Result<(), ValidationError>
+where
+    T: ?Sized,
+    &'a T: IntoIterator,
+    <&'a T as IntoIterator>::IntoIter: ExactSizeIterator,
+{
+    if value.into_iter().len() > max {
+        return Err(ValidationError::field(field, format!("must have at most {} items", max)));
+    }
+
+    Ok(())
+}
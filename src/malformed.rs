@@ -0,0 +1,282 @@
+//! Malformed variants of valid requests, for checking that parsing incoming requests fails
+//! gracefully.
+//!
+//! [`malformed_requests`] derives a corpus of broken requests from a valid `http::Request`:
+//! truncated bodies, wrong `Content-Type`s, overlong path parameters and path parameters and
+//! query values with percent-encoded sequences that aren't valid UTF-8. [`assert_no_panics`]
+//! converts all of them to an endpoint's incoming request type and panics if any conversion
+//! panics instead of returning an error:
+//!
+//! ```
+//! # use std::convert::TryFrom;
+//! # use ruma_api::{deps::http, ruma_api};
+//! #
+//! # ruma_api! {
+//! #     mod get_alias;
+//! #
+//! #     metadata {
+//! #         description: "Resolve a room alias to a room ID.",
+//! #         method: GET,
+//! #         name: "get_alias",
+//! #         path: "/_matrix/client/r0/directory/room/:room_alias",
+//! #         rate_limited: false,
+//! #         requires_authentication: false,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(path)]
+//! #         pub room_alias: String,
+//! #     }
+//! #
+//! #     response {
+//! #         pub room_id: String,
+//! #     }
+//! # }
+//! #
+//! # fn main() -> Result<(), ruma_api::error::IntoHttpError> {
+//! # let room_alias = "#room:example.org".to_owned();
+//! let http_request = http::Request::<Vec<u8>>::try_from(get_alias::Request { room_alias })?;
+//! ruma_api::malformed::assert_no_panics::<get_alias::Request>(&http_request);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Some variants are still valid for some endpoints, e.g. a request with a wrong `Content-Type`
+//! to an endpoint that doesn't check it, so converting them successfully is fine.
+//!
+//! Endpoints declared with `#[ruma_api(generate_tests)]` get a test that runs this for their
+//! default request.
+//!
+//! [`malformed_requests`]: fn.malformed_requests.html
+//! [`assert_no_panics`]: fn.assert_no_panics.html
+
+use std::{
+    convert::TryFrom,
+    panic::{self, AssertUnwindSafe},
+};
+
+use http::{
+    header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE},
+    Uri,
+};
+
+use crate::{conversion::TryFromHttpRequest, Endpoint, Metadata, Outgoing};
+
+/// The lengths of overlong path parameters, growing exponentially up to 64 KiB.
+const OVERLONG_LENGTHS: [usize; 3] = [1 << 8, 1 << 12, 1 << 16];
+
+/// Percent-encoded sequences that don't decode to valid UTF-8: a lone continuation byte, a lead
+/// byte followed by an ASCII character, and an encoded UTF-16 surrogate.
+const INVALID_UTF8_SEQUENCES: [&str; 3] = ["%FF", "%C3%28", "%ED%A0%80"];
+
+/// A malformed variant of a request.
+#[derive(Debug)]
+pub struct MalformedRequest {
+    description: String,
+    http_request: http::Request<Vec<u8>>,
+}
+
+impl MalformedRequest {
+    /// What is wrong with the request, e.g. "body truncated to 3 bytes".
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// The malformed request.
+    pub fn http_request(&self) -> &http::Request<Vec<u8>> {
+        &self.http_request
+    }
+
+    /// Returns the malformed request.
+    pub fn into_http_request(self) -> http::Request<Vec<u8>> {
+        self.http_request
+    }
+}
+
+/// Derives malformed variants from a valid request to the endpoint with the given metadata.
+///
+/// Path parameters are found through the endpoint's path, so the path of the request must not
+/// have a prefix.
+pub fn malformed_requests(
+    metadata: &Metadata,
+    http_request: &http::Request<Vec<u8>>,
+) -> Vec<MalformedRequest> {
+    let mut variants = Vec::new();
+    truncated_bodies(http_request, &mut variants);
+    wrong_content_types(http_request, &mut variants);
+    malformed_path_parameters(metadata, http_request, &mut variants);
+    malformed_query_values(http_request, &mut variants);
+    variants
+}
+
+/// Converts all malformed variants of the given valid request to the incoming request type of
+/// `E`.
+///
+/// # Panics
+///
+/// Panics if any of the conversions panics, listing the variants that made it panic.
+pub fn assert_no_panics<E>(http_request: &http::Request<Vec<u8>>)
+where
+    E: Endpoint,
+    <E as Outgoing>::Incoming: TryFromHttpRequest,
+{
+    let panicked: Vec<_> = malformed_requests(&E::METADATA, http_request)
+        .into_iter()
+        .filter(|variant| {
+            let http_request = copy_request(&variant.http_request);
+            panic::catch_unwind(AssertUnwindSafe(|| {
+                let _ = <E as Outgoing>::Incoming::try_from(http_request);
+            }))
+            .is_err()
+        })
+        .map(|variant| variant.description)
+        .collect();
+
+    assert!(
+        panicked.is_empty(),
+        "parsing malformed requests to `{}` panicked: {}",
+        E::METADATA.name(),
+        panicked.join(", ")
+    );
+}
+
+/// Bodies cut off after half, a quarter, … of their length, and right before their end.
+fn truncated_bodies(http_request: &http::Request<Vec<u8>>, variants: &mut Vec<MalformedRequest>) {
+    let len = http_request.body().len();
+    let mut lengths = Vec::new();
+    let mut truncated_len = len;
+    while truncated_len > 0 {
+        truncated_len /= 2;
+        lengths.push(truncated_len);
+    }
+    if len > 1 {
+        lengths.push(len - 1);
+    }
+    lengths.sort_unstable();
+    lengths.dedup();
+
+    for truncated_len in lengths {
+        let mut truncated = copy_request(http_request);
+        truncated.body_mut().truncate(truncated_len);
+        if truncated.headers().contains_key(CONTENT_LENGTH) {
+            truncated.headers_mut().insert(CONTENT_LENGTH, HeaderValue::from(truncated_len));
+        }
+        variants.push(MalformedRequest {
+            description: format!("body truncated to {} bytes", truncated_len),
+            http_request: truncated,
+        });
+    }
+}
+
+/// A `Content-Type` that no endpoint accepts, and none at all.
+fn wrong_content_types(
+    http_request: &http::Request<Vec<u8>>,
+    variants: &mut Vec<MalformedRequest>,
+) {
+    let mut wrong = copy_request(http_request);
+    wrong.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("text/x-malformed"));
+    variants.push(MalformedRequest {
+        description: "wrong Content-Type".to_owned(),
+        http_request: wrong,
+    });
+
+    if http_request.headers().contains_key(CONTENT_TYPE) {
+        let mut missing = copy_request(http_request);
+        missing.headers_mut().remove(CONTENT_TYPE);
+        variants.push(MalformedRequest {
+            description: "missing Content-Type".to_owned(),
+            http_request: missing,
+        });
+    }
+}
+
+/// Path parameters that are overlong or contain invalid UTF-8.
+fn malformed_path_parameters(
+    metadata: &Metadata,
+    http_request: &http::Request<Vec<u8>>,
+    variants: &mut Vec<MalformedRequest>,
+) {
+    let endpoint_segments: Vec<_> = metadata.path().split('/').collect();
+    let segments: Vec<_> = http_request.uri().path().split('/').collect();
+    if segments.len() != endpoint_segments.len() {
+        return;
+    }
+
+    for (index, name) in endpoint_segments.iter().enumerate() {
+        if !name.starts_with(':') {
+            continue;
+        }
+
+        let overlong = OVERLONG_LENGTHS.iter().map(|&len| {
+            ("a".repeat(len), format!("path parameter `{}` of {} bytes", &name[1..], len))
+        });
+        let invalid = INVALID_UTF8_SEQUENCES.iter().map(|sequence| {
+            (sequence.to_string(), format!("path parameter `{}` of `{}`", &name[1..], sequence))
+        });
+
+        for (value, description) in overlong.chain(invalid) {
+            let mut new_segments = segments.clone();
+            new_segments[index] = &value;
+            let path_and_query = match http_request.uri().query() {
+                Some(query) => format!("{}?{}", new_segments.join("/"), query),
+                None => new_segments.join("/"),
+            };
+            push_with_path_and_query(http_request, &path_and_query, description, variants);
+        }
+    }
+}
+
+/// Query values that contain invalid UTF-8.
+fn malformed_query_values(
+    http_request: &http::Request<Vec<u8>>,
+    variants: &mut Vec<MalformedRequest>,
+) {
+    let query = match http_request.uri().query() {
+        Some(query) => query,
+        None => return,
+    };
+    let pairs: Vec<_> = query.split('&').collect();
+
+    for (index, pair) in pairs.iter().enumerate() {
+        let key = pair.split('=').next().unwrap_or_default();
+        for sequence in &INVALID_UTF8_SEQUENCES {
+            let mut new_pairs = pairs.clone();
+            let new_pair = format!("{}={}", key, sequence);
+            new_pairs[index] = &new_pair;
+            let path_and_query = format!("{}?{}", http_request.uri().path(), new_pairs.join("&"));
+            let description = format!("query parameter `{}` of `{}`", key, sequence);
+            push_with_path_and_query(http_request, &path_and_query, description, variants);
+        }
+    }
+}
+
+/// Adds a copy of the request with the given path and query to the variants, if it is a valid
+/// URI.
+fn push_with_path_and_query(
+    http_request: &http::Request<Vec<u8>>,
+    path_and_query: &str,
+    description: String,
+    variants: &mut Vec<MalformedRequest>,
+) {
+    let mut parts = http_request.uri().clone().into_parts();
+    parts.path_and_query = match path_and_query.parse() {
+        Ok(path_and_query) => Some(path_and_query),
+        Err(_) => return,
+    };
+
+    if let Ok(uri) = Uri::from_parts(parts) {
+        let mut malformed = copy_request(http_request);
+        *malformed.uri_mut() = uri;
+        variants.push(MalformedRequest { description, http_request: malformed });
+    }
+}
+
+/// Copies the parts of a request that matter for parsing it, since `http::Request` isn't `Clone`.
+fn copy_request(http_request: &http::Request<Vec<u8>>) -> http::Request<Vec<u8>> {
+    let mut copy = http::Request::new(http_request.body().clone());
+    *copy.method_mut() = http_request.method().clone();
+    *copy.uri_mut() = http_request.uri().clone();
+    *copy.version_mut() = http_request.version();
+    *copy.headers_mut() = http_request.headers().clone();
+    copy
+}
@@ -0,0 +1,68 @@
+//! Helpers for implementing [`Endpoint`](../trait.Endpoint.html) and the HTTP conversions of
+//! requests and responses manually.
+//!
+//! These do the same as the code generated by `ruma_api!`, so manual implementations encode
+//! paths, query strings and bodies the same way generated ones do:
+//!
+//! ```
+//! # fn main() -> Result<(), ruma_api::error::IntoHttpError> {
+//! use ruma_api::manual::{encode_path_segment, parse_path_args, serialize_query};
+//!
+//! let path = format!("/_matrix/client/r0/rooms/{}/state", encode_path_segment(&"!a b:example.org"));
+//! assert_eq!(path, "/_matrix/client/r0/rooms/!a%20b:example.org/state");
+//!
+//! let args = parse_path_args("/_matrix/client/r0/rooms/:room_id/state", &path).unwrap();
+//! assert_eq!(args, ["!a b:example.org"]);
+//!
+//! assert_eq!(serialize_query(&[("limit", "10"), ("dir", "b")])?, "limit=10&dir=b");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::{borrow::Cow, fmt::Display};
+
+use percent_encoding::percent_decode_str;
+use serde::Serialize;
+
+use crate::{error::IntoHttpError, path};
+
+/// Percent-encodes a path segment with the [`PATH_SEGMENT`](../path/constant.PATH_SEGMENT.html)
+/// encode set.
+///
+/// To append segments to a path without allocating for each of them, use
+/// [`path::push_segment`](../path/fn.push_segment.html).
+pub fn encode_path_segment(segment: &impl Display) -> String {
+    let mut encoded = String::new();
+    path::push_segment(&mut encoded, segment);
+    encoded.remove(0);
+    encoded
+}
+
+/// Extracts the percent-decoded values of the path parameters in `template`, like `:room_id`,
+/// from the given request path, in the order they appear in the template.
+///
+/// The other segments are not compared, it is assumed that the request was already routed to the
+/// endpoint. Returns `None` if the path has fewer segments than the template.
+pub fn parse_path_args<'a>(template: &str, path: &'a str) -> Option<Vec<Cow<'a, str>>> {
+    let mut path_segments = path.get(1..).unwrap_or("").split('/');
+    let mut args = Vec::new();
+
+    for template_segment in template.get(1..).unwrap_or("").split('/') {
+        let segment = path_segments.next()?;
+        if template_segment.starts_with(':') {
+            args.push(percent_decode_str(segment).decode_utf8_lossy());
+        }
+    }
+
+    Some(args)
+}
+
+/// Serializes a value, usually a struct, as a query string without the leading `?`.
+pub fn serialize_query<T: Serialize + ?Sized>(query: &T) -> Result<String, IntoHttpError> {
+    Ok(serde_urlencoded::to_string(query)?)
+}
+
+/// Serializes a value as a JSON body.
+pub fn json_body<T: Serialize + ?Sized>(body: &T) -> Result<Vec<u8>, IntoHttpError> {
+    Ok(serde_json::to_vec(body)?)
+}
@@ -0,0 +1,443 @@
+//! Types for the headers of media requests and responses.
+//!
+//! [`ContentDisposition`](struct.ContentDisposition.html) can be used as the type of an optional
+//! header field in `ruma_api!` responses:
+//!
+//! ```text
+//! response {
+//!     #[ruma_api(raw_body)]
+//!     pub file: Vec<u8>,
+//!     #[ruma_api(header = CONTENT_TYPE)]
+//!     pub content_type: Option<String>,
+//!     #[ruma_api(header = CONTENT_DISPOSITION)]
+//!     pub content_disposition: Option<ContentDisposition>,
+//! }
+//! ```
+//!
+//! [`ByteRange`](enum.ByteRange.html) and [`ContentRange`](struct.ContentRange.html) are the
+//! `Range` request header and `Content-Range` response header of resumable downloads.
+
+use std::{
+    cmp,
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+};
+
+use http::header::{HeaderValue, InvalidHeaderValue};
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+
+/// Characters that don't need to be percent-encoded in an extended parameter value (`attr-char`
+/// in RFC 5987).
+const ATTR_CHAR: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'!')
+    .remove(b'#')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'+')
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'^')
+    .remove(b'_')
+    .remove(b'`')
+    .remove(b'|')
+    .remove(b'~');
+
+/// The value of a `Content-Disposition` header, as defined in RFC 6266.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ContentDisposition {
+    /// Whether the content should be displayed inline or downloaded.
+    pub disposition_type: DispositionType,
+
+    /// The suggested file name, if any.
+    ///
+    /// When parsing, the `filename*` parameter takes precedence over `filename`.
+    pub filename: Option<String>,
+}
+
+impl ContentDisposition {
+    /// Creates a new `ContentDisposition` with the given type and no file name.
+    pub fn new(disposition_type: DispositionType) -> Self {
+        Self { disposition_type, filename: None }
+    }
+
+    /// Sets the suggested file name.
+    pub fn with_filename(mut self, filename: impl Into<String>) -> Self {
+        self.filename = Some(filename.into());
+        self
+    }
+}
+
+/// The type of a `Content-Disposition` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DispositionType {
+    /// The content can be displayed inline.
+    Inline,
+
+    /// The content should be downloaded.
+    ///
+    /// Unknown disposition types are parsed as this, as required by RFC 6266.
+    Attachment,
+}
+
+impl Display for ContentDisposition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.disposition_type {
+            DispositionType::Inline => f.write_str("inline")?,
+            DispositionType::Attachment => f.write_str("attachment")?,
+        }
+
+        if let Some(filename) = &self.filename {
+            if filename.chars().all(|c| c.is_ascii() && !c.is_ascii_control()) {
+                f.write_str("; filename=\"")?;
+                for c in filename.chars() {
+                    if c == '"' || c == '\\' {
+                        f.write_str("\\")?;
+                    }
+                    write!(f, "{}", c)?;
+                }
+                f.write_str("\"")?;
+            } else {
+                write!(f, "; filename*=utf-8''{}", utf8_percent_encode(filename, ATTR_CHAR))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl TryFrom<&str> for ContentDisposition {
+    type Error = InvalidContentDisposition;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let mut parts = Parser { rest: s };
+
+        let disposition_type = parts.token().ok_or(InvalidContentDisposition)?;
+        let disposition_type = if disposition_type.eq_ignore_ascii_case("inline") {
+            DispositionType::Inline
+        } else {
+            DispositionType::Attachment
+        };
+
+        let mut filename = None;
+        let mut ext_filename = None;
+
+        while !parts.is_empty() {
+            if !parts.eat(';') {
+                return Err(InvalidContentDisposition);
+            }
+            // Be lenient about a trailing semicolon.
+            if parts.is_empty() {
+                break;
+            }
+
+            let name = parts.token().ok_or(InvalidContentDisposition)?;
+            if !parts.eat('=') {
+                return Err(InvalidContentDisposition);
+            }
+
+            if name.eq_ignore_ascii_case("filename*") {
+                let value = parts.token().ok_or(InvalidContentDisposition)?;
+                ext_filename = decode_ext_value(value);
+            } else {
+                let value = parts.value().ok_or(InvalidContentDisposition)?;
+                if name.eq_ignore_ascii_case("filename") {
+                    filename = Some(value);
+                }
+            }
+        }
+
+        Ok(Self { disposition_type, filename: ext_filename.or(filename) })
+    }
+}
+
+impl TryFrom<ContentDisposition> for HeaderValue {
+    type Error = InvalidHeaderValue;
+
+    fn try_from(content_disposition: ContentDisposition) -> Result<Self, Self::Error> {
+        HeaderValue::from_str(&content_disposition.to_string())
+    }
+}
+
+/// An error when parsing a `Content-Disposition` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidContentDisposition;
+
+impl Display for InvalidContentDisposition {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid Content-Disposition header")
+    }
+}
+
+impl std::error::Error for InvalidContentDisposition {}
+
+/// The value of a `Range` header requesting a single range of bytes, as defined in RFC 7233.
+///
+/// It can be used as the type of an optional header field in `ruma_api!` requests, to resume a
+/// download:
+///
+/// ```text
+/// request {
+///     #[ruma_api(header = RANGE)]
+///     pub range: Option<ByteRange>,
+/// }
+/// ```
+///
+/// Requests for several ranges at once are not supported, they fail to parse like invalid ranges.
+/// Servers can then ignore the header and send the complete content, as RFC 7233 allows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    /// The bytes from the first to the last given position, inclusive: `bytes=first-last`.
+    FromTo(u64, u64),
+
+    /// The bytes from the given position to the end: `bytes=first-`.
+    From(u64),
+
+    /// The given number of bytes at the end: `bytes=-length`.
+    Last(u64),
+}
+
+impl ByteRange {
+    /// The `Content-Range` of a response with this range of content of the given length.
+    ///
+    /// Returns `None` if the range is not satisfiable, i.e. starts after the end of the content.
+    pub fn to_content_range(self, complete_length: u64) -> Option<ContentRange> {
+        let (first, last) = match self {
+            ByteRange::FromTo(first, last) => {
+                (first, cmp::min(last, complete_length.checked_sub(1)?))
+            }
+            ByteRange::From(first) => (first, complete_length.checked_sub(1)?),
+            ByteRange::Last(0) => return None,
+            ByteRange::Last(length) => {
+                (complete_length.saturating_sub(length), complete_length.checked_sub(1)?)
+            }
+        };
+
+        if first > last {
+            return None;
+        }
+
+        Some(ContentRange { first, last, complete_length: Some(complete_length) })
+    }
+}
+
+impl Display for ByteRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ByteRange::FromTo(first, last) => write!(f, "bytes={}-{}", first, last),
+            ByteRange::From(first) => write!(f, "bytes={}-", first),
+            ByteRange::Last(length) => write!(f, "bytes=-{}", length),
+        }
+    }
+}
+
+impl TryFrom<&str> for ByteRange {
+    type Error = InvalidRange;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let spec = strip_unit(s.trim(), "bytes=").ok_or(InvalidRange)?;
+        let mut parts = spec.splitn(2, '-');
+        let first = parts.next().ok_or(InvalidRange)?.trim();
+        let last = parts.next().ok_or(InvalidRange)?.trim();
+
+        match (first, last) {
+            ("", length) => Ok(ByteRange::Last(parse_position(length)?)),
+            (first, "") => Ok(ByteRange::From(parse_position(first)?)),
+            (first, last) => {
+                let (first, last) = (parse_position(first)?, parse_position(last)?);
+                if first > last {
+                    return Err(InvalidRange);
+                }
+
+                Ok(ByteRange::FromTo(first, last))
+            }
+        }
+    }
+}
+
+impl TryFrom<ByteRange> for HeaderValue {
+    type Error = InvalidHeaderValue;
+
+    fn try_from(range: ByteRange) -> Result<Self, Self::Error> {
+        HeaderValue::from_str(&range.to_string())
+    }
+}
+
+/// The value of a `Content-Range` header of a `206 Partial Content` response, as defined in
+/// RFC 7233.
+///
+/// It can be used as the type of an optional header field in `ruma_api!` responses. Responses
+/// with a `Content-Range` header are sent with the status `206 Partial Content`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ContentRange {
+    /// The position of the first byte in the response.
+    pub first: u64,
+
+    /// The position of the last byte in the response, inclusive.
+    pub last: u64,
+
+    /// The length of the complete content, if known.
+    pub complete_length: Option<u64>,
+}
+
+impl Display for ContentRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "bytes {}-{}/", self.first, self.last)?;
+        match self.complete_length {
+            Some(complete_length) => write!(f, "{}", complete_length),
+            None => f.write_str("*"),
+        }
+    }
+}
+
+impl TryFrom<&str> for ContentRange {
+    type Error = InvalidRange;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let spec = strip_unit(s.trim(), "bytes ").ok_or(InvalidRange)?;
+        let mut parts = spec.splitn(2, '/');
+        let range = parts.next().ok_or(InvalidRange)?;
+        let complete_length = match parts.next().ok_or(InvalidRange)?.trim() {
+            "*" => None,
+            complete_length => Some(parse_position(complete_length)?),
+        };
+
+        let mut positions = range.splitn(2, '-');
+        let first = parse_position(positions.next().ok_or(InvalidRange)?.trim())?;
+        let last = parse_position(positions.next().ok_or(InvalidRange)?.trim())?;
+
+        let fits = match complete_length {
+            Some(complete_length) => last < complete_length,
+            None => true,
+        };
+        if first > last || !fits {
+            return Err(InvalidRange);
+        }
+
+        Ok(Self { first, last, complete_length })
+    }
+}
+
+impl TryFrom<ContentRange> for HeaderValue {
+    type Error = InvalidHeaderValue;
+
+    fn try_from(content_range: ContentRange) -> Result<Self, Self::Error> {
+        HeaderValue::from_str(&content_range.to_string())
+    }
+}
+
+/// An error when parsing a `Range` or `Content-Range` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InvalidRange;
+
+impl Display for InvalidRange {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("invalid byte range")
+    }
+}
+
+impl std::error::Error for InvalidRange {}
+
+/// Removes the given range unit prefix, ignoring the case of the unit.
+fn strip_unit<'a>(s: &'a str, unit: &str) -> Option<&'a str> {
+    if s.len() >= unit.len() && s.is_char_boundary(unit.len()) {
+        let (prefix, rest) = s.split_at(unit.len());
+        if prefix.eq_ignore_ascii_case(unit) {
+            return Some(rest);
+        }
+    }
+
+    None
+}
+
+/// Parses a byte position, which only consists of digits.
+fn parse_position(s: &str) -> Result<u64, InvalidRange> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(InvalidRange);
+    }
+
+    s.parse().map_err(|_| InvalidRange)
+}
+
+/// A minimal parser for the parameter syntax of HTTP headers.
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl<'a> Parser<'a> {
+    fn skip_whitespace(&mut self) {
+        self.rest = self.rest.trim_start_matches(&[' ', '\t'][..]);
+    }
+
+    fn is_empty(&mut self) -> bool {
+        self.skip_whitespace();
+        self.rest.is_empty()
+    }
+
+    /// Consumes the given character if it is next.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.rest.starts_with(c) {
+            self.rest = &self.rest[c.len_utf8()..];
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Parses a token, which is also used for `filename*` values.
+    fn token(&mut self) -> Option<&'a str> {
+        self.skip_whitespace();
+        let end = self.rest.find(|c: char| !is_token_char(c)).unwrap_or(self.rest.len());
+        if end == 0 {
+            return None;
+        }
+
+        let (token, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        Some(token)
+    }
+
+    /// Parses a token or a quoted string.
+    fn value(&mut self) -> Option<String> {
+        if !self.eat('"') {
+            return self.token().map(ToOwned::to_owned);
+        }
+
+        let mut value = String::new();
+        let mut chars = self.rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[i + 1..];
+                    return Some(value);
+                }
+                '\\' => value.push(chars.next()?.1),
+                c => value.push(c),
+            }
+        }
+
+        None
+    }
+}
+
+/// Whether the character is allowed in a token, as defined in RFC 7230.
+fn is_token_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c)
+}
+
+/// Decodes an extended parameter value like `UTF-8''na%C3%AFve.txt`, as defined in RFC 5987.
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?;
+    let _language = parts.next()?;
+    let encoded = parts.next()?;
+
+    let bytes = percent_decode_str(encoded);
+    if charset.eq_ignore_ascii_case("utf-8") {
+        bytes.decode_utf8().ok().map(|s| s.into_owned())
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") {
+        Some(bytes.map(char::from).collect())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,575 @@
+//! Options for converting requests to `http::Request`s that apply to all endpoints.
+//!
+//! For servers, [`IncomingRequestOptions`](struct.IncomingRequestOptions.html) holds checks of
+//! incoming requests that apply to all endpoints, and [`ResponseOptions`](struct.ResponseOptions.html)
+//! the headers added to all outgoing responses.
+//!
+//! Use [`Endpoint::try_into_http_request_with`](../trait.Endpoint.html#method.try_into_http_request_with)
+//! to convert a request with these options, or with any other [`CustomizeRequest`] implementation.
+//!
+//! [`CustomizeRequest`]: trait.CustomizeRequest.html
+
+use std::time::Duration;
+
+use http::{
+    header::{HeaderValue, AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, HOST, USER_AGENT},
+    uri::Authority,
+    Uri,
+};
+
+#[cfg(feature = "server")]
+use crate::security::SecurityHeaders;
+use crate::{
+    error::FromHttpRequestError,
+    versions::{warn_if_outdated, MatrixVersion},
+    Direction, Metadata, ServerKind,
+};
+
+/// The header used to tell the server how long the client is going to wait for a response, in
+/// integer milliseconds.
+pub const REQUEST_TIMEOUT: &str = "x-request-timeout";
+
+/// The header used to correlate a request with its response and with log entries on both the
+/// client and the server.
+pub const REQUEST_ID: &str = "x-request-id";
+
+/// The value that replaces credentials in request previews and captures.
+#[cfg(any(feature = "capture", feature = "client"))]
+pub const REDACTED: &str = "redacted";
+
+/// Options for converting requests to `http::Request`s.
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct RequestOptions {
+    /// How long the client is going to wait for a response.
+    ///
+    /// If set, this is sent in the [`X-Request-Timeout`](constant.REQUEST_TIMEOUT.html) header so
+    /// the server can stop working on requests the client has already given up on.
+    pub timeout: Option<Duration>,
+
+    /// The ID to send in the [`X-Request-Id`](constant.REQUEST_ID.html) header.
+    ///
+    /// With the `request-id` feature enabled, a random UUID is generated if this is `None`.
+    pub request_id: Option<String>,
+
+    /// The product identifier to send in the `User-Agent` header.
+    ///
+    /// This doesn't override a `User-Agent` header set by the endpoint itself.
+    pub user_agent: Option<String>,
+
+    /// The absolute URL of the server the request is sent to, like `https://matrix.example.org`.
+    ///
+    /// If set, the placeholder scheme and authority of the request URI are replaced with the ones
+    /// of this URL, resulting in an absolute-form request target as needed when sending through a
+    /// forward proxy. A path in this URL is prepended to the endpoint's path. Unless
+    /// [`host`](#structfield.host) is set, the `Host` header is set to the URL's authority.
+    pub base_url: Option<Uri>,
+
+    /// The value for the `Host` header, if it should differ from the authority of
+    /// [`base_url`](#structfield.base_url), e.g. for a server name delegated through
+    /// `.well-known`.
+    pub host: Option<String>,
+
+    /// The address to connect to, if it differs from the server name, e.g. the IP address and
+    /// port a federation server name was resolved to through SRV records.
+    ///
+    /// If set, this replaces the authority of the request URI after
+    /// [`base_url`](#structfield.base_url) was applied, while the `Host` header keeps the authority
+    /// of `base_url` (or [`host`](#structfield.host)). Transports then connect to the URI's
+    /// authority, but have to use the [`tls_server_name`](fn.tls_server_name.html) of the request
+    /// for SNI and for checking the server's certificate.
+    pub connect_to: Option<Authority>,
+
+    /// The access token to authenticate the request with.
+    pub access_token: Option<String>,
+
+    /// Where to put the [`access_token`](#structfield.access_token).
+    pub access_token_placement: AccessTokenPlacement,
+
+    /// The absolute URL of the identity server, used instead of
+    /// [`base_url`](#structfield.base_url) for identity server endpoints by
+    /// [`for_endpoint`](#method.for_endpoint).
+    pub identity_server_url: Option<Uri>,
+
+    /// The absolute URL of the push gateway, used instead of [`base_url`](#structfield.base_url)
+    /// for push gateway endpoints by [`for_endpoint`](#method.for_endpoint).
+    pub push_gateway_url: Option<Uri>,
+
+    /// The version of the spec the client uses with the server, e.g. the
+    /// [latest](../versions/struct.SupportedVersions.html#method.latest) one they both support.
+    ///
+    /// With the `tracing` feature, [`for_endpoint`](#method.for_endpoint) logs a warning for
+    /// endpoints that are deprecated or removed in this version.
+    pub matrix_version: Option<MatrixVersion>,
+}
+
+/// Options for converting responses to `http::Response`s on the server, used with
+/// [`Endpoint::try_into_http_response_with`](../trait.Endpoint.html#method.try_into_http_response_with).
+///
+/// The default options don't change the responses. See the [`security`](../security/index.html)
+/// module for the security headers.
+#[cfg(feature = "server")]
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct ResponseOptions {
+    /// The security headers added to the responses of all endpoints that aren't media endpoints.
+    pub security_headers: Option<SecurityHeaders>,
+
+    /// The security headers added to the responses of media endpoints, the ones under
+    /// `/_matrix/media`.
+    pub media_security_headers: Option<SecurityHeaders>,
+}
+
+#[cfg(feature = "server")]
+impl ResponseOptions {
+    /// Options with the [recommended](../security/struct.SecurityHeaders.html#method.recommended)
+    /// security headers for all endpoints, and the
+    /// [ones for media](../security/struct.SecurityHeaders.html#method.recommended_for_media) for
+    /// media endpoints.
+    pub fn recommended() -> Self {
+        Self {
+            security_headers: Some(SecurityHeaders::recommended()),
+            media_security_headers: Some(SecurityHeaders::recommended_for_media()),
+        }
+    }
+
+    /// The security headers for the endpoint with the given metadata, if any.
+    pub fn security_headers_for(&self, metadata: &Metadata) -> Option<&SecurityHeaders> {
+        if metadata.path().starts_with("/_matrix/media/") {
+            self.media_security_headers.as_ref()
+        } else {
+            self.security_headers.as_ref()
+        }
+    }
+
+    /// Applies these options to the given `http::Response` of the endpoint with the given
+    /// metadata.
+    pub fn apply<T>(&self, metadata: &Metadata, response: &mut http::Response<T>) {
+        if let Some(security_headers) = self.security_headers_for(metadata) {
+            security_headers.apply(response);
+        }
+    }
+}
+
+/// Options for checking incoming `http::Request`s before converting them, used with
+/// [`Endpoint::try_from_http_request_with`](../trait.Endpoint.html#method.try_from_http_request_with).
+#[derive(Clone, Debug, Default)]
+#[non_exhaustive]
+pub struct IncomingRequestOptions {
+    /// The server names requests are expected to be sent to, e.g. all names of a homeserver.
+    ///
+    /// If not empty, requests with an authority (or, for HTTP/1.1, a `Host` header) that isn't
+    /// one of these names are rejected with `FromHttpRequestError::UnexpectedAuthority`. This
+    /// matters for federation endpoints, where the request signature covers the destination
+    /// server name. Names are compared case-insensitively.
+    pub server_names: Vec<String>,
+
+    /// The version of the spec the server implements.
+    ///
+    /// With the `tracing` feature,
+    /// [`Endpoint::try_from_http_request_with`](../trait.Endpoint.html#method.try_from_http_request_with)
+    /// logs a warning for requests to endpoints that are deprecated or removed in this version.
+    pub matrix_version: Option<MatrixVersion>,
+}
+
+impl IncomingRequestOptions {
+    /// Logs a warning with the `tracing` feature if the endpoint with the given metadata is
+    /// deprecated or removed in [`matrix_version`](#structfield.matrix_version).
+    pub fn warn_if_outdated(&self, metadata: &Metadata) {
+        if let Some(version) = self.matrix_version {
+            warn_if_outdated(metadata, version);
+        }
+    }
+
+    /// Checks the given request against these options.
+    pub fn check<T>(&self, request: &http::Request<T>) -> Result<(), FromHttpRequestError> {
+        if self.server_names.is_empty() {
+            return Ok(());
+        }
+
+        let authority = request_authority(request);
+        match authority {
+            Some(authority)
+                if self.server_names.iter().any(|name| name.eq_ignore_ascii_case(authority)) =>
+            {
+                Ok(())
+            }
+            _ => Err(FromHttpRequestError::UnexpectedAuthority {
+                authority: authority.map(ToOwned::to_owned),
+            }),
+        }
+    }
+}
+
+/// Where the access token of a request is sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AccessTokenPlacement {
+    /// In the `Authorization` header, as `Bearer <token>`.
+    Header,
+
+    /// In the `access_token` query parameter, for older application service and media APIs that
+    /// don't accept the header.
+    Query,
+}
+
+// `#[derive(Default)]` with `#[default]` needs a newer Rust version than the minimum supported one.
+#[allow(clippy::derivable_impls)]
+impl Default for AccessTokenPlacement {
+    fn default() -> Self {
+        Self::Header
+    }
+}
+
+/// A modification of a request after it was converted to an `http::Request`.
+///
+/// This is implemented for `&RequestOptions` and for closures taking the `http::Request`, which can
+/// change anything about it, e.g. add parameters for application services to its URI.
+pub trait CustomizeRequest {
+    /// Applies the modification to the given request.
+    fn customize(self, request: &mut http::Request<Vec<u8>>);
+}
+
+impl CustomizeRequest for &RequestOptions {
+    fn customize(self, request: &mut http::Request<Vec<u8>>) {
+        self.apply(request);
+    }
+}
+
+impl<F: FnOnce(&mut http::Request<Vec<u8>>)> CustomizeRequest for F {
+    fn customize(self, request: &mut http::Request<Vec<u8>>) {
+        self(request);
+    }
+}
+
+impl RequestOptions {
+    /// These options, adjusted to the conventions of the endpoint with the given metadata.
+    ///
+    /// For identity server and push gateway endpoints, the
+    /// [`identity_server_url`](#structfield.identity_server_url) or
+    /// [`push_gateway_url`](#structfield.push_gateway_url) replaces the
+    /// [`base_url`](#structfield.base_url) if it is set, and the [`host`](#structfield.host) and
+    /// [`connect_to`](#structfield.connect_to) are dropped. Without a [`timeout`](#structfield.timeout), the endpoint's
+    /// [suggested timeout](../struct.Metadata.html#method.suggested_timeout) is used. For endpoints
+    /// with the [`ServerToClient`](../enum.Direction.html#variant.ServerToClient) direction, the
+    /// access token is sent in the query string.
+    pub fn for_endpoint(&self, metadata: &Metadata) -> Self {
+        let mut options = self.clone();
+
+        if let Some(version) = self.matrix_version {
+            warn_if_outdated(metadata, version);
+        }
+
+        let server_url = match metadata.server_kind() {
+            ServerKind::Homeserver => None,
+            ServerKind::IdentityServer => self.identity_server_url.as_ref(),
+            ServerKind::PushGateway => self.push_gateway_url.as_ref(),
+        };
+        if let Some(server_url) = server_url {
+            options.base_url = Some(server_url.clone());
+            options.host = None;
+            options.connect_to = None;
+        }
+
+        if options.timeout.is_none() {
+            options.timeout = metadata.suggested_timeout();
+        }
+
+        if metadata.direction() == Direction::ServerToClient {
+            options.access_token_placement = Direction::ServerToClient.access_token_placement();
+        }
+
+        options
+    }
+
+    /// Options for sending requests to the server with the given name, through the given address.
+    ///
+    /// This sets the [`base_url`](#structfield.base_url) to `https://{server_name}`, so the `Host`
+    /// header contains the server name, and [`connect_to`](#structfield.connect_to) to the given
+    /// address, which becomes the authority of the request URI.
+    pub fn delegated(server_name: &Authority, connect_to: Authority) -> Self {
+        Self {
+            base_url: Some(
+                Uri::builder()
+                    .scheme("https")
+                    .authority(server_name.clone())
+                    .path_and_query("/")
+                    .build()
+                    .expect("https URI with a valid authority to be valid"),
+            ),
+            connect_to: Some(connect_to),
+            ..Self::default()
+        }
+    }
+
+    /// Applies these options to the given `http::Request`.
+    pub fn apply<T>(&self, request: &mut http::Request<T>) {
+        if let Some(timeout) = self.timeout {
+            request
+                .headers_mut()
+                .insert(REQUEST_TIMEOUT, HeaderValue::from(timeout.as_millis() as u64));
+        }
+
+        match &self.request_id {
+            Some(request_id) => {
+                if let Ok(value) = HeaderValue::from_str(request_id) {
+                    request.headers_mut().insert(REQUEST_ID, value);
+                }
+            }
+            #[cfg(feature = "request-id")]
+            None => {
+                if !request.headers().contains_key(REQUEST_ID) {
+                    let value = HeaderValue::from_str(&generate_request_id())
+                        .expect("UUIDs are valid header values");
+                    request.headers_mut().insert(REQUEST_ID, value);
+                }
+            }
+            #[cfg(not(feature = "request-id"))]
+            None => {}
+        }
+
+        if let Some(access_token) = &self.access_token {
+            match self.access_token_placement {
+                AccessTokenPlacement::Header => {
+                    if let Ok(value) = HeaderValue::from_str(&format!("Bearer {}", access_token)) {
+                        request.headers_mut().insert(AUTHORIZATION, value);
+                    }
+                }
+                AccessTokenPlacement::Query => {
+                    if let Some(uri) = with_access_token_query(request.uri(), access_token) {
+                        *request.uri_mut() = uri;
+                    }
+                }
+            }
+        }
+
+        if let Some(base_url) = &self.base_url {
+            if let Some(uri) = join_uri(base_url, request.uri()) {
+                *request.uri_mut() = uri;
+            }
+        }
+
+        let host = match (&self.host, &self.base_url) {
+            (Some(host), _) => HeaderValue::from_str(host).ok(),
+            (None, Some(_)) => {
+                request.uri().authority().and_then(|a| HeaderValue::from_str(a.as_str()).ok())
+            }
+            (None, None) => None,
+        };
+        if let Some(host) = host {
+            request.headers_mut().insert(HOST, host);
+        }
+
+        if let Some(connect_to) = &self.connect_to {
+            if let Some(uri) = with_authority(request.uri(), connect_to) {
+                *request.uri_mut() = uri;
+            }
+        }
+
+        if let Some(user_agent) = &self.user_agent {
+            if !request.headers().contains_key(USER_AGENT) {
+                if let Ok(value) = HeaderValue::from_str(user_agent) {
+                    request.headers_mut().insert(USER_AGENT, value);
+                }
+            }
+        }
+    }
+}
+
+/// Puts the path and query of `uri` after the given base URL.
+fn join_uri(base_url: &Uri, uri: &Uri) -> Option<Uri> {
+    let scheme = base_url.scheme()?.clone();
+    let authority = base_url.authority()?.clone();
+    let base_path = base_url.path().trim_end_matches('/');
+    let path_and_query = uri.path_and_query().map_or("/", |pq| pq.as_str());
+
+    Uri::builder()
+        .scheme(scheme)
+        .authority(authority)
+        .path_and_query(format!("{}{}", base_path, path_and_query).as_str())
+        .build()
+        .ok()
+}
+
+/// Replaces the authority of the given absolute URI.
+fn with_authority(uri: &Uri, authority: &Authority) -> Option<Uri> {
+    uri.scheme()?;
+
+    let mut parts = uri.clone().into_parts();
+    parts.authority = Some(authority.clone());
+    Uri::from_parts(parts).ok()
+}
+
+/// Gets the name a transport has to use for SNI and for checking the server's certificate when
+/// sending the given request: the host of its `Host` header, or of its URI if it has none.
+///
+/// This differs from the host of the URI for requests converted with
+/// [`RequestOptions::connect_to`](struct.RequestOptions.html#structfield.connect_to) set.
+pub fn tls_server_name<T>(request: &http::Request<T>) -> Option<String> {
+    let authority = match request.headers().get(HOST) {
+        Some(host) => host.to_str().ok()?.parse::<Authority>().ok()?,
+        None => request.uri().authority()?.clone(),
+    };
+
+    let host = authority.host();
+    // IPv6 addresses are in brackets in the authority, but not in the server name.
+    Some(host.trim_start_matches('[').trim_end_matches(']').to_owned())
+}
+
+/// Appends the `access_token` query parameter to the given URI.
+fn with_access_token_query(uri: &Uri, access_token: &str) -> Option<Uri> {
+    let param = serde_urlencoded::to_string([("access_token", access_token)]).ok()?;
+    let path_and_query = match uri.query() {
+        Some(query) if !query.is_empty() => format!("{}?{}&{}", uri.path(), query, param),
+        _ => format!("{}?{}", uri.path(), param),
+    };
+
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = Some(path_and_query.parse().ok()?);
+    Uri::from_parts(parts).ok()
+}
+
+/// Replaces the values of the `Authorization`, `Proxy-Authorization` and `Cookie` headers.
+#[cfg(any(feature = "capture", feature = "client"))]
+pub(crate) fn redact_credential_headers(headers: &mut http::HeaderMap) {
+    use http::header::{COOKIE, PROXY_AUTHORIZATION};
+
+    for name in &[AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE] {
+        if headers.contains_key(name) {
+            headers.insert(name, HeaderValue::from_static(REDACTED));
+        }
+    }
+}
+
+/// Replaces the value of the `access_token` query parameter of the given URI.
+#[cfg(any(feature = "capture", feature = "client"))]
+pub(crate) fn redact_access_token(uri: &Uri) -> Uri {
+    let query = match uri.query() {
+        Some(query) if query.split('&').any(|pair| pair.starts_with("access_token=")) => query,
+        _ => return uri.clone(),
+    };
+
+    let query = query
+        .split('&')
+        .map(|pair| {
+            if pair.starts_with("access_token=") {
+                format!("access_token={}", REDACTED)
+            } else {
+                pair.to_owned()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = format!("{}?{}", uri.path(), query).parse().ok();
+    Uri::from_parts(parts).unwrap_or_else(|_| {
+        // Dropping the whole query is the only safe fallback, the token must not be shown.
+        let mut parts = uri.clone().into_parts();
+        parts.path_and_query = uri.path().parse().ok();
+        Uri::from_parts(parts).unwrap_or_default()
+    })
+}
+
+/// Gets the access token of an incoming request, from the `Authorization` header or the
+/// `access_token` query parameter.
+pub fn access_token<T>(request: &http::Request<T>) -> Option<String> {
+    if let Some(value) = request.headers().get(AUTHORIZATION) {
+        let mut parts = value.to_str().ok()?.splitn(2, ' ');
+        if parts.next() == Some("Bearer") {
+            return parts.next().map(|token| token.trim().to_owned());
+        }
+    }
+
+    query_access_token(request)
+}
+
+/// Gets the `access_token` query parameter of an incoming request.
+pub(crate) fn query_access_token<T>(request: &http::Request<T>) -> Option<String> {
+    let query = request.uri().query()?;
+    serde_urlencoded::from_str::<Vec<(String, String)>>(query)
+        .ok()?
+        .into_iter()
+        .find(|(key, _)| key == "access_token")
+        .map(|(_, value)| value)
+}
+
+/// Gets the authority an incoming request was sent to, from the request URI (the `:authority`
+/// pseudo-header in HTTP/2) or the `Host` header.
+pub fn request_authority<T>(request: &http::Request<T>) -> Option<&str> {
+    match request.uri().authority() {
+        Some(authority) => Some(authority.as_str()),
+        None => request.headers().get(HOST)?.to_str().ok(),
+    }
+}
+
+/// Checks that an incoming request has the given `Content-Type`.
+///
+/// Only the essence of the header is compared, case-insensitively, so `application/json` also
+/// matches `Application/JSON; charset=utf-8`.
+pub fn check_content_type<T>(
+    request: &http::Request<T>,
+    expected: &str,
+) -> Result<(), FromHttpRequestError> {
+    let content_type = request.headers().get(CONTENT_TYPE).map(|value| {
+        value.to_str().map(ToOwned::to_owned).unwrap_or_else(|_| format!("{:?}", value))
+    });
+
+    match content_type.as_ref().and_then(|value| value.split(';').next()) {
+        Some(essence) if essence.trim().eq_ignore_ascii_case(expected) => Ok(()),
+        _ => Err(FromHttpRequestError::UnexpectedContentType {
+            content_type,
+            expected: expected.to_owned(),
+        }),
+    }
+}
+
+/// Checks that the `Content-Length` header of an incoming request, if any, matches the length of
+/// its body.
+///
+/// A mismatch means that the body was truncated or padded somewhere on the way, e.g. by a proxy
+/// that doesn't normalize requests.
+pub fn check_content_length<T: AsRef<[u8]>>(
+    request: &http::Request<T>,
+) -> Result<(), FromHttpRequestError> {
+    let body_length = request.body().as_ref().len();
+    let content_length = match request.headers().get(CONTENT_LENGTH) {
+        Some(value) => value,
+        None => return Ok(()),
+    };
+
+    match content_length.to_str().ok().and_then(|value| value.trim().parse::<usize>().ok()) {
+        Some(length) if length == body_length => Ok(()),
+        _ => Err(FromHttpRequestError::ContentLengthMismatch {
+            content_length: content_length
+                .to_str()
+                .map(ToOwned::to_owned)
+                .unwrap_or_else(|_| format!("{:?}", content_length)),
+            body_length,
+        }),
+    }
+}
+
+/// Gets the timeout from the `X-Request-Timeout` header of an incoming request.
+///
+/// Returns `None` if the header is missing or isn't an integer number of milliseconds.
+pub fn request_timeout<T>(request: &http::Request<T>) -> Option<Duration> {
+    let value = request.headers().get(REQUEST_TIMEOUT)?.to_str().ok()?;
+    value.trim().parse().ok().map(Duration::from_millis)
+}
+
+/// Gets the value of the `X-Request-Id` header of a request or response.
+pub fn request_id(headers: &http::HeaderMap) -> Option<&str> {
+    headers.get(REQUEST_ID)?.to_str().ok()
+}
+
+/// Generates a random (version 4) UUID to use as a request ID.
+#[cfg(feature = "request-id")]
+pub fn generate_request_id() -> String {
+    let mut bytes: [u8; 16] = rand::random();
+    // Set the version (4) and the variant (RFC 4122).
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
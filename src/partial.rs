@@ -0,0 +1,145 @@
+//! Fallible deserialization of parts of a request or response.
+//!
+//! Wrapping a field in [`Partial`](enum.Partial.html) means that a value which fails to
+//! deserialize doesn't make the whole request or response fail to deserialize. Instead, the error
+//! and the raw JSON are kept for that field only.
+//!
+//! In `ruma_api!`, body fields can use `#[ruma_api(partial)]` to get this behavior for the field's
+//! value in the 'Incoming' type. Nested structures can use `#[wrap_incoming(T with Partial)]` as
+//! described in the documentation of the [`Outgoing`](../derive.Outgoing.html) derive macro.
+
+use std::fmt::{self, Display, Formatter};
+
+use serde::{
+    de::{Deserialize, DeserializeOwned, Deserializer},
+    ser::{Serialize, Serializer},
+};
+use serde_json::Value as JsonValue;
+
+use crate::TryIntoOutgoing;
+
+/// The result of deserializing a value that is allowed to be invalid.
+#[derive(Debug)]
+pub enum Partial<T> {
+    /// The value was deserialized successfully.
+    Ok(T),
+    /// The value could not be deserialized.
+    Err(PartialError),
+}
+
+impl<T> Partial<T> {
+    /// Converts this `Partial` into a `Result`.
+    pub fn into_result(self) -> Result<T, PartialError> {
+        match self {
+            Partial::Ok(value) => Ok(value),
+            Partial::Err(err) => Err(err),
+        }
+    }
+
+    /// Returns the value if it was deserialized successfully.
+    pub fn ok(self) -> Option<T> {
+        self.into_result().ok()
+    }
+
+    /// Returns the error if the value could not be deserialized.
+    pub fn err(&self) -> Option<&PartialError> {
+        match self {
+            Partial::Ok(_) => None,
+            Partial::Err(err) => Some(err),
+        }
+    }
+
+    /// Whether the value was deserialized successfully.
+    pub fn is_ok(&self) -> bool {
+        self.err().is_none()
+    }
+
+    /// Converts from `&Partial<T>` to `Result<&T, &PartialError>`.
+    pub fn as_ref(&self) -> Result<&T, &PartialError> {
+        match self {
+            Partial::Ok(value) => Ok(value),
+            Partial::Err(err) => Err(err),
+        }
+    }
+}
+
+impl<T> From<T> for Partial<T> {
+    fn from(value: T) -> Self {
+        Partial::Ok(value)
+    }
+}
+
+impl<T> TryIntoOutgoing<T> for Partial<T> {
+    fn try_into_outgoing(self) -> Result<T, PartialError> {
+        self.into_result()
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Partial<T>
+where
+    T: DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let json = JsonValue::deserialize(deserializer)?;
+
+        Ok(match T::deserialize(&json) {
+            Ok(value) => Partial::Ok(value),
+            Err(err) => Partial::Err(PartialError { message: err.to_string(), json }),
+        })
+    }
+}
+
+impl<T> Serialize for Partial<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Partial::Ok(value) => value.serialize(serializer),
+            Partial::Err(err) => err.json.serialize(serializer),
+        }
+    }
+}
+
+/// A value that could not be deserialized, along with the reason.
+#[derive(Clone, Debug)]
+pub struct PartialError {
+    message: String,
+    json: JsonValue,
+}
+
+impl PartialError {
+    #[cfg(feature = "deferred")]
+    pub(crate) fn new(message: String, json: JsonValue) -> Self {
+        Self { message, json }
+    }
+
+    /// The deserialization error message.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// The JSON value that failed to deserialize.
+    pub fn json(&self) -> &JsonValue {
+        &self.json
+    }
+
+    /// Consumes the error, returning the JSON value that failed to deserialize.
+    pub fn into_json(self) -> JsonValue {
+        self.json
+    }
+}
+
+impl Display for PartialError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for PartialError {}
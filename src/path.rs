@@ -0,0 +1,313 @@
+//! Building and validating the paths of endpoints.
+//!
+//! This is used by the code generated by `ruma_api!`, but can also be used by manual
+//! implementations of `Endpoint` and the request conversion.
+
+#[cfg(feature = "ruma-identifiers")]
+use std::convert::TryFrom;
+use std::{
+    fmt::{self, Display, Write},
+    str::FromStr,
+};
+
+use percent_encoding::{percent_encode, utf8_percent_encode, AsciiSet, CONTROLS};
+
+#[cfg(feature = "ruma-identifiers")]
+use crate::error::{IdentifierKind, InvalidIdentifier};
+use crate::error::{InvalidInteger, InvalidPathSegment};
+
+/// The maximum length of a Matrix identifier in bytes.
+pub const MAX_IDENTIFIER_LEN: usize = 255;
+
+/// Characters that are percent-encoded in a path segment by default. This is the same set the
+/// `url` crate uses for path segments.
+///
+/// It covers the characters with a special meaning in URLs, like `#` in room aliases, but leaves
+/// characters that are allowed in path segments as they are, like `$` in event IDs and `:` in
+/// server names.
+pub const PATH_SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'<')
+    .add(b'>')
+    .add(b'`')
+    .add(b'#')
+    .add(b'?')
+    .add(b'{')
+    .add(b'}')
+    .add(b'/')
+    .add(b'%');
+
+/// Characters that are percent-encoded in a path segment with `#[ruma_api(path, encode = STRICT)]`.
+///
+/// This encodes everything except the unreserved characters of RFC 3986 (ASCII letters and
+/// digits, `-`, `.`, `_` and `~`), e.g. `$` and `:` too, for servers that don't accept them
+/// unencoded.
+pub const STRICT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'!')
+    .add(b'"')
+    .add(b'#')
+    .add(b'$')
+    .add(b'%')
+    .add(b'&')
+    .add(b'\'')
+    .add(b'(')
+    .add(b')')
+    .add(b'*')
+    .add(b'+')
+    .add(b',')
+    .add(b'/')
+    .add(b':')
+    .add(b';')
+    .add(b'<')
+    .add(b'=')
+    .add(b'>')
+    .add(b'?')
+    .add(b'@')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Appends `/` followed by the percent-encoded `segment` to `path`, using the
+/// [`PATH_SEGMENT`](constant.PATH_SEGMENT.html) encode set.
+///
+/// The segment is encoded while it is formatted, so this doesn't allocate apart from growing
+/// `path`. A segment that is `.` or `..` is encoded as `%2E` or `%2E%2E`, so it can't change
+/// which path the request is sent to.
+pub fn push_segment(path: &mut String, segment: &impl Display) {
+    push_segment_with(path, segment, PATH_SEGMENT);
+}
+
+/// Like [`push_segment`](fn.push_segment.html), but percent-encodes the segment with the given
+/// encode set.
+pub fn push_segment_with(path: &mut String, segment: &impl Display, encode_set: &'static AsciiSet) {
+    path.push('/');
+    let start = path.len();
+    write!(PercentEncoder(path, encode_set), "{}", segment)
+        .expect("writing to a String to succeed");
+    encode_dot_segment(path, start);
+}
+
+/// Like [`push_segment_with`](fn.push_segment_with.html), but for a segment of raw bytes, like the
+/// value of a `#[ruma_api(path, decode = RAW)]` field.
+///
+/// Bytes that aren't ASCII are always percent-encoded.
+pub fn push_bytes_segment_with(path: &mut String, segment: &[u8], encode_set: &'static AsciiSet) {
+    path.push('/');
+    let start = path.len();
+    path.extend(percent_encode(segment, encode_set));
+    encode_dot_segment(path, start);
+}
+
+/// Percent-encodes the segment of `path` starting at `start` if it is `.` or `..`, which clients
+/// and servers would otherwise resolve as a reference to the current or parent path.
+fn encode_dot_segment(path: &mut String, start: usize) {
+    let encoded = match &path[start..] {
+        "." => "%2E",
+        ".." => "%2E%2E",
+        _ => return,
+    };
+    path.truncate(start);
+    path.push_str(encoded);
+}
+
+/// Checks that a path parameter follows the grammar of Matrix identifiers, for fields with
+/// `#[ruma_api(path, identifier)]`.
+///
+/// The parameter has to be between 1 and [`MAX_IDENTIFIER_LEN`](constant.MAX_IDENTIFIER_LEN.html)
+/// bytes long, can't be `.` or `..` and can't contain `/` or control characters. This makes sure a
+/// parameter built from user input can't add segments to the path or change its meaning, even if
+/// the server decodes it before routing the request.
+pub fn check_identifier(segment: &str) -> Result<(), InvalidPathSegment> {
+    if segment.is_empty() {
+        return Err(InvalidPathSegment::Empty);
+    }
+    if segment.len() > MAX_IDENTIFIER_LEN {
+        return Err(InvalidPathSegment::TooLong { length: segment.len() });
+    }
+    if segment == "." || segment == ".." {
+        return Err(InvalidPathSegment::DotSegment);
+    }
+    match segment.chars().find(|c| *c == '/' || c.is_control()) {
+        Some(c) => Err(InvalidPathSegment::InvalidCharacter(c)),
+        None => Ok(()),
+    }
+}
+
+/// Parses an integer path or query parameter, for fields of integer types like `u64` and `i64`.
+///
+/// Only decimal digits are accepted, with a leading `-` for negative numbers. Unlike `FromStr`,
+/// this tells values that are out of the range of `T` apart from values that aren't numbers at
+/// all.
+pub fn parse_integer<T: FromStr>(segment: &str) -> Result<T, InvalidInteger> {
+    let negative = segment.starts_with('-');
+    let digits = if negative { &segment[1..] } else { segment };
+    if digits.is_empty() {
+        return Err(InvalidInteger::Empty);
+    }
+    if let Some(c) = digits.chars().find(|c| !c.is_ascii_digit()) {
+        return Err(InvalidInteger::InvalidCharacter(c));
+    }
+
+    // All digits are valid, so `FromStr` can only fail because the value is out of range.
+    segment.parse().map_err(|_| {
+        if negative {
+            InvalidInteger::Underflow
+        } else {
+            InvalidInteger::Overflow
+        }
+    })
+}
+
+/// The identifier types from ruma-identifiers that `ruma_api!` parses from path and query
+/// parameters with [`parse_identifier`](fn.parse_identifier.html).
+#[cfg(feature = "ruma-identifiers")]
+pub trait Identifier: Sized {
+    /// The kind of identifier this type holds.
+    const KIND: IdentifierKind;
+
+    /// Parses an identifier with the type's `TryFrom<&str>` implementation.
+    fn parse(s: &str) -> Result<Self, ruma_identifiers::Error>;
+}
+
+macro_rules! impl_identifier {
+    ($($ty:ident),*) => {
+        $(
+            #[cfg(feature = "ruma-identifiers")]
+            impl Identifier for ruma_identifiers::$ty {
+                const KIND: IdentifierKind = IdentifierKind::$ty;
+
+                fn parse(s: &str) -> Result<Self, ruma_identifiers::Error> {
+                    Self::try_from(s)
+                }
+            }
+        )*
+    };
+}
+
+impl_identifier!(RoomId, RoomAliasId, UserId, EventId);
+
+/// Parses a path or query parameter of a `RoomId`, `RoomAliasId`, `UserId` or `EventId` field.
+///
+/// Unlike the `TryFrom<&str>` implementations of these types, the error says which kind of
+/// identifier was expected, so a server can tell the client which parameter was wrong.
+#[cfg(feature = "ruma-identifiers")]
+pub fn parse_identifier<T: Identifier>(segment: &str) -> Result<T, InvalidIdentifier> {
+    T::parse(segment).map_err(|error| InvalidIdentifier::new(T::KIND, error))
+}
+
+/// Whether the given request path matches the path of an endpoint, like
+/// `/_matrix/client/r0/rooms/:room_id/state`.
+///
+/// The literal segments have to be equal, path parameters match any non-empty segment.
+pub fn matches(template: &str, path: &str) -> bool {
+    let mut template_segments = template.split('/');
+    let mut path_segments = path.split('/');
+
+    loop {
+        match (template_segments.next(), path_segments.next()) {
+            (Some(template_segment), Some(segment)) if template_segment.starts_with(':') => {
+                if segment.is_empty() {
+                    return false;
+                }
+            }
+            (Some(template_segment), Some(segment)) => {
+                if template_segment != segment {
+                    return false;
+                }
+            }
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+/// A `fmt::Write` adapter that percent-encodes everything written to it.
+struct PercentEncoder<'a>(&'a mut String, &'static AsciiSet);
+
+impl Write for PercentEncoder<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.extend(utf8_percent_encode(s, self.1));
+        Ok(())
+    }
+}
+
+/// Panics if the given endpoint path is invalid.
+///
+/// A valid path starts with `/` and doesn't contain whitespace, control characters, a query
+/// string or a fragment. Path parameters like `:room_id` need to be whole segments with a name,
+/// and each name can only appear once.
+///
+/// This is a `const fn`, so it can be used to check a path at compile time. This requires the
+/// `nightly` feature, with which `ruma_api!` does so for every endpoint:
+///
+/// ```text
+/// const _: () = ruma_api::path::assert_valid_path("/_matrix/client/r0/rooms/:room_id/state");
+/// ```
+#[cfg(feature = "nightly")]
+pub const fn assert_valid_path(path: &str) {
+    let bytes = path.as_bytes();
+    if bytes.is_empty() || bytes[0] != b'/' {
+        panic!("endpoint path needs to start with '/'");
+    }
+
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'?' | b'#' => panic!("endpoint path must not contain a query string or fragment"),
+            0..=b' ' | 0x7f => {
+                panic!("endpoint path must not contain whitespace or control characters")
+            }
+            b':' => {
+                if bytes[i - 1] != b'/' {
+                    panic!("path parameters need to be whole path segments");
+                }
+                if i + 1 == bytes.len() || bytes[i + 1] == b'/' {
+                    panic!("path parameters need a name");
+                }
+            }
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    let mut i = 1;
+    while i < bytes.len() {
+        if bytes[i] == b':' {
+            let mut j = i + 1;
+            while j < bytes.len() {
+                if bytes[j] == b':' && segments_equal(bytes, i, j) {
+                    panic!("path parameters can't appear more than once");
+                }
+                j += 1;
+            }
+        }
+
+        i += 1;
+    }
+}
+
+/// Whether the path segments starting at the given indices are equal.
+#[cfg(feature = "nightly")]
+const fn segments_equal(bytes: &[u8], mut a: usize, mut b: usize) -> bool {
+    loop {
+        let a_end = a == bytes.len() || bytes[a] == b'/';
+        let b_end = b == bytes.len() || bytes[b] == b'/';
+        if a_end || b_end {
+            return a_end && b_end;
+        }
+        if bytes[a] != bytes[b] {
+            return false;
+        }
+
+        a += 1;
+        b += 1;
+    }
+}
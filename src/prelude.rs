@@ -0,0 +1,28 @@
+//! The most commonly needed items of this crate, for importing them with a single line:
+//!
+//! ```
+//! use ruma_api::prelude::*;
+//! ```
+//!
+//! This includes the `TryFrom` and `TryInto` traits, which convert requests and responses from and
+//! into their `http` counterparts.
+
+pub use std::convert::{TryFrom, TryInto};
+
+// Not `crate::ruma_api`, which would also re-export the `extern crate self as ruma_api` alias.
+#[cfg(feature = "serde")]
+pub use crate::TryIntoOutgoing;
+pub use crate::{
+    conversion::{
+        TryFromHttpRequest, TryFromHttpResponse, TryIntoHttpRequest, TryIntoHttpResponse,
+    },
+    error::{
+        FromHttpRequestError, FromHttpResponseError, IntoHttpError, RequestDeserializationError,
+        ResponseDeserializationError, ServerError, ValidationError,
+    },
+    Endpoint, Metadata, Outgoing, StableEndpoint, UnstableEndpoint, Validate,
+};
+#[cfg(feature = "with-ruma-api-macros")]
+pub use ruma_api_macros::ruma_api;
+#[cfg(all(feature = "client", feature = "with-ruma-api-macros"))]
+pub use ruma_api_macros::ruma_api_group;
@@ -0,0 +1,106 @@
+//! Inspecting an outgoing request before it is sent.
+//!
+//! A [`RequestPreview`] describes the `http::Request` a request would be converted to, without
+//! its body and with all credentials redacted, so it can be shown in debugging UIs or in prompts
+//! that ask the user for consent:
+//!
+//! ```
+//! # use ruma_api::{options::RequestOptions, ruma_api, Endpoint};
+//! #
+//! # ruma_api! {
+//! #     mod set_topic;
+//! #
+//! #     metadata {
+//! #         description: "Set the topic of a room.",
+//! #         method: PUT,
+//! #         name: "set_topic",
+//! #         path: "/_matrix/client/r0/rooms/:room_id/state/m.room.topic",
+//! #         rate_limited: false,
+//! #         requires_authentication: true,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(path)]
+//! #         pub room_id: String,
+//! #         pub topic: String,
+//! #     }
+//! #
+//! #     response {}
+//! # }
+//! #
+//! # fn main() -> Result<(), ruma_api::error::IntoHttpError> {
+//! # let request =
+//! #     set_topic::Request { room_id: "!room:example.org".to_owned(), topic: "Hello".to_owned() };
+//! let mut options = RequestOptions::default();
+//! options.base_url = Some("https://matrix.example.org".parse().unwrap());
+//! options.access_token = Some("secret".to_owned());
+//!
+//! let preview = request.preview_with(&options)?;
+//! println!("{}", preview); // PUT https://matrix.example.org/_matrix/… (17 bytes)
+//! # assert!(preview.to_string().starts_with("PUT https://matrix.example.org/_matrix/client/"));
+//! # assert!(preview.to_string().ends_with(" (17 bytes)"));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`RequestPreview`]: struct.RequestPreview.html
+
+use std::fmt::{self, Display, Formatter};
+
+use http::{HeaderMap, Method, Uri};
+
+use crate::options::{redact_access_token, redact_credential_headers};
+
+/// A description of an outgoing `http::Request`, with credentials redacted.
+#[derive(Clone, Debug)]
+pub struct RequestPreview {
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body_len: usize,
+}
+
+impl RequestPreview {
+    /// Describes the given request.
+    ///
+    /// The `Authorization`, `Proxy-Authorization` and `Cookie` headers and the `access_token`
+    /// query parameter are replaced with [`REDACTED`](../options/constant.REDACTED.html).
+    pub fn new<T: AsRef<[u8]>>(http_request: &http::Request<T>) -> Self {
+        let mut headers = http_request.headers().clone();
+        redact_credential_headers(&mut headers);
+
+        Self {
+            method: http_request.method().clone(),
+            uri: redact_access_token(http_request.uri()),
+            headers,
+            body_len: http_request.body().as_ref().len(),
+        }
+    }
+
+    /// The method of the request.
+    pub fn method(&self) -> &Method {
+        &self.method
+    }
+
+    /// The URI of the request. Unless a base URL was applied to the request, it has the placeholder
+    /// authority the request was converted with.
+    pub fn uri(&self) -> &Uri {
+        &self.uri
+    }
+
+    /// The headers of the request.
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// The length of the body of the request in bytes.
+    pub fn body_len(&self) -> usize {
+        self.body_len
+    }
+}
+
+impl Display for RequestPreview {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} ({} bytes)", self.method, self.uri, self.body_len)
+    }
+}
@@ -0,0 +1,95 @@
+//! Helpers for proxies and bridges that receive responses from one server and re-emit them with
+//! the response types of ruma-api.
+//!
+//! Converting a response back into an `http::Response` only produces the headers the endpoint
+//! defines. [`HeaderForwarding`](struct.HeaderForwarding.html) copies further headers of the
+//! upstream response, like caching headers, that are safe to pass on.
+
+use http::header::{self, HeaderMap, HeaderName};
+
+use crate::{conversion::TryIntoHttpResponse, error::IntoHttpError};
+
+/// The hop-by-hop headers, which only apply to a single connection and are never forwarded.
+const HOP_BY_HOP: &[HeaderName] = &[
+    header::CONNECTION,
+    header::PROXY_AUTHENTICATE,
+    header::PROXY_AUTHORIZATION,
+    header::TE,
+    header::TRAILER,
+    header::TRANSFER_ENCODING,
+    header::UPGRADE,
+];
+
+/// A policy for forwarding the headers of an upstream response.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct HeaderForwarding {
+    /// The headers that are forwarded.
+    ///
+    /// Hop-by-hop headers like `Connection` and `Transfer-Encoding`, and the headers listed in
+    /// the upstream `Connection` header, are never forwarded, even if they are in this list.
+    ///
+    /// Defaults to `Content-Type`, `Content-Language`, `Content-Disposition`, `Cache-Control`,
+    /// `Expires`, `ETag`, `Last-Modified` and `Vary`.
+    pub allowlist: Vec<HeaderName>,
+}
+
+impl Default for HeaderForwarding {
+    fn default() -> Self {
+        Self {
+            allowlist: vec![
+                header::CONTENT_TYPE,
+                header::CONTENT_LANGUAGE,
+                header::CONTENT_DISPOSITION,
+                header::CACHE_CONTROL,
+                header::EXPIRES,
+                header::ETAG,
+                header::LAST_MODIFIED,
+                header::VARY,
+            ],
+        }
+    }
+}
+
+impl HeaderForwarding {
+    /// Copies the allowed headers of `upstream` into `headers`.
+    ///
+    /// Headers that are already in `headers` are kept as they are.
+    pub fn forward_headers(&self, upstream: &HeaderMap, headers: &mut HeaderMap) {
+        let connection_headers: Vec<HeaderName> = upstream
+            .get_all(header::CONNECTION)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|name| name.trim().parse().ok())
+            .collect();
+
+        for name in &self.allowlist {
+            if HOP_BY_HOP.contains(name)
+                || connection_headers.contains(name)
+                || headers.contains_key(name)
+            {
+                continue;
+            }
+
+            for value in upstream.get_all(name) {
+                headers.append(name.clone(), value.clone());
+            }
+        }
+    }
+
+    /// Converts `response` into an `http::Response` and adds the allowed headers of the
+    /// `upstream` response it was created from.
+    pub fn forward_response<T, R>(
+        &self,
+        upstream: &http::Response<T>,
+        response: R,
+    ) -> Result<http::Response<Vec<u8>>, IntoHttpError>
+    where
+        R: TryIntoHttpResponse,
+    {
+        let mut http_response = response.try_into()?;
+        self.forward_headers(upstream.headers(), http_response.headers_mut());
+        Ok(http_response)
+    }
+}
@@ -0,0 +1,243 @@
+//! De-/serialization of query string parameters through their `Display` and `FromStr`
+//! implementations.
+//!
+//! `ruma_api!` uses these for `#[ruma_api(query, as_str)]` fields, which allows enums like the
+//! direction of a pagination request (`dir=f` / `dir=b`) to be used as query parameters without a
+//! custom serde implementation. They can also be used with serde's `with` attribute directly.
+//!
+//! Before a query string is deserialized, [`check_encoding`](fn.check_encoding.html) makes sure
+//! its percent-encoding is well-formed. The values are then decoded lossily, replacing
+//! percent-encoded sequences that aren't valid UTF-8 with U+FFFD.
+//! [`check_utf8`](fn.check_utf8.html) rejects them instead, which `ruma_api!` does for
+//! `#[ruma_api(query, decode = STRICT)]` fields.
+
+#[cfg(feature = "percent-encoding")]
+use std::collections::HashSet;
+
+#[cfg(feature = "percent-encoding")]
+use percent_encoding::percent_decode_str;
+
+#[cfg(feature = "percent-encoding")]
+use crate::error::{DuplicateQueryParameter, InvalidQueryString};
+
+/// Checks that every `%` in a query string starts a percent-encoded byte, and that the names of
+/// all parameters are valid UTF-8 after percent-decoding.
+///
+/// `ruma_api!` does this for incoming requests with query fields before deserializing them, so a
+/// malformed query string is reported as such instead of as a confusing error about one of the
+/// fields.
+#[cfg(feature = "percent-encoding")]
+pub fn check_encoding(query: &str) -> Result<(), InvalidQueryString> {
+    let bytes = query.as_bytes();
+    let is_hex_digit = |i: usize| match bytes.get(i) {
+        Some(byte) => byte.is_ascii_hexdigit(),
+        None => false,
+    };
+    if let Some(position) = (0..bytes.len())
+        .find(|&i| bytes[i] == b'%' && !(is_hex_digit(i + 1) && is_hex_digit(i + 2)))
+    {
+        return Err(InvalidQueryString::InvalidPercentEncoding { position });
+    }
+
+    let mut position = 0;
+    for pair in query.split('&') {
+        let key = pair.split('=').next().unwrap_or_default().replace('+', " ");
+        if percent_decode_str(&key).decode_utf8().is_err() {
+            return Err(InvalidQueryString::InvalidUtf8Name { position });
+        }
+        position += pair.len() + 1;
+    }
+
+    Ok(())
+}
+
+/// Checks that all values of the given key in a query string are valid UTF-8 after
+/// percent-decoding.
+#[cfg(feature = "percent-encoding")]
+pub fn check_utf8(query: &str, key: &str) -> Result<(), InvalidQueryString> {
+    for pair in query.split('&') {
+        if decode_key(pair) == key {
+            let value = match pair.find('=') {
+                Some(i) => &pair[i + 1..],
+                None => "",
+            };
+            let value = value.replace('+', " ");
+            if percent_decode_str(&value).decode_utf8().is_err() {
+                return Err(InvalidQueryString::InvalidUtf8Value { name: key.to_owned() });
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that none of the given keys appears more than once in a query string.
+///
+/// `ruma_api!` does this for the keys of the query fields of incoming requests, unless the
+/// endpoint has `duplicate_query_parameters: LastWins` in its metadata.
+#[cfg(feature = "percent-encoding")]
+pub fn check_unique(query: &str, keys: &[&str]) -> Result<(), DuplicateQueryParameter> {
+    let mut seen = Vec::new();
+    for key in query.split('&').map(decode_key) {
+        if !keys.contains(&key.as_str()) {
+            continue;
+        }
+        if seen.contains(&key) {
+            return Err(DuplicateQueryParameter::new(key));
+        }
+        seen.push(key);
+    }
+
+    Ok(())
+}
+
+/// Removes all but the last occurrence of each of the given keys from a query string.
+///
+/// `ruma_api!` does this for the keys of the query fields of incoming requests for endpoints with
+/// `duplicate_query_parameters: LastWins` in their metadata, so a parameter that is given twice
+/// gets the later value.
+#[cfg(feature = "percent-encoding")]
+pub fn keep_last(query: &str, keys: &[&str]) -> String {
+    // Going through the pairs from the end, the first occurrence of a key is the one to keep.
+    let mut seen = HashSet::new();
+    let mut pairs: Vec<_> = query
+        .split('&')
+        .rev()
+        .filter(|pair| {
+            let key = decode_key(pair);
+            !keys.contains(&key.as_str()) || seen.insert(key)
+        })
+        .collect();
+    pairs.reverse();
+    pairs.join("&")
+}
+
+/// The percent-decoded key of a `key=value` pair of a query string.
+#[cfg(feature = "percent-encoding")]
+fn decode_key(pair: &str) -> String {
+    let key = pair.split('=').next().unwrap_or_default().replace('+', " ");
+    percent_decode_str(&key).decode_utf8_lossy().into_owned()
+}
+
+/// De-/serialization of a value as the string given by its `Display` and `FromStr`
+/// implementations.
+pub mod as_str {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    /// Serializes a value as its `Display` string.
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Display,
+        S: Serializer,
+    {
+        serializer.collect_str(value)
+    }
+
+    /// Deserializes a value from a string with its `FromStr` implementation.
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        String::deserialize(deserializer)?.parse().map_err(de::Error::custom)
+    }
+}
+
+/// Like [`as_str`](as_str/index.html), but for `Option`al values.
+///
+/// Use together with `#[serde(default)]`.
+pub mod opt_as_str {
+    use std::{fmt::Display, str::FromStr};
+
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    /// Serializes an optional value as its `Display` string.
+    pub fn serialize<T, S>(value: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Display,
+        S: Serializer,
+    {
+        match value {
+            Some(value) => serializer.collect_str(value),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes an optional value from a string with its `FromStr` implementation.
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| s.parse().map_err(de::Error::custom))
+            .transpose()
+    }
+}
+
+/// Deserializes an integer with [`path::parse_integer`](../path/fn.parse_integer.html), which
+/// reports invalid digits and values out of range as an
+/// [`InvalidInteger`](../error/enum.InvalidInteger.html).
+///
+/// `ruma_api!` uses this for `#[ruma_api(query)]` fields of integer types.
+#[cfg(feature = "percent-encoding")]
+pub fn integer<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: std::str::FromStr,
+    D: serde::Deserializer<'de>,
+{
+    let value = <std::borrow::Cow<'_, str> as serde::Deserialize>::deserialize(deserializer)?;
+    crate::path::parse_integer(&value).map_err(serde::de::Error::custom)
+}
+
+/// Like [`integer`](fn.integer.html), but for `Option`al values.
+///
+/// Use together with `#[serde(default)]`.
+#[cfg(feature = "percent-encoding")]
+pub fn opt_integer<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: std::str::FromStr,
+    D: serde::Deserializer<'de>,
+{
+    let value =
+        <Option<std::borrow::Cow<'_, str>> as serde::Deserialize>::deserialize(deserializer)?;
+    value
+        .map(|value| crate::path::parse_integer(&value).map_err(serde::de::Error::custom))
+        .transpose()
+}
+
+/// Deserializes a `RoomId`, `RoomAliasId`, `UserId` or `EventId` with
+/// [`path::parse_identifier`](../path/fn.parse_identifier.html), which reports an invalid value
+/// as an [`InvalidIdentifier`](../error/struct.InvalidIdentifier.html) naming the expected kind
+/// of identifier.
+///
+/// `ruma_api!` uses this for `#[ruma_api(query)]` fields of these types.
+#[cfg(all(feature = "percent-encoding", feature = "ruma-identifiers"))]
+pub fn identifier<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: crate::path::Identifier,
+    D: serde::Deserializer<'de>,
+{
+    let value = <std::borrow::Cow<'_, str> as serde::Deserialize>::deserialize(deserializer)?;
+    crate::path::parse_identifier(&value).map_err(serde::de::Error::custom)
+}
+
+/// Like [`identifier`](fn.identifier.html), but for `Option`al values.
+///
+/// Use together with `#[serde(default)]`.
+#[cfg(all(feature = "percent-encoding", feature = "ruma-identifiers"))]
+pub fn opt_identifier<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    T: crate::path::Identifier,
+    D: serde::Deserializer<'de>,
+{
+    let value =
+        <Option<std::borrow::Cow<'_, str>> as serde::Deserialize>::deserialize(deserializer)?;
+    value
+        .map(|value| crate::path::parse_identifier(&value).map_err(serde::de::Error::custom))
+        .transpose()
+}
@@ -0,0 +1,438 @@
+//! A queue of requests that are sent once the server is reachable, retrying them with backoff.
+//!
+//! Clients with flaky connectivity put requests like sending messages in a [`SendQueue`] instead
+//! of sending them right away. The queue doesn't depend on an async runtime: it tells the client
+//! when the next request is due, and the client sends it when that time has come:
+//!
+//! ```
+//! # use std::time::{Duration, Instant};
+//! # use ruma_api::{
+//! #     client::Transport,
+//! #     error::IntoHttpError,
+//! #     queue::{QueuedRequest, SendOutcome, SendQueue},
+//! #     ruma_api,
+//! # };
+//! #
+//! # ruma_api! {
+//! #     mod send_message;
+//! #
+//! #     metadata {
+//! #         description: "Send a message to a room.",
+//! #         method: PUT,
+//! #         name: "send_message",
+//! #         path: "/_matrix/client/r0/rooms/:room_id/send/m.room.message/:txn_id",
+//! #         rate_limited: true,
+//! #         requires_authentication: true,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(path)]
+//! #         pub room_id: String,
+//! #         #[ruma_api(path)]
+//! #         pub txn_id: String,
+//! #         pub body: String,
+//! #     }
+//! #
+//! #     response {
+//! #         pub event_id: String,
+//! #     }
+//! # }
+//! #
+//! # async fn sleep(_: Duration) {}
+//! #
+//! # async fn example(transport: &impl Transport) -> Result<(), IntoHttpError> {
+//! let mut queue = SendQueue::new();
+//! queue.push(QueuedRequest::new(send_message::Request {
+//!     room_id: "!room:example.org".to_owned(),
+//!     txn_id: "1".to_owned(),
+//!     body: "Hello".to_owned(),
+//! })?);
+//!
+//! while let Some(wait) = queue.next_due_in(Instant::now()) {
+//!     sleep(wait).await;
+//!     match queue.send_next(transport, Instant::now()).await {
+//!         Some(SendOutcome::Sent { response, .. }) => { /* … */ }
+//!         Some(SendOutcome::Failed { request, error }) => { /* … */ }
+//!         Some(SendOutcome::Retrying { .. }) | None => {}
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Requests that fail because of the transport or a transient server error (see
+//! [`ErrorClassification::is_transient`]) are retried with exponential backoff, or after the time
+//! the server asked for. When a request to an endpoint with `rate_limited: true` in its metadata
+//! is rate-limited, all queued requests to rate-limited endpoints wait, since servers apply rate
+//! limits per user rather than per endpoint. Requests that wait for a retry don't hold up the
+//! requests behind them, so requests whose order matters should only be queued once the previous
+//! one was sent.
+//!
+//! Queued requests are stored in their converted form, so the queue can be persisted with
+//! `serde`, e.g. with [`SendQueue::requests`] and `SendQueue::from_iter`, and doesn't depend on
+//! the request types. The transport's [`request_options`] are only applied when a request is sent,
+//! so the access token isn't persisted with it and a refreshed token is used for retries.
+//!
+//! [`SendQueue`]: struct.SendQueue.html
+//! [`ErrorClassification::is_transient`]: ../error/enum.ErrorClassification.html#method.is_transient
+//! [`SendQueue::requests`]: struct.SendQueue.html#method.requests
+//! [`request_options`]: ../client/trait.Transport.html#method.request_options
+
+use std::{
+    cmp,
+    collections::VecDeque,
+    iter::FromIterator,
+    time::{Duration, Instant},
+};
+
+use http::{
+    header::{HeaderName, HeaderValue},
+    HeaderMap, Method, Uri,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::{
+    client::Transport,
+    conversion::{TryFromHttpRequest, TryFromHttpResponse},
+    error::{ErrorClassification, IntoHttpError, ServerError},
+    options::CustomizeRequest,
+    Endpoint, Outgoing,
+};
+
+/// A request that was converted to an `http::Request` for sending it later.
+#[derive(Clone, Debug)]
+pub struct QueuedRequest {
+    name: String,
+    rate_limited: bool,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl QueuedRequest {
+    /// Converts a request for queueing it.
+    pub fn new<E>(request: E) -> Result<Self, IntoHttpError>
+    where
+        E: Endpoint,
+        <E as Outgoing>::Incoming: TryFromHttpRequest,
+        <E::Response as Outgoing>::Incoming: TryFromHttpResponse,
+    {
+        let (parts, body) = request.try_into()?.into_parts();
+        Ok(Self {
+            name: E::METADATA.name().to_owned(),
+            rate_limited: E::METADATA.rate_limited(),
+            method: parts.method,
+            uri: parts.uri,
+            headers: parts.headers,
+            body,
+        })
+    }
+
+    /// The name of the endpoint the request is sent to.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Whether the endpoint the request is sent to is rate limited by the server.
+    pub fn rate_limited(&self) -> bool {
+        self.rate_limited
+    }
+
+    /// Creates the `http::Request` for sending this request.
+    pub fn to_http_request(&self) -> http::Request<Vec<u8>> {
+        let mut http_request = http::Request::new(self.body.clone());
+        *http_request.method_mut() = self.method.clone();
+        *http_request.uri_mut() = self.uri.clone();
+        *http_request.headers_mut() = self.headers.clone();
+        http_request
+    }
+}
+
+/// The serialized form of a `QueuedRequest`. Header values and the body are kept as bytes, since
+/// neither of them has to be valid UTF-8.
+#[derive(Deserialize, Serialize)]
+struct QueuedRequestRepr {
+    name: String,
+    rate_limited: bool,
+    #[serde(with = "crate::method_serde")]
+    method: Method,
+    uri: String,
+    headers: Vec<(String, Vec<u8>)>,
+    body: Vec<u8>,
+}
+
+impl Serialize for QueuedRequest {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        QueuedRequestRepr {
+            name: self.name.clone(),
+            rate_limited: self.rate_limited,
+            method: self.method.clone(),
+            uri: self.uri.to_string(),
+            headers: self
+                .headers
+                .iter()
+                .map(|(name, value)| (name.as_str().to_owned(), value.as_bytes().to_owned()))
+                .collect(),
+            body: self.body.clone(),
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for QueuedRequest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let repr = QueuedRequestRepr::deserialize(deserializer)?;
+
+        let mut headers = HeaderMap::with_capacity(repr.headers.len());
+        for (name, value) in repr.headers {
+            headers.append(
+                HeaderName::from_bytes(name.as_bytes()).map_err(de::Error::custom)?,
+                HeaderValue::from_bytes(&value).map_err(de::Error::custom)?,
+            );
+        }
+
+        Ok(Self {
+            name: repr.name,
+            rate_limited: repr.rate_limited,
+            method: repr.method,
+            uri: repr.uri.parse().map_err(de::Error::custom)?,
+            headers,
+            body: repr.body,
+        })
+    }
+}
+
+/// How long a [`SendQueue`](struct.SendQueue.html) waits before retrying a request.
+#[derive(Clone, Copy, Debug)]
+#[non_exhaustive]
+pub struct Backoff {
+    /// How long to wait before the first retry. Each further retry waits twice as long as the
+    /// previous one.
+    pub initial: Duration,
+
+    /// The longest time to wait before a retry, unless the server asks for a longer one.
+    pub max: Duration,
+
+    /// How often a request is sent before it is given up on, if at all.
+    pub max_attempts: Option<u32>,
+}
+
+impl Backoff {
+    /// The time to wait after the given number of failed attempts.
+    fn delay(&self, attempts: u32) -> Duration {
+        let delay = 1u32
+            .checked_shl(attempts.saturating_sub(1))
+            .and_then(|factor| self.initial.checked_mul(factor))
+            .unwrap_or(self.max);
+        cmp::min(delay, self.max)
+    }
+
+    /// The time the given delay after `now`, and the delay. Delays the server asks for can be too
+    /// long to be represented, like a huge `Retry-After`, those are replaced by `max`.
+    fn deadline(&self, now: Instant, delay: Duration) -> (Instant, Duration) {
+        match now.checked_add(delay) {
+            Some(deadline) => (deadline, delay),
+            None => (now + self.max, self.max),
+        }
+    }
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self { initial: Duration::from_secs(1), max: Duration::from_secs(300), max_attempts: None }
+    }
+}
+
+/// The result of [`SendQueue::send_next`](struct.SendQueue.html#method.send_next).
+#[derive(Debug)]
+pub enum SendOutcome<E> {
+    /// The request was sent successfully and removed from the queue.
+    Sent {
+        /// The request.
+        request: QueuedRequest,
+        /// The response of the server.
+        response: http::Response<Vec<u8>>,
+    },
+    /// The request failed in a way that won't change by retrying it, or was sent as often as
+    /// [`Backoff::max_attempts`](struct.Backoff.html#structfield.max_attempts) allows, and was
+    /// removed from the queue.
+    Failed {
+        /// The request.
+        request: QueuedRequest,
+        /// The error of the last attempt.
+        error: QueueError<E>,
+    },
+    /// The request failed and stays in the queue to be retried.
+    Retrying {
+        /// The error of this attempt.
+        error: QueueError<E>,
+        /// How long until the request is retried.
+        retry_in: Duration,
+    },
+}
+
+/// An error when sending a queued request.
+#[derive(Debug)]
+pub enum QueueError<E> {
+    /// The transport failed to send the request.
+    Transport(E),
+    /// The server returned an error response.
+    Server(ServerError),
+}
+
+/// A queue of requests that are sent in order, retrying failed ones with backoff.
+///
+/// See the [module documentation](index.html) for details.
+#[derive(Debug, Default)]
+pub struct SendQueue {
+    entries: VecDeque<Entry>,
+    backoff: Backoff,
+    /// Until when requests to rate-limited endpoints wait, after one of them was rate-limited.
+    rate_limited_until: Option<Instant>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    request: QueuedRequest,
+    attempts: u32,
+    not_before: Option<Instant>,
+}
+
+impl SendQueue {
+    /// Creates an empty queue with the default backoff.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates an empty queue with the given backoff.
+    pub fn with_backoff(backoff: Backoff) -> Self {
+        Self { backoff, ..Self::default() }
+    }
+
+    /// Adds a request to the end of the queue.
+    pub fn push(&mut self, request: QueuedRequest) {
+        self.entries.push_back(Entry { request, attempts: 0, not_before: None });
+    }
+
+    /// The number of queued requests.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The queued requests in the order they are sent, e.g. for persisting them.
+    pub fn requests(&self) -> impl Iterator<Item = &QueuedRequest> {
+        self.entries.iter().map(|entry| &entry.request)
+    }
+
+    /// How long until the next request is due, or `None` if the queue is empty.
+    ///
+    /// A zero duration means that a request can be sent right away.
+    pub fn next_due_in(&self, now: Instant) -> Option<Duration> {
+        self.entries
+            .iter()
+            .map(|entry| match self.due_at(entry) {
+                Some(due) => due.saturating_duration_since(now),
+                None => Duration::from_secs(0),
+            })
+            .min()
+    }
+
+    /// Sends the first request that is due, if any.
+    ///
+    /// The transport's [`request_options`] are applied to the request before it is sent. Returns
+    /// `None` if no request is due at the given time.
+    ///
+    /// [`request_options`]: ../client/trait.Transport.html#method.request_options
+    pub async fn send_next<T>(
+        &mut self,
+        transport: &T,
+        now: Instant,
+    ) -> Option<SendOutcome<T::Error>>
+    where
+        T: Transport + ?Sized,
+    {
+        let index = self.entries.iter().position(|entry| match self.due_at(entry) {
+            Some(due) => due <= now,
+            None => true,
+        })?;
+
+        let mut http_request = self.entries[index].request.to_http_request();
+        transport.request_options().customize(&mut http_request);
+
+        let (error, retry_after, transient) = match transport.send_http(http_request).await {
+            Ok(response) if response.status().is_success() => {
+                let request = self.entries.remove(index).unwrap().request;
+                return Some(SendOutcome::Sent { request, response });
+            }
+            Ok(response) => {
+                let error = ServerError::new(response);
+                let classification = error.classification();
+                if let ErrorClassification::RateLimited { retry_after } = &classification {
+                    if self.entries[index].request.rate_limited {
+                        let delay = retry_after.unwrap_or(self.backoff.initial);
+                        self.rate_limited_until = Some(self.backoff.deadline(now, delay).0);
+                    }
+                }
+                (
+                    QueueError::Server(error),
+                    classification.retry_after(),
+                    classification.is_transient(),
+                )
+            }
+            Err(error) => (QueueError::Transport(error), None, true),
+        };
+
+        let backoff = self.backoff;
+        let entry = &mut self.entries[index];
+        entry.attempts += 1;
+        let exhausted = match backoff.max_attempts {
+            Some(max_attempts) => entry.attempts >= max_attempts,
+            None => false,
+        };
+
+        if !transient || exhausted {
+            let request = self.entries.remove(index).unwrap().request;
+            return Some(SendOutcome::Failed { request, error });
+        }
+
+        let (not_before, retry_in) =
+            backoff.deadline(now, retry_after.unwrap_or_else(|| backoff.delay(entry.attempts)));
+        entry.not_before = Some(not_before);
+        Some(SendOutcome::Retrying { error, retry_in })
+    }
+
+    /// The earliest time the given entry can be sent at, if it has to wait.
+    fn due_at(&self, entry: &Entry) -> Option<Instant> {
+        let rate_limited_until =
+            if entry.request.rate_limited { self.rate_limited_until } else { None };
+        cmp::max(entry.not_before, rate_limited_until)
+    }
+}
+
+impl FromIterator<QueuedRequest> for SendQueue {
+    fn from_iter<I: IntoIterator<Item = QueuedRequest>>(iter: I) -> Self {
+        let mut queue = Self::new();
+        queue.extend(iter);
+        queue
+    }
+}
+
+impl Extend<QueuedRequest> for SendQueue {
+    fn extend<I: IntoIterator<Item = QueuedRequest>>(&mut self, iter: I) {
+        for request in iter {
+            self.push(request);
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Security headers for the responses of a server.
+//!
+//! Every server implementation needs the same defaults, like `X-Content-Type-Options: nosniff`
+//! for all responses and a restrictive `Content-Security-Policy` for media downloads, which may
+//! contain HTML uploaded by anyone. [`SecurityHeaders`] holds them, and
+//! [`ResponseOptions`](../options/struct.ResponseOptions.html) selects them by the category of the
+//! endpoint when converting responses with
+//! [`Endpoint::try_into_http_response_with`](../trait.Endpoint.html#method.try_into_http_response_with):
+//!
+//! ```
+//! # use ruma_api::{
+//! #     deps::http,
+//! #     options::ResponseOptions,
+//! #     ruma_api,
+//! #     security::MEDIA_CONTENT_SECURITY_POLICY,
+//! #     Endpoint,
+//! # };
+//! #
+//! # ruma_api! {
+//! #     mod get_content;
+//! #
+//! #     metadata {
+//! #         description: "Download some content.",
+//! #         method: GET,
+//! #         name: "get_content",
+//! #         path: "/_matrix/media/r0/download/:server_name/:media_id",
+//! #         rate_limited: false,
+//! #         requires_authentication: false,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(path)]
+//! #         pub server_name: String,
+//! #         #[ruma_api(path)]
+//! #         pub media_id: String,
+//! #     }
+//! #
+//! #     response {
+//! #         #[ruma_api(raw_body)]
+//! #         pub file: Vec<u8>,
+//! #     }
+//! # }
+//! #
+//! # fn main() -> Result<(), ruma_api::error::IntoHttpError> {
+//! # let response = get_content::Response { file: b"<html></html>".to_vec() };
+//! let options = ResponseOptions::recommended();
+//! let http_response = get_content::Request::try_into_http_response_with(response, &options)?;
+//! # assert_eq!(
+//! #     http_response.headers()[http::header::CONTENT_SECURITY_POLICY],
+//! #     MEDIA_CONTENT_SECURITY_POLICY,
+//! # );
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! No security headers are added unless the options enable them.
+//!
+//! [`SecurityHeaders`]: struct.SecurityHeaders.html
+
+use std::fmt::{self, Display, Formatter};
+
+use http::header::{
+    Entry, HeaderName, HeaderValue, CONTENT_SECURITY_POLICY, REFERRER_POLICY, SET_COOKIE,
+    X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+};
+
+/// The `Content-Security-Policy` the spec recommends for media downloads, which prevents
+/// uploaded HTML or SVG files from running scripts in the context of the server.
+pub const MEDIA_CONTENT_SECURITY_POLICY: &str = "sandbox; default-src 'none'; script-src 'none'; \
+     plugin-types application/pdf; style-src 'unsafe-inline'; object-src 'self';";
+
+/// Security headers added to responses.
+///
+/// Headers the response already has, e.g. from a header field of the endpoint, are kept.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SecurityHeaders {
+    /// Whether to send `X-Content-Type-Options: nosniff`, so browsers don't guess a content type
+    /// other than the one in the `Content-Type` header.
+    pub nosniff: bool,
+
+    /// The value of the `Content-Security-Policy` header.
+    pub content_security_policy: Option<String>,
+
+    /// The value of the `X-Frame-Options` header.
+    pub frame_options: Option<FrameOptions>,
+
+    /// The value of the `Referrer-Policy` header, e.g. `no-referrer`.
+    pub referrer_policy: Option<String>,
+
+    /// The `SameSite` attribute added to the `Set-Cookie` headers of the response that don't
+    /// have one.
+    pub same_site: Option<SameSite>,
+}
+
+impl SecurityHeaders {
+    /// The headers recommended for all responses: `X-Content-Type-Options: nosniff`,
+    /// `X-Frame-Options: DENY`, `Referrer-Policy: no-referrer` and `SameSite=Lax` for cookies.
+    pub fn recommended() -> Self {
+        Self {
+            nosniff: true,
+            content_security_policy: None,
+            frame_options: Some(FrameOptions::Deny),
+            referrer_policy: Some("no-referrer".to_owned()),
+            same_site: Some(SameSite::Lax),
+        }
+    }
+
+    /// The [recommended](#method.recommended) headers, with the
+    /// [`MEDIA_CONTENT_SECURITY_POLICY`](constant.MEDIA_CONTENT_SECURITY_POLICY.html) for media
+    /// downloads.
+    pub fn recommended_for_media() -> Self {
+        Self {
+            content_security_policy: Some(MEDIA_CONTENT_SECURITY_POLICY.to_owned()),
+            ..Self::recommended()
+        }
+    }
+
+    /// Adds these headers to the given `http::Response`.
+    ///
+    /// Values that aren't valid in a header are skipped.
+    pub fn apply<T>(&self, response: &mut http::Response<T>) {
+        let headers = response.headers_mut();
+
+        let mut set_default = |name: HeaderName, value: &str| {
+            if headers.contains_key(&name) {
+                return;
+            }
+            if let Ok(value) = HeaderValue::from_str(value) {
+                headers.insert(name, value);
+            }
+        };
+
+        if self.nosniff {
+            set_default(X_CONTENT_TYPE_OPTIONS, "nosniff");
+        }
+        if let Some(policy) = &self.content_security_policy {
+            set_default(CONTENT_SECURITY_POLICY, policy);
+        }
+        if let Some(frame_options) = self.frame_options {
+            set_default(X_FRAME_OPTIONS, frame_options.as_str());
+        }
+        if let Some(policy) = &self.referrer_policy {
+            set_default(REFERRER_POLICY, policy);
+        }
+
+        if let Some(same_site) = self.same_site {
+            if let Entry::Occupied(mut cookies) = headers.entry(SET_COOKIE) {
+                for value in cookies.iter_mut() {
+                    if let Some(with_same_site) = with_same_site(value, same_site) {
+                        *value = with_same_site;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The given `Set-Cookie` value with the given `SameSite` attribute, or `None` if it already has
+/// one or isn't valid UTF-8.
+fn with_same_site(value: &HeaderValue, same_site: SameSite) -> Option<HeaderValue> {
+    let value = value.to_str().ok()?;
+    let has_same_site = value
+        .split(';')
+        .skip(1)
+        .any(|attribute| attribute.trim().to_ascii_lowercase().starts_with("samesite"));
+    if has_same_site {
+        return None;
+    }
+
+    HeaderValue::from_str(&format!("{}; SameSite={}", value, same_site)).ok()
+}
+
+/// The values of the `X-Frame-Options` header.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameOptions {
+    /// `DENY`: The response can't be shown in a frame.
+    Deny,
+
+    /// `SAMEORIGIN`: The response can only be shown in a frame of the same origin.
+    SameOrigin,
+}
+
+impl FrameOptions {
+    /// The value of the header.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Deny => "DENY",
+            Self::SameOrigin => "SAMEORIGIN",
+        }
+    }
+}
+
+impl Display for FrameOptions {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The values of the `SameSite` attribute of cookies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+    /// `Strict`: The cookie is only sent with requests from the same site.
+    Strict,
+
+    /// `Lax`: Like `Strict`, but the cookie is also sent when navigating to the site from another
+    /// one.
+    Lax,
+
+    /// `None`: The cookie is sent with all requests. Browsers require the `Secure` attribute
+    /// with this.
+    None,
+}
+
+impl SameSite {
+    /// The value of the attribute.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Strict => "Strict",
+            Self::Lax => "Lax",
+            Self::None => "None",
+        }
+    }
+}
+
+impl Display for SameSite {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
@@ -0,0 +1,491 @@
+//! Detecting drift between endpoint definitions and the OpenAPI definitions of the Matrix
+//! specification.
+//!
+//! With the `spec-check` feature, every endpoint generated by `ruma_api!` implements
+//! [`EndpointFields`], which describes where its fields are sent and whether they are required. A
+//! [`Spec`] loads the OpenAPI 2.0 definitions published with the specification and compares them
+//! with an endpoint:
+//!
+//! ```no_run
+//! # use std::fs;
+//! # use ruma_api::{ruma_api, spec_check::Spec};
+//! #
+//! # ruma_api! {
+//! #     mod set_topic;
+//! #
+//! #     metadata {
+//! #         description: "Set the topic of a room.",
+//! #         method: PUT,
+//! #         name: "set_topic",
+//! #         path: "/_matrix/client/r0/rooms/:room_id/state/m.room.topic",
+//! #         rate_limited: false,
+//! #         requires_authentication: true,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(path)]
+//! #         pub room_id: String,
+//! #         pub topic: String,
+//! #     }
+//! #
+//! #     response {}
+//! # }
+//! #
+//! # fn main() -> Result<(), Box<dyn std::error::Error>> {
+//! let definition: serde_json::Value =
+//!     serde_json::from_str(&fs::read_to_string("client_server.json")?)?;
+//! let spec = Spec::from_openapi(&definition)?;
+//! for drift in spec.check::<set_topic::Request>() {
+//!     println!("{}", drift);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Only the query parameters, headers and top-level body properties are compared. Path
+//! parameters are compared implicitly by finding the operation for the endpoint's path, since
+//! their names differ between the specification and the endpoints. Bodies whose schema only
+//! references another definition, or that aren't JSON objects, are skipped.
+//!
+//! The definitions are expected with their placeholders resolved, like in the published
+//! `api-docs` JSON files.
+//!
+//! [`EndpointFields`]: trait.EndpointFields.html
+//! [`Spec`]: struct.Spec.html
+
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Display, Formatter},
+};
+
+use http::Method;
+use serde_json::Value as JsonValue;
+
+use crate::{
+    conversion::{TryFromHttpRequest, TryFromHttpResponse},
+    examples::FieldLocation,
+    Endpoint, Metadata, Outgoing,
+};
+
+/// Access to the wire names of an endpoint's fields, implemented for the endpoint's request type.
+pub trait EndpointFields {
+    /// The request fields, in declaration order.
+    fn request_fields() -> Vec<FieldInfo>;
+
+    /// The response fields, in declaration order.
+    fn response_fields() -> Vec<FieldInfo>;
+}
+
+/// A request or response field as it is sent.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FieldInfo {
+    /// The name of the field in the `Request` or `Response` struct.
+    pub field: &'static str,
+
+    /// The name of the query parameter, header or body property, or `None` if it is only known
+    /// at runtime, like for flattened fields, header maps and newtype bodies.
+    pub name: Option<String>,
+
+    /// Where the field is sent.
+    pub location: FieldLocation,
+
+    /// Whether the field must be present, i.e. it is neither an `Option` nor has a serde default.
+    pub required: bool,
+}
+
+/// The operations of one or more OpenAPI definitions.
+#[derive(Clone, Debug, Default)]
+pub struct Spec {
+    operations: BTreeMap<(String, String), Operation>,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Operation {
+    path: String,
+    query: Vec<Parameter>,
+    request_headers: Vec<Parameter>,
+    request_body: Option<Vec<Parameter>>,
+    response_headers: Vec<Parameter>,
+    response_body: Option<Vec<Parameter>>,
+}
+
+#[derive(Clone, Debug)]
+struct Parameter {
+    name: String,
+    /// Whether the parameter is required, or `None` if the definitions can't tell.
+    required: Option<bool>,
+}
+
+/// The keys of a path item that are operations.
+const OPERATION_METHODS: [&str; 7] = ["get", "put", "post", "delete", "options", "head", "patch"];
+
+impl Spec {
+    /// Loads the operations of an OpenAPI 2.0 definition.
+    pub fn from_openapi(definition: &JsonValue) -> Result<Self, SpecError> {
+        let base_path = definition.get("basePath").and_then(JsonValue::as_str).unwrap_or("");
+        let paths = definition
+            .get("paths")
+            .and_then(JsonValue::as_object)
+            .ok_or_else(|| SpecError::new("the definition has no `paths` object"))?;
+
+        let mut operations = BTreeMap::new();
+        for (path, path_item) in paths {
+            let path_item = path_item
+                .as_object()
+                .ok_or_else(|| SpecError::new(format!("the path `{}` is not an object", path)))?;
+            let shared_parameters = path_item.get("parameters");
+
+            for (method, operation) in path_item {
+                if !OPERATION_METHODS.contains(&method.as_str()) || !operation.is_object() {
+                    continue;
+                }
+                let method = method.to_ascii_uppercase();
+
+                let full_path = format!("{}{}", base_path, path);
+                let mut parsed = Operation { path: full_path.clone(), ..Operation::default() };
+                for parameter in shared_parameters.into_iter().chain(operation.get("parameters")) {
+                    parsed.add_parameters(parameter);
+                }
+                parsed.add_response(operation);
+
+                operations.insert((method, normalize_path(&full_path)), parsed);
+            }
+        }
+
+        Ok(Self { operations })
+    }
+
+    /// Adds the operations of another definition, e.g. to check endpoints of several APIs
+    /// against the same `Spec`.
+    pub fn extend(&mut self, other: Spec) {
+        self.operations.extend(other.operations);
+    }
+
+    /// Compares the endpoint `E` with its operation in the definitions.
+    pub fn check<E>(&self) -> Vec<Drift>
+    where
+        E: Endpoint + EndpointFields,
+        <E as Outgoing>::Incoming: TryFromHttpRequest,
+        <E::Response as Outgoing>::Incoming: TryFromHttpResponse,
+    {
+        self.check_fields(&E::METADATA, &E::request_fields(), &E::response_fields())
+    }
+
+    /// Compares the fields of the endpoint with the given metadata with its operation in the
+    /// definitions.
+    pub fn check_fields(
+        &self,
+        metadata: &Metadata,
+        request_fields: &[FieldInfo],
+        response_fields: &[FieldInfo],
+    ) -> Vec<Drift> {
+        let operation = match self.operation(metadata) {
+            Some(operation) => operation,
+            None => {
+                return vec![Drift::MissingOperation {
+                    method: metadata.method().clone(),
+                    path: metadata.path(),
+                }]
+            }
+        };
+
+        let mut drifts = Vec::new();
+        let parts = [
+            (Part::Query, Some(&operation.query), request_fields, FieldLocation::Query),
+            (
+                Part::RequestHeader,
+                Some(&operation.request_headers),
+                request_fields,
+                FieldLocation::Header,
+            ),
+            (
+                Part::RequestBody,
+                operation.request_body.as_ref(),
+                request_fields,
+                FieldLocation::Body,
+            ),
+            (
+                Part::ResponseHeader,
+                Some(&operation.response_headers),
+                response_fields,
+                FieldLocation::Header,
+            ),
+            (
+                Part::ResponseBody,
+                operation.response_body.as_ref(),
+                response_fields,
+                FieldLocation::Body,
+            ),
+        ];
+
+        for (part, parameters, fields, location) in parts.iter() {
+            if let Some(parameters) = parameters {
+                let fields: Vec<_> = fields.iter().filter(|f| f.location == *location).collect();
+                compare(*part, parameters, &fields, &mut drifts);
+            }
+        }
+
+        drifts
+    }
+
+    /// The operations in the definitions that none of the given endpoints implement, as their
+    /// method and path.
+    pub fn unimplemented(&self, endpoints: &[Metadata]) -> Vec<(Method, String)> {
+        self.operations
+            .iter()
+            .filter_map(|((method, path), operation)| {
+                let method = Method::from_bytes(method.as_bytes()).ok()?;
+                let implemented = endpoints.iter().any(|metadata| {
+                    metadata.allows_method(&method) && normalize_path(metadata.path()) == *path
+                });
+                if implemented {
+                    None
+                } else {
+                    Some((method, operation.path.clone()))
+                }
+            })
+            .collect()
+    }
+
+    fn operation(&self, metadata: &Metadata) -> Option<&Operation> {
+        self.operations.get(&(metadata.method().to_string(), normalize_path(metadata.path())))
+    }
+}
+
+impl Operation {
+    fn add_parameters(&mut self, parameters: &JsonValue) {
+        for parameter in parameters.as_array().into_iter().flatten() {
+            let name = match parameter.get("name").and_then(JsonValue::as_str) {
+                Some(name) => name.to_owned(),
+                None => continue,
+            };
+            let required =
+                Some(parameter.get("required").and_then(JsonValue::as_bool).unwrap_or(false));
+
+            match parameter.get("in").and_then(JsonValue::as_str) {
+                Some("query") => self.query.push(Parameter { name, required }),
+                Some("header") => self
+                    .request_headers
+                    .push(Parameter { name: name.to_ascii_lowercase(), required }),
+                Some("body") => {
+                    self.request_body = parameter.get("schema").and_then(schema_properties)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn add_response(&mut self, operation: &JsonValue) {
+        let response = match operation.get("responses").and_then(|responses| responses.get("200")) {
+            Some(response) => response,
+            None => return,
+        };
+
+        self.response_body = match response.get("schema") {
+            Some(schema) => schema_properties(schema),
+            None => Some(Vec::new()),
+        };
+
+        // OpenAPI 2.0 can't mark response headers as required.
+        let headers = response.get("headers").and_then(JsonValue::as_object);
+        for name in headers.into_iter().flat_map(|headers| headers.keys()) {
+            self.response_headers
+                .push(Parameter { name: name.to_ascii_lowercase(), required: None });
+        }
+    }
+}
+
+/// The top-level properties of an object schema, or `None` if they aren't known without
+/// resolving references.
+fn schema_properties(schema: &JsonValue) -> Option<Vec<Parameter>> {
+    if schema.get("$ref").is_some() || schema.get("additionalProperties").is_some() {
+        return None;
+    }
+
+    let mut parameters = Vec::new();
+    for part in schema.get("allOf").and_then(JsonValue::as_array).into_iter().flatten() {
+        parameters.extend(schema_properties(part)?);
+    }
+
+    let properties = schema.get("properties").and_then(JsonValue::as_object);
+    let is_object = schema.get("type").and_then(JsonValue::as_str) == Some("object");
+    if properties.is_none() && !is_object && parameters.is_empty() {
+        return None;
+    }
+
+    let required: Vec<_> = schema
+        .get("required")
+        .and_then(JsonValue::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(JsonValue::as_str)
+        .collect();
+    parameters.extend(properties.into_iter().flatten().map(|(name, _)| Parameter {
+        name: name.clone(),
+        required: Some(required.contains(&name.as_str())),
+    }));
+
+    Some(parameters)
+}
+
+fn compare(part: Part, parameters: &[Parameter], fields: &[&FieldInfo], drifts: &mut Vec<Drift>) {
+    // Header names are case-insensitive, and both sides are lowercase already.
+    let has_dynamic_fields = fields.iter().any(|field| field.name.is_none());
+
+    for parameter in parameters {
+        match fields.iter().find(|field| field.name.as_ref() == Some(&parameter.name)) {
+            Some(field) if parameter.required == Some(!field.required) => {
+                drifts.push(Drift::RequiredMismatch {
+                    part,
+                    name: parameter.name.clone(),
+                    spec_required: !field.required,
+                });
+            }
+            Some(_) => {}
+            None if !has_dynamic_fields => {
+                drifts.push(Drift::MissingFromEndpoint { part, name: parameter.name.clone() });
+            }
+            None => {}
+        }
+    }
+
+    for field in fields {
+        if let Some(name) = &field.name {
+            if !parameters.iter().any(|parameter| &parameter.name == name) {
+                drifts.push(Drift::MissingFromSpec {
+                    part,
+                    name: name.clone(),
+                    field: field.field,
+                });
+            }
+        }
+    }
+}
+
+/// Replaces the path parameters, `:name` in endpoints and `{name}` in the definitions, with `{}`.
+fn normalize_path(path: &str) -> String {
+    path.split('/')
+        .map(|segment| {
+            if segment.starts_with(':') || (segment.starts_with('{') && segment.ends_with('}')) {
+                "{}"
+            } else {
+                segment
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// A part of a request or response that is compared with the definitions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Part {
+    /// The query string of the request.
+    Query,
+
+    /// The headers of the request.
+    RequestHeader,
+
+    /// The top-level properties of the request body.
+    RequestBody,
+
+    /// The headers of the successful response.
+    ResponseHeader,
+
+    /// The top-level properties of the successful response body.
+    ResponseBody,
+}
+
+impl Display for Part {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Query => "query parameter",
+            Self::RequestHeader => "request header",
+            Self::RequestBody => "request body property",
+            Self::ResponseHeader => "response header",
+            Self::ResponseBody => "response body property",
+        })
+    }
+}
+
+/// A difference between an endpoint and its operation in the definitions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Drift {
+    /// The definitions have no operation with the endpoint's method and path.
+    MissingOperation {
+        /// The method of the endpoint.
+        method: Method,
+        /// The path of the endpoint.
+        path: &'static str,
+    },
+
+    /// The definitions have a parameter or property the endpoint has no field for.
+    MissingFromEndpoint {
+        /// Where the parameter or property is sent.
+        part: Part,
+        /// The name of the parameter or property.
+        name: String,
+    },
+
+    /// The endpoint has a field the definitions have no parameter or property for.
+    MissingFromSpec {
+        /// Where the field is sent.
+        part: Part,
+        /// The name the field is sent under.
+        name: String,
+        /// The name of the field in the `Request` or `Response` struct.
+        field: &'static str,
+    },
+
+    /// A field is required in the definitions but optional in the endpoint, or vice versa.
+    RequiredMismatch {
+        /// Where the field is sent.
+        part: Part,
+        /// The name the field is sent under.
+        name: String,
+        /// Whether the definitions require the field.
+        spec_required: bool,
+    },
+}
+
+impl Display for Drift {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingOperation { method, path } => {
+                write!(f, "the spec has no operation `{} {}`", method, path)
+            }
+            Self::MissingFromEndpoint { part, name } => {
+                write!(f, "the {} `{}` is missing from the endpoint", part, name)
+            }
+            Self::MissingFromSpec { part, name, field } => {
+                write!(f, "the {} `{}` (field `{}`) is missing from the spec", part, name, field)
+            }
+            Self::RequiredMismatch { part, name, spec_required: true } => {
+                write!(f, "the {} `{}` is required by the spec but optional", part, name)
+            }
+            Self::RequiredMismatch { part, name, spec_required: false } => {
+                write!(f, "the {} `{}` is optional in the spec but required", part, name)
+            }
+        }
+    }
+}
+
+/// An error when loading an OpenAPI definition.
+#[derive(Clone, Debug)]
+pub struct SpecError {
+    message: String,
+}
+
+impl SpecError {
+    fn new(message: impl Into<String>) -> Self {
+        Self { message: message.into() }
+    }
+}
+
+impl Display for SpecError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid OpenAPI definition: {}", self.message)
+    }
+}
+
+impl std::error::Error for SpecError {}
@@ -0,0 +1,182 @@
+//! Types and serde helpers for the integer millisecond timestamps and durations used throughout
+//! the Matrix APIs.
+//!
+//! The helper modules are meant to be used with serde's `with` attribute on request and response
+//! fields, e.g. `#[serde(with = "ruma_api::time::duration::ms")]`.
+
+use std::{
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::InvalidInteger;
+
+/// A timestamp represented as the number of milliseconds since the unix epoch.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize,
+)]
+#[serde(transparent)]
+pub struct MilliSecondsSinceUnixEpoch(pub u64);
+
+impl MilliSecondsSinceUnixEpoch {
+    /// Creates a new `MilliSecondsSinceUnixEpoch` from the given `SystemTime`.
+    ///
+    /// Returns `None` if the time is before the unix epoch or too far in the future to be
+    /// represented.
+    pub fn from_system_time(time: SystemTime) -> Option<Self> {
+        let duration = time.duration_since(UNIX_EPOCH).ok()?;
+        u64::try_from(duration.as_millis()).ok().map(Self)
+    }
+
+    /// The current system time.
+    pub fn now() -> Self {
+        Self::from_system_time(SystemTime::now()).expect("system time to be after the unix epoch")
+    }
+
+    /// Converts this timestamp to a `SystemTime`.
+    pub fn to_system_time(self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_millis(self.0)
+    }
+}
+
+impl Display for MilliSecondsSinceUnixEpoch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl TryFrom<&str> for MilliSecondsSinceUnixEpoch {
+    type Error = InvalidInteger;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        if s.is_empty() {
+            return Err(InvalidInteger::Empty);
+        }
+        if let Some(c) = s.chars().find(|c| !c.is_ascii_digit()) {
+            return Err(InvalidInteger::InvalidCharacter(c));
+        }
+
+        // All characters are digits, so parsing can only fail because the value is too large.
+        s.parse().map(Self).map_err(|_| InvalidInteger::Overflow)
+    }
+}
+
+/// De-/serialization of `SystemTime` as milliseconds since the unix epoch.
+pub mod ms_since_unix_epoch {
+    use std::time::SystemTime;
+
+    use serde::{de, ser, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::MilliSecondsSinceUnixEpoch;
+
+    /// Serializes a `SystemTime` as an integer number of milliseconds since the unix epoch.
+    pub fn serialize<S>(time: &SystemTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        MilliSecondsSinceUnixEpoch::from_system_time(*time)
+            .ok_or_else(|| ser::Error::custom("time is not representable as milliseconds"))?
+            .serialize(serializer)
+    }
+
+    /// Deserializes an integer number of milliseconds since the unix epoch to a `SystemTime`.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<SystemTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let ms = MilliSecondsSinceUnixEpoch::deserialize(deserializer)?;
+        SystemTime::UNIX_EPOCH
+            .checked_add(std::time::Duration::from_millis(ms.0))
+            .ok_or_else(|| de::Error::custom("timestamp out of range"))
+    }
+}
+
+/// De-/serialization of `Option<SystemTime>` as milliseconds since the unix epoch.
+///
+/// Use together with `#[serde(default, skip_serializing_if = "Option::is_none")]`.
+pub mod opt_ms_since_unix_epoch {
+    use std::time::SystemTime;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    /// Serializes an `Option<SystemTime>` as milliseconds since the unix epoch.
+    pub fn serialize<S>(time: &Option<SystemTime>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match time {
+            Some(time) => super::ms_since_unix_epoch::serialize(time, serializer),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    /// Deserializes an optional number of milliseconds since the unix epoch.
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<SystemTime>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        struct Wrapper(#[serde(with = "super::ms_since_unix_epoch")] SystemTime);
+
+        Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|w| w.0))
+    }
+}
+
+/// De-/serialization of `Duration`s.
+pub mod duration {
+    /// De-/serialization of `Duration` as an integer number of milliseconds.
+    pub mod ms {
+        use std::{convert::TryFrom, time::Duration};
+
+        use serde::{ser, Deserialize, Deserializer, Serialize, Serializer};
+
+        /// Serializes a `Duration` as an integer number of milliseconds.
+        pub fn serialize<S>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            u64::try_from(duration.as_millis())
+                .map_err(|_| ser::Error::custom("duration too long"))?
+                .serialize(serializer)
+        }
+
+        /// Deserializes an integer number of milliseconds to a `Duration`.
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            u64::deserialize(deserializer).map(Duration::from_millis)
+        }
+    }
+
+    /// De-/serialization of `Option<Duration>` as an integer number of milliseconds.
+    ///
+    /// Use together with `#[serde(default, skip_serializing_if = "Option::is_none")]`.
+    pub mod opt_ms {
+        use std::time::Duration;
+
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        /// Serializes an `Option<Duration>` as an integer number of milliseconds.
+        pub fn serialize<S>(duration: &Option<Duration>, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match duration {
+                Some(duration) => super::ms::serialize(duration, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        /// Deserializes an optional integer number of milliseconds to a `Duration`.
+        pub fn deserialize<'de, D>(deserializer: D) -> Result<Option<Duration>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            Ok(Option::<u64>::deserialize(deserializer)?.map(Duration::from_millis))
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! Types for the [User-Interactive Authentication API][uiaa] (UIAA).
+//!
+//! Endpoints that require it are declared with `uiaa: true` in the `metadata` section of
+//! `ruma_api!`. A `401` response of such an endpoint whose body describes the authentication
+//! flows is then returned as
+//! [`FromHttpResponseError::Uiaa`](../error/enum.FromHttpResponseError.html#variant.Uiaa) instead
+//! of a generic server error.
+//!
+//! [uiaa]: https://matrix.org/docs/spec/client_server/r0.6.1#user-interactive-authentication-api
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// The information about the authentication flows of an endpoint, sent by the server with a `401`
+/// response.
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+pub struct UiaaInfo {
+    /// The flows the client can complete to authenticate the request.
+    pub flows: Vec<AuthFlow>,
+
+    /// The stages the client has already completed in this session.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub completed: Vec<String>,
+
+    /// The parameters the client needs for the stages, keyed by stage type.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub params: BTreeMap<String, JsonValue>,
+
+    /// The session identifier to pass back to the server in the next authentication attempt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+
+    /// The error code of the previous authentication attempt, if it failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errcode: Option<String>,
+
+    /// The error message of the previous authentication attempt, if it failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// A list of stages that together authenticate a request.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AuthFlow {
+    /// The stage types, e.g. `m.login.password`, in the order they have to be completed.
+    pub stages: Vec<String>,
+}
@@ -0,0 +1,133 @@
+//! A catch-all request type for routes that no endpoint handles.
+//!
+//! Servers that route requests to endpoints with `matches_path` convert the requests that don't
+//! match any of them to an [`UnknownEndpoint`], which keeps the whole request. It can be answered
+//! with the `M_UNRECOGNIZED` error the spec requires, or forwarded, e.g. by an application service
+//! proxy:
+//!
+//! ```
+//! # use ruma_api::{deps::http, ruma_api, unknown::UnknownEndpoint};
+//! #
+//! # ruma_api! {
+//! #     mod get_alias;
+//! #
+//! #     metadata {
+//! #         description: "Resolve a room alias to a room ID.",
+//! #         method: GET,
+//! #         name: "get_alias",
+//! #         path: "/_matrix/client/r0/directory/room/:room_alias",
+//! #         rate_limited: false,
+//! #         requires_authentication: false,
+//! #     }
+//! #
+//! #     request {
+//! #         #[ruma_api(path)]
+//! #         pub room_alias: String,
+//! #     }
+//! #
+//! #     response {
+//! #         pub room_id: String,
+//! #     }
+//! # }
+//! #
+//! # fn handle_get_alias(_: http::Request<Vec<u8>>) -> http::Response<Vec<u8>> {
+//! #     unimplemented!()
+//! # }
+//! #
+//! fn route(http_request: http::Request<Vec<u8>>) -> http::Response<Vec<u8>> {
+//!     if get_alias::Request::matches_path(http_request.uri().path()) {
+//!         handle_get_alias(http_request)
+//!     } else {
+//!         UnknownEndpoint::from(http_request).unrecognized_response()
+//!     }
+//! }
+//! # let http_request = http::Request::builder().uri("/_matrix/client/r0/foo").body(Vec::new());
+//! # assert_eq!(route(http_request.unwrap()).status(), http::StatusCode::NOT_FOUND);
+//! ```
+//!
+//! [`UnknownEndpoint`]: struct.UnknownEndpoint.html
+
+use http::{
+    header::{HeaderValue, ALLOW, CONTENT_LENGTH, CONTENT_TYPE},
+    HeaderMap, Method, StatusCode,
+};
+
+/// An incoming request to a route that no endpoint handles.
+#[derive(Debug)]
+pub struct UnknownEndpoint {
+    http_request: http::Request<Vec<u8>>,
+}
+
+impl UnknownEndpoint {
+    /// The method of the request.
+    pub fn method(&self) -> &Method {
+        self.http_request.method()
+    }
+
+    /// The path of the request, still percent-encoded.
+    pub fn path(&self) -> &str {
+        self.http_request.uri().path()
+    }
+
+    /// The query string of the request, still percent-encoded, if it has one.
+    pub fn query(&self) -> Option<&str> {
+        self.http_request.uri().query()
+    }
+
+    /// The decoded parameters of the query string, in the order they appear in.
+    ///
+    /// Parameters that can't be decoded are skipped.
+    pub fn query_pairs(&self) -> Vec<(String, String)> {
+        serde_urlencoded::from_str(self.query().unwrap_or("")).unwrap_or_default()
+    }
+
+    /// The headers of the request.
+    pub fn headers(&self) -> &HeaderMap {
+        self.http_request.headers()
+    }
+
+    /// The body of the request.
+    pub fn body(&self) -> &[u8] {
+        self.http_request.body()
+    }
+
+    /// Returns the request as it was received, e.g. for forwarding it to another server.
+    pub fn into_http_request(self) -> http::Request<Vec<u8>> {
+        self.http_request
+    }
+
+    /// The `404 Not Found` response with an `M_UNRECOGNIZED` error, for a route that no endpoint
+    /// handles.
+    pub fn unrecognized_response(&self) -> http::Response<Vec<u8>> {
+        unrecognized(StatusCode::NOT_FOUND)
+    }
+
+    /// The `405 Method Not Allowed` response with an `M_UNRECOGNIZED` error, for a path that is
+    /// handled by endpoints with the given methods, but not with the method of this request.
+    pub fn method_not_allowed_response(&self, allowed: &[Method]) -> http::Response<Vec<u8>> {
+        let mut response = unrecognized(StatusCode::METHOD_NOT_ALLOWED);
+        let allow = allowed.iter().map(Method::as_str).collect::<Vec<_>>().join(", ");
+        if let Ok(allow) = HeaderValue::from_str(&allow) {
+            response.headers_mut().insert(ALLOW, allow);
+        }
+        response
+    }
+}
+
+impl From<http::Request<Vec<u8>>> for UnknownEndpoint {
+    fn from(http_request: http::Request<Vec<u8>>) -> Self {
+        Self { http_request }
+    }
+}
+
+/// An error response with the `M_UNRECOGNIZED` error code and the given status.
+fn unrecognized(status: StatusCode) -> http::Response<Vec<u8>> {
+    let body = br#"{"errcode":"M_UNRECOGNIZED","error":"Unrecognized request"}"#.to_vec();
+    let content_length = body.len();
+    let mut response = http::Response::new(body);
+    *response.status_mut() = status;
+    let headers = response.headers_mut();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers.insert(CONTENT_LENGTH, HeaderValue::from(content_length));
+    response
+}
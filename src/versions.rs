@@ -0,0 +1,238 @@
+//! Versions of the Matrix client-server API and the endpoint for negotiating them.
+//!
+//! Like the endpoints in [`well_known`](../well_known/index.html), the
+//! [`get_supported_versions`](get_supported_versions/index.html) endpoint is defined in ruma-api
+//! itself since clients need it to decide which other endpoints they can use.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    convert::TryFrom,
+    fmt::{self, Display, Formatter},
+};
+
+#[cfg(feature = "with-ruma-api-macros")]
+use crate::ruma_api;
+use crate::Metadata;
+
+#[cfg(feature = "with-ruma-api-macros")]
+ruma_api! {
+    pub mod get_supported_versions;
+
+    metadata {
+        description: "Get the versions of the client-server API supported by this homeserver.",
+        method: GET,
+        name: "api_versions",
+        path: "/_matrix/client/versions",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {
+        /// A list of Matrix client API protocol versions supported by the homeserver.
+        pub versions: Vec<String>,
+
+        /// Experimental features supported by the server.
+        #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+        pub unstable_features: BTreeMap<String, bool>,
+    }
+}
+
+/// A version of the Matrix client-server API.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum MatrixVersion {
+    /// Version r0.0.1.
+    R0_0_1,
+    /// Version r0.1.0.
+    R0_1_0,
+    /// Version r0.2.0.
+    R0_2_0,
+    /// Version r0.3.0.
+    R0_3_0,
+    /// Version r0.4.0.
+    R0_4_0,
+    /// Version r0.5.0.
+    R0_5_0,
+    /// Version r0.6.0.
+    R0_6_0,
+}
+
+impl MatrixVersion {
+    /// All known versions, from oldest to newest.
+    pub const ALL: &'static [MatrixVersion] = &[
+        MatrixVersion::R0_0_1,
+        MatrixVersion::R0_1_0,
+        MatrixVersion::R0_2_0,
+        MatrixVersion::R0_3_0,
+        MatrixVersion::R0_4_0,
+        MatrixVersion::R0_5_0,
+        MatrixVersion::R0_6_0,
+    ];
+
+    /// The version as it appears in the `/versions` response, e.g. `r0.6.0`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MatrixVersion::R0_0_1 => "r0.0.1",
+            MatrixVersion::R0_1_0 => "r0.1.0",
+            MatrixVersion::R0_2_0 => "r0.2.0",
+            MatrixVersion::R0_3_0 => "r0.3.0",
+            MatrixVersion::R0_4_0 => "r0.4.0",
+            MatrixVersion::R0_5_0 => "r0.5.0",
+            MatrixVersion::R0_6_0 => "r0.6.0",
+        }
+    }
+}
+
+impl Display for MatrixVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for MatrixVersion {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for MatrixVersion {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let version = String::deserialize(deserializer)?;
+        Self::try_from(version.as_str()).map_err(serde::de::Error::custom)
+    }
+}
+
+impl TryFrom<&str> for MatrixVersion {
+    type Error = UnknownVersionError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Self::ALL.iter().copied().find(|version| version.as_str() == s).ok_or(UnknownVersionError)
+    }
+}
+
+/// An error when converting a string that isn't a known version to a
+/// [`MatrixVersion`](enum.MatrixVersion.html).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct UnknownVersionError;
+
+impl Display for UnknownVersionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown Matrix version")
+    }
+}
+
+impl std::error::Error for UnknownVersionError {}
+
+/// The versions and unstable features supported by a homeserver, as returned by
+/// [`get_supported_versions`](get_supported_versions/index.html).
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SupportedVersions {
+    /// The supported versions known to this crate.
+    pub versions: BTreeSet<MatrixVersion>,
+
+    /// The supported versions that are not known to this crate.
+    pub unknown_versions: Vec<String>,
+
+    /// Experimental features and whether they are enabled.
+    pub unstable_features: BTreeMap<String, bool>,
+}
+
+impl SupportedVersions {
+    /// Whether the homeserver supports the given version.
+    pub fn supports(&self, version: MatrixVersion) -> bool {
+        self.versions.contains(&version)
+    }
+
+    /// The newest version supported by both the homeserver and this crate.
+    pub fn latest(&self) -> Option<MatrixVersion> {
+        self.versions.iter().next_back().copied()
+    }
+
+    /// Whether the homeserver advertises the given unstable feature as enabled.
+    pub fn supports_unstable_feature(&self, feature: &str) -> bool {
+        self.unstable_features.get(feature).copied().unwrap_or(false)
+    }
+}
+
+#[cfg(feature = "with-ruma-api-macros")]
+impl From<get_supported_versions::Response> for SupportedVersions {
+    fn from(response: get_supported_versions::Response) -> Self {
+        let mut supported = SupportedVersions {
+            unstable_features: response.unstable_features,
+            ..Default::default()
+        };
+
+        for version in response.versions {
+            match MatrixVersion::try_from(version.as_str()) {
+                Ok(version) => {
+                    supported.versions.insert(version);
+                }
+                Err(UnknownVersionError) => supported.unknown_versions.push(version),
+            }
+        }
+
+        supported
+    }
+}
+
+/// The status of an endpoint in a version of the spec, see
+/// [`Metadata::status_for`](../struct.Metadata.html#method.status_for).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum EndpointStatus {
+    /// The endpoint can be used.
+    Supported,
+
+    /// The endpoint still works, but will be removed in a later version.
+    Deprecated,
+
+    /// The endpoint was removed.
+    Removed,
+}
+
+impl EndpointStatus {
+    /// The status in lowercase, e.g. `deprecated`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EndpointStatus::Supported => "supported",
+            EndpointStatus::Deprecated => "deprecated",
+            EndpointStatus::Removed => "removed",
+        }
+    }
+}
+
+impl Display for EndpointStatus {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Logs a warning with the `tracing` feature if the endpoint with the given metadata is deprecated
+/// or removed in the given version.
+#[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+pub(crate) fn warn_if_outdated(metadata: &Metadata, version: MatrixVersion) {
+    #[cfg(feature = "tracing")]
+    {
+        let status = metadata.status_for(version);
+        if status != EndpointStatus::Supported {
+            tracing::warn!(
+                endpoint = metadata.name(),
+                version = version.as_str(),
+                status = status.as_str(),
+                "the endpoint `{}` is {} in Matrix {}",
+                metadata.name(),
+                status,
+                version
+            );
+        }
+    }
+}
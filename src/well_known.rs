@@ -0,0 +1,148 @@
+//! Endpoints for discovering the servers behind a server name through `.well-known`, and
+//! [`discover_homeserver`](fn.discover_homeserver.html) to use them.
+//!
+//! Unlike other endpoints, these are defined in ruma-api itself since they are needed to find out
+//! where to send the requests for all other endpoints.
+
+use http::{uri::Authority, StatusCode, Uri};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    client::{send_with_options, ResponseFuture, SendError, Transport},
+    error::FromHttpResponseError,
+    ruma_api,
+};
+
+ruma_api! {
+    pub mod client;
+
+    metadata {
+        description: "Get discovery information about the domain for clients.",
+        method: GET,
+        name: "client_well_known",
+        path: "/.well-known/matrix/client",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {
+        /// Information about the homeserver to connect to.
+        #[serde(rename = "m.homeserver")]
+        pub homeserver: HomeserverInfo,
+
+        /// Information about the identity server to connect to.
+        #[serde(rename = "m.identity_server", default, skip_serializing_if = "Option::is_none")]
+        pub identity_server: Option<IdentityServerInfo>,
+    }
+}
+
+ruma_api! {
+    pub mod server;
+
+    metadata {
+        description: "Get the server to delegate server-server communication to.",
+        method: GET,
+        name: "server_well_known",
+        path: "/.well-known/matrix/server",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {
+        /// The server name to delegate server-server communication to, with an optional port.
+        #[serde(rename = "m.server")]
+        pub server: String,
+    }
+}
+
+/// Information about a homeserver, used in the `.well-known` response for clients.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct HomeserverInfo {
+    /// The base URL of the homeserver's client-server API.
+    pub base_url: String,
+}
+
+/// Information about an identity server, used in the `.well-known` response for clients.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct IdentityServerInfo {
+    /// The base URL of the identity server.
+    pub base_url: String,
+}
+
+/// The servers found by [`discover_homeserver`](fn.discover_homeserver.html).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Discovery {
+    /// The base URL of the homeserver's client-server API, without a trailing slash.
+    pub homeserver_url: String,
+
+    /// The base URL of the identity server, if one is advertised.
+    pub identity_server_url: Option<String>,
+
+    /// The host and port to use for the server-server API.
+    pub federation_server: String,
+}
+
+/// Discovers the servers for the given server name using both `.well-known` endpoints.
+///
+/// If there is no `.well-known` file for clients (the server responds with 404), the homeserver is
+/// assumed to be at `https://{server_name}`. If the `.well-known` file for servers can't be
+/// retrieved for any reason other than the transport failing, the server name is used for
+/// federation, with the default port 8448 unless it has an explicit port. Other errors are
+/// returned as is.
+///
+/// The requests are converted with the transport's
+/// [`request_options`](../client/trait.Transport.html#method.request_options), with `base_url`
+/// set to `https://{server_name}`.
+pub fn discover_homeserver<'a, T>(
+    transport: &'a T,
+    server_name: &'a Authority,
+) -> ResponseFuture<'a, Discovery, T::Error>
+where
+    T: Transport + ?Sized,
+    T::Error: Send,
+{
+    let mut options = transport.request_options();
+    options.base_url = Some(
+        Uri::builder()
+            .scheme("https")
+            .authority(server_name.clone())
+            .path_and_query("/")
+            .build()
+            .expect("https URI with a valid authority to be valid"),
+    );
+
+    let client_response = send_with_options(transport, client::Request, &options);
+    let server_response = send_with_options(transport, server::Request, &options);
+
+    Box::pin(async move {
+        let (homeserver_url, identity_server_url) = match client_response.await {
+            Ok(response) => (
+                response.homeserver.base_url.trim_end_matches('/').to_owned(),
+                response.identity_server.map(|info| info.base_url),
+            ),
+            Err(SendError::FromHttpResponse(FromHttpResponseError::Http(err)))
+                if err.status() == StatusCode::NOT_FOUND =>
+            {
+                (format!("https://{}", server_name), None)
+            }
+            Err(SendError::FromHttpResponse(FromHttpResponseError::NonMatrixError {
+                status: StatusCode::NOT_FOUND,
+                ..
+            })) => (format!("https://{}", server_name), None),
+            Err(err) => return Err(err),
+        };
+
+        let federation_server = match server_response.await {
+            Ok(response) => response.server,
+            Err(SendError::Transport(err)) => return Err(SendError::Transport(err)),
+            Err(_) if server_name.port().is_some() => server_name.to_string(),
+            Err(_) => format!("{}:8448", server_name.host()),
+        };
+
+        Ok(Discovery { homeserver_url, identity_server_url, federation_server })
+    })
+}
@@ -0,0 +1,74 @@
+use std::convert::TryFrom;
+
+use ruma_api::{canonical_json, exports::http, ruma_api};
+use serde_json::json;
+
+ruma_api! {
+    metadata {
+        description: "Send a value over federation.",
+        method: PUT,
+        name: "send_value",
+        path: "/_matrix/federation/value",
+        rate_limited: false,
+        requires_authentication: true,
+        canonical_json: true,
+    }
+
+    request {
+        pub content: serde_json::Value,
+    }
+
+    response {
+        pub depth: serde_json::Value,
+    }
+}
+
+#[test]
+fn canonical_request() {
+    let request = Request { content: json!({ "depth": 12, "name": "x" }) };
+    let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+    assert_eq!(http_request.body(), br#"{"content":{"depth":12,"name":"x"}}"#);
+}
+
+#[test]
+fn float_in_request() {
+    let request = Request { content: json!({ "events": [{ "ratio": 0.5 }] }) };
+    let err = http::Request::<Vec<u8>>::try_from(request).unwrap_err();
+
+    let non_canonical = err.non_canonical_json().unwrap();
+    assert_eq!(non_canonical.path(), "content.events[0].ratio");
+    assert!(non_canonical.is_float());
+    assert_eq!(
+        err.to_string(),
+        "The body is not canonical JSON: floats are not allowed at `content.events[0].ratio`: `0.5`"
+    );
+}
+
+#[test]
+fn integer_out_of_range_in_response() {
+    let response = Response { depth: json!(canonical_json::MAX_SAFE_INTEGER) };
+    assert!(http::Response::<Vec<u8>>::try_from(response).is_ok());
+
+    let response = Response { depth: json!(canonical_json::MAX_SAFE_INTEGER + 1) };
+    let err = http::Response::<Vec<u8>>::try_from(response).unwrap_err();
+
+    let non_canonical = err.non_canonical_json().unwrap();
+    assert_eq!(non_canonical.path(), "depth");
+    assert!(!non_canonical.is_float());
+}
+
+#[test]
+fn validate() {
+    assert!(canonical_json::validate(&json!({ "a": [1, -2, "3.5"], "b": null })).is_ok());
+    assert!(canonical_json::validate(&json!(canonical_json::MIN_SAFE_INTEGER)).is_ok());
+
+    let err = canonical_json::validate(&json!(canonical_json::MIN_SAFE_INTEGER - 1)).unwrap_err();
+    assert_eq!(err.path(), "");
+    assert_eq!(err.to_string(), "integer out of range: `-9007199254740992`");
+
+    let err = canonical_json::validate(&json!(1u64 << 63)).unwrap_err();
+    assert!(!err.is_float());
+
+    let err = canonical_json::validate(&json!([[1], [2, 1.0]])).unwrap_err();
+    assert_eq!(err.path(), "[1][1]");
+}
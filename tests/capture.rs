@@ -0,0 +1,122 @@
+#![cfg(feature = "capture")]
+
+use std::convert::TryFrom;
+
+use ruma_api::{
+    capture::{Capture, CaptureEntry, ReplayError},
+    exports::http,
+    options::RequestOptions,
+    ruma_api, Endpoint,
+};
+
+ruma_api! {
+    mod set_topic;
+
+    metadata {
+        description: "Set the topic of a room.",
+        method: PUT,
+        name: "set_topic",
+        path: "/_matrix/client/r0/rooms/:room_id/state/m.room.topic",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+        pub topic: String,
+    }
+
+    response {
+        pub event_id: String,
+    }
+}
+
+ruma_api! {
+    mod upload;
+
+    metadata {
+        description: "Upload a file.",
+        method: POST,
+        name: "upload",
+        path: "/_matrix/media/r0/upload",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(raw_body)]
+        pub file: Vec<u8>,
+    }
+
+    response {
+        pub content_uri: String,
+    }
+}
+
+fn topic_entry() -> CaptureEntry {
+    let mut options = RequestOptions::default();
+    options.base_url = Some("https://matrix.example.org".parse().unwrap());
+    options.access_token = Some("secret".to_owned());
+
+    let request = set_topic::Request { room_id: "!room:example.org".into(), topic: "Hi".into() };
+    let http_request = request.try_into_http_request_with(&options).unwrap();
+    let http_response =
+        http::Response::try_from(set_topic::Response { event_id: "$event".into() }).unwrap();
+
+    CaptureEntry::new::<set_topic::Request>(&http_request, &http_response)
+}
+
+#[test]
+fn round_trip_and_replay() {
+    let capture = Capture { entries: vec![topic_entry()] };
+    let json = serde_json::to_string(&capture).unwrap();
+    assert!(!json.contains("secret"));
+
+    let capture: Capture = serde_json::from_str(&json).unwrap();
+    let entry = capture.entries_for::<set_topic::Request>().next().unwrap();
+    assert_eq!(entry.endpoint(), "set_topic");
+    assert_eq!(entry.request_json().unwrap()["topic"], "Hi");
+    assert_eq!(entry.response_json().unwrap()["event_id"], "$event");
+
+    let request = entry.replay_request::<set_topic::Request>().unwrap();
+    assert_eq!(request.room_id, "!room:example.org");
+    assert_eq!(request.topic, "Hi");
+
+    let response = entry.replay_response::<set_topic::Request>().unwrap();
+    assert_eq!(response.event_id, "$event");
+}
+
+#[test]
+fn credentials_are_redacted() {
+    let http_request = topic_entry().to_http_request().unwrap();
+    assert_eq!(http_request.headers()[http::header::AUTHORIZATION], "redacted");
+    assert_eq!(http_request.uri().host(), Some("matrix.example.org"));
+}
+
+#[test]
+fn binary_bodies() {
+    let request = upload::Request { file: vec![0xff, 0x00, 0xfe] };
+    let http_request = http::Request::try_from(request).unwrap();
+    let http_response =
+        http::Response::try_from(upload::Response { content_uri: "mxc://example.org/abc".into() })
+            .unwrap();
+
+    let entry = CaptureEntry::new::<upload::Request>(&http_request, &http_response);
+    let json = serde_json::to_value(&entry).unwrap();
+    assert_eq!(json["request"]["body"], serde_json::json!({ "bytes": [255, 0, 254] }));
+
+    let entry: CaptureEntry = serde_json::from_value(json).unwrap();
+    let request = entry.replay_request::<upload::Request>().unwrap();
+    assert_eq!(request.file, [0xff, 0x00, 0xfe]);
+}
+
+#[test]
+fn other_endpoints_are_rejected() {
+    match topic_entry().replay_response::<upload::Request>() {
+        Err(ReplayError::EndpointMismatch { expected: "upload", found }) => {
+            assert_eq!(found, "set_topic");
+        }
+        other => panic!("unexpected result: {:?}", other.map(|_| ())),
+    }
+}
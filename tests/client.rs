@@ -0,0 +1,263 @@
+mod common;
+
+use std::{
+    io::Read,
+    sync::{Arc, Mutex},
+};
+
+use ruma_api::{
+    client::{
+        send, send_customized, send_with_progress, HttpResponseFuture, ProgressBody, SendError,
+        Transport, UploadProgress,
+    },
+    exports::http,
+    options::RequestOptions,
+    ruma_api, ruma_api_group, Direction, Endpoint,
+};
+
+use self::common::block_on;
+
+ruma_api! {
+    pub mod get_name;
+
+    metadata {
+        description: "Get a name.",
+        method: GET,
+        name: "get_name",
+        path: "/_matrix/name/:user",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub user: String,
+    }
+
+    response {
+        pub name: String,
+    }
+}
+
+ruma_api! {
+    pub mod upload;
+
+    metadata {
+        description: "Upload a file.",
+        method: POST,
+        name: "upload",
+        path: "/_matrix/upload",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(raw_body)]
+        pub file: Vec<u8>,
+    }
+
+    response {
+        pub name: String,
+    }
+}
+
+ruma_api! {
+    pub mod push_transaction;
+
+    metadata {
+        description: "Push events to an application service.",
+        method: PUT,
+        name: "push_transaction",
+        path: "/_matrix/app/v1/transactions/:txn_id",
+        rate_limited: false,
+        requires_authentication: true,
+        direction: ServerToClient,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub txn_id: String,
+        pub events: Vec<serde_json::Value>,
+    }
+
+    response {}
+}
+
+ruma_api_group! {
+    /// Endpoints for names.
+    pub trait NameApi {
+        get_name,
+        /// Same endpoint with a different method name.
+        fetch_name: self::get_name,
+    }
+}
+
+/// A transport that answers every request with a fixed status and body.
+struct MockTransport(u16, &'static str);
+
+impl Transport for MockTransport {
+    type Error = std::convert::Infallible;
+
+    fn request_options(&self) -> RequestOptions {
+        let mut options = RequestOptions::default();
+        options.user_agent = Some("ruma-api-test/1.0".to_owned());
+        options
+    }
+
+    fn send_http(&self, request: http::Request<Vec<u8>>) -> HttpResponseFuture<'_, Self::Error> {
+        assert_eq!(request.uri().path(), "/_matrix/name/alice");
+        assert_eq!(request.headers()[http::header::USER_AGENT], "ruma-api-test/1.0");
+
+        let response =
+            http::Response::builder().status(self.0).body(self.1.as_bytes().to_vec()).unwrap();
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// A transport that answers every request with the request's query string as the name.
+struct QueryEchoTransport;
+
+impl Transport for QueryEchoTransport {
+    type Error = std::convert::Infallible;
+
+    fn send_http(&self, request: http::Request<Vec<u8>>) -> HttpResponseFuture<'_, Self::Error> {
+        let body = serde_json::json!({ "name": request.uri().query() });
+        let response = http::Response::new(serde_json::to_vec(&body).unwrap());
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// A homeserver's transport to an application service, which expects the `hs_token` in the query
+/// string.
+struct AppserviceTransport;
+
+impl Transport for AppserviceTransport {
+    type Error = std::convert::Infallible;
+
+    fn request_options(&self) -> RequestOptions {
+        let mut options = RequestOptions::default();
+        options.access_token = Some("hs_token".to_owned());
+        options
+    }
+
+    fn send_http(&self, request: http::Request<Vec<u8>>) -> HttpResponseFuture<'_, Self::Error> {
+        assert_eq!(request.uri().query(), Some("access_token=hs_token"));
+        assert!(!request.headers().contains_key(http::header::AUTHORIZATION));
+
+        let response = http::Response::new(b"{}".to_vec());
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+/// A transport that sends request bodies in chunks of four bytes and answers with the number of
+/// chunks as the name.
+struct ChunkingTransport;
+
+impl Transport for ChunkingTransport {
+    type Error = std::convert::Infallible;
+
+    fn send_http(&self, _: http::Request<Vec<u8>>) -> HttpResponseFuture<'_, Self::Error> {
+        unreachable!("only send_http_with_progress is used")
+    }
+
+    fn send_http_with_progress(
+        &self,
+        request: http::Request<ProgressBody>,
+    ) -> HttpResponseFuture<'_, Self::Error> {
+        let chunks = request.into_body().with_chunk_size(4).count();
+        let body = serde_json::json!({ "name": chunks.to_string() });
+        let response = http::Response::new(serde_json::to_vec(&body).unwrap());
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[test]
+fn group_methods_send_requests() {
+    let transport = MockTransport(200, r#"{"name":"Alice"}"#);
+
+    let response = block_on(transport.get_name(get_name::Request { user: "alice".into() }));
+    assert_eq!(response.unwrap().name, "Alice");
+
+    let response = block_on(transport.fetch_name(get_name::Request { user: "alice".into() }));
+    assert_eq!(response.unwrap().name, "Alice");
+}
+
+#[test]
+fn group_methods_report_errors() {
+    let transport = MockTransport(404, r#"{"errcode":"M_NOT_FOUND"}"#);
+
+    match block_on(transport.get_name(get_name::Request { user: "alice".into() })) {
+        Err(SendError::FromHttpResponse(_)) => {}
+        other => panic!("unexpected result: {:?}", other.map(|res| res.name)),
+    }
+}
+
+#[test]
+fn send_customized_changes_the_request() {
+    let request = get_name::Request { user: "alice".into() };
+    let response = block_on(send_customized(&QueryEchoTransport, request, |req| {
+        *req.uri_mut() = format!("{}?user_id=@bot:example.org", req.uri()).parse().unwrap();
+    }));
+
+    assert_eq!(response.unwrap().name, "user_id=@bot:example.org");
+}
+
+#[test]
+fn server_to_client_requests_send_token_in_query() {
+    assert_eq!(push_transaction::Request::METADATA.direction(), Direction::ServerToClient);
+    assert_eq!(get_name::Request::METADATA.direction(), Direction::ClientToServer);
+
+    let request = push_transaction::Request { txn_id: "1".into(), events: Vec::new() };
+    assert!(block_on(send(&AppserviceTransport, request)).is_ok());
+}
+
+/// Returns a progress callback and the progress it recorded.
+fn record_progress(
+) -> (impl FnMut(UploadProgress) + Send + 'static, Arc<Mutex<Vec<UploadProgress>>>) {
+    let progress = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&progress);
+    (move |p| recorded.lock().unwrap().push(p), progress)
+}
+
+#[test]
+fn send_with_progress_reports_chunks() {
+    let (on_progress, progress) = record_progress();
+    let request = upload::Request { file: b"0123456789".to_vec() };
+    let response = block_on(send_with_progress(&ChunkingTransport, request, on_progress));
+
+    assert_eq!(response.unwrap().name, "3");
+    assert_eq!(
+        *progress.lock().unwrap(),
+        [
+            UploadProgress { sent: 4, total: 10 },
+            UploadProgress { sent: 8, total: 10 },
+            UploadProgress { sent: 10, total: 10 },
+        ]
+    );
+}
+
+#[test]
+fn send_with_progress_reports_whole_body_by_default() {
+    let (on_progress, progress) = record_progress();
+    let request = upload::Request { file: b"0123456789".to_vec() };
+    // Only the progress matters, `QueryEchoTransport`'s response lacks a name for this request.
+    let _ = block_on(send_with_progress(&QueryEchoTransport, request, on_progress));
+
+    assert_eq!(*progress.lock().unwrap(), [UploadProgress { sent: 10, total: 10 }]);
+}
+
+#[test]
+fn progress_body_read() {
+    let (on_progress, progress) = record_progress();
+    let mut body = ProgressBody::new(b"0123456789".to_vec(), on_progress).with_chunk_size(6);
+
+    let mut buf = [0; 4];
+    assert_eq!(body.read(&mut buf).unwrap(), 4);
+    assert_eq!(&buf, b"0123");
+
+    assert_eq!(body.into_bytes(), b"456789");
+    assert_eq!(
+        *progress.lock().unwrap(),
+        [UploadProgress { sent: 4, total: 10 }, UploadProgress { sent: 10, total: 10 }]
+    );
+}
@@ -0,0 +1,109 @@
+use std::convert::TryFrom;
+
+use ruma_api::{
+    conditional::{EntityTag, IfMatch},
+    exports::http,
+    ruma_api,
+};
+
+ruma_api! {
+    metadata {
+        description: "Replace some account data.",
+        method: PUT,
+        name: "set_account_data",
+        path: "/_matrix/account_data/:data_type",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub data_type: String,
+        #[ruma_api(header = IF_MATCH)]
+        pub if_match: Option<IfMatch>,
+        pub content: String,
+    }
+
+    response {
+        #[ruma_api(header = ETAG)]
+        pub etag: Option<EntityTag>,
+    }
+}
+
+fn strong(tag: &str) -> EntityTag {
+    EntityTag::strong(tag).unwrap()
+}
+
+#[test]
+fn parse_entity_tag() {
+    let tag = EntityTag::try_from(r#""xyzzy""#).unwrap();
+    assert_eq!(tag, strong("xyzzy"));
+    assert_eq!(tag.to_string(), r#""xyzzy""#);
+
+    let tag = EntityTag::try_from(r#"W/"a,b""#).unwrap();
+    assert!(tag.is_weak());
+    assert_eq!(tag.tag(), "a,b");
+    assert_eq!(tag.to_string(), r#"W/"a,b""#);
+
+    assert!(EntityTag::try_from("xyzzy").is_err());
+    assert!(EntityTag::try_from(r#""unterminated"#).is_err());
+    assert!(EntityTag::try_from(r#""a" "b""#).is_err());
+    assert!(EntityTag::strong("with \"quotes\"").is_err());
+    assert!(EntityTag::strong("with space").is_err());
+}
+
+#[test]
+fn compare_entity_tags() {
+    let weak = EntityTag::weak("1").unwrap();
+
+    assert!(strong("1").strong_eq(&strong("1")));
+    assert!(!strong("1").strong_eq(&weak));
+    assert!(strong("1").weak_eq(&weak));
+    assert!(!strong("1").weak_eq(&strong("2")));
+}
+
+#[test]
+fn parse_if_match() {
+    assert_eq!(IfMatch::try_from(" * "), Ok(IfMatch::Any));
+    assert_eq!(
+        IfMatch::try_from(r#""a", W/"b,c" ,"d""#),
+        Ok(IfMatch::Tags(vec![strong("a"), EntityTag::weak("b,c").unwrap(), strong("d")]))
+    );
+    assert_eq!(IfMatch::Tags(vec![strong("a"), strong("b")]).to_string(), r#""a", "b""#);
+
+    assert!(IfMatch::try_from("").is_err());
+    assert!(IfMatch::try_from(r#""a" "b""#).is_err());
+    assert!(IfMatch::try_from(r#""a","#).is_err());
+}
+
+#[test]
+fn if_match_matches() {
+    assert!(IfMatch::Any.matches(Some(&strong("1"))));
+    assert!(!IfMatch::Any.matches(None));
+
+    let if_match = IfMatch::Tags(vec![strong("1"), strong("2")]);
+    assert!(if_match.matches(Some(&strong("2"))));
+    assert!(!if_match.matches(Some(&strong("3"))));
+    assert!(!if_match.matches(Some(&EntityTag::weak("1").unwrap())));
+}
+
+#[test]
+fn conditional_write() {
+    let request = Request {
+        data_type: "m.test".into(),
+        if_match: Some(strong("1").into()),
+        content: "new".into(),
+    };
+    let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+    assert_eq!(http_request.headers()[http::header::IF_MATCH], r#""1""#);
+
+    let request = Request::try_from(http_request).unwrap();
+    assert!(request.if_match.unwrap().matches(Some(&strong("1"))));
+
+    let response = Response { etag: Some(strong("2")) };
+    let http_response = http::Response::<Vec<u8>>::try_from(response).unwrap();
+    assert_eq!(http_response.headers()[http::header::ETAG], r#""2""#);
+
+    let response = Response::try_from(http_response).unwrap();
+    assert_eq!(response.etag, Some(strong("2")));
+}
@@ -1,4 +1,4 @@
-use ruma_api::ruma_api;
+use ruma_api::{exports::http, ruma_api};
 use ruma_identifiers::UserId;
 
 ruma_api! {
@@ -59,3 +59,3358 @@ fn request_serde() -> Result<(), Box<dyn std::error::Error + 'static>> {
 
     Ok(())
 }
+
+#[test]
+fn invalid_path_identifier() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use std::convert::TryFrom;
+
+    let req = Request {
+        hello: "hi".to_owned(),
+        world: "test".to_owned(),
+        q1: "q1".to_owned(),
+        q2: 1,
+        bar: "bar".to_owned(),
+        baz: UserId::try_from("@bazme:ruma.io")?,
+    };
+
+    let mut http_req = http::Request::<Vec<u8>>::try_from(req)?;
+    *http_req.uri_mut() =
+        "http://invalid-host-please-change/_matrix/foo/bar/bazme?q1=q1&q2=1".parse().unwrap();
+
+    let err = Request::try_from(http_req).unwrap_err();
+    assert!(err.to_string().starts_with("deserialization failed: invalid path parameter"));
+
+    match err {
+        ruma_api::error::FromHttpRequestError::Deserialization(err) => {
+            let invalid = err.invalid_identifier().expect("expected an invalid identifier");
+            assert_eq!(invalid.kind(), ruma_api::error::IdentifierKind::UserId);
+            assert_eq!(err.method(), http::Method::POST);
+            assert_eq!(err.uri().path(), "/_matrix/foo/bar/bazme");
+            assert_eq!(err.headers()[http::header::CONTENT_TYPE], "test");
+            assert_eq!(err.body(), br#"{"hello":"hi"}"#);
+        }
+        err => panic!("unexpected error: {}", err),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn truncated_path_is_an_error() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use std::convert::TryFrom;
+
+    let req = Request {
+        hello: "hi".to_owned(),
+        world: "test".to_owned(),
+        q1: "q1".to_owned(),
+        q2: 1,
+        bar: "bar".to_owned(),
+        baz: UserId::try_from("@bazme:ruma.io")?,
+    };
+
+    let mut http_req = http::Request::<Vec<u8>>::try_from(req)?;
+    *http_req.uri_mut() =
+        "http://invalid-host-please-change/_matrix/foo/bar?q1=q1&q2=1".parse().unwrap();
+
+    let err = Request::try_from(http_req).unwrap_err();
+    assert!(err.to_string().starts_with("deserialization failed: invalid path parameter"));
+
+    Ok(())
+}
+
+#[test]
+fn dot_segment_path_field_is_encoded() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use std::convert::TryFrom;
+
+    let req = Request {
+        hello: "hi".to_owned(),
+        world: "test".to_owned(),
+        q1: "q1".to_owned(),
+        q2: 1,
+        bar: "..".to_owned(),
+        baz: UserId::try_from("@bazme:ruma.io")?,
+    };
+
+    let http_req = http::Request::<Vec<u8>>::try_from(req)?;
+    assert_eq!(http_req.uri().path(), "/_matrix/foo/%2E%2E/@bazme:ruma.io");
+
+    let req = Request::try_from(http_req)?;
+    assert_eq!(req.bar, "..");
+
+    Ok(())
+}
+
+mod two_phase {
+    use std::convert::TryFrom;
+
+    use ruma_api::exports::http;
+    use ruma_identifiers::UserId;
+
+    use super::Request;
+
+    fn request() -> Request {
+        Request {
+            hello: "hi".to_owned(),
+            world: "test".to_owned(),
+            q1: "q1".to_owned(),
+            q2: 1,
+            bar: "bar".to_owned(),
+            baz: UserId::try_from("@bazme:ruma.io").unwrap(),
+        }
+    }
+
+    #[test]
+    fn matches_path() {
+        assert!(Request::matches_path("/_matrix/foo/bar/@bazme:ruma.io"));
+        assert!(!Request::matches_path("/_matrix/foo/bar"));
+        assert!(!Request::matches_path("/_matrix/foo/bar/"));
+        assert!(!Request::matches_path("/_matrix/foo/bar/baz/qux"));
+        assert!(!Request::matches_path("/_matrix/bar/bar/baz"));
+    }
+
+    #[test]
+    fn parts_then_body() {
+        let (parts, body) = http::Request::<Vec<u8>>::try_from(request()).unwrap().into_parts();
+
+        let partial = Request::try_from_parts(parts).unwrap();
+        assert_eq!(partial.parts().uri.path(), "/_matrix/foo/bar/@bazme:ruma.io");
+
+        let req = partial.complete(body).unwrap();
+        assert_eq!(req.hello, "hi");
+        assert_eq!(req.baz, request().baz);
+    }
+
+    #[test]
+    fn invalid_parts_are_rejected_without_body() {
+        let (mut parts, _) = http::Request::<Vec<u8>>::try_from(request()).unwrap().into_parts();
+        parts.headers.remove(http::header::CONTENT_TYPE);
+        assert!(Request::try_from_parts(parts).is_err());
+
+        let (mut parts, _) = http::Request::<Vec<u8>>::try_from(request()).unwrap().into_parts();
+        parts.uri = "/_matrix/foo/bar/bazme?q1=q1&q2=1".parse().unwrap();
+        assert!(Request::try_from_parts(parts).is_err());
+
+        let (mut parts, _) = http::Request::<Vec<u8>>::try_from(request()).unwrap().into_parts();
+        parts.uri = "/_matrix/foo/bar/@bazme:ruma.io?q1=q1&q2=x".parse().unwrap();
+        assert!(Request::try_from_parts(parts).is_err());
+    }
+
+    #[test]
+    fn invalid_body_fails_completion() {
+        let (mut parts, _) = http::Request::<Vec<u8>>::try_from(request()).unwrap().into_parts();
+        parts.headers.remove(http::header::CONTENT_LENGTH);
+        let partial = Request::try_from_parts(parts).unwrap();
+        assert!(partial.complete(b"{}".to_vec()).is_err());
+    }
+}
+
+mod renamed_field {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, ruma_api};
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "renamed_field",
+            path: "/_matrix/foo",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {
+            #[ruma_api(renamed_from = "chunk")]
+            pub events: Vec<String>,
+        }
+
+        /// The maximum number of events in a response.
+        pub const MAX_EVENTS: usize = 10;
+
+        impl Response {
+            /// Creates a new `Response` with the given events.
+            pub fn new(events: Vec<String>) -> Self {
+                Self { events }
+            }
+        }
+    }
+
+    #[test]
+    fn accepts_old_and_new_name() -> Result<(), Box<dyn std::error::Error + 'static>> {
+        for body in &[&br#"{"events":["a"]}"#[..], &br#"{"chunk":["a"]}"#[..]] {
+            let http_res = http::Response::builder().body(body.to_vec()).unwrap();
+            assert_eq!(Response::try_from(http_res)?.events, vec!["a".to_owned()]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn outgoing_name() -> Result<(), Box<dyn std::error::Error + 'static>> {
+        let http_res = http::Response::<Vec<u8>>::try_from(Response::new(vec![]))?;
+        assert_eq!(MAX_EVENTS, 10);
+
+        #[cfg(not(feature = "compat"))]
+        assert_eq!(http_res.body(), br#"{"events":[]}"#);
+        #[cfg(feature = "compat")]
+        assert_eq!(http_res.body(), br#"{"events":[],"chunk":[]}"#);
+
+        Ok(())
+    }
+
+    #[test]
+    fn prefers_new_name() -> Result<(), Box<dyn std::error::Error + 'static>> {
+        let body = br#"{"chunk":["old"],"events":["new"]}"#;
+        let http_res = http::Response::builder().body(body.to_vec()).unwrap();
+        assert_eq!(Response::try_from(http_res)?.events, vec!["new".to_owned()]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn roundtrip() -> Result<(), Box<dyn std::error::Error + 'static>> {
+        let events = vec!["a".to_owned(), "b".to_owned()];
+        let http_res = http::Response::<Vec<u8>>::try_from(Response::new(events.clone()))?;
+        assert_eq!(Response::try_from(http_res)?.events, events);
+
+        Ok(())
+    }
+}
+
+mod cfg_fields {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, ruma_api};
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "cfg_fields",
+            path: "/_matrix/foo",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            pub stable: String,
+            // `cfg(any())` is always false, `cfg(all())` is always true
+            #[cfg(any())]
+            pub unstable_body: String,
+            #[cfg(any())]
+            #[ruma_api(query)]
+            pub unstable_query: String,
+            #[cfg(all())]
+            #[ruma_api(query)]
+            pub enabled_query: String,
+            #[cfg(any())]
+            #[ruma_api(header = CONTENT_TYPE)]
+            pub unstable_header: String,
+        }
+
+        response {
+            #[cfg(any())]
+            pub unstable_body: String,
+            #[cfg(any())]
+            #[ruma_api(header = CONTENT_TYPE)]
+            pub unstable_header: String,
+            #[cfg(all())]
+            pub enabled_body: String,
+        }
+    }
+
+    #[test]
+    fn disabled_fields_are_removed() -> Result<(), Box<dyn std::error::Error + 'static>> {
+        let req = Request { stable: "s".to_owned(), enabled_query: "q".to_owned() };
+
+        let http_req = http::Request::<Vec<u8>>::try_from(req)?;
+        assert_eq!(http_req.uri().query(), Some("enabled_query=q"));
+        assert_eq!(http_req.body(), br#"{"stable":"s"}"#);
+        // Only the content type and length of the JSON body, not the disabled header field.
+        assert_eq!(http_req.headers().len(), 2);
+        assert_eq!(http_req.headers()[http::header::CONTENT_TYPE], "application/json");
+        assert_eq!(http_req.headers()[http::header::CONTENT_LENGTH], "14");
+
+        let req = Request::try_from(http_req)?;
+        assert_eq!(req.stable, "s");
+        assert_eq!(req.enabled_query, "q");
+
+        let http_res =
+            http::Response::<Vec<u8>>::try_from(Response { enabled_body: "b".to_owned() })?;
+        assert_eq!(http_res.body(), br#"{"enabled_body":"b"}"#);
+        assert_eq!(Response::try_from(http_res)?.enabled_body, "b");
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "compat")]
+mod compat {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, ruma_api};
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "compat",
+            path: "/_matrix/foo",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {
+            pub count: u32,
+            pub next_batch: Option<String>,
+            #[serde(default)]
+            pub list: Vec<String>,
+            #[serde(default, skip_serializing_if = "Option::is_none")]
+            pub limit: Option<u32>,
+        }
+    }
+
+    #[test]
+    fn relaxed_response_body() -> Result<(), Box<dyn std::error::Error + 'static>> {
+        let body = br#"{"count":"5","next_batch":123,"list":null,"limit":null}"#;
+        let res = Response::try_from(http::Response::builder().body(body.to_vec()).unwrap())?;
+
+        assert_eq!(res.count, 5);
+        assert_eq!(res.next_batch, None);
+        assert!(res.list.is_empty());
+        assert_eq!(res.limit, None);
+
+        let res = Response::try_from(
+            http::Response::builder().body(br#"{"count":1,"next_batch":"n"}"#.to_vec()).unwrap(),
+        )?;
+        assert_eq!(res.count, 1);
+        assert_eq!(res.next_batch, Some("n".to_owned()));
+
+        Ok(())
+    }
+}
+
+mod partial {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, ruma_api};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct Room {
+        pub name: String,
+    }
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "partial",
+            path: "/_matrix/foo",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {
+            pub next_batch: String,
+            #[ruma_api(partial)]
+            pub room: Room,
+        }
+    }
+
+    #[test]
+    fn invalid_field_is_reported_separately() -> Result<(), Box<dyn std::error::Error + 'static>> {
+        let body = br#"{"next_batch":"abc","room":{"name":5}}"#;
+        let res =
+            IncomingResponse::try_from(http::Response::builder().body(body.to_vec()).unwrap())?;
+
+        assert_eq!(res.next_batch, "abc");
+        let err = res.room.err().unwrap();
+        assert_eq!(err.json(), &serde_json::json!({ "name": 5 }));
+
+        let body = br#"{"next_batch":"abc","room":{"name":"r"}}"#;
+        let res =
+            IncomingResponse::try_from(http::Response::builder().body(body.to_vec()).unwrap())?;
+        assert_eq!(res.room.ok().unwrap().name, "r");
+
+        Ok(())
+    }
+}
+
+mod batch {
+    use std::convert::TryFrom;
+
+    use ruma_api::{batch, exports::http, ruma_api};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Debug, Deserialize, Serialize)]
+    pub struct Pdu {
+        pub event_id: String,
+    }
+
+    ruma_api! {
+        metadata {
+            description: "Send a transaction.",
+            method: PUT,
+            name: "send_transaction",
+            path: "/_matrix/federation/v1/send/:txn_id",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub txn_id: String,
+            pub origin: String,
+            #[ruma_api(batch)]
+            pub pdus: Vec<Pdu>,
+        }
+
+        response {}
+    }
+
+    #[test]
+    fn invalid_items_are_reported_with_their_position() {
+        let body = br#"{"origin":"example.org","pdus":[{"event_id":"$a"},{"event_id":5},{"event_id":"$c"}]}"#;
+        let http_request = http::Request::builder()
+            .method("PUT")
+            .uri("https://example.org/_matrix/federation/v1/send/1")
+            .body(body.to_vec())
+            .unwrap();
+        let req = IncomingRequest::try_from(http_request).unwrap();
+
+        assert_eq!(req.origin, "example.org");
+        assert_eq!(req.pdus.len(), 3);
+        let errors: Vec<_> = batch::errors(&req.pdus).map(|(i, err)| (i, err.json())).collect();
+        assert_eq!(errors, vec![(1, &serde_json::json!({ "event_id": 5 }))]);
+
+        let (pdus, errors) = batch::partition(req.pdus);
+        let event_ids: Vec<_> = pdus.iter().map(|pdu| pdu.event_id.as_str()).collect();
+        assert_eq!(event_ids, ["$a", "$c"]);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, 1);
+    }
+
+    #[test]
+    fn invalid_array_fails() {
+        let body = br#"{"origin":"example.org","pdus":{}}"#;
+        let http_request = http::Request::builder()
+            .method("PUT")
+            .uri("https://example.org/_matrix/federation/v1/send/1")
+            .body(body.to_vec())
+            .unwrap();
+        assert!(IncomingRequest::try_from(http_request).is_err());
+    }
+}
+
+#[test]
+fn metadata_eq_hash() {
+    use std::collections::HashSet;
+
+    use ruma_api::Endpoint;
+
+    let mut set = HashSet::new();
+    assert!(set.insert(Request::METADATA));
+    assert!(set.insert(renamed_field::Request::METADATA));
+    assert!(!set.insert(Request::METADATA));
+
+    assert_eq!(Request::METADATA, Request::METADATA);
+    assert_ne!(Request::METADATA, cfg_fields::Request::METADATA);
+}
+
+#[test]
+fn metadata_serde() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use ruma_api::{Endpoint, MetadataRef};
+
+    let json = serde_json::to_string(&Request::METADATA)?;
+    assert_eq!(
+        json,
+        r#"{"description":"Does something.","method":"POST","name":"my_endpoint","path":"/_matrix/foo/:bar/:baz","rate_limited":false,"requires_authentication":false,"direction":"client_to_server","server_kind":"homeserver"}"#
+    );
+
+    let metadata: MetadataRef<'_> = serde_json::from_str(&json)?;
+    assert_eq!(metadata, MetadataRef::from(&Request::METADATA));
+
+    Ok(())
+}
+
+#[test]
+fn metadata_ref_owned_strings() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use ruma_api::MetadataRef;
+    use serde::Deserialize;
+
+    let json = r#"{"description":"Does \"something\".","method":"GET","name":"my_endpoint","path":"/_matrix/foo","rate_limited":false,"requires_authentication":false}"#;
+
+    let metadata: MetadataRef<'_> = serde_json::from_str(json)?;
+    assert_eq!(metadata.description, r#"Does "something"."#);
+    assert_eq!(metadata.name, "my_endpoint");
+
+    // Deserializers that can't borrow from the input.
+    let value: serde_json::Value = serde_json::from_str(json)?;
+    assert_eq!(MetadataRef::deserialize(value)?, metadata);
+    let mut deserializer = serde_json::Deserializer::from_reader(json.as_bytes());
+    assert_eq!(MetadataRef::deserialize(&mut deserializer)?, metadata);
+
+    Ok(())
+}
+
+mod modules {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, ruma_api, Endpoint};
+
+    /// Used from within the generated modules.
+    type Name = String;
+
+    ruma_api! {
+        pub mod get_name;
+
+        metadata {
+            description: "Get a name.",
+            method: GET,
+            name: "get_name",
+            path: "/_matrix/name",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {
+            pub name: Name,
+        }
+    }
+
+    ruma_api! {
+        mod set_name;
+
+        metadata {
+            description: "Set a name.",
+            method: PUT,
+            name: "set_name",
+            path: "/_matrix/name",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {
+            pub name: Name,
+        }
+
+        response {}
+    }
+
+    pub use self::{
+        get_name::{Request as GetNameRequest, Response as GetNameResponse},
+        set_name::Request as SetNameRequest,
+    };
+
+    #[test]
+    fn endpoints_in_modules() -> Result<(), Box<dyn std::error::Error + 'static>> {
+        assert_eq!(GetNameRequest::METADATA.name(), "get_name");
+        assert_eq!(SetNameRequest::METADATA.name(), "set_name");
+
+        let http_req = http::Request::<Vec<u8>>::try_from(SetNameRequest { name: "n".into() })?;
+        assert_eq!(http_req.method(), http::Method::PUT);
+
+        let http_res = http::Response::<Vec<u8>>::try_from(GetNameResponse { name: "n".into() })?;
+        assert_eq!(GetNameResponse::try_from(http_res)?.name, "n");
+
+        Ok(())
+    }
+}
+
+mod stability {
+    use ruma_api::{
+        conversion::{TryFromHttpRequest, TryFromHttpResponse},
+        ruma_api, Outgoing, StableEndpoint, UnstableEndpoint,
+    };
+
+    ruma_api! {
+        mod get_knock_rule;
+
+        metadata {
+            description: "Get the knock rule of a room.",
+            method: GET,
+            name: "get_knock_rule",
+            path: "/_matrix/client/unstable/xyz.amorgan.knock/rule",
+            rate_limited: false,
+            requires_authentication: true,
+            unstable: true,
+        }
+
+        request {}
+
+        response {}
+    }
+
+    fn assert_stable<E>()
+    where
+        E: StableEndpoint,
+        <E as Outgoing>::Incoming: TryFromHttpRequest,
+        <E::Response as Outgoing>::Incoming: TryFromHttpResponse,
+    {
+    }
+
+    fn assert_unstable<E>()
+    where
+        E: UnstableEndpoint,
+        <E as Outgoing>::Incoming: TryFromHttpRequest,
+        <E::Response as Outgoing>::Incoming: TryFromHttpResponse,
+    {
+    }
+
+    #[test]
+    fn marker_traits() {
+        assert_stable::<super::Request>();
+        assert_unstable::<get_knock_rule::Request>();
+    }
+}
+
+mod request_content_type {
+    use std::convert::TryFrom;
+
+    use ruma_api::{error::FromHttpRequestError, exports::http, ruma_api};
+
+    ruma_api! {
+        metadata {
+            description: "Upload some JSON.",
+            method: POST,
+            name: "upload_json",
+            path: "/_matrix/json",
+            rate_limited: false,
+            requires_authentication: false,
+            request_content_type: "application/json",
+        }
+
+        request {
+            pub value: u32,
+        }
+
+        response {}
+    }
+
+    fn http_request(content_type: Option<&str>) -> http::Request<Vec<u8>> {
+        let mut builder = http::Request::builder().method("POST").uri("/_matrix/json");
+        if let Some(content_type) = content_type {
+            builder = builder.header(http::header::CONTENT_TYPE, content_type);
+        }
+        builder.body(br#"{"value":1}"#.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn set_on_outgoing_requests() {
+        let http_req = http::Request::<Vec<u8>>::try_from(Request { value: 1 }).unwrap();
+        assert_eq!(http_req.headers()[http::header::CONTENT_TYPE], "application/json");
+    }
+
+    #[test]
+    fn checked_on_incoming_requests() {
+        assert!(Request::try_from(http_request(Some("application/json"))).is_ok());
+        assert!(Request::try_from(http_request(Some("Application/JSON; charset=utf-8"))).is_ok());
+
+        match Request::try_from(http_request(Some("text/plain"))) {
+            Err(FromHttpRequestError::UnexpectedContentType { content_type, expected }) => {
+                assert_eq!(content_type.as_deref(), Some("text/plain"));
+                assert_eq!(expected, "application/json");
+            }
+            res => panic!("unexpected result: {:?}", res.map(|req| req.value)),
+        }
+
+        let err = Request::try_from(http_request(None)).unwrap_err();
+        assert_eq!(err.to_string(), "the request has no Content-Type, expected `application/json`");
+    }
+}
+
+mod decode_modes {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, ruma_api};
+
+    ruma_api! {
+        metadata {
+            description: "Does something with path segments that may not be UTF-8.",
+            method: GET,
+            name: "decode_modes",
+            path: "/_matrix/decode/:lossy/:strict/:raw",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub lossy: String,
+            #[ruma_api(path, decode = STRICT)]
+            pub strict: String,
+            #[ruma_api(path, decode = RAW)]
+            pub raw: Vec<u8>,
+            #[ruma_api(query, decode = STRICT)]
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub q: Option<String>,
+        }
+
+        response {}
+    }
+
+    fn http_request(path_and_query: &str) -> http::Request<Vec<u8>> {
+        http::Request::builder().uri(path_and_query).body(Vec::new()).unwrap()
+    }
+
+    #[test]
+    fn raw_roundtrip() {
+        let request = Request {
+            lossy: "a".into(),
+            strict: "b".into(),
+            raw: vec![0xff, b'/', b'c'],
+            q: Some("ü".into()),
+        };
+        let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+        assert_eq!(http_request.uri().path(), "/_matrix/decode/a/b/%FF%2Fc");
+
+        let request = Request::try_from(http_request).unwrap();
+        assert_eq!(request.raw, [0xff, b'/', b'c']);
+        assert_eq!(request.q.unwrap(), "ü");
+    }
+
+    #[test]
+    fn lossy_replaces_invalid_utf8() {
+        let request = Request::try_from(http_request("/_matrix/decode/a%FF/b/c")).unwrap();
+        assert_eq!(request.lossy, "a\u{fffd}");
+    }
+
+    #[test]
+    fn strict_rejects_invalid_utf8() {
+        assert!(Request::try_from(http_request("/_matrix/decode/a/b%FF/c")).is_err());
+        assert!(Request::try_from(http_request("/_matrix/decode/a/b/c?q=%FF")).is_err());
+        assert!(Request::try_from(http_request("/_matrix/decode/a/b/c?other=%FF")).is_ok());
+    }
+
+    #[test]
+    fn malformed_query_string() {
+        use ruma_api::error::{FromHttpRequestError, InvalidQueryString};
+
+        let query_error = |query: &str| {
+            let uri = format!("/_matrix/decode/a/b/c?{}", query);
+            match Request::try_from(http_request(&uri)) {
+                Err(FromHttpRequestError::Deserialization(err)) => {
+                    err.invalid_query_string().cloned()
+                }
+                _ => None,
+            }
+        };
+
+        assert_eq!(
+            query_error("q=%zz"),
+            Some(InvalidQueryString::InvalidPercentEncoding { position: 2 })
+        );
+        assert_eq!(
+            query_error("q=a&other=%4"),
+            Some(InvalidQueryString::InvalidPercentEncoding { position: 10 })
+        );
+        assert_eq!(
+            query_error("q=a&%FF=1"),
+            Some(InvalidQueryString::InvalidUtf8Name { position: 4 })
+        );
+        assert_eq!(
+            query_error("q=%FF"),
+            Some(InvalidQueryString::InvalidUtf8Value { name: "q".to_owned() })
+        );
+    }
+}
+
+#[test]
+fn content_length_check() {
+    use std::convert::TryFrom;
+
+    use ruma_api::error::FromHttpRequestError;
+
+    use self::modules::SetNameRequest;
+
+    let http_req =
+        || http::Request::<Vec<u8>>::try_from(SetNameRequest { name: "n".to_owned() }).unwrap();
+    assert_eq!(http_req().headers()[http::header::CONTENT_LENGTH], "12");
+    assert!(SetNameRequest::try_from(http_req()).is_ok());
+
+    let mut truncated = http_req();
+    truncated.body_mut().pop();
+    match SetNameRequest::try_from(truncated) {
+        Err(FromHttpRequestError::ContentLengthMismatch { content_length, body_length }) => {
+            assert_eq!(content_length, "12");
+            assert_eq!(body_length, 11);
+        }
+        res => panic!("unexpected result: {:?}", res.map(|req| req.name)),
+    }
+}
+
+#[test]
+fn authority_check() {
+    use ruma_api::{error::FromHttpRequestError, options::IncomingRequestOptions, Endpoint};
+
+    fn http_request(host: Option<&str>) -> http::Request<Vec<u8>> {
+        let mut builder = http::Request::builder().uri("/_matrix/foo");
+        if let Some(host) = host {
+            builder = builder.header(http::header::HOST, host);
+        }
+
+        builder.body(Vec::new()).unwrap()
+    }
+
+    let mut options = IncomingRequestOptions::default();
+    assert!(
+        renamed_field::Request::try_from_http_request_with(http_request(None), &options).is_ok()
+    );
+
+    options.server_names = vec!["example.org".to_owned(), "matrix.example.org:8448".to_owned()];
+    for host in &["Example.org", "matrix.example.org:8448"] {
+        let http_req = http_request(Some(host));
+        assert!(renamed_field::Request::try_from_http_request_with(http_req, &options).is_ok());
+    }
+
+    let http_req = http::Request::builder()
+        .uri("https://other.example.org/_matrix/foo")
+        .body(Vec::new())
+        .unwrap();
+    match renamed_field::Request::try_from_http_request_with(http_req, &options) {
+        Err(FromHttpRequestError::UnexpectedAuthority { authority }) => {
+            assert_eq!(authority.as_deref(), Some("other.example.org"));
+        }
+        _ => panic!("expected an unexpected authority error"),
+    }
+
+    match renamed_field::Request::try_from_http_request_with(http_request(None), &options) {
+        Err(FromHttpRequestError::UnexpectedAuthority { authority: None }) => {}
+        _ => panic!("expected an unexpected authority error"),
+    }
+}
+
+#[test]
+fn request_timeout_option() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use std::{convert::TryFrom, time::Duration};
+
+    use ruma_api::{
+        options::{request_timeout, RequestOptions},
+        Endpoint,
+    };
+
+    let req = Request {
+        hello: "hi".to_owned(),
+        world: "test".to_owned(),
+        q1: "q1".to_owned(),
+        q2: 1,
+        bar: "bar".to_owned(),
+        baz: UserId::try_from("@bazme:ruma.io")?,
+    };
+
+    let mut options = RequestOptions::default();
+    options.timeout = Some(Duration::from_secs(30));
+
+    let http_req = req.clone().try_into_http_request_with(&options)?;
+    assert_eq!(http_req.headers()["x-request-timeout"], "30000");
+    assert_eq!(request_timeout(&http_req), Some(Duration::from_secs(30)));
+
+    let http_req = req.try_into_http_request_with(&RequestOptions::default())?;
+    assert_eq!(request_timeout(&http_req), None);
+
+    Ok(())
+}
+
+#[test]
+fn request_id_option() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use std::convert::TryFrom;
+
+    use ruma_api::{
+        error::FromHttpResponseError,
+        options::{request_id, RequestOptions},
+        Endpoint,
+    };
+
+    let mut options = RequestOptions::default();
+    options.request_id = Some("abc-123".to_owned());
+
+    let http_req = renamed_field::Request.try_into_http_request_with(&options)?;
+    assert_eq!(request_id(http_req.headers()), Some("abc-123"));
+
+    let http_req = renamed_field::Request.try_into_http_request_with(&RequestOptions::default())?;
+    #[cfg(not(feature = "request-id"))]
+    assert_eq!(request_id(http_req.headers()), None);
+    #[cfg(feature = "request-id")]
+    assert_eq!(request_id(http_req.headers()).map(str::len), Some(36));
+
+    let http_res = http::Response::builder()
+        .status(500)
+        .header("x-request-id", "abc-123")
+        .body(b"{}".to_vec())
+        .unwrap();
+    match renamed_field::Response::try_from(http_res) {
+        Err(FromHttpResponseError::Http(err)) => assert_eq!(err.request_id(), Some("abc-123")),
+        _ => panic!("expected a server error"),
+    }
+
+    Ok(())
+}
+
+#[test]
+fn non_json_error_response() {
+    use std::convert::TryFrom;
+
+    use ruma_api::error::FromHttpResponseError;
+
+    let http_res = http::Response::builder()
+        .status(502)
+        .header("content-type", "text/html; charset=utf-8")
+        .body(b"<html>Bad Gateway</html>".to_vec())
+        .unwrap();
+    match renamed_field::Response::try_from(http_res) {
+        Err(FromHttpResponseError::NonMatrixError { status, content_type, body }) => {
+            assert_eq!(status, http::StatusCode::BAD_GATEWAY);
+            assert_eq!(content_type, "text/html; charset=utf-8");
+            assert_eq!(body, b"<html>Bad Gateway</html>");
+        }
+        _ => panic!("expected a non-Matrix error"),
+    }
+
+    let http_res = http::Response::builder()
+        .status(400)
+        .header("content-type", "application/json")
+        .body(br#"{"errcode":"M_UNKNOWN"}"#.to_vec())
+        .unwrap();
+    match renamed_field::Response::try_from(http_res) {
+        Err(FromHttpResponseError::Http(err)) => assert_eq!(err.status().as_u16(), 400),
+        _ => panic!("expected a server error"),
+    }
+}
+
+#[test]
+fn base_url_and_host_options() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use ruma_api::{options::RequestOptions, Endpoint};
+
+    let mut options = RequestOptions::default();
+    options.base_url = Some("https://matrix.example.org:8448/prefix/".parse()?);
+
+    let http_req = renamed_field::Request.try_into_http_request_with(&options)?;
+    assert_eq!(http_req.uri(), "https://matrix.example.org:8448/prefix/_matrix/foo");
+    assert_eq!(http_req.headers()[http::header::HOST], "matrix.example.org:8448");
+
+    options.host = Some("example.org".to_owned());
+    let http_req = renamed_field::Request.try_into_http_request_with(&options)?;
+    assert_eq!(http_req.uri(), "https://matrix.example.org:8448/prefix/_matrix/foo");
+    assert_eq!(http_req.headers()[http::header::HOST], "example.org");
+
+    let http_req = renamed_field::Request.try_into_http_request_with(&RequestOptions::default())?;
+    assert!(!http_req.headers().contains_key(http::header::HOST));
+
+    Ok(())
+}
+
+#[test]
+fn connect_to_option() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use ruma_api::{
+        options::{tls_server_name, RequestOptions},
+        Endpoint,
+    };
+
+    let options =
+        RequestOptions::delegated(&"example.org:8448".parse()?, "192.0.2.1:8448".parse()?);
+    let http_req = renamed_field::Request.try_into_http_request_with(&options)?;
+    assert_eq!(http_req.uri(), "https://192.0.2.1:8448/_matrix/foo");
+    assert_eq!(http_req.headers()[http::header::HOST], "example.org:8448");
+    assert_eq!(tls_server_name(&http_req).as_deref(), Some("example.org"));
+
+    let mut options = RequestOptions::default();
+    options.base_url = Some("https://matrix.example.org/prefix".parse()?);
+    options.host = Some("example.org".to_owned());
+    options.connect_to = Some("[2001:db8::1]:443".parse()?);
+    let http_req = renamed_field::Request.try_into_http_request_with(&options)?;
+    assert_eq!(http_req.uri(), "https://[2001:db8::1]:443/prefix/_matrix/foo");
+    assert_eq!(http_req.headers()[http::header::HOST], "example.org");
+    assert_eq!(tls_server_name(&http_req).as_deref(), Some("example.org"));
+
+    let mut options = RequestOptions::default();
+    options.base_url = Some("https://[2001:db8::2]:8448".parse()?);
+    let http_req = renamed_field::Request.try_into_http_request_with(&options)?;
+    assert_eq!(tls_server_name(&http_req).as_deref(), Some("2001:db8::2"));
+
+    Ok(())
+}
+
+#[test]
+fn access_token_options() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use std::convert::TryFrom;
+
+    use ruma_api::{
+        options::{access_token, AccessTokenPlacement, RequestOptions},
+        Endpoint,
+    };
+
+    let mut options = RequestOptions::default();
+    options.access_token = Some("secret/token".to_owned());
+
+    let http_req = renamed_field::Request.try_into_http_request_with(&options)?;
+    assert_eq!(http_req.headers()[http::header::AUTHORIZATION], "Bearer secret/token");
+    assert_eq!(http_req.uri().query(), None);
+    assert_eq!(access_token(&http_req).as_deref(), Some("secret/token"));
+
+    options.access_token_placement = AccessTokenPlacement::Query;
+    let http_req = renamed_field::Request.try_into_http_request_with(&options)?;
+    assert!(!http_req.headers().contains_key(http::header::AUTHORIZATION));
+    assert_eq!(
+        http_req.uri().path_and_query().unwrap(),
+        "/_matrix/foo?access_token=secret%2Ftoken"
+    );
+    assert_eq!(access_token(&http_req).as_deref(), Some("secret/token"));
+
+    let req = Request {
+        hello: "hi".to_owned(),
+        world: "test".to_owned(),
+        q1: "one".to_owned(),
+        q2: 2,
+        bar: "bar".to_owned(),
+        baz: UserId::try_from("@bazme:ruma.io")?,
+    };
+    let http_req = req.try_into_http_request_with(&options)?;
+    assert_eq!(http_req.uri().query(), Some("q1=one&q2=2&access_token=secret%2Ftoken"));
+
+    // The access token doesn't interfere with parsing the query fields.
+    let req = Request::try_from(http_req)?;
+    assert_eq!(req.q2, 2);
+
+    Ok(())
+}
+
+#[test]
+fn customize_with_closure() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use ruma_api::Endpoint;
+
+    let http_req =
+        renamed_field::Request.try_into_http_request_with(|req: &mut http::Request<_>| {
+            req.headers_mut().insert("x-experimental", http::HeaderValue::from_static("1"));
+        })?;
+    assert_eq!(http_req.uri().path(), "/_matrix/foo");
+    assert_eq!(http_req.headers()["x-experimental"], "1");
+
+    Ok(())
+}
+
+mod generated_tests {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, ruma_api};
+
+    ruma_api! {
+        #[ruma_api(generate_tests)]
+        pub mod set_topic;
+
+        metadata {
+            description: "Set a topic.",
+            method: PUT,
+            name: "set_topic",
+            path: "/_matrix/topic/:room",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub room: String,
+            #[ruma_api(query)]
+            pub notify: bool,
+            pub topic: String,
+        }
+
+        response {
+            #[ruma_api(header = CONTENT_LANGUAGE)]
+            pub language: String,
+            pub previous_topic: Option<String>,
+        }
+
+        impl Default for Request {
+            fn default() -> Self {
+                Self { room: "room".into(), notify: false, topic: "Topic".into() }
+            }
+        }
+
+        impl Default for Response {
+            fn default() -> Self {
+                Self { language: "en".into(), previous_topic: None }
+            }
+        }
+    }
+
+    #[test]
+    fn malformed_variants() {
+        use ruma_api::{malformed::malformed_requests, Endpoint as _};
+
+        let http_request = http::Request::try_from(set_topic::Request::default()).unwrap();
+        let variants = malformed_requests(&set_topic::Request::METADATA, &http_request);
+        let descriptions: Vec<_> = variants.iter().map(|v| v.description()).collect();
+
+        // `{"topic":"Topic"}` is 17 bytes long.
+        for description in &[
+            "body truncated to 0 bytes",
+            "body truncated to 8 bytes",
+            "body truncated to 16 bytes",
+            "wrong Content-Type",
+            "missing Content-Type",
+            "path parameter `room` of 65536 bytes",
+            "path parameter `room` of `%C3%28`",
+            "query parameter `notify` of `%FF`",
+        ] {
+            assert!(descriptions.contains(description), "missing {}", description);
+        }
+
+        let truncated = variants[0].http_request();
+        assert_eq!(truncated.body().len(), 0);
+        assert_eq!(truncated.headers()[http::header::CONTENT_LENGTH], "0");
+
+        let invalid = variants
+            .into_iter()
+            .find(|v| v.description() == "query parameter `notify` of `%FF`")
+            .unwrap()
+            .into_http_request();
+        assert_eq!(invalid.uri().query(), Some("notify=%FF"));
+        assert!(set_topic::Request::try_from(invalid).is_err());
+    }
+
+    #[test]
+    fn response_into_writer() {
+        let response = set_topic::Response { language: "en".into(), previous_topic: None };
+        let expected = http::Response::<Vec<u8>>::try_from(response.clone()).unwrap();
+
+        let mut buffer = b"reused ".to_vec();
+        let http_response = response.try_into_http_response_with_writer(&mut buffer).unwrap();
+
+        // The length of the body written to the writer is up to the transport.
+        let mut expected_headers = expected.headers().clone();
+        expected_headers.remove(http::header::CONTENT_LENGTH);
+        assert_eq!(http_response.headers(), &expected_headers);
+        assert_eq!(buffer, [&b"reused "[..], expected.body()].concat());
+    }
+}
+
+mod header_only_response {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, ruma_api};
+
+    ruma_api! {
+        metadata {
+            description: "Check whether some media exists.",
+            method: GET,
+            name: "check_media",
+            path: "/_matrix/media/:media_id",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub media_id: String,
+        }
+
+        response {
+            #[ruma_api(header = CONTENT_TYPE)]
+            pub content_type: String,
+            #[ruma_api(header = CONTENT_LENGTH)]
+            pub content_length: String,
+        }
+    }
+
+    #[test]
+    fn outgoing_response_has_no_body() {
+        let response = Response { content_type: "image/png".into(), content_length: "1024".into() };
+        let http_response = http::Response::<Vec<u8>>::try_from(response).unwrap();
+
+        assert!(http_response.body().is_empty());
+        assert_eq!(http_response.headers()[http::header::CONTENT_TYPE], "image/png");
+        assert_eq!(http_response.headers().get_all(http::header::CONTENT_TYPE).iter().count(), 1);
+        assert_eq!(http_response.headers()[http::header::CONTENT_LENGTH], "1024");
+    }
+
+    #[test]
+    fn incoming_response_ignores_empty_body() {
+        let http_response = http::Response::builder()
+            .header(http::header::CONTENT_TYPE, "image/png")
+            .header(http::header::CONTENT_LENGTH, "1024")
+            .body(Vec::new())
+            .unwrap();
+
+        let response = Response::try_from(http_response).unwrap();
+        assert_eq!(response.content_type, "image/png");
+        assert_eq!(response.content_length, "1024");
+    }
+}
+
+mod custom_raw_body {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, ruma_api};
+
+    /// A stand-in for byte buffer types like `bytes::Bytes`.
+    #[derive(Clone, Debug, PartialEq)]
+    pub struct Bytes(Vec<u8>);
+
+    impl From<Vec<u8>> for Bytes {
+        fn from(bytes: Vec<u8>) -> Self {
+            Self(bytes)
+        }
+    }
+
+    impl From<Bytes> for Vec<u8> {
+        fn from(bytes: Bytes) -> Self {
+            bytes.0
+        }
+    }
+
+    ruma_api! {
+        metadata {
+            description: "Upload and echo a file.",
+            method: POST,
+            name: "echo_file",
+            path: "/_matrix/media/echo",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(raw_body)]
+            pub file: Bytes,
+        }
+
+        response {
+            #[ruma_api(raw_body)]
+            pub file: Bytes,
+        }
+    }
+
+    #[test]
+    fn request_roundtrip() {
+        let request = Request { file: Bytes(b"hello".to_vec()) };
+        let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+        assert_eq!(http_request.body(), b"hello");
+
+        let request = Request::try_from(http_request).unwrap();
+        assert_eq!(request.file, Bytes(b"hello".to_vec()));
+    }
+
+    #[test]
+    fn response_roundtrip() {
+        let response = Response { file: Bytes(b"world".to_vec()) };
+        let http_response = http::Response::<Vec<u8>>::try_from(response).unwrap();
+        assert_eq!(http_response.body(), b"world");
+
+        let response = Response::try_from(http_response).unwrap();
+        assert_eq!(response.file, Bytes(b"world".to_vec()));
+
+        let response = Response { file: Bytes(b"world".to_vec()) };
+        let written = response.try_into_http_response_with_writer(Vec::new()).unwrap();
+        assert_eq!(written.body(), b"world");
+    }
+}
+
+mod query_map_with_query_fields {
+    use std::{collections::BTreeMap, convert::TryFrom};
+
+    use ruma_api::{exports::http, ruma_api};
+
+    ruma_api! {
+        metadata {
+            description: "Register an account.",
+            method: POST,
+            name: "register",
+            path: "/_matrix/client/r0/register",
+            rate_limited: true,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(query)]
+            pub kind: String,
+            #[ruma_api(query)]
+            #[serde(rename = "inhibit_login")]
+            pub no_login: bool,
+            #[ruma_api(query_map)]
+            pub extra: BTreeMap<String, String>,
+        }
+
+        response {}
+    }
+
+    #[test]
+    fn merged_on_serialization() {
+        let mut extra = BTreeMap::new();
+        extra.insert("org.example.flag".to_owned(), "on".to_owned());
+        let request = Request { kind: "guest".into(), no_login: true, extra };
+
+        let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+        assert_eq!(
+            http_request.uri().query(),
+            Some("kind=guest&inhibit_login=true&org.example.flag=on")
+        );
+    }
+
+    #[test]
+    fn partitioned_on_parse() {
+        let http_request = http::Request::post(
+            "http://example.org/_matrix/client/r0/register?kind=user&x=1&inhibit_login=false&y=2",
+        )
+        .body(Vec::new())
+        .unwrap();
+
+        let request = Request::try_from(http_request).unwrap();
+        assert_eq!(request.kind, "user");
+        assert!(!request.no_login);
+        assert_eq!(request.extra.len(), 2);
+        assert_eq!(request.extra["x"], "1");
+        assert_eq!(request.extra["y"], "2");
+    }
+
+    #[test]
+    fn empty_query_map() {
+        let request = Request { kind: "guest".into(), no_login: false, extra: BTreeMap::new() };
+
+        let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+        assert_eq!(http_request.uri().query(), Some("kind=guest&inhibit_login=false"));
+    }
+}
+
+mod header_map {
+    use std::convert::TryFrom;
+
+    use ruma_api::{
+        exports::http::{self, header::HeaderValue, HeaderMap},
+        ruma_api,
+    };
+
+    ruma_api! {
+        metadata {
+            description: "Forward a request.",
+            method: POST,
+            name: "forward",
+            path: "/_matrix/app/forward",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(header = AUTHORIZATION)]
+            pub authorization: String,
+            #[ruma_api(header_map)]
+            pub headers: HeaderMap,
+            pub payload: String,
+        }
+
+        response {
+            #[ruma_api(header = LOCATION)]
+            pub location: String,
+            #[ruma_api(header_map)]
+            pub headers: HeaderMap,
+        }
+    }
+
+    #[test]
+    fn request_roundtrip() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-forwarded-for", HeaderValue::from_static("10.0.0.1"));
+        headers.append("x-custom", HeaderValue::from_static("a"));
+        headers.append("x-custom", HeaderValue::from_static("b"));
+        headers.insert(http::header::AUTHORIZATION, HeaderValue::from_static("Bearer ignored"));
+        let request =
+            Request { authorization: "Bearer token".into(), headers, payload: "hi".into() };
+
+        let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+        let authorization = http_request.headers().get_all(http::header::AUTHORIZATION);
+        assert_eq!(authorization.iter().collect::<Vec<_>>(), vec!["Bearer token"]);
+        assert_eq!(http_request.headers()["x-forwarded-for"], "10.0.0.1");
+        assert_eq!(http_request.headers().get_all("x-custom").iter().count(), 2);
+
+        let request = Request::try_from(http_request).unwrap();
+        assert_eq!(request.authorization, "Bearer token");
+        assert!(!request.headers.contains_key(http::header::AUTHORIZATION));
+        assert_eq!(request.headers["x-forwarded-for"], "10.0.0.1");
+        assert_eq!(request.headers.get_all("x-custom").iter().count(), 2);
+        assert_eq!(request.payload, "hi");
+    }
+
+    #[test]
+    fn response_roundtrip() {
+        let mut headers = HeaderMap::new();
+        headers.insert("x-request-id", HeaderValue::from_static("abc"));
+        let response = Response { location: "/somewhere".into(), headers };
+
+        let http_response = http::Response::<Vec<u8>>::try_from(response).unwrap();
+        assert_eq!(http_response.headers()[http::header::LOCATION], "/somewhere");
+        assert_eq!(http_response.headers()["x-request-id"], "abc");
+
+        let response = Response::try_from(http_response).unwrap();
+        assert_eq!(response.location, "/somewhere");
+        assert!(!response.headers.contains_key(http::header::LOCATION));
+        assert_eq!(response.headers["x-request-id"], "abc");
+    }
+}
+
+mod incoming_to_outgoing {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, partial::Partial, ruma_api};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+    pub struct Room {
+        pub name: String,
+    }
+
+    ruma_api! {
+        metadata {
+            description: "Forward some rooms.",
+            method: POST,
+            name: "forward_rooms",
+            path: "/_matrix/foo/rooms",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(partial)]
+            pub room: Room,
+            #[wrap_incoming(Room with Partial)]
+            pub others: Vec<Room>,
+        }
+
+        response {}
+    }
+
+    fn http_request(body: &[u8]) -> http::Request<Vec<u8>> {
+        http::Request::post("http://example.org/_matrix/foo/rooms").body(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn valid_request_converts_back() {
+        let body = br#"{"room":{"name":"a"},"others":[{"name":"b"}]}"#;
+        let incoming = IncomingRequest::try_from(http_request(body)).unwrap();
+
+        let request = Request::try_from(incoming).unwrap();
+        assert_eq!(request.room, Room { name: "a".into() });
+        assert_eq!(request.others, vec![Room { name: "b".into() }]);
+    }
+
+    #[test]
+    fn invalid_field_fails_conversion() {
+        let body = br#"{"room":{"name":"a"},"others":[{"name":"b"},{"name":1}]}"#;
+        let incoming = IncomingRequest::try_from(http_request(body)).unwrap();
+
+        let err = Request::try_from(incoming).unwrap_err();
+        assert_eq!(err.json(), &serde_json::json!({ "name": 1 }));
+    }
+}
+
+mod uiaa {
+    use std::convert::TryFrom;
+
+    use ruma_api::{error::FromHttpResponseError, exports::http, ruma_api, uiaa::AuthFlow};
+
+    ruma_api! {
+        metadata {
+            description: "Does something that needs user-interactive authentication.",
+            method: POST,
+            name: "uiaa",
+            path: "/_matrix/foo/uiaa",
+            rate_limited: false,
+            requires_authentication: true,
+            uiaa: true,
+        }
+
+        request {}
+
+        response {}
+    }
+
+    fn http_response(status: u16, body: &[u8]) -> http::Response<Vec<u8>> {
+        http::Response::builder()
+            .status(status)
+            .header("content-type", "application/json")
+            .body(body.to_vec())
+            .unwrap()
+    }
+
+    #[test]
+    fn auth_flows_are_returned_as_uiaa_error() {
+        let body = br#"{
+            "flows": [{ "stages": ["m.login.password"] }],
+            "params": {},
+            "session": "xxxxxx"
+        }"#;
+        match Response::try_from(http_response(401, body)) {
+            Err(FromHttpResponseError::Uiaa(info)) => {
+                assert_eq!(info.flows, vec![AuthFlow { stages: vec!["m.login.password".into()] }]);
+                assert_eq!(info.session.as_deref(), Some("xxxxxx"));
+                assert!(info.completed.is_empty());
+            }
+            _ => panic!("expected a UIAA error"),
+        }
+    }
+
+    #[test]
+    fn other_errors_are_server_errors() {
+        let body = br#"{ "errcode": "M_UNKNOWN_TOKEN", "error": "Unrecognised access token" }"#;
+        match Response::try_from(http_response(401, body)) {
+            Err(FromHttpResponseError::Http(err)) => assert_eq!(err.status().as_u16(), 401),
+            _ => panic!("expected a server error"),
+        }
+    }
+
+    #[test]
+    fn endpoints_without_uiaa_ignore_auth_flows() {
+        let body = br#"{ "flows": [{ "stages": ["m.login.password"] }] }"#;
+        match super::renamed_field::Response::try_from(http_response(401, body)) {
+            Err(FromHttpResponseError::Http(_)) => {}
+            _ => panic!("expected a server error"),
+        }
+    }
+}
+
+mod constructor {
+    use ruma_api::{exports::http, ruma_api};
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "constructor",
+            path: "/_matrix/foo/:room",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub room: String,
+            #[ruma_api(query)]
+            #[serde(default)]
+            pub limit: u32,
+            pub body: String,
+            pub topic: Option<String>,
+            #[serde(default = "default_kind")]
+            pub kind: String,
+            #[ruma_api(header_map)]
+            pub headers: http::HeaderMap,
+        }
+
+        response {}
+    }
+
+    fn default_kind() -> String {
+        "text".to_owned()
+    }
+
+    #[test]
+    fn takes_required_fields() {
+        let request = Request::new("!room:example.org".to_owned(), "hello".to_owned());
+
+        assert_eq!(request.room, "!room:example.org");
+        assert_eq!(request.limit, 0);
+        assert_eq!(request.body, "hello");
+        assert_eq!(request.topic, None);
+        assert_eq!(request.kind, "text");
+        assert!(request.headers.is_empty());
+    }
+
+    #[test]
+    fn empty_request() {
+        let super::renamed_field::Request = super::renamed_field::Request::new();
+    }
+
+    ruma_api! {
+        mod sync;
+
+        metadata {
+            description: "Get the latest events.",
+            method: GET,
+            name: "sync",
+            path: "/_matrix/client/r0/sync",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {
+            #[ruma_api(query)]
+            pub since: Option<String>,
+            #[ruma_api(query)]
+            #[serde(default)]
+            pub full_state: bool,
+            #[ruma_api(query)]
+            #[serde(default = "super::default_kind")]
+            pub set_presence: String,
+        }
+
+        response {
+            #[ruma_api(header_map)]
+            pub headers: http::HeaderMap,
+            pub next_batch: Option<String>,
+        }
+    }
+
+    #[test]
+    fn default_without_required_fields() {
+        let request = sync::Request { since: Some("s72594".to_owned()), ..Default::default() };
+
+        assert_eq!(request.since.as_deref(), Some("s72594"));
+        assert!(!request.full_state);
+        assert_eq!(request.set_presence, "text");
+
+        let response = sync::Response::default();
+        assert!(response.headers.is_empty());
+        assert_eq!(response.next_batch, None);
+    }
+}
+
+mod examples {
+    use ruma_api::{
+        examples::{EndpointExamples, FieldExample, FieldLocation},
+        ruma_api,
+    };
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: PUT,
+            name: "examples",
+            path: "/_matrix/foo/:room_id",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            #[ruma_api(example = "!room:example.org")]
+            pub room_id: String,
+            #[ruma_api(query)]
+            #[ruma_api(example = "10")]
+            pub limit: u32,
+            pub topic: String,
+        }
+
+        response {
+            #[ruma_api(example = r#"["$event:example.org"]"#)]
+            pub events: Vec<String>,
+            #[ruma_api(header = CONTENT_TYPE)]
+            #[ruma_api(example = "application/json")]
+            pub content_type: String,
+        }
+    }
+
+    #[test]
+    fn lists_examples() {
+        assert_eq!(
+            Request::request_examples(),
+            vec![
+                FieldExample {
+                    field: "room_id",
+                    location: FieldLocation::Path,
+                    example: "!room:example.org",
+                },
+                FieldExample { field: "limit", location: FieldLocation::Query, example: "10" },
+            ]
+        );
+        assert_eq!(
+            Request::response_examples(),
+            vec![
+                FieldExample {
+                    field: "events",
+                    location: FieldLocation::Body,
+                    example: r#"["$event:example.org"]"#,
+                },
+                FieldExample {
+                    field: "content_type",
+                    location: FieldLocation::Header,
+                    example: "application/json",
+                },
+            ]
+        );
+        assert!(super::renamed_field::Request::request_examples().is_empty());
+    }
+}
+
+mod path_encoding {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, ruma_api};
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "path_encoding",
+            path: "/_matrix/foo/:room_alias/:event_id",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub room_alias: String,
+            #[ruma_api(path, encode = STRICT)]
+            pub event_id: String,
+        }
+
+        response {}
+    }
+
+    #[test]
+    fn encode_sets() {
+        let request = Request::new("#room:example.org".to_owned(), "$event:example.org".to_owned());
+        let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+
+        assert_eq!(
+            http_request.uri().path(),
+            "/_matrix/foo/%23room:example.org/%24event%3Aexample.org"
+        );
+
+        let request = Request::try_from(http_request).unwrap();
+        assert_eq!(request.room_alias, "#room:example.org");
+        assert_eq!(request.event_id, "$event:example.org");
+    }
+}
+
+mod query_as_str {
+    use std::{
+        convert::TryFrom,
+        fmt::{self, Display, Formatter},
+        str::FromStr,
+    };
+
+    use ruma_api::{exports::http, ruma_api};
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+    pub enum Direction {
+        Forward,
+        Backward,
+    }
+
+    impl Display for Direction {
+        fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+            match self {
+                Direction::Forward => f.write_str("f"),
+                Direction::Backward => f.write_str("b"),
+            }
+        }
+    }
+
+    impl FromStr for Direction {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s {
+                "f" => Ok(Direction::Forward),
+                "b" => Ok(Direction::Backward),
+                _ => Err(format!("invalid direction `{}`", s)),
+            }
+        }
+    }
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "query_as_str",
+            path: "/_matrix/foo",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(query, as_str)]
+            pub dir: Direction,
+            #[ruma_api(query, as_str)]
+            pub other_dir: Option<Direction>,
+        }
+
+        response {}
+    }
+
+    #[test]
+    fn roundtrip() {
+        let request = Request { dir: Direction::Backward, other_dir: Some(Direction::Forward) };
+        let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+        assert_eq!(http_request.uri().query(), Some("dir=b&other_dir=f"));
+
+        let request = Request::try_from(http_request).unwrap();
+        assert_eq!(request.dir, Direction::Backward);
+        assert_eq!(request.other_dir, Some(Direction::Forward));
+    }
+
+    #[test]
+    fn missing_optional_value() {
+        let http_request =
+            http::Request::<Vec<u8>>::try_from(Request::new(Direction::Forward)).unwrap();
+        assert_eq!(http_request.uri().query(), Some("dir=f"));
+        assert_eq!(Request::try_from(http_request).unwrap().other_dir, None);
+    }
+
+    #[test]
+    fn invalid_value() {
+        let http_request =
+            http::Request::builder().uri("/_matrix/foo?dir=x").body(Vec::new()).unwrap();
+        assert!(Request::try_from(http_request).is_err());
+    }
+}
+
+mod map_body {
+    use std::{collections::BTreeMap, convert::TryFrom};
+
+    use ruma_api::{exports::http, ruma_api};
+    use ruma_identifiers::UserId;
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+    pub struct DeviceKeys {
+        pub algorithms: Vec<String>,
+    }
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "map_body",
+            path: "/_matrix/keys",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(body)]
+            pub keys: BTreeMap<UserId, DeviceKeys>,
+        }
+
+        response {
+            #[ruma_api(body)]
+            pub keys: BTreeMap<UserId, DeviceKeys>,
+        }
+    }
+
+    fn keys() -> BTreeMap<UserId, DeviceKeys> {
+        let mut keys = BTreeMap::new();
+        keys.insert(
+            UserId::try_from("@alice:example.org").unwrap(),
+            DeviceKeys { algorithms: vec!["m.megolm.v1.aes-sha2".into()] },
+        );
+        keys.insert(
+            UserId::try_from("@bob:example.org").unwrap(),
+            DeviceKeys { algorithms: vec![] },
+        );
+        keys
+    }
+
+    #[test]
+    fn request_roundtrip() {
+        let http_request = http::Request::<Vec<u8>>::try_from(Request { keys: keys() }).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(http_request.body()).unwrap(),
+            serde_json::json!({
+                "@alice:example.org": { "algorithms": ["m.megolm.v1.aes-sha2"] },
+                "@bob:example.org": { "algorithms": [] },
+            })
+        );
+
+        assert_eq!(Request::try_from(http_request).unwrap().keys, keys());
+    }
+
+    #[test]
+    fn response_roundtrip() {
+        let http_response = http::Response::<Vec<u8>>::try_from(Response { keys: keys() }).unwrap();
+        assert_eq!(Response::try_from(http_response).unwrap().keys, keys());
+    }
+
+    #[test]
+    fn empty_map() {
+        let http_response = http::Response::new(b"{}".to_vec());
+        assert!(Response::try_from(http_response).unwrap().keys.is_empty());
+    }
+
+    #[test]
+    fn invalid_key() {
+        let http_response = http::Response::new(br#"{ "alice": { "algorithms": [] } }"#.to_vec());
+        assert!(Response::try_from(http_response).is_err());
+    }
+
+    #[test]
+    fn non_object_body() {
+        for body in &[&b"[]"[..], b"null", b"\"keys\"", b""] {
+            let http_response = http::Response::new(body.to_vec());
+            assert!(Response::try_from(http_response).is_err());
+
+            let http_request = http::Request::post("/_matrix/keys").body(body.to_vec()).unwrap();
+            assert!(Request::try_from(http_request).is_err());
+        }
+    }
+}
+
+mod array_body {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, ruma_api};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+    pub struct Pdu {
+        pub event_id: String,
+    }
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: PUT,
+            name: "array_body",
+            path: "/_matrix/federation/send/:txn_id",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub txn_id: String,
+            #[ruma_api(body)]
+            pub events: Vec<Pdu>,
+        }
+
+        response {
+            #[ruma_api(body)]
+            pub events: Vec<Pdu>,
+        }
+    }
+
+    fn events() -> Vec<Pdu> {
+        vec![Pdu { event_id: "$a:example.org".into() }, Pdu { event_id: "$b:example.org".into() }]
+    }
+
+    #[test]
+    fn request_body_is_bare_array() {
+        let request = Request { txn_id: "1".into(), events: events() };
+        let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+        assert_eq!(
+            http_request.body(),
+            br#"[{"event_id":"$a:example.org"},{"event_id":"$b:example.org"}]"#
+        );
+
+        let request = Request::try_from(http_request).unwrap();
+        assert_eq!(request.txn_id, "1");
+        assert_eq!(request.events, events());
+    }
+
+    #[test]
+    fn response_body_is_bare_array() {
+        let http_response =
+            http::Response::<Vec<u8>>::try_from(Response { events: vec![] }).unwrap();
+        assert_eq!(http_response.body(), b"[]");
+
+        let http_response =
+            http::Response::<Vec<u8>>::try_from(Response { events: events() }).unwrap();
+        assert_eq!(Response::try_from(http_response).unwrap().events, events());
+    }
+
+    #[test]
+    fn wrapped_array_is_an_error() {
+        let http_response = http::Response::new(br#"{ "events": [] }"#.to_vec());
+        assert!(Response::try_from(http_response).is_err());
+    }
+}
+
+mod fallback {
+    use std::convert::TryFrom;
+
+    use ruma_api::{exports::http, fallback::Fallback, ruma_api};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+    pub struct Body {
+        pub room_ids: Vec<String>,
+    }
+
+    #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+    pub struct LegacyBody {
+        pub room_id: String,
+    }
+
+    impl From<LegacyBody> for Body {
+        fn from(legacy: LegacyBody) -> Self {
+            Self { room_ids: vec![legacy.room_id] }
+        }
+    }
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: GET,
+            name: "fallback",
+            path: "/_matrix/foo",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {}
+
+        response {
+            #[ruma_api(body, fallback = LegacyBody)]
+            pub body: Body,
+        }
+    }
+
+    fn http_response(body: &[u8]) -> http::Response<Vec<u8>> {
+        http::Response::new(body.to_vec())
+    }
+
+    #[test]
+    fn current_shape() {
+        let res = IncomingResponse::try_from(http_response(br#"{"room_ids":["!a:example.org"]}"#))
+            .unwrap();
+        assert_eq!(res.body, Fallback::Current(Body { room_ids: vec!["!a:example.org".into()] }));
+    }
+
+    #[test]
+    fn legacy_shape() {
+        let res =
+            IncomingResponse::try_from(http_response(br#"{"room_id":"!a:example.org"}"#)).unwrap();
+        assert!(res.body.is_legacy());
+
+        let res = Response::try_from(res).unwrap();
+        assert_eq!(res.body, Body { room_ids: vec!["!a:example.org".into()] });
+    }
+
+    #[test]
+    fn neither_shape() {
+        let err =
+            IncomingResponse::try_from(http_response(br#"{"room":"!a:example.org"}"#)).unwrap_err();
+        assert!(err.to_string().contains("room_ids"));
+    }
+
+    #[test]
+    fn outgoing_response_uses_current_shape() {
+        let response = Response { body: Body { room_ids: vec![] } };
+        let http_response = http::Response::<Vec<u8>>::try_from(response).unwrap();
+        assert_eq!(http_response.body(), br#"{"room_ids":[]}"#);
+    }
+}
+
+mod manual {
+    use ruma_api::manual::{encode_path_segment, json_body, parse_path_args, serialize_query};
+
+    #[test]
+    fn path_roundtrip() {
+        let template = "/_matrix/client/r0/rooms/:room_id/state/:event_type/:state_key";
+        let path = format!(
+            "/_matrix/client/r0/rooms/{}/state/{}/{}",
+            encode_path_segment(&"!room/id:example.org"),
+            encode_path_segment(&"m.room.name"),
+            encode_path_segment(&""),
+        );
+        assert_eq!(path, "/_matrix/client/r0/rooms/!room%2Fid:example.org/state/m.room.name/");
+
+        let args = parse_path_args(template, &path).unwrap();
+        assert_eq!(args, ["!room/id:example.org", "m.room.name", ""]);
+    }
+
+    #[test]
+    fn truncated_path() {
+        assert_eq!(parse_path_args("/_matrix/foo/:bar/baz", "/_matrix/foo/x"), None);
+        assert_eq!(parse_path_args("/_matrix/foo/:bar", "/_matrix/foo"), None);
+    }
+
+    #[test]
+    fn query_and_body() {
+        #[derive(serde::Serialize)]
+        struct Query {
+            from: &'static str,
+            limit: Option<u32>,
+        }
+
+        let query = Query { from: "a b", limit: None };
+        assert_eq!(serialize_query(&query).unwrap(), "from=a+b");
+        assert_eq!(json_body(&query).unwrap(), br#"{"from":"a b","limit":null}"#);
+    }
+}
+
+mod return_request {
+    use std::{collections::BTreeMap, convert::TryFrom};
+
+    use ruma_api::{exports::http, ruma_api};
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+    pub struct Filter {
+        pub limit: u32,
+    }
+
+    ruma_api! {
+        metadata {
+            description: "Does something.",
+            method: POST,
+            name: "return_request",
+            path: "/_matrix/foo/:bar",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub bar: String,
+            #[ruma_api(query)]
+            pub filter: Option<Filter>,
+            #[ruma_api(query_map)]
+            pub extra: Vec<(String, String)>,
+            #[ruma_api(header = CONTENT_TYPE)]
+            pub content_type: String,
+            pub counts: BTreeMap<u32, u32>,
+            #[serde(skip_serializing_if = "Option::is_none")]
+            pub names: Option<BTreeMap<Vec<u8>, String>>,
+        }
+
+        response {}
+    }
+
+    fn request() -> Request {
+        let mut counts = BTreeMap::new();
+        counts.insert(1, 2);
+
+        Request {
+            bar: "bar".into(),
+            filter: None,
+            extra: vec![("x".into(), "y".into())],
+            content_type: "application/json".into(),
+            counts,
+            names: None,
+        }
+    }
+
+    #[test]
+    fn success() {
+        let http_request = request().try_into_http_request_or_return().unwrap();
+        assert_eq!(http_request.uri().path(), "/_matrix/foo/bar");
+        assert_eq!(http_request.uri().query(), Some("x=y"));
+        assert_eq!(http_request.body(), br#"{"counts":{"1":2}}"#);
+    }
+
+    #[test]
+    fn query_error_returns_request() {
+        let request = Request { filter: Some(Filter { limit: 5 }), ..request() };
+        let (returned, err) = request.try_into_http_request_or_return().unwrap_err().into_parts();
+
+        assert!(err.to_string().starts_with("Query parameter serialization failed"));
+        assert_eq!(returned.bar, "bar");
+        assert_eq!(returned.filter, Some(Filter { limit: 5 }));
+        assert_eq!(returned.extra, [("x".to_owned(), "y".to_owned())]);
+        assert_eq!(returned.content_type, "application/json");
+        assert_eq!(returned.counts.get(&1), Some(&2));
+    }
+
+    #[test]
+    fn body_error_returns_request() {
+        let mut names = BTreeMap::new();
+        names.insert(b"a".to_vec(), "A".to_owned());
+        let request = Request { names: Some(names.clone()), ..request() };
+
+        let err = request.try_into_http_request_or_return().unwrap_err();
+        assert!(err.error().to_string().starts_with("JSON serialization failed"));
+        assert_eq!(err.request().names, Some(names));
+
+        let err = http::Request::<Vec<u8>>::try_from(err.into_request()).unwrap_err();
+        assert!(err.to_string().starts_with("JSON serialization failed"));
+    }
+}
+
+mod cache_key {
+    use ruma_api::{ruma_api, Endpoint as _};
+
+    ruma_api! {
+        metadata {
+            description: "Get the context of an event.",
+            method: GET,
+            name: "get_context",
+            path: "/_matrix/client/r0/rooms/:room_id/context/:event_id",
+            rate_limited: false,
+            requires_authentication: true,
+            cacheable: true,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub room_id: String,
+            #[ruma_api(path)]
+            pub event_id: String,
+            #[ruma_api(query)]
+            pub limit: u32,
+            #[ruma_api(query_map)]
+            pub extra: Vec<(String, String)>,
+        }
+
+        response {}
+    }
+
+    fn request(extra: &[(&str, &str)]) -> Request {
+        Request {
+            room_id: "!room:example.org".to_owned(),
+            event_id: "$event".to_owned(),
+            limit: 10,
+            extra: extra.iter().map(|(k, v)| ((*k).to_owned(), (*v).to_owned())).collect(),
+        }
+    }
+
+    #[test]
+    fn query_order_is_normalized() {
+        let key = request(&[("filter", "a b"), ("dir", "b")]).cache_key().unwrap();
+        let same = request(&[("dir", "b"), ("filter", "a b")]).cache_key().unwrap();
+        let other = request(&[("dir", "f")]).cache_key().unwrap();
+
+        assert_eq!(key, same);
+        assert_ne!(key, other);
+        assert_eq!(key.name(), "get_context");
+        assert_eq!(key.path(), "/_matrix/client/r0/rooms/!room:example.org/context/$event");
+        assert_eq!(
+            key.to_string(),
+            "get_context /_matrix/client/r0/rooms/!room:example.org/context/$event\
+             ?dir=b&filter=a+b&limit=10"
+        );
+    }
+
+    #[test]
+    fn not_cacheable() {
+        ruma_api! {
+            mod send_message;
+
+            metadata {
+                description: "Send a message.",
+                method: PUT,
+                name: "send_message",
+                path: "/_matrix/client/r0/rooms/:room_id/send/:txn_id",
+                rate_limited: false,
+                requires_authentication: true,
+            }
+
+            request {
+                #[ruma_api(path)]
+                pub room_id: String,
+                #[ruma_api(path)]
+                pub txn_id: String,
+            }
+
+            response {}
+        }
+
+        let request = send_message::Request {
+            room_id: "!room:example.org".to_owned(),
+            txn_id: "1".to_owned(),
+        };
+        assert_eq!(request.cache_key(), None);
+    }
+}
+
+mod validation {
+    use std::convert::TryFrom;
+
+    use ruma_api::{
+        error::{FromHttpRequestError, ValidationError},
+        exports::http,
+        ruma_api, Validate,
+    };
+
+    ruma_api! {
+        metadata {
+            description: "Get public rooms.",
+            method: GET,
+            name: "get_public_rooms",
+            path: "/_matrix/client/r0/publicRooms",
+            rate_limited: false,
+            requires_authentication: false,
+            validate: true,
+        }
+
+        request {
+            #[ruma_api(query)]
+            pub limit: u32,
+        }
+
+        response {}
+    }
+
+    impl Validate for Request {
+        fn validate(&self) -> Result<(), ValidationError> {
+            if self.limit > 1000 {
+                return Err(ValidationError::field("limit", "must be at most 1000"));
+            }
+            Ok(())
+        }
+    }
+
+    fn http_request(limit: u32) -> http::Request<Vec<u8>> {
+        http::Request::builder()
+            .uri(format!("http://localhost/_matrix/client/r0/publicRooms?limit={}", limit))
+            .body(Vec::new())
+            .unwrap()
+    }
+
+    #[test]
+    fn valid_request() {
+        let request = Request::try_from(http_request(1000)).unwrap();
+        assert_eq!(request.limit, 1000);
+    }
+
+    #[test]
+    fn invalid_request() {
+        match Request::try_from(http_request(1001)) {
+            Err(FromHttpRequestError::Validation(err)) => {
+                assert_eq!(err.field_name(), Some("limit"));
+                assert_eq!(err.to_string(), "`limit` must be at most 1000");
+            }
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn validated_after_reading_body() {
+        let (parts, body) = http_request(1001).into_parts();
+        let partial = Request::try_from_parts(parts).unwrap();
+        match partial.complete(body) {
+            Err(FromHttpRequestError::Validation(_)) => {}
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+}
+
+mod field_limits {
+    use std::convert::TryFrom;
+
+    use ruma_api::{error::FromHttpRequestError, exports::http, ruma_api};
+
+    ruma_api! {
+        metadata {
+            description: "Create a room.",
+            method: POST,
+            name: "create_room",
+            path: "/_matrix/client/r0/createRoom",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {
+            #[ruma_api(max_len = 8)]
+            pub room_alias_name: Option<String>,
+            #[ruma_api(max_items = 2)]
+            pub invite: Vec<String>,
+            #[ruma_api(query, max_len = 4)]
+            pub preset: String,
+        }
+
+        response {}
+    }
+
+    fn http_request(preset: &str, body: &str) -> http::Request<Vec<u8>> {
+        http::Request::builder()
+            .method("POST")
+            .uri(format!("http://localhost/_matrix/client/r0/createRoom?preset={}", preset))
+            .body(body.as_bytes().to_vec())
+            .unwrap()
+    }
+
+    fn violated_field(result: Result<Request, FromHttpRequestError>) -> &'static str {
+        match result {
+            Err(FromHttpRequestError::Validation(err)) => err.field_name().unwrap(),
+            result => panic!("unexpected result: {:?}", result),
+        }
+    }
+
+    #[test]
+    fn within_limits() {
+        let body = r#"{"room_alias_name":"12345678","invite":["a","b"]}"#;
+        let request = Request::try_from(http_request("ab", body)).unwrap();
+        assert_eq!(request.room_alias_name.as_deref(), Some("12345678"));
+
+        let request = Request::try_from(http_request("ab", r#"{"invite":[]}"#)).unwrap();
+        assert_eq!(request.room_alias_name, None);
+    }
+
+    #[test]
+    fn exceeded_limits() {
+        let body = r#"{"room_alias_name":"123456789","invite":[]}"#;
+        assert_eq!(violated_field(Request::try_from(http_request("ab", body))), "room_alias_name");
+
+        let body = r#"{"invite":["a","b","c"]}"#;
+        assert_eq!(violated_field(Request::try_from(http_request("ab", body))), "invite");
+
+        let body = r#"{"invite":[]}"#;
+        assert_eq!(violated_field(Request::try_from(http_request("abcde", body))), "preset");
+    }
+}
+
+mod credentials {
+    use ruma_api::{
+        credentials::{Authenticated, Credentials, CredentialsError, XMatrix},
+        error::FromHttpRequestError,
+        exports::http,
+        options::IncomingRequestOptions,
+        ruma_api, Endpoint as _,
+    };
+
+    ruma_api! {
+        metadata {
+            description: "Get the profile of a user.",
+            method: GET,
+            name: "get_profile",
+            path: "/_matrix/client/r0/profile",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {}
+
+        response {}
+    }
+
+    fn http_request(authorization: Option<&str>, uri: &str) -> http::Request<Vec<u8>> {
+        let mut builder = http::Request::builder().uri(uri);
+        if let Some(authorization) = authorization {
+            builder = builder.header(http::header::AUTHORIZATION, authorization);
+        }
+        builder.body(Vec::new()).unwrap()
+    }
+
+    fn parse(authorization: &str) -> Result<Option<Credentials>, CredentialsError> {
+        Credentials::from_request(&http_request(Some(authorization), "/"))
+    }
+
+    #[test]
+    fn access_token() {
+        let http_req = http_request(Some("Bearer secret"), "/_matrix/client/r0/profile");
+        let Authenticated { credentials, request: _ } =
+            Request::try_from_http_request_authenticated(http_req, &Default::default()).unwrap();
+        assert_eq!(credentials.unwrap().access_token(), Some("secret"));
+
+        let http_req = http_request(None, "/_matrix/client/r0/profile?access_token=secret%2F1");
+        let credentials = Credentials::from_request(&http_req).unwrap().unwrap();
+        assert_eq!(credentials, Credentials::AccessToken("secret/1".to_owned()));
+    }
+
+    #[test]
+    fn x_matrix() {
+        let credentials =
+            parse(r#"X-Matrix origin=origin.example.org,key="ed25519:key1",sig="ABC\"DEF,GHI""#)
+                .unwrap()
+                .unwrap();
+        assert_eq!(
+            credentials.x_matrix(),
+            Some(&XMatrix {
+                origin: "origin.example.org".to_owned(),
+                destination: None,
+                key: "ed25519:key1".to_owned(),
+                sig: r#"ABC"DEF,GHI"#.to_owned(),
+            })
+        );
+
+        assert_eq!(
+            parse("X-Matrix origin=origin.example.org,key=\"ed25519:key1\""),
+            Err(CredentialsError::MissingXMatrixParameter("sig"))
+        );
+    }
+
+    #[test]
+    fn malformed() {
+        assert_eq!(parse("Bearer"), Err(CredentialsError::MalformedAccessToken));
+        assert_eq!(
+            parse("Basic dXNlcjpwYXNz"),
+            Err(CredentialsError::UnknownScheme("Basic".to_owned()))
+        );
+
+        let http_req = http_request(Some("Basic dXNlcjpwYXNz"), "/_matrix/client/r0/profile");
+        match Request::try_from_http_request_authenticated(http_req, &Default::default()) {
+            Err(FromHttpRequestError::InvalidCredentials(CredentialsError::UnknownScheme(_))) => {}
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn missing_credentials() {
+        let options = IncomingRequestOptions::default();
+        let http_req = http_request(None, "/_matrix/client/r0/profile");
+        match Request::try_from_http_request_authenticated(http_req, &options) {
+            Err(FromHttpRequestError::MissingCredentials) => {}
+            other => panic!("unexpected result: {:?}", other.map(|_| ())),
+        }
+    }
+}
+
+#[test]
+fn extensions_are_kept() {
+    use ruma_api::{options::IncomingRequestOptions, Endpoint};
+
+    #[derive(Debug, PartialEq)]
+    struct UserId(&'static str);
+
+    let mut http_req = http::Request::builder().uri("/_matrix/foo").body(Vec::new()).unwrap();
+    http_req.extensions_mut().insert(UserId("@alice:example.org"));
+
+    let options = IncomingRequestOptions::default();
+    let request =
+        renamed_field::Request::try_from_http_request_with_extensions(http_req, &options).unwrap();
+    assert_eq!(request.extensions.get::<UserId>(), Some(&UserId("@alice:example.org")));
+    let _: &renamed_field::Request = &request;
+}
+
+mod path_identifiers {
+    use std::convert::TryFrom;
+
+    use ruma_api::{
+        error::{FromHttpRequestError, InvalidPathSegment},
+        exports::http,
+        ruma_api,
+    };
+
+    ruma_api! {
+        metadata {
+            description: "Get a room alias.",
+            method: GET,
+            name: "get_alias",
+            path: "/_matrix/client/r0/directory/room/:room_alias",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path, identifier, encode = STRICT)]
+            pub room_alias: String,
+        }
+
+        response {}
+    }
+
+    fn outgoing_error(room_alias: &str) -> Option<InvalidPathSegment> {
+        let request = Request { room_alias: room_alias.to_owned() };
+        let err = request.try_into_http_request_or_return().unwrap_err();
+        assert_eq!(err.request().room_alias, room_alias);
+        err.error().invalid_path_segment().copied()
+    }
+
+    fn incoming(segment: &str) -> Result<Request, FromHttpRequestError> {
+        let uri = format!("http://localhost/_matrix/client/r0/directory/room/{}", segment);
+        Request::try_from(http::Request::builder().uri(uri).body(Vec::new()).unwrap())
+    }
+
+    #[test]
+    fn valid_identifier() {
+        let request = Request { room_alias: "#room:example.org".to_owned() };
+        let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+        assert_eq!(
+            http_request.uri().path(),
+            "/_matrix/client/r0/directory/room/%23room%3Aexample.org"
+        );
+
+        let request = incoming("%23room%3Aexample.org").unwrap();
+        assert_eq!(request.room_alias, "#room:example.org");
+    }
+
+    #[test]
+    fn invalid_outgoing_identifier() {
+        assert_eq!(outgoing_error(""), Some(InvalidPathSegment::Empty));
+        assert_eq!(outgoing_error(".."), Some(InvalidPathSegment::DotSegment));
+        assert_eq!(outgoing_error("a/b"), Some(InvalidPathSegment::InvalidCharacter('/')));
+        assert_eq!(
+            outgoing_error(&"a".repeat(256)),
+            Some(InvalidPathSegment::TooLong { length: 256 })
+        );
+    }
+
+    #[test]
+    fn invalid_incoming_identifier() {
+        assert!(incoming("..").is_err());
+        assert!(incoming("a%2Fb").is_err());
+        assert!(incoming("a%0Ab").is_err());
+        assert!(Request::try_from_parts(
+            http::Request::builder()
+                .uri("http://localhost/_matrix/client/r0/directory/room/a%2Fb")
+                .body(())
+                .unwrap()
+                .into_parts()
+                .0
+        )
+        .is_err());
+    }
+}
+
+mod integer_parameters {
+    use std::convert::TryFrom;
+
+    use ruma_api::{
+        error::{FromHttpRequestError, InvalidInteger},
+        exports::http,
+        path::parse_integer,
+        ruma_api,
+    };
+
+    ruma_api! {
+        metadata {
+            description: "Get a user by its numeric ID.",
+            method: GET,
+            name: "get_user",
+            path: "/_admin/v1/users/:id/events/:offset",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub id: u64,
+            #[ruma_api(path)]
+            pub offset: i64,
+            #[ruma_api(query)]
+            pub limit: u8,
+            #[ruma_api(query)]
+            pub since: Option<i64>,
+        }
+
+        response {}
+    }
+
+    fn incoming(path: &str, query: &str) -> Result<Request, FromHttpRequestError> {
+        let uri = format!("http://localhost/_admin/v1/users/{}?{}", path, query);
+        Request::try_from(http::Request::builder().uri(uri).body(Vec::new()).unwrap())
+    }
+
+    fn path_error(path: &str) -> Option<InvalidInteger> {
+        match incoming(path, "limit=1") {
+            Err(FromHttpRequestError::Deserialization(err)) => err.invalid_integer(),
+            _ => panic!("expected a deserialization error"),
+        }
+    }
+
+    #[test]
+    fn round_trip_at_the_boundaries() {
+        let request = Request { id: u64::MAX, offset: i64::MIN, limit: u8::MAX, since: Some(-1) };
+        let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+        assert_eq!(
+            http_request.uri(),
+            "http://invalid-host-please-change/_admin/v1/users/18446744073709551615/events/\
+             -9223372036854775808?limit=255&since=-1"
+        );
+
+        let request = Request::try_from(http_request).unwrap();
+        assert_eq!(request.id, u64::MAX);
+        assert_eq!(request.offset, i64::MIN);
+        assert_eq!(request.limit, u8::MAX);
+        assert_eq!(request.since, Some(-1));
+
+        let request = incoming("0/events/0", "limit=0").unwrap();
+        assert_eq!((request.id, request.offset, request.limit, request.since), (0, 0, 0, None));
+    }
+
+    #[test]
+    fn invalid_path_parameters() {
+        assert_eq!(path_error("18446744073709551616/events/0"), Some(InvalidInteger::Overflow));
+        assert_eq!(path_error("-1/events/0"), Some(InvalidInteger::Underflow));
+        assert_eq!(path_error("1/events/-9223372036854775809"), Some(InvalidInteger::Underflow));
+        assert_eq!(path_error("1.5/events/0"), Some(InvalidInteger::InvalidCharacter('.')));
+        assert_eq!(path_error("%2B1/events/0"), Some(InvalidInteger::InvalidCharacter('+')));
+        assert_eq!(path_error("%221%22/events/0"), Some(InvalidInteger::InvalidCharacter('"')));
+        assert_eq!(path_error("/events/0"), Some(InvalidInteger::Empty));
+    }
+
+    #[test]
+    fn invalid_query_parameters() {
+        for query in &["limit=256", "limit=-1", "limit=", "limit=1&since=1e3", "limit=%201"] {
+            match incoming("1/events/0", query) {
+                Err(FromHttpRequestError::Deserialization(_)) => {}
+                other => panic!("expected a deserialization error for {}, got {:?}", query, other),
+            }
+        }
+
+        let err = incoming("1/events/0", "limit=256").unwrap_err();
+        assert!(err.to_string().contains("the integer is too large"), "{}", err);
+    }
+
+    #[test]
+    fn parse_integer_boundaries() {
+        assert_eq!(parse_integer::<u8>("255"), Ok(255));
+        assert_eq!(parse_integer::<u8>("256"), Err(InvalidInteger::Overflow));
+        assert_eq!(parse_integer::<i8>("-128"), Ok(-128));
+        assert_eq!(parse_integer::<i8>("-129"), Err(InvalidInteger::Underflow));
+        assert_eq!(parse_integer::<i8>("-"), Err(InvalidInteger::Empty));
+        assert_eq!(parse_integer::<u32>("0x10"), Err(InvalidInteger::InvalidCharacter('x')));
+        assert_eq!(parse_integer::<u32>("007"), Ok(7));
+    }
+}
+
+mod identifier_parameters {
+    use std::convert::TryFrom;
+
+    use ruma_api::{
+        error::{FromHttpRequestError, IdentifierKind},
+        exports::http,
+        ruma_api,
+    };
+    use ruma_identifiers::{EventId, RoomAliasId, RoomId, UserId};
+
+    ruma_api! {
+        metadata {
+            description: "Get an event as seen by a user.",
+            method: GET,
+            name: "get_event",
+            path: "/_matrix/client/r0/rooms/:room_id/event/:event_id",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub room_id: RoomId,
+            #[ruma_api(path)]
+            pub event_id: EventId,
+            #[ruma_api(query)]
+            pub user_id: UserId,
+            #[ruma_api(query)]
+            pub alias: Option<RoomAliasId>,
+        }
+
+        response {}
+    }
+
+    fn incoming(path: &str, query: &str) -> Result<Request, FromHttpRequestError> {
+        let uri = format!("http://localhost/_matrix/client/r0/rooms/{}?{}", path, query);
+        Request::try_from(http::Request::builder().uri(uri).body(Vec::new()).unwrap())
+    }
+
+    fn path_error(path: &str) -> Option<IdentifierKind> {
+        match incoming(path, "user_id=%40carl%3Aexample.com") {
+            Err(FromHttpRequestError::Deserialization(err)) => {
+                err.invalid_identifier().map(|err| err.kind())
+            }
+            _ => panic!("expected a deserialization error"),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let request = Request {
+            room_id: RoomId::try_from("!n8f893n9:example.com").unwrap(),
+            event_id: EventId::try_from("$h29iv0s8:example.com").unwrap(),
+            user_id: UserId::try_from("@carl:example.com").unwrap(),
+            alias: Some(RoomAliasId::try_from("#ruma:example.com").unwrap()),
+        };
+        let http_request = http::Request::<Vec<u8>>::try_from(request.clone()).unwrap();
+
+        let parsed = Request::try_from(http_request).unwrap();
+        assert_eq!(parsed.room_id, request.room_id);
+        assert_eq!(parsed.event_id, request.event_id);
+        assert_eq!(parsed.user_id, request.user_id);
+        assert_eq!(parsed.alias, request.alias);
+
+        let path = "!n8f893n9:example.com/event/$h29iv0s8:example.com";
+        let parsed = incoming(path, "user_id=%40carl%3Aexample.com").unwrap();
+        assert_eq!(parsed.alias, None);
+    }
+
+    #[test]
+    fn invalid_path_parameters() {
+        assert_eq!(
+            path_error("n8f893n9/event/$h29iv0s8:example.com"),
+            Some(IdentifierKind::RoomId)
+        );
+        assert_eq!(
+            path_error("!n8f893n9:example.com/event/h29iv0s8"),
+            Some(IdentifierKind::EventId)
+        );
+        assert_eq!(
+            path_error("@carl:example.com/event/$h29iv0s8:example.com"),
+            Some(IdentifierKind::RoomId)
+        );
+    }
+
+    #[test]
+    fn invalid_query_parameters() {
+        let path = "!n8f893n9:example.com/event/$h29iv0s8:example.com";
+
+        let err = incoming(path, "user_id=carl").unwrap_err();
+        assert!(err.to_string().contains("the user ID is invalid"), "{}", err);
+
+        let err = incoming(path, "user_id=%40carl%3Aexample.com&alias=ruma").unwrap_err();
+        assert!(err.to_string().contains("the room alias is invalid"), "{}", err);
+    }
+}
+
+mod duplicate_query_parameters {
+    use std::convert::TryFrom;
+
+    use ruma_api::{error::FromHttpRequestError, exports::http, ruma_api};
+
+    ruma_api! {
+        mod reject;
+
+        metadata {
+            description: "Get the messages of a room.",
+            method: GET,
+            name: "get_messages",
+            path: "/_matrix/client/r0/rooms/:room_id/messages",
+            rate_limited: false,
+            requires_authentication: true,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub room_id: String,
+            #[ruma_api(query)]
+            pub from: String,
+            #[ruma_api(query)]
+            pub limit: Option<u32>,
+        }
+
+        response {}
+    }
+
+    ruma_api! {
+        mod last_wins;
+
+        metadata {
+            description: "Get the messages of a room.",
+            method: GET,
+            name: "get_messages",
+            path: "/_matrix/client/r0/rooms/:room_id/messages",
+            rate_limited: false,
+            requires_authentication: true,
+            duplicate_query_parameters: LastWins,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub room_id: String,
+            #[ruma_api(query)]
+            pub from: String,
+            #[ruma_api(query)]
+            pub limit: Option<u32>,
+        }
+
+        response {}
+    }
+
+    fn http_request(query: &str) -> http::Request<Vec<u8>> {
+        let uri = format!("http://localhost/_matrix/client/r0/rooms/!room/messages?{}", query);
+        http::Request::builder().uri(uri).body(Vec::new()).unwrap()
+    }
+
+    #[test]
+    fn rejected_by_default() {
+        let request = reject::Request::try_from(http_request("from=t1&limit=5&li%6Dit=10"));
+        match request {
+            Err(FromHttpRequestError::Deserialization(err)) => {
+                assert_eq!(err.duplicate_query_parameter().unwrap().name(), "limit");
+                assert_eq!(err.to_string(), "the query parameter `limit` appears more than once");
+            }
+            _ => panic!("expected a deserialization error"),
+        }
+
+        let request = reject::Request::try_from(http_request("from=t1&limit=5")).unwrap();
+        assert_eq!(request.limit, Some(5));
+    }
+
+    #[test]
+    fn last_value_wins() {
+        let request =
+            last_wins::Request::try_from(http_request("from=t1&limit=5&from=t2&limit=10")).unwrap();
+        assert_eq!(request.from, "t2");
+        assert_eq!(request.limit, Some(10));
+    }
+
+    #[test]
+    fn keep_last() {
+        assert_eq!(ruma_api::query::keep_last("a=1&b=2&a=3&c=4&c=5", &["a"]), "b=2&a=3&c=4&c=5");
+        assert!(ruma_api::query::check_unique("a=1&b=2&b=3", &["a"]).is_ok());
+    }
+}
+
+mod raw_identifiers {
+    use std::convert::TryFrom;
+
+    use ruma_api::{
+        examples::{EndpointExamples, FieldLocation},
+        exports::http,
+        ruma_api,
+    };
+
+    ruma_api! {
+        metadata {
+            description: "Send an event of the given type.",
+            method: PUT,
+            name: "send_event",
+            path: "/_matrix/client/r0/rooms/:room_id/send/:type",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub room_id: String,
+            #[ruma_api(path, encode = STRICT)]
+            pub r#type: String,
+            #[ruma_api(query)]
+            pub r#for: Option<String>,
+            #[ruma_api(example = "1")]
+            pub r#ref: u32,
+        }
+
+        response {
+            pub r#type: String,
+        }
+    }
+
+    #[test]
+    fn request_round_trip() {
+        let request = Request {
+            room_id: "!room:example.org".to_owned(),
+            r#type: "m.room.message".to_owned(),
+            r#for: Some("alice".to_owned()),
+            r#ref: 1,
+        };
+        let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+        assert_eq!(
+            http_request.uri().path_and_query().unwrap(),
+            "/_matrix/client/r0/rooms/!room:example.org/send/m.room.message?for=alice"
+        );
+        assert_eq!(http_request.body(), br#"{"ref":1}"#);
+
+        let request = Request::try_from(http_request).unwrap();
+        assert_eq!(request.r#type, "m.room.message");
+        assert_eq!(request.r#for.as_deref(), Some("alice"));
+        assert_eq!(request.r#ref, 1);
+    }
+
+    #[test]
+    fn response_round_trip() {
+        let response = Response { r#type: "m.room.message".to_owned() };
+        let http_response = http::Response::<Vec<u8>>::try_from(response).unwrap();
+        assert_eq!(http_response.body(), br#"{"type":"m.room.message"}"#);
+
+        let response = Response::try_from(http_response).unwrap();
+        assert_eq!(response.r#type, "m.room.message");
+    }
+
+    #[test]
+    fn examples_use_the_unprefixed_name() {
+        let examples = Request::request_examples();
+        assert_eq!(examples[0].field, "ref");
+        assert_eq!(examples[0].location, FieldLocation::Body);
+    }
+}
+
+mod server_kind {
+    use ruma_api::{exports::http, options::RequestOptions, ruma_api, Endpoint as _, ServerKind};
+
+    ruma_api! {
+        metadata {
+            description: "Look up the Matrix user ID for a 3PID.",
+            method: GET,
+            name: "lookup_3pid",
+            path: "/_matrix/identity/api/v1/lookup",
+            rate_limited: false,
+            requires_authentication: false,
+            server_kind: IdentityServer,
+        }
+
+        request {
+            #[ruma_api(query)]
+            pub address: String,
+        }
+
+        response {}
+    }
+
+    fn options() -> RequestOptions {
+        let mut options = RequestOptions::default();
+        options.base_url = Some("https://matrix.example.org".parse().unwrap());
+        options.host = Some("example.org".to_owned());
+        options
+    }
+
+    #[test]
+    fn identity_server_url_is_used() {
+        assert_eq!(Request::METADATA.server_kind(), ServerKind::IdentityServer);
+
+        let mut options = options();
+        options.identity_server_url = Some("https://identity.example.org/".parse().unwrap());
+        let options = options.for_endpoint(&Request::METADATA);
+
+        let request = Request { address: "alice@example.org".to_owned() };
+        let http_request = request.try_into_http_request_with(&options).unwrap();
+        assert_eq!(
+            http_request.uri(),
+            "https://identity.example.org/_matrix/identity/api/v1/lookup?address=alice%40example.org"
+        );
+        assert_eq!(http_request.headers()[http::header::HOST], "identity.example.org");
+    }
+
+    #[test]
+    fn base_url_is_kept_without_identity_server_url() {
+        let options = options().for_endpoint(&Request::METADATA);
+        assert_eq!(options.base_url.unwrap(), "https://matrix.example.org/");
+        assert_eq!(options.host.as_deref(), Some("example.org"));
+    }
+
+    #[test]
+    fn homeserver_endpoints_keep_base_url() {
+        let mut options = options();
+        options.identity_server_url = Some("https://identity.example.org".parse().unwrap());
+        let options = options.for_endpoint(&super::Request::METADATA);
+        assert_eq!(options.base_url.unwrap(), "https://matrix.example.org/");
+    }
+}
+
+mod multiple_methods {
+    use std::convert::TryFrom;
+
+    use ruma_api::{
+        exports::http::{self, Method},
+        ruma_api, Endpoint as _,
+    };
+
+    ruma_api! {
+        metadata {
+            description: "Get the public rooms of a server, optionally filtered.",
+            method: GET | POST,
+            name: "get_public_rooms",
+            path: "/_matrix/client/r0/publicRooms",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(query)]
+            pub server: Option<String>,
+            pub limit: Option<u32>,
+        }
+
+        response {
+            pub total_room_count_estimate: u32,
+        }
+    }
+
+    #[test]
+    fn metadata() {
+        assert_eq!(*Request::METADATA.method(), Method::GET);
+        assert_eq!(Request::METADATA.other_methods(), [Method::POST]);
+        assert!(Request::METADATA.allows_method(&Method::POST));
+        assert!(!Request::METADATA.allows_method(&Method::PUT));
+    }
+
+    #[test]
+    fn chosen_method() {
+        let request = Request { server: Some("example.org".to_owned()), limit: Some(10) };
+        let http_request = request.try_into_http_request_with_method(Method::POST).unwrap();
+        assert_eq!(*http_request.method(), Method::POST);
+        assert_eq!(http_request.uri().query(), Some("server=example.org"));
+
+        let incoming = Request::try_from(http_request).unwrap();
+        assert_eq!(incoming.limit, Some(10));
+    }
+
+    #[test]
+    fn default_method() {
+        let http_request = http::Request::try_from(Request { server: None, limit: None }).unwrap();
+        assert_eq!(*http_request.method(), Method::GET);
+    }
+
+    #[test]
+    fn unsupported_method() {
+        let err = Request { server: None, limit: None }
+            .try_into_http_request_with_method(Method::PUT)
+            .unwrap_err();
+        let unsupported = err.unsupported_method().unwrap();
+        assert_eq!(*unsupported.method(), Method::PUT);
+        assert_eq!(unsupported.endpoint(), "get_public_rooms");
+    }
+
+    #[test]
+    fn empty_body_is_accepted() {
+        let http_request = http::Request::builder()
+            .method(Method::GET)
+            .uri("https://example.org/_matrix/client/r0/publicRooms?server=example.org")
+            .body(Vec::new())
+            .unwrap();
+
+        let incoming = Request::try_from(http_request).unwrap();
+        assert_eq!(incoming.server.as_deref(), Some("example.org"));
+        assert_eq!(incoming.limit, None);
+    }
+}
+
+mod suggested_timeout {
+    use std::time::Duration;
+
+    use ruma_api::{
+        options::{request_timeout, RequestOptions},
+        ruma_api, Endpoint as _, MetadataRef,
+    };
+
+    ruma_api! {
+        metadata {
+            description: "Get events since the last sync.",
+            method: GET,
+            name: "sync",
+            path: "/_matrix/client/r0/sync",
+            rate_limited: false,
+            requires_authentication: true,
+            suggested_timeout: Duration::from_secs(90),
+        }
+
+        request {}
+
+        response {}
+    }
+
+    #[test]
+    fn metadata() {
+        assert_eq!(Request::METADATA.suggested_timeout(), Some(Duration::from_secs(90)));
+        assert_eq!(super::Request::METADATA.suggested_timeout(), None);
+    }
+
+    #[test]
+    fn used_without_timeout_option() {
+        let options = RequestOptions::default().for_endpoint(&Request::METADATA);
+        let http_request = Request.try_into_http_request_with(&options).unwrap();
+        assert_eq!(request_timeout(&http_request), Some(Duration::from_secs(90)));
+
+        let mut options = RequestOptions::default();
+        options.timeout = Some(Duration::from_secs(5));
+        let options = options.for_endpoint(&Request::METADATA);
+        assert_eq!(options.timeout, Some(Duration::from_secs(5)));
+    }
+
+    #[test]
+    fn serde() {
+        let json = serde_json::to_string(&Request::METADATA).unwrap();
+        assert!(json.contains(r#""suggested_timeout_ms":90000"#));
+
+        let metadata: MetadataRef<'_> = serde_json::from_str(&json).unwrap();
+        assert_eq!(metadata.suggested_timeout, Some(Duration::from_secs(90)));
+    }
+}
+
+mod deprecation {
+    use ruma_api::{
+        options::RequestOptions,
+        ruma_api,
+        versions::{EndpointStatus, MatrixVersion},
+        Endpoint as _,
+    };
+
+    ruma_api! {
+        metadata {
+            description: "Get the presence list of a user.",
+            method: GET,
+            name: "get_presence_list",
+            path: "/_matrix/client/r0/presence/list/:user_id",
+            rate_limited: false,
+            requires_authentication: true,
+            deprecated: R0_4_0,
+            removed: R0_6_0,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub user_id: String,
+        }
+
+        response {}
+    }
+
+    #[test]
+    fn status_for() {
+        let metadata = Request::METADATA;
+        assert_eq!(metadata.deprecated(), Some(MatrixVersion::R0_4_0));
+        assert_eq!(metadata.removed(), Some(MatrixVersion::R0_6_0));
+
+        assert_eq!(metadata.status_for(MatrixVersion::R0_3_0), EndpointStatus::Supported);
+        assert_eq!(metadata.status_for(MatrixVersion::R0_5_0), EndpointStatus::Deprecated);
+        assert_eq!(metadata.status_for(MatrixVersion::R0_6_0), EndpointStatus::Removed);
+        assert_eq!(
+            super::Request::METADATA.status_for(MatrixVersion::R0_6_0),
+            EndpointStatus::Supported
+        );
+    }
+
+    #[test]
+    fn metadata_serde() {
+        let json = serde_json::to_string(&Request::METADATA).unwrap();
+        assert!(json.ends_with(r#""deprecated":"r0.4.0","removed":"r0.6.0"}"#));
+
+        let metadata: ruma_api::MetadataRef<'_> = serde_json::from_str(&json).unwrap();
+        assert_eq!(metadata.removed, Some(MatrixVersion::R0_6_0));
+    }
+
+    #[test]
+    fn options_for_outdated_endpoint() {
+        let mut options = RequestOptions::default();
+        options.matrix_version = Some(MatrixVersion::R0_6_0);
+        let options = options.for_endpoint(&Request::METADATA);
+        assert_eq!(options.matrix_version, Some(MatrixVersion::R0_6_0));
+    }
+}
+
+mod cookies {
+    use std::convert::TryFrom;
+
+    use ruma_api::{
+        error::FromHttpResponseError,
+        exports::http::{
+            self,
+            header::{COOKIE, SET_COOKIE},
+        },
+        ruma_api,
+    };
+
+    ruma_api! {
+        metadata {
+            description: "Log in to a bridged network with a session cookie.",
+            method: POST,
+            name: "bridge_login",
+            path: "/_matrix/bridge/login",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(cookie = "session")]
+            pub session: String,
+            #[ruma_api(cookie = "locale")]
+            pub locale: Option<String>,
+            pub user: String,
+        }
+
+        response {
+            #[ruma_api(cookie = "session")]
+            pub session: String,
+            #[ruma_api(cookie = "csrf")]
+            pub csrf: Option<String>,
+        }
+    }
+
+    #[test]
+    fn request_cookies_share_one_header() {
+        let request = Request {
+            session: "abc".to_owned(),
+            locale: Some("en".to_owned()),
+            user: "alice".to_owned(),
+        };
+        let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+        assert_eq!(http_request.headers().get_all(COOKIE).iter().count(), 1);
+        assert_eq!(http_request.headers()[COOKIE], "session=abc; locale=en");
+
+        let incoming = Request::try_from(http_request).unwrap();
+        assert_eq!(incoming.session, "abc");
+        assert_eq!(incoming.locale.as_deref(), Some("en"));
+    }
+
+    #[test]
+    fn request_cookies_from_several_headers() {
+        let http_request = http::Request::builder()
+            .method("POST")
+            .uri("https://example.org/_matrix/bridge/login")
+            .header(COOKIE, "theme=dark")
+            .header(COOKIE, "other=1; session=\"abc\"")
+            .body(br#"{"user":"alice"}"#.to_vec())
+            .unwrap();
+
+        let incoming = Request::try_from(http_request).unwrap();
+        assert_eq!(incoming.session, "abc");
+        assert_eq!(incoming.locale, None);
+    }
+
+    #[test]
+    fn missing_request_cookie() {
+        let http_request = http::Request::builder()
+            .method("POST")
+            .uri("https://example.org/_matrix/bridge/login")
+            .header(COOKIE, "locale=en")
+            .body(br#"{"user":"alice"}"#.to_vec())
+            .unwrap();
+
+        assert!(Request::try_from(http_request).is_err());
+    }
+
+    #[test]
+    fn response_cookies_get_one_header_each() {
+        let response = Response { session: "abc".to_owned(), csrf: Some("xyz".to_owned()) };
+        let http_response = http::Response::<Vec<u8>>::try_from(response).unwrap();
+        let set_cookies: Vec<_> = http_response.headers().get_all(SET_COOKIE).iter().collect();
+        assert_eq!(set_cookies, ["session=abc", "csrf=xyz"]);
+
+        let incoming = Response::try_from(http_response).unwrap();
+        assert_eq!(incoming.session, "abc");
+        assert_eq!(incoming.csrf.as_deref(), Some("xyz"));
+    }
+
+    #[test]
+    fn response_cookie_attributes_are_ignored() {
+        let http_response = http::Response::builder()
+            .header(SET_COOKIE, "session=old; Path=/")
+            .header(SET_COOKIE, "session=new; Path=/; HttpOnly; Secure")
+            .body(b"{}".to_vec())
+            .unwrap();
+
+        let incoming = Response::try_from(http_response).unwrap();
+        assert_eq!(incoming.session, "new");
+        assert_eq!(incoming.csrf, None);
+    }
+
+    #[test]
+    fn missing_response_cookie() {
+        let http_response =
+            http::Response::builder().header(SET_COOKIE, "csrf=xyz").body(b"{}".to_vec()).unwrap();
+
+        match Response::try_from(http_response) {
+            Err(FromHttpResponseError::Deserialization(_)) => {}
+            other => panic!("expected a deserialization error, got {:?}", other),
+        }
+    }
+}
+
+mod preview {
+    use ruma_api::{
+        exports::http,
+        options::{RequestOptions, REDACTED},
+        Endpoint as _,
+    };
+
+    use super::generated_tests::set_topic::Request;
+
+    fn request() -> Request {
+        Request { room: "!room:example.org".to_owned(), notify: true, topic: "Hello".to_owned() }
+    }
+
+    #[test]
+    fn describes_request() {
+        let preview = request().preview().unwrap();
+
+        assert_eq!(preview.method(), http::Method::PUT);
+        assert_eq!(preview.uri().path(), "/_matrix/topic/!room:example.org");
+        assert_eq!(preview.uri().query(), Some("notify=true"));
+        assert_eq!(preview.headers()[http::header::CONTENT_TYPE], "application/json");
+        assert_eq!(preview.body_len(), br#"{"topic":"Hello"}"#.len());
+    }
+
+    #[test]
+    fn redacts_access_token() {
+        let mut options = RequestOptions::default();
+        options.base_url = Some("https://matrix.example.org".parse().unwrap());
+        options.access_token = Some("secret".to_owned());
+
+        let preview = request().preview_with(&options).unwrap();
+        assert_eq!(preview.headers()[http::header::AUTHORIZATION], REDACTED);
+        assert_eq!(
+            preview.to_string(),
+            "PUT https://matrix.example.org/_matrix/topic/!room:example.org?notify=true (17 bytes)"
+        );
+
+        options.access_token_placement = ruma_api::options::AccessTokenPlacement::Query;
+        let preview = request().preview_with(&options).unwrap();
+        assert_eq!(preview.uri().query(), Some("notify=true&access_token=redacted"));
+        assert!(!format!("{:?}", preview).contains("secret"));
+    }
+}
@@ -0,0 +1,207 @@
+#![cfg(feature = "dedup")]
+
+mod common;
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll},
+};
+
+use ruma_api::{
+    client::{HttpResponseFuture, Transport},
+    dedup::RequestDeduplicator,
+    exports::http,
+    ruma_api,
+};
+
+use self::common::poll;
+
+ruma_api! {
+    mod get_display_name;
+
+    metadata {
+        description: "Get the display name of a user.",
+        method: GET,
+        name: "get_display_name",
+        path: "/_matrix/client/r0/profile/:user_id/displayname",
+        rate_limited: false,
+        requires_authentication: false,
+        cacheable: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub user_id: String,
+    }
+
+    response {
+        pub displayname: String,
+    }
+}
+
+ruma_api! {
+    mod set_display_name;
+
+    metadata {
+        description: "Set the display name of a user.",
+        method: PUT,
+        name: "set_display_name",
+        path: "/_matrix/client/r0/profile/:user_id/displayname",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub user_id: String,
+    }
+
+    response {
+        pub displayname: String,
+    }
+}
+
+/// A transport that holds back all responses until it is opened, answering with the number of
+/// requests it has sent so far as the display name.
+#[derive(Default)]
+struct GatedTransport {
+    open: AtomicBool,
+    sent: AtomicUsize,
+}
+
+impl Transport for GatedTransport {
+    type Error = std::convert::Infallible;
+
+    fn send_http(&self, _: http::Request<Vec<u8>>) -> HttpResponseFuture<'_, Self::Error> {
+        let sent = self.sent.fetch_add(1, Ordering::SeqCst) + 1;
+        let body = serde_json::json!({ "displayname": sent.to_string() });
+        let response = http::Response::new(serde_json::to_vec(&body).unwrap());
+        Box::pin(Gate { open: &self.open, response: Some(response) })
+    }
+}
+
+struct Gate<'a> {
+    open: &'a AtomicBool,
+    response: Option<http::Response<Vec<u8>>>,
+}
+
+impl Future for Gate<'_> {
+    type Output = Result<http::Response<Vec<u8>>, std::convert::Infallible>;
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+        if self.open.load(Ordering::SeqCst) {
+            Poll::Ready(Ok(self.response.take().unwrap()))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+fn get(user_id: &str) -> get_display_name::Request {
+    get_display_name::Request { user_id: user_id.to_owned() }
+}
+
+#[test]
+fn identical_requests_share_response() {
+    let deduplicator = RequestDeduplicator::new(GatedTransport::default());
+    let transport = deduplicator.transport();
+
+    let mut first = deduplicator.send(get("@alice:example.org"));
+    let mut second = deduplicator.send(get("@alice:example.org"));
+    let mut other = deduplicator.send(get("@bob:example.org"));
+
+    assert!(poll(first.as_mut()).is_pending());
+    assert!(poll(second.as_mut()).is_pending());
+    assert!(poll(other.as_mut()).is_pending());
+    assert_eq!(deduplicator.in_flight(), 2);
+    assert_eq!(transport.sent.load(Ordering::SeqCst), 2);
+
+    transport.open.store(true, Ordering::SeqCst);
+    let first = match poll(first.as_mut()) {
+        Poll::Ready(result) => result.unwrap(),
+        Poll::Pending => panic!("request was not sent"),
+    };
+    let second = match poll(second.as_mut()) {
+        Poll::Ready(result) => result.unwrap(),
+        Poll::Pending => panic!("response was not shared"),
+    };
+
+    assert!(Arc::ptr_eq(&first, &second));
+    assert_eq!(first.displayname, "1");
+    assert_eq!(deduplicator.in_flight(), 1);
+}
+
+#[test]
+fn waiters_send_request_if_first_is_dropped() {
+    let deduplicator = RequestDeduplicator::new(GatedTransport::default());
+    let transport = deduplicator.transport();
+
+    let mut first = deduplicator.send(get("@alice:example.org"));
+    let mut second = deduplicator.send(get("@alice:example.org"));
+    assert!(poll(first.as_mut()).is_pending());
+    assert!(poll(second.as_mut()).is_pending());
+
+    drop(first);
+    assert_eq!(deduplicator.in_flight(), 0);
+
+    transport.open.store(true, Ordering::SeqCst);
+    match poll(second.as_mut()) {
+        Poll::Ready(result) => assert_eq!(result.unwrap().displayname, "2"),
+        Poll::Pending => panic!("request was not sent"),
+    }
+}
+
+#[test]
+fn requests_to_other_endpoints_are_not_deduplicated() {
+    let deduplicator = RequestDeduplicator::new(GatedTransport::default());
+    let transport = deduplicator.transport();
+    let set = || set_display_name::Request { user_id: "@alice:example.org".to_owned() };
+
+    let mut first = deduplicator.send(set());
+    let mut second = deduplicator.send(set());
+    assert!(poll(first.as_mut()).is_pending());
+    assert!(poll(second.as_mut()).is_pending());
+
+    assert_eq!(deduplicator.in_flight(), 0);
+    assert_eq!(transport.sent.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn waiters_send_request_if_first_is_dropped_before_polling() {
+    let deduplicator = RequestDeduplicator::new(GatedTransport::default());
+    let transport = deduplicator.transport();
+
+    let first = deduplicator.send(get("@alice:example.org"));
+    let mut second = deduplicator.send(get("@alice:example.org"));
+    assert!(poll(second.as_mut()).is_pending());
+
+    drop(first);
+    assert_eq!(deduplicator.in_flight(), 0);
+
+    transport.open.store(true, Ordering::SeqCst);
+    match poll(second.as_mut()) {
+        Poll::Ready(result) => assert_eq!(result.unwrap().displayname, "1"),
+        Poll::Pending => panic!("request was not sent"),
+    }
+}
+
+#[test]
+fn deduplicators_do_not_share_responses() {
+    let alice = RequestDeduplicator::new(GatedTransport::default());
+    let bob = RequestDeduplicator::new(GatedTransport::default());
+
+    let mut first = alice.send(get("@carol:example.org"));
+    let mut second = bob.send(get("@carol:example.org"));
+    assert!(poll(first.as_mut()).is_pending());
+    assert!(poll(second.as_mut()).is_pending());
+
+    assert_eq!(alice.in_flight(), 1);
+    assert_eq!(bob.in_flight(), 1);
+    assert_eq!(alice.transport().sent.load(Ordering::SeqCst), 1);
+    assert_eq!(bob.transport().sent.load(Ordering::SeqCst), 1);
+}
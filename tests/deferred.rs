@@ -0,0 +1,67 @@
+#![cfg(feature = "deferred")]
+
+use std::{collections::BTreeMap, convert::TryFrom, thread};
+
+use ruma_api::{deferred::Deferred, exports::http, ruma_api};
+
+type Rooms = BTreeMap<String, Vec<u64>>;
+
+ruma_api! {
+    metadata {
+        description: "Get everything that happened since the last sync.",
+        method: GET,
+        name: "sync",
+        path: "/_matrix/client/r0/sync",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {}
+
+    response {
+        #[ruma_api(deferred)]
+        pub rooms: Rooms,
+        pub next_batch: String,
+    }
+}
+
+fn http_response(body: &[u8]) -> http::Response<Vec<u8>> {
+    http::Response::builder().body(body.to_vec()).unwrap()
+}
+
+#[test]
+fn deserialized_on_demand() {
+    let response =
+        IncomingResponse::try_from(http_response(br#"{"rooms":{"!a":[1,2]},"next_batch":"s1"}"#))
+            .unwrap();
+    assert_eq!(response.next_batch, "s1");
+    assert_eq!(response.rooms.json().get(), r#"{"!a":[1,2]}"#);
+
+    let rooms = response.rooms;
+    let rooms = thread::spawn(move || rooms.deserialize().unwrap()).join().unwrap();
+    assert_eq!(rooms["!a"], [1, 2]);
+}
+
+#[test]
+fn invalid_value_fails_late() {
+    let response =
+        IncomingResponse::try_from(http_response(br#"{"rooms":[],"next_batch":"s1"}"#)).unwrap();
+    assert!(response.rooms.deserialize().is_err());
+
+    let err = Response::try_from(response).unwrap_err();
+    assert_eq!(err.json(), &serde_json::json!([]));
+}
+
+#[test]
+fn roundtrip() {
+    let mut rooms = Rooms::new();
+    rooms.insert("!a".to_owned(), vec![1]);
+    let response = Response { rooms: rooms.clone(), next_batch: "s1".to_owned() };
+
+    let http_response = http::Response::<Vec<u8>>::try_from(response).unwrap();
+    let incoming = IncomingResponse::try_from(http_response).unwrap();
+    assert_eq!(Response::try_from(incoming).unwrap().rooms, rooms);
+
+    let deferred = Deferred::new(&rooms).unwrap();
+    assert_eq!(deferred.deserialize().unwrap(), rooms);
+}
@@ -0,0 +1,70 @@
+#![cfg(feature = "docs")]
+
+use ruma_api::{
+    docs::{render, EndpointDoc},
+    ruma_api,
+};
+
+ruma_api! {
+    mod deactivate_user;
+
+    metadata {
+        description: "Deactivate a user.",
+        method: POST,
+        name: "deactivate_user",
+        path: "/_synapse/admin/v1/deactivate/:user_id",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        #[ruma_api(example = "@alice:example.org")]
+        pub user_id: String,
+        #[ruma_api(example = "true")]
+        pub erase: bool,
+        #[ruma_api(query)]
+        pub reason: Option<String>,
+    }
+
+    response {}
+}
+
+ruma_api! {
+    mod whoami;
+
+    metadata {
+        description: "Get the user ID of the access token.",
+        method: GET,
+        name: "whoami",
+        path: "/_matrix/client/r0/account/whoami",
+        rate_limited: true,
+        requires_authentication: true,
+    }
+
+    request {}
+
+    response {
+        #[ruma_api(example = r#""@alice:example.org""#)]
+        pub user_id: String,
+    }
+}
+
+#[test]
+fn renders_endpoints() {
+    let reference = render(&[
+        EndpointDoc::of::<deactivate_user::Request>(),
+        EndpointDoc::of::<whoami::Request>(),
+    ]);
+
+    assert!(reference.starts_with("## `deactivate_user`\n\nDeactivate a user.\n"));
+    assert!(reference.contains("`POST /_synapse/admin/v1/deactivate/:user_id`"));
+    assert!(reference.contains("| `erase` | body | yes | `true` |"));
+    assert!(reference.contains("| `reason` | query | no |  |"));
+    assert!(reference.contains("```json\n{\n  \"erase\": true\n}\n```"));
+
+    let whoami = &reference[reference.find("## `whoami`").unwrap()..];
+    assert!(whoami.contains("* Rate-limited: yes"));
+    assert!(whoami.contains("### Request\n\nNo parameters."));
+    assert!(whoami.contains("\"user_id\": \"@alice:example.org\""));
+}
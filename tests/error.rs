@@ -0,0 +1,101 @@
+use std::time::Duration;
+
+use ruma_api::{
+    error::{ErrorClassification, ErrorKind, FromHttpResponseError, ServerError},
+    exports::http,
+};
+
+fn server_error(status: u16, retry_after: Option<&str>, body: &str) -> ServerError {
+    let mut builder = http::Response::builder().status(status);
+    if let Some(retry_after) = retry_after {
+        builder = builder.header("retry-after", retry_after);
+    }
+
+    ServerError::new(builder.body(body.as_bytes().to_vec()).unwrap())
+}
+
+#[test]
+fn rate_limited() {
+    assert_eq!(
+        server_error(429, Some("3"), "").classification(),
+        ErrorClassification::RateLimited { retry_after: Some(Duration::from_secs(3)) }
+    );
+    assert_eq!(
+        server_error(429, None, r#"{"errcode":"M_LIMIT_EXCEEDED","retry_after_ms":1500}"#)
+            .classification(),
+        ErrorClassification::RateLimited { retry_after: Some(Duration::from_millis(1500)) }
+    );
+    assert_eq!(
+        server_error(400, None, r#"{"errcode":"M_LIMIT_EXCEEDED"}"#).classification(),
+        ErrorClassification::RateLimited { retry_after: None }
+    );
+}
+
+#[test]
+fn unavailable() {
+    let classification = server_error(503, Some("120"), "<html></html>").classification();
+    assert_eq!(
+        classification,
+        ErrorClassification::Unavailable { retry_after: Some(Duration::from_secs(120)) }
+    );
+    assert!(classification.is_transient());
+    assert_eq!(classification.retry_after(), Some(Duration::from_secs(120)));
+
+    assert_eq!(
+        server_error(502, Some("Wed, 21 Oct 2015 07:28:00 GMT"), "").classification(),
+        ErrorClassification::Unavailable { retry_after: None }
+    );
+}
+
+#[test]
+fn unauthorized() {
+    assert_eq!(
+        server_error(401, None, r#"{"errcode":"M_UNKNOWN_TOKEN","soft_logout":true}"#)
+            .classification(),
+        ErrorClassification::Unauthorized(ErrorKind::UnknownToken { soft_logout: true })
+    );
+    assert_eq!(
+        server_error(403, None, r#"{"errcode":"M_FORBIDDEN","error":"no"}"#).classification(),
+        ErrorClassification::Unauthorized(ErrorKind::Forbidden)
+    );
+}
+
+#[test]
+fn client_and_internal_errors() {
+    let classification = server_error(400, None, r#"{"errcode":"M_BAD_JSON"}"#).classification();
+    assert_eq!(
+        classification,
+        ErrorClassification::ClientError(ErrorKind::Other("M_BAD_JSON".to_owned()))
+    );
+    assert!(!classification.is_transient());
+
+    assert_eq!(
+        server_error(500, None, "oops").classification(),
+        ErrorClassification::Internal(ErrorKind::Unknown)
+    );
+}
+
+#[test]
+fn soft_logout() {
+    let err = server_error(
+        401,
+        None,
+        r#"{"errcode":"M_UNKNOWN_TOKEN","error":"Token expired","soft_logout":true}"#,
+    );
+    assert_eq!(err.kind(), ErrorKind::UnknownToken { soft_logout: true });
+    assert!(err.requires_refresh());
+    assert!(FromHttpResponseError::Http(err).requires_refresh());
+
+    let err = server_error(401, None, r#"{"errcode":"M_UNKNOWN_TOKEN"}"#);
+    assert_eq!(err.kind(), ErrorKind::UnknownToken { soft_logout: false });
+    assert!(!err.requires_refresh());
+
+    assert_eq!(server_error(500, None, "").kind(), ErrorKind::Unknown);
+}
+
+#[test]
+fn precondition_failed() {
+    let classification = server_error(412, None, r#"{"errcode":"M_UNKNOWN"}"#).classification();
+    assert_eq!(classification, ErrorClassification::PreconditionFailed(ErrorKind::Unknown));
+    assert!(!classification.is_transient());
+}
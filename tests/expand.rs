@@ -0,0 +1,11 @@
+/// Compares the code `ruma_api!` generates for the endpoints in `tests/expand` with the
+/// `.expanded.rs` snapshots next to them.
+///
+/// This needs `cargo-expand` and a nightly toolchain, so it only runs with `--ignored`. After an
+/// intended change to the generated code, update the snapshots with
+/// `MACROTEST=overwrite cargo +nightly test --test expand -- --ignored`.
+#[test]
+#[ignore]
+fn expand() {
+    macrotest::expand("tests/expand/*.rs");
+}
@@ -0,0 +1,1075 @@
+use ruma_api::ruma_api;
+
+use ruma_api::exports::serde::de::Error as _;
+use ruma_api::exports::serde::Deserialize as _;
+use ruma_api::Endpoint as _;
+use std::convert::TryInto as _;
+#[doc = "Data for a request to the `all_field_kinds` API endpoint.\n\nDoes something."]
+#[incoming_no_deserialize]
+pub struct Request {
+    pub room_id: String,
+    pub limit: u32,
+    pub content_type: String,
+    pub body: String,
+}
+#[automatically_derived]
+impl ::core::fmt::Debug for Request {
+    #[inline]
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        ::core::fmt::Formatter::debug_struct_field4_finish(
+            f,
+            "Request",
+            "room_id",
+            &self.room_id,
+            "limit",
+            &self.limit,
+            "content_type",
+            &self.content_type,
+            "body",
+            &&self.body,
+        )
+    }
+}
+#[automatically_derived]
+impl ::core::clone::Clone for Request {
+    #[inline]
+    fn clone(&self) -> Request {
+        Request {
+            room_id: ::core::clone::Clone::clone(&self.room_id),
+            limit: ::core::clone::Clone::clone(&self.limit),
+            content_type: ::core::clone::Clone::clone(&self.content_type),
+            body: ::core::clone::Clone::clone(&self.body),
+        }
+    }
+}
+impl ruma_api::Outgoing for Request {
+    type Incoming = Self;
+}
+#[allow(clippy::new_without_default, clippy::too_many_arguments)]
+impl Request {
+    #[doc = r" Creates a new `Request` with the given required fields, and the default values for"]
+    #[doc = r" all other fields."]
+    pub fn new(room_id: String, limit: u32, content_type: String, body: String) -> Self {
+        Self {
+            room_id: room_id,
+            limit: limit,
+            content_type: content_type,
+            body: body,
+        }
+    }
+}
+#[doc = r" Data in the request body."]
+struct RequestBody {
+    pub body: String,
+}
+#[automatically_derived]
+impl ::core::fmt::Debug for RequestBody {
+    #[inline]
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        ::core::fmt::Formatter::debug_struct_field1_finish(f, "RequestBody", "body", &&self.body)
+    }
+}
+impl ruma_api::Outgoing for RequestBody {
+    type Incoming = Self;
+}
+#[doc(hidden)]
+#[allow(
+    non_upper_case_globals,
+    unused_attributes,
+    unused_qualifications,
+    clippy::absolute_paths
+)]
+const _: () = {
+    use ruma_api::exports::serde as _serde;
+    #[automatically_derived]
+    impl _serde::Serialize for RequestBody {
+        fn serialize<__S>(
+            &self,
+            __serializer: __S,
+        ) -> _serde::__private229::Result<__S::Ok, __S::Error>
+        where
+            __S: _serde::Serializer,
+        {
+            let mut __serde_state = _serde::Serializer::serialize_struct(
+                __serializer,
+                "RequestBody",
+                false as usize + 1,
+            )?;
+            _serde::ser::SerializeStruct::serialize_field(&mut __serde_state, "body", &self.body)?;
+            _serde::ser::SerializeStruct::end(__serde_state)
+        }
+    }
+};
+#[doc(hidden)]
+#[allow(
+    non_upper_case_globals,
+    unused_attributes,
+    unused_qualifications,
+    clippy::absolute_paths
+)]
+const _: () = {
+    use ruma_api::exports::serde as _serde;
+    #[automatically_derived]
+    impl<'de> _serde::Deserialize<'de> for RequestBody {
+        fn deserialize<__D>(__deserializer: __D) -> _serde::__private229::Result<Self, __D::Error>
+        where
+            __D: _serde::Deserializer<'de>,
+        {
+            #[allow(non_camel_case_types)]
+            #[doc(hidden)]
+            enum __Field {
+                __field0,
+                __ignore,
+            }
+            #[doc(hidden)]
+            struct __FieldVisitor;
+            #[automatically_derived]
+            impl<'de> _serde::de::Visitor<'de> for __FieldVisitor {
+                type Value = __Field;
+                fn expecting(
+                    &self,
+                    __formatter: &mut _serde::__private229::Formatter,
+                ) -> _serde::__private229::fmt::Result {
+                    _serde::__private229::Formatter::write_str(__formatter, "field identifier")
+                }
+                fn visit_u64<__E>(
+                    self,
+                    __value: u64,
+                ) -> _serde::__private229::Result<Self::Value, __E>
+                where
+                    __E: _serde::de::Error,
+                {
+                    match __value {
+                        0u64 => _serde::__private229::Ok(__Field::__field0),
+                        _ => _serde::__private229::Ok(__Field::__ignore),
+                    }
+                }
+                fn visit_str<__E>(
+                    self,
+                    __value: &str,
+                ) -> _serde::__private229::Result<Self::Value, __E>
+                where
+                    __E: _serde::de::Error,
+                {
+                    match __value {
+                        "body" => _serde::__private229::Ok(__Field::__field0),
+                        _ => _serde::__private229::Ok(__Field::__ignore),
+                    }
+                }
+                fn visit_bytes<__E>(
+                    self,
+                    __value: &[u8],
+                ) -> _serde::__private229::Result<Self::Value, __E>
+                where
+                    __E: _serde::de::Error,
+                {
+                    match __value {
+                        b"body" => _serde::__private229::Ok(__Field::__field0),
+                        _ => _serde::__private229::Ok(__Field::__ignore),
+                    }
+                }
+            }
+            #[automatically_derived]
+            impl<'de> _serde::Deserialize<'de> for __Field {
+                #[inline]
+                fn deserialize<__D>(
+                    __deserializer: __D,
+                ) -> _serde::__private229::Result<Self, __D::Error>
+                where
+                    __D: _serde::Deserializer<'de>,
+                {
+                    _serde::Deserializer::deserialize_identifier(__deserializer, __FieldVisitor)
+                }
+            }
+            #[doc(hidden)]
+            struct __Visitor<'de> {
+                marker: _serde::__private229::PhantomData<RequestBody>,
+                lifetime: _serde::__private229::PhantomData<&'de ()>,
+            }
+            #[automatically_derived]
+            impl<'de> _serde::de::Visitor<'de> for __Visitor<'de> {
+                type Value = RequestBody;
+                fn expecting(
+                    &self,
+                    __formatter: &mut _serde::__private229::Formatter,
+                ) -> _serde::__private229::fmt::Result {
+                    _serde::__private229::Formatter::write_str(__formatter, "struct RequestBody")
+                }
+                #[inline]
+                fn visit_seq<__A>(
+                    self,
+                    mut __seq: __A,
+                ) -> _serde::__private229::Result<Self::Value, __A::Error>
+                where
+                    __A: _serde::de::SeqAccess<'de>,
+                {
+                    let __field0 = match _serde::de::SeqAccess::next_element::<String>(&mut __seq)?
+                    {
+                        _serde::__private229::Some(__value) => __value,
+                        _serde::__private229::None => {
+                            return _serde::__private229::Err(_serde::de::Error::invalid_length(
+                                0usize,
+                                &"struct RequestBody with 1 element",
+                            ))
+                        }
+                    };
+                    _serde::__private229::Ok(RequestBody { body: __field0 })
+                }
+                #[inline]
+                fn visit_map<__A>(
+                    self,
+                    mut __map: __A,
+                ) -> _serde::__private229::Result<Self::Value, __A::Error>
+                where
+                    __A: _serde::de::MapAccess<'de>,
+                {
+                    let mut __field0: _serde::__private229::Option<String> =
+                        _serde::__private229::None;
+                    while let _serde::__private229::Some(__key) =
+                        _serde::de::MapAccess::next_key::<__Field>(&mut __map)?
+                    {
+                        match __key {
+                            __Field::__field0 => {
+                                if _serde::__private229::Option::is_some(&__field0) {
+                                    return _serde::__private229::Err(
+                                        <__A::Error as _serde::de::Error>::duplicate_field("body"),
+                                    );
+                                }
+                                __field0 = _serde::__private229::Some(
+                                    _serde::de::MapAccess::next_value::<String>(&mut __map)?,
+                                );
+                            }
+                            _ => {
+                                let _ = _serde::de::MapAccess::next_value::<_serde::de::IgnoredAny>(
+                                    &mut __map,
+                                )?;
+                            }
+                        }
+                    }
+                    let __field0 = match __field0 {
+                        _serde::__private229::Some(__field0) => __field0,
+                        _serde::__private229::None => {
+                            _serde::__private229::de::missing_field("body")?
+                        }
+                    };
+                    _serde::__private229::Ok(RequestBody { body: __field0 })
+                }
+            }
+            #[doc(hidden)]
+            const FIELDS: &'static [&'static str] = &["body"];
+            _serde::Deserializer::deserialize_struct(
+                __deserializer,
+                "RequestBody",
+                FIELDS,
+                __Visitor {
+                    marker: _serde::__private229::PhantomData::<RequestBody>,
+                    lifetime: _serde::__private229::PhantomData,
+                },
+            )
+        }
+    }
+};
+#[doc = r" Data in the request's query string."]
+struct RequestQuery {
+    pub limit: u32,
+}
+#[automatically_derived]
+impl ::core::fmt::Debug for RequestQuery {
+    #[inline]
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        ::core::fmt::Formatter::debug_struct_field1_finish(f, "RequestQuery", "limit", &&self.limit)
+    }
+}
+#[doc(hidden)]
+#[allow(
+    non_upper_case_globals,
+    unused_attributes,
+    unused_qualifications,
+    clippy::absolute_paths
+)]
+const _: () = {
+    use ruma_api::exports::serde as _serde;
+    #[automatically_derived]
+    impl<'de> _serde::Deserialize<'de> for RequestQuery {
+        fn deserialize<__D>(__deserializer: __D) -> _serde::__private229::Result<Self, __D::Error>
+        where
+            __D: _serde::Deserializer<'de>,
+        {
+            #[allow(non_camel_case_types)]
+            #[doc(hidden)]
+            enum __Field {
+                __field0,
+                __ignore,
+            }
+            #[doc(hidden)]
+            struct __FieldVisitor;
+            #[automatically_derived]
+            impl<'de> _serde::de::Visitor<'de> for __FieldVisitor {
+                type Value = __Field;
+                fn expecting(
+                    &self,
+                    __formatter: &mut _serde::__private229::Formatter,
+                ) -> _serde::__private229::fmt::Result {
+                    _serde::__private229::Formatter::write_str(__formatter, "field identifier")
+                }
+                fn visit_u64<__E>(
+                    self,
+                    __value: u64,
+                ) -> _serde::__private229::Result<Self::Value, __E>
+                where
+                    __E: _serde::de::Error,
+                {
+                    match __value {
+                        0u64 => _serde::__private229::Ok(__Field::__field0),
+                        _ => _serde::__private229::Ok(__Field::__ignore),
+                    }
+                }
+                fn visit_str<__E>(
+                    self,
+                    __value: &str,
+                ) -> _serde::__private229::Result<Self::Value, __E>
+                where
+                    __E: _serde::de::Error,
+                {
+                    match __value {
+                        "limit" => _serde::__private229::Ok(__Field::__field0),
+                        _ => _serde::__private229::Ok(__Field::__ignore),
+                    }
+                }
+                fn visit_bytes<__E>(
+                    self,
+                    __value: &[u8],
+                ) -> _serde::__private229::Result<Self::Value, __E>
+                where
+                    __E: _serde::de::Error,
+                {
+                    match __value {
+                        b"limit" => _serde::__private229::Ok(__Field::__field0),
+                        _ => _serde::__private229::Ok(__Field::__ignore),
+                    }
+                }
+            }
+            #[automatically_derived]
+            impl<'de> _serde::Deserialize<'de> for __Field {
+                #[inline]
+                fn deserialize<__D>(
+                    __deserializer: __D,
+                ) -> _serde::__private229::Result<Self, __D::Error>
+                where
+                    __D: _serde::Deserializer<'de>,
+                {
+                    _serde::Deserializer::deserialize_identifier(__deserializer, __FieldVisitor)
+                }
+            }
+            #[doc(hidden)]
+            struct __Visitor<'de> {
+                marker: _serde::__private229::PhantomData<RequestQuery>,
+                lifetime: _serde::__private229::PhantomData<&'de ()>,
+            }
+            #[automatically_derived]
+            impl<'de> _serde::de::Visitor<'de> for __Visitor<'de> {
+                type Value = RequestQuery;
+                fn expecting(
+                    &self,
+                    __formatter: &mut _serde::__private229::Formatter,
+                ) -> _serde::__private229::fmt::Result {
+                    _serde::__private229::Formatter::write_str(__formatter, "struct RequestQuery")
+                }
+                #[inline]
+                fn visit_seq<__A>(
+                    self,
+                    mut __seq: __A,
+                ) -> _serde::__private229::Result<Self::Value, __A::Error>
+                where
+                    __A: _serde::de::SeqAccess<'de>,
+                {
+                    let __field0 = match _serde::de::SeqAccess::next_element::<u32>(&mut __seq)? {
+                        _serde::__private229::Some(__value) => __value,
+                        _serde::__private229::None => {
+                            return _serde::__private229::Err(_serde::de::Error::invalid_length(
+                                0usize,
+                                &"struct RequestQuery with 1 element",
+                            ))
+                        }
+                    };
+                    _serde::__private229::Ok(RequestQuery { limit: __field0 })
+                }
+                #[inline]
+                fn visit_map<__A>(
+                    self,
+                    mut __map: __A,
+                ) -> _serde::__private229::Result<Self::Value, __A::Error>
+                where
+                    __A: _serde::de::MapAccess<'de>,
+                {
+                    let mut __field0: _serde::__private229::Option<u32> =
+                        _serde::__private229::None;
+                    while let _serde::__private229::Some(__key) =
+                        _serde::de::MapAccess::next_key::<__Field>(&mut __map)?
+                    {
+                        match __key {
+                            __Field::__field0 => {
+                                if _serde::__private229::Option::is_some(&__field0) {
+                                    return _serde::__private229::Err(
+                                        <__A::Error as _serde::de::Error>::duplicate_field("limit"),
+                                    );
+                                }
+                                __field0 = _serde::__private229::Some(
+                                    _serde::de::MapAccess::next_value::<u32>(&mut __map)?,
+                                );
+                            }
+                            _ => {
+                                let _ = _serde::de::MapAccess::next_value::<_serde::de::IgnoredAny>(
+                                    &mut __map,
+                                )?;
+                            }
+                        }
+                    }
+                    let __field0 = match __field0 {
+                        _serde::__private229::Some(__field0) => __field0,
+                        _serde::__private229::None => {
+                            _serde::__private229::de::missing_field("limit")?
+                        }
+                    };
+                    _serde::__private229::Ok(RequestQuery { limit: __field0 })
+                }
+            }
+            #[doc(hidden)]
+            const FIELDS: &'static [&'static str] = &["limit"];
+            _serde::Deserializer::deserialize_struct(
+                __deserializer,
+                "RequestQuery",
+                FIELDS,
+                __Visitor {
+                    marker: _serde::__private229::PhantomData::<RequestQuery>,
+                    lifetime: _serde::__private229::PhantomData,
+                },
+            )
+        }
+    }
+};
+#[doc(hidden)]
+#[allow(
+    non_upper_case_globals,
+    unused_attributes,
+    unused_qualifications,
+    clippy::absolute_paths
+)]
+const _: () = {
+    use ruma_api::exports::serde as _serde;
+    #[automatically_derived]
+    impl _serde::Serialize for RequestQuery {
+        fn serialize<__S>(
+            &self,
+            __serializer: __S,
+        ) -> _serde::__private229::Result<__S::Ok, __S::Error>
+        where
+            __S: _serde::Serializer,
+        {
+            let mut __serde_state = _serde::Serializer::serialize_struct(
+                __serializer,
+                "RequestQuery",
+                false as usize + 1,
+            )?;
+            _serde::ser::SerializeStruct::serialize_field(
+                &mut __serde_state,
+                "limit",
+                &self.limit,
+            )?;
+            _serde::ser::SerializeStruct::end(__serde_state)
+        }
+    }
+};
+#[doc = "Data in the response from the `all_field_kinds` API endpoint."]
+#[incoming_no_deserialize]
+pub struct Response {
+    pub content_type: String,
+    pub events: Vec<String>,
+}
+#[automatically_derived]
+impl ::core::fmt::Debug for Response {
+    #[inline]
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        ::core::fmt::Formatter::debug_struct_field2_finish(
+            f,
+            "Response",
+            "content_type",
+            &self.content_type,
+            "events",
+            &&self.events,
+        )
+    }
+}
+#[automatically_derived]
+impl ::core::clone::Clone for Response {
+    #[inline]
+    fn clone(&self) -> Response {
+        Response {
+            content_type: ::core::clone::Clone::clone(&self.content_type),
+            events: ::core::clone::Clone::clone(&self.events),
+        }
+    }
+}
+impl ruma_api::Outgoing for Response {
+    type Incoming = Self;
+}
+#[doc = r" Data in the response body."]
+struct ResponseBody {
+    pub events: Vec<String>,
+}
+#[automatically_derived]
+impl ::core::fmt::Debug for ResponseBody {
+    #[inline]
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        ::core::fmt::Formatter::debug_struct_field1_finish(
+            f,
+            "ResponseBody",
+            "events",
+            &&self.events,
+        )
+    }
+}
+impl ruma_api::Outgoing for ResponseBody {
+    type Incoming = Self;
+}
+#[doc(hidden)]
+#[allow(
+    non_upper_case_globals,
+    unused_attributes,
+    unused_qualifications,
+    clippy::absolute_paths
+)]
+const _: () = {
+    use ruma_api::exports::serde as _serde;
+    #[automatically_derived]
+    impl _serde::Serialize for ResponseBody {
+        fn serialize<__S>(
+            &self,
+            __serializer: __S,
+        ) -> _serde::__private229::Result<__S::Ok, __S::Error>
+        where
+            __S: _serde::Serializer,
+        {
+            let mut __serde_state = _serde::Serializer::serialize_struct(
+                __serializer,
+                "ResponseBody",
+                false as usize + 1,
+            )?;
+            _serde::ser::SerializeStruct::serialize_field(
+                &mut __serde_state,
+                "events",
+                &self.events,
+            )?;
+            _serde::ser::SerializeStruct::end(__serde_state)
+        }
+    }
+};
+#[doc(hidden)]
+#[allow(
+    non_upper_case_globals,
+    unused_attributes,
+    unused_qualifications,
+    clippy::absolute_paths
+)]
+const _: () = {
+    use ruma_api::exports::serde as _serde;
+    #[automatically_derived]
+    impl<'de> _serde::Deserialize<'de> for ResponseBody {
+        fn deserialize<__D>(__deserializer: __D) -> _serde::__private229::Result<Self, __D::Error>
+        where
+            __D: _serde::Deserializer<'de>,
+        {
+            #[allow(non_camel_case_types)]
+            #[doc(hidden)]
+            enum __Field {
+                __field0,
+                __ignore,
+            }
+            #[doc(hidden)]
+            struct __FieldVisitor;
+            #[automatically_derived]
+            impl<'de> _serde::de::Visitor<'de> for __FieldVisitor {
+                type Value = __Field;
+                fn expecting(
+                    &self,
+                    __formatter: &mut _serde::__private229::Formatter,
+                ) -> _serde::__private229::fmt::Result {
+                    _serde::__private229::Formatter::write_str(__formatter, "field identifier")
+                }
+                fn visit_u64<__E>(
+                    self,
+                    __value: u64,
+                ) -> _serde::__private229::Result<Self::Value, __E>
+                where
+                    __E: _serde::de::Error,
+                {
+                    match __value {
+                        0u64 => _serde::__private229::Ok(__Field::__field0),
+                        _ => _serde::__private229::Ok(__Field::__ignore),
+                    }
+                }
+                fn visit_str<__E>(
+                    self,
+                    __value: &str,
+                ) -> _serde::__private229::Result<Self::Value, __E>
+                where
+                    __E: _serde::de::Error,
+                {
+                    match __value {
+                        "events" => _serde::__private229::Ok(__Field::__field0),
+                        _ => _serde::__private229::Ok(__Field::__ignore),
+                    }
+                }
+                fn visit_bytes<__E>(
+                    self,
+                    __value: &[u8],
+                ) -> _serde::__private229::Result<Self::Value, __E>
+                where
+                    __E: _serde::de::Error,
+                {
+                    match __value {
+                        b"events" => _serde::__private229::Ok(__Field::__field0),
+                        _ => _serde::__private229::Ok(__Field::__ignore),
+                    }
+                }
+            }
+            #[automatically_derived]
+            impl<'de> _serde::Deserialize<'de> for __Field {
+                #[inline]
+                fn deserialize<__D>(
+                    __deserializer: __D,
+                ) -> _serde::__private229::Result<Self, __D::Error>
+                where
+                    __D: _serde::Deserializer<'de>,
+                {
+                    _serde::Deserializer::deserialize_identifier(__deserializer, __FieldVisitor)
+                }
+            }
+            #[doc(hidden)]
+            struct __Visitor<'de> {
+                marker: _serde::__private229::PhantomData<ResponseBody>,
+                lifetime: _serde::__private229::PhantomData<&'de ()>,
+            }
+            #[automatically_derived]
+            impl<'de> _serde::de::Visitor<'de> for __Visitor<'de> {
+                type Value = ResponseBody;
+                fn expecting(
+                    &self,
+                    __formatter: &mut _serde::__private229::Formatter,
+                ) -> _serde::__private229::fmt::Result {
+                    _serde::__private229::Formatter::write_str(__formatter, "struct ResponseBody")
+                }
+                #[inline]
+                fn visit_seq<__A>(
+                    self,
+                    mut __seq: __A,
+                ) -> _serde::__private229::Result<Self::Value, __A::Error>
+                where
+                    __A: _serde::de::SeqAccess<'de>,
+                {
+                    let __field0 =
+                        match _serde::de::SeqAccess::next_element::<Vec<String>>(&mut __seq)? {
+                            _serde::__private229::Some(__value) => __value,
+                            _serde::__private229::None => {
+                                return _serde::__private229::Err(
+                                    _serde::de::Error::invalid_length(
+                                        0usize,
+                                        &"struct ResponseBody with 1 element",
+                                    ),
+                                )
+                            }
+                        };
+                    _serde::__private229::Ok(ResponseBody { events: __field0 })
+                }
+                #[inline]
+                fn visit_map<__A>(
+                    self,
+                    mut __map: __A,
+                ) -> _serde::__private229::Result<Self::Value, __A::Error>
+                where
+                    __A: _serde::de::MapAccess<'de>,
+                {
+                    let mut __field0: _serde::__private229::Option<Vec<String>> =
+                        _serde::__private229::None;
+                    while let _serde::__private229::Some(__key) =
+                        _serde::de::MapAccess::next_key::<__Field>(&mut __map)?
+                    {
+                        match __key {
+                            __Field::__field0 => {
+                                if _serde::__private229::Option::is_some(&__field0) {
+                                    return _serde::__private229::Err(
+                                        <__A::Error as _serde::de::Error>::duplicate_field(
+                                            "events",
+                                        ),
+                                    );
+                                }
+                                __field0 = _serde::__private229::Some(
+                                    _serde::de::MapAccess::next_value::<Vec<String>>(&mut __map)?,
+                                );
+                            }
+                            _ => {
+                                let _ = _serde::de::MapAccess::next_value::<_serde::de::IgnoredAny>(
+                                    &mut __map,
+                                )?;
+                            }
+                        }
+                    }
+                    let __field0 = match __field0 {
+                        _serde::__private229::Some(__field0) => __field0,
+                        _serde::__private229::None => {
+                            _serde::__private229::de::missing_field("events")?
+                        }
+                    };
+                    _serde::__private229::Ok(ResponseBody { events: __field0 })
+                }
+            }
+            #[doc(hidden)]
+            const FIELDS: &'static [&'static str] = &["events"];
+            _serde::Deserializer::deserialize_struct(
+                __deserializer,
+                "ResponseBody",
+                FIELDS,
+                __Visitor {
+                    marker: _serde::__private229::PhantomData::<ResponseBody>,
+                    lifetime: _serde::__private229::PhantomData,
+                },
+            )
+        }
+    }
+};
+impl Request {
+    #[doc = r" Converts this request into an `http::Request`, handing it back together"]
+    #[doc = r" with the error if that fails."]
+    #[allow(clippy::useless_conversion, unused_mut, unused_variables)]
+    pub fn try_into_http_request_or_return(
+        self,
+    ) -> Result<
+        ruma_api::exports::http::Request<Vec<u8>>,
+        ruma_api::error::IntoHttpRequestError<Self>,
+    > {
+        let request = self;
+        let metadata = Request::METADATA;
+        let request_query = RequestQuery {
+            limit: request.limit,
+        };
+        let request_body = RequestBody { body: request.body };
+        let query_str = match ruma_api::manual::serialize_query(&request_query) {
+            Ok(query_str) => query_str,
+            Err(err) => {
+                return Err(ruma_api::error::IntoHttpRequestError::new(
+                    Request {
+                        room_id: request.room_id,
+                        limit: request_query.limit,
+                        content_type: request.content_type,
+                        body: request_body.body,
+                    },
+                    std::convert::From::from(err),
+                ))
+            }
+        };
+        let body = match ruma_api::exports::serde_json::to_vec(&request_body) {
+            Ok(body) => body,
+            Err(err) => {
+                return Err(ruma_api::error::IntoHttpRequestError::new(
+                    Request {
+                        room_id: request.room_id,
+                        limit: request_query.limit,
+                        content_type: request.content_type,
+                        body: request_body.body,
+                    },
+                    std::convert::From::from(err),
+                ))
+            }
+        };
+        let mut uri = String::with_capacity(118usize);
+        uri.push_str("http://invalid-host-please-change");
+        {
+            uri.push_str("/_matrix");
+            uri.push_str("/foo");
+            ruma_api::path::push_segment(&mut uri, &request.room_id);
+        }
+        {
+            uri.push('?');
+            uri.push_str(&query_str);
+        }
+        let mut http_request = ruma_api::exports::http::Request::new(body);
+        *http_request.method_mut() = ruma_api::exports::http::Method::POST;
+        *http_request.uri_mut() = uri.parse().unwrap();
+        {
+            let headers = http_request.headers_mut();
+            headers.append(
+                ruma_api::exports::http::header::CONTENT_TYPE,
+                ruma_api::exports::http::header::HeaderValue::from_str(
+                    request.content_type.as_ref(),
+                )
+                .expect("failed to convert value into HeaderValue"),
+            );
+        }
+        {
+            let headers = http_request.headers_mut();
+            if !headers.contains_key(ruma_api::exports::http::header::CONTENT_TYPE) {
+                headers.insert(
+                    ruma_api::exports::http::header::CONTENT_TYPE,
+                    ruma_api::exports::http::header::HeaderValue::from_static("application/json"),
+                );
+            }
+        }
+        {
+            let content_length = http_request.body().len();
+            http_request.headers_mut().insert(
+                ruma_api::exports::http::header::CONTENT_LENGTH,
+                ruma_api::exports::http::header::HeaderValue::from(content_length),
+            );
+        }
+        Ok(http_request)
+    }
+}
+impl std::convert::TryFrom<Request> for ruma_api::exports::http::Request<Vec<u8>> {
+    type Error = ruma_api::error::IntoHttpError;
+    fn try_from(request: Request) -> Result<Self, Self::Error> {
+        Ok(request.try_into_http_request_or_return()?)
+    }
+}
+impl std::convert::TryFrom<ruma_api::exports::http::Response<Vec<u8>>> for Response {
+    type Error = ruma_api::error::FromHttpResponseError;
+    #[allow(clippy::useless_conversion, unused_variables)]
+    fn try_from(response: ruma_api::exports::http::Response<Vec<u8>>) -> Result<Self, Self::Error> {
+        if response.status().as_u16() < 400 {
+            let mut headers = response.headers().clone();
+            let response_body: <ResponseBody as ruma_api::Outgoing>::Incoming =
+                match ruma_api::exports::serde_json::from_slice(response.body().as_slice()) {
+                    Ok(body) => body,
+                    Err(err) => {
+                        return Err(ruma_api::error::ResponseDeserializationError::new(
+                            err, response,
+                        )
+                        .into());
+                    }
+                };
+            Ok(Self {
+                content_type: headers
+                    .remove(ruma_api::exports::http::header::CONTENT_TYPE)
+                    .expect("response missing expected header")
+                    .to_str()
+                    .expect("failed to convert HeaderValue to str")
+                    .to_owned(),
+                events: response_body.events,
+            })
+        } else {
+            Err(ruma_api::error::FromHttpResponseError::from_error_response(
+                response,
+            ))
+        }
+    }
+}
+impl std::convert::TryFrom<ruma_api::exports::http::Request<Vec<u8>>> for Request {
+    type Error = ruma_api::error::FromHttpRequestError;
+    #[allow(clippy::useless_conversion, unused_variables)]
+    fn try_from(request: ruma_api::exports::http::Request<Vec<u8>>) -> Result<Self, Self::Error> {
+        ruma_api::options::check_content_length(&request)?;
+        let path_params: [&str; 1usize] = {
+            let mut path_segments = request.uri().path().get(1..).unwrap_or("").split('/');
+            [path_segments.nth(2usize).unwrap_or("")]
+        };
+        let request_query: RequestQuery = match ruma_api::exports::serde_urlencoded::from_str(
+            &request.uri().query().unwrap_or(""),
+        ) {
+            Ok(query) => query,
+            Err(err) => {
+                return Err(
+                    ruma_api::error::RequestDeserializationError::new(err, &request).into(),
+                );
+            }
+        };
+        let headers = request.headers();
+        let request_body: <RequestBody as ruma_api::Outgoing>::Incoming =
+            match ruma_api::exports::serde_json::from_slice(request.body().as_slice()) {
+                Ok(body) => body,
+                Err(err) => {
+                    return Err(
+                        ruma_api::error::RequestDeserializationError::new(err, &request).into(),
+                    );
+                }
+            };
+        let incoming = Self {
+            room_id: {
+                use ruma_api::error::RequestDeserializationError;
+                use std::ops::Deref as _;
+                let segment = path_params[0usize].as_bytes();
+                let decoded = ruma_api::exports::percent_encoding::percent_decode(segment)
+                    .decode_utf8_lossy();
+                match std::convert::TryFrom::try_from(decoded.deref()) {
+                    Ok(val) => val,
+                    Err(err) => {
+                        return Err(RequestDeserializationError::new(err, &request).into());
+                    }
+                }
+            },
+            limit: request_query.limit,
+            content_type: match headers
+                .get(ruma_api::exports::http::header::CONTENT_TYPE)
+                .and_then(|v| v.to_str().ok())
+            {
+                Some(header) => header.to_owned(),
+                None => {
+                    return Err(ruma_api::error::RequestDeserializationError::new(
+                        ruma_api::exports::serde_json::Error::missing_field("CONTENT_TYPE"),
+                        &request,
+                    )
+                    .into());
+                }
+            },
+            body: request_body.body,
+        };
+        Ok(incoming)
+    }
+}
+impl Request {
+    #[doc = r" Whether the given request path matches the path of this endpoint, with any"]
+    #[doc = r" value for the path parameters."]
+    pub fn matches_path(path: &str) -> bool {
+        ruma_api::path::matches(Request::METADATA.path(), path)
+    }
+    #[doc = r" Checks the path, query string and headers of an incoming request before its"]
+    #[doc = r" body is read, e.g. to reject it early."]
+    #[doc = r""]
+    #[doc = r" The request is expected to be routed to this endpoint already, see"]
+    #[doc = r" `matches_path`. The returned `PartialIncoming` is completed with the body."]
+    #[allow(clippy::useless_conversion, unused_variables)]
+    pub fn try_from_parts(
+        parts: ruma_api::exports::http::request::Parts,
+    ) -> Result<ruma_api::conversion::PartialIncoming<Request>, ruma_api::error::FromHttpRequestError>
+    {
+        let request = ruma_api::exports::http::Request::from_parts(parts, Vec::<u8>::new());
+        let path_params: [&str; 1usize] = {
+            let mut path_segments = request.uri().path().get(1..).unwrap_or("").split('/');
+            [path_segments.nth(2usize).unwrap_or("")]
+        };
+        let request_query: RequestQuery = match ruma_api::exports::serde_urlencoded::from_str(
+            &request.uri().query().unwrap_or(""),
+        ) {
+            Ok(query) => query,
+            Err(err) => {
+                return Err(
+                    ruma_api::error::RequestDeserializationError::new(err, &request).into(),
+                );
+            }
+        };
+        let headers = request.headers();
+        let _: String = {
+            use ruma_api::error::RequestDeserializationError;
+            use std::ops::Deref as _;
+            let segment = path_params[0usize].as_bytes();
+            let decoded =
+                ruma_api::exports::percent_encoding::percent_decode(segment).decode_utf8_lossy();
+            match std::convert::TryFrom::try_from(decoded.deref()) {
+                Ok(val) => val,
+                Err(err) => {
+                    return Err(RequestDeserializationError::new(err, &request).into());
+                }
+            }
+        };
+        let _: String = match headers
+            .get(ruma_api::exports::http::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+        {
+            Some(header) => header.to_owned(),
+            None => {
+                return Err(ruma_api::error::RequestDeserializationError::new(
+                    ruma_api::exports::serde_json::Error::missing_field("CONTENT_TYPE"),
+                    &request,
+                )
+                .into());
+            }
+        };
+        Ok(ruma_api::conversion::PartialIncoming::new(
+            request.into_parts().0,
+        ))
+    }
+}
+impl std::convert::TryFrom<Response> for ruma_api::exports::http::Response<Vec<u8>> {
+    type Error = ruma_api::error::IntoHttpError;
+    #[allow(clippy::useless_conversion, unused_variables)]
+    fn try_from(response: Response) -> Result<Self, Self::Error> {
+        let builder = ruma_api::exports::http::Response::builder().header(
+            ruma_api::exports::http::header::CONTENT_TYPE,
+            "application/json",
+        );
+        let builder = builder.header(
+            ruma_api::exports::http::header::CONTENT_TYPE,
+            response.content_type,
+        );
+        let mut response = builder
+            .body(ruma_api::exports::serde_json::to_vec(&ResponseBody {
+                events: response.events,
+            })?)
+            .unwrap();
+        let content_length = response.body().len();
+        response.headers_mut().insert(
+            ruma_api::exports::http::header::CONTENT_LENGTH,
+            ruma_api::exports::http::header::HeaderValue::from(content_length),
+        );
+        Ok(response)
+    }
+}
+impl Response {
+    #[doc = r" Converts this response into an `http::Response`, writing the body to the given"]
+    #[doc = r" writer instead of a new `Vec<u8>`."]
+    #[doc = r""]
+    #[doc = r" The writer is used as the body of the returned `http::Response`. Since the"]
+    #[doc = r" length of the body isn't known, setting the `Content-Length` header is left"]
+    #[doc = r" to the transport."]
+    #[allow(clippy::useless_conversion, unused_mut, unused_variables)]
+    pub fn try_into_http_response_with_writer<W: std::io::Write>(
+        self,
+        mut writer: W,
+    ) -> Result<ruma_api::exports::http::Response<W>, ruma_api::error::IntoHttpError> {
+        let response = self;
+        let builder = ruma_api::exports::http::Response::builder().header(
+            ruma_api::exports::http::header::CONTENT_TYPE,
+            "application/json",
+        );
+        let builder = builder.header(
+            ruma_api::exports::http::header::CONTENT_TYPE,
+            response.content_type,
+        );
+        ruma_api::exports::serde_json::to_writer(
+            &mut writer,
+            &ResponseBody {
+                events: response.events,
+            },
+        )?;
+        Ok(builder.body(writer).unwrap())
+    }
+}
+impl ruma_api::Endpoint for Request {
+    type Response = Response;
+    #[doc = r" Metadata for the `#name` endpoint."]
+    const METADATA: ruma_api::Metadata = ruma_api::Metadata::new(
+        "Does something.",
+        &ruma_api::exports::http::Method::POST,
+        "all_field_kinds",
+        "/_matrix/foo/:room_id",
+    )
+    .with_rate_limited(true)
+    .with_requires_authentication(true);
+}
+impl ruma_api::StableEndpoint for Request {}
+impl ruma_api::examples::EndpointExamples for Request {
+    fn request_examples() -> Vec<ruma_api::examples::FieldExample> {
+        {
+            #[allow(unused_mut)]
+            let mut examples = Vec::new();
+            examples
+        }
+    }
+    fn response_examples() -> Vec<ruma_api::examples::FieldExample> {
+        {
+            #[allow(unused_mut)]
+            let mut examples = Vec::new();
+            examples
+        }
+    }
+}
+
+fn main() {}
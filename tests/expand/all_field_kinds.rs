@@ -0,0 +1,30 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "all_field_kinds",
+        path: "/_matrix/foo/:room_id",
+        rate_limited: true,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+        #[ruma_api(query)]
+        pub limit: u32,
+        #[ruma_api(header = CONTENT_TYPE)]
+        pub content_type: String,
+        pub body: String,
+    }
+
+    response {
+        #[ruma_api(header = CONTENT_TYPE)]
+        pub content_type: String,
+        pub events: Vec<String>,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,485 @@
+use std::collections::BTreeMap;
+
+use ruma_api::ruma_api;
+
+use ruma_api::exports::serde::de::Error as _;
+use ruma_api::exports::serde::Deserialize as _;
+use ruma_api::Endpoint as _;
+use std::convert::TryInto as _;
+#[doc = "Data for a request to the `newtype_body` API endpoint.\n\nDoes something."]
+#[incoming_no_deserialize]
+pub struct Request {
+    pub content: BTreeMap<String, String>,
+}
+#[automatically_derived]
+impl ::core::fmt::Debug for Request {
+    #[inline]
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        ::core::fmt::Formatter::debug_struct_field1_finish(f, "Request", "content", &&self.content)
+    }
+}
+#[automatically_derived]
+impl ::core::clone::Clone for Request {
+    #[inline]
+    fn clone(&self) -> Request {
+        Request {
+            content: ::core::clone::Clone::clone(&self.content),
+        }
+    }
+}
+impl ruma_api::Outgoing for Request {
+    type Incoming = Self;
+}
+#[allow(clippy::new_without_default, clippy::too_many_arguments)]
+impl Request {
+    #[doc = r" Creates a new `Request` with the given required fields, and the default values for"]
+    #[doc = r" all other fields."]
+    pub fn new(content: BTreeMap<String, String>) -> Self {
+        Self { content: content }
+    }
+}
+#[doc = r" Data in the request body."]
+struct RequestBody(pub BTreeMap<String, String>);
+#[automatically_derived]
+impl ::core::fmt::Debug for RequestBody {
+    #[inline]
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        ::core::fmt::Formatter::debug_tuple_field1_finish(f, "RequestBody", &&self.0)
+    }
+}
+impl ruma_api::Outgoing for RequestBody {
+    type Incoming = Self;
+}
+#[doc(hidden)]
+#[allow(
+    non_upper_case_globals,
+    unused_attributes,
+    unused_qualifications,
+    clippy::absolute_paths
+)]
+const _: () = {
+    use ruma_api::exports::serde as _serde;
+    #[automatically_derived]
+    impl _serde::Serialize for RequestBody {
+        fn serialize<__S>(
+            &self,
+            __serializer: __S,
+        ) -> _serde::__private229::Result<__S::Ok, __S::Error>
+        where
+            __S: _serde::Serializer,
+        {
+            _serde::Serializer::serialize_newtype_struct(__serializer, "RequestBody", &self.0)
+        }
+    }
+};
+#[doc(hidden)]
+#[allow(
+    non_upper_case_globals,
+    unused_attributes,
+    unused_qualifications,
+    clippy::absolute_paths
+)]
+const _: () = {
+    use ruma_api::exports::serde as _serde;
+    #[automatically_derived]
+    impl<'de> _serde::Deserialize<'de> for RequestBody {
+        fn deserialize<__D>(__deserializer: __D) -> _serde::__private229::Result<Self, __D::Error>
+        where
+            __D: _serde::Deserializer<'de>,
+        {
+            #[doc(hidden)]
+            struct __Visitor<'de> {
+                marker: _serde::__private229::PhantomData<RequestBody>,
+                lifetime: _serde::__private229::PhantomData<&'de ()>,
+            }
+            #[automatically_derived]
+            impl<'de> _serde::de::Visitor<'de> for __Visitor<'de> {
+                type Value = RequestBody;
+                fn expecting(
+                    &self,
+                    __formatter: &mut _serde::__private229::Formatter,
+                ) -> _serde::__private229::fmt::Result {
+                    _serde::__private229::Formatter::write_str(
+                        __formatter,
+                        "tuple struct RequestBody",
+                    )
+                }
+                #[inline]
+                fn visit_newtype_struct<__E>(
+                    self,
+                    __e: __E,
+                ) -> _serde::__private229::Result<Self::Value, __E::Error>
+                where
+                    __E: _serde::Deserializer<'de>,
+                {
+                    let __field0: BTreeMap<String, String> =
+                        <BTreeMap<String, String> as _serde::Deserialize>::deserialize(__e)?;
+                    _serde::__private229::Ok(RequestBody(__field0))
+                }
+                #[inline]
+                fn visit_seq<__A>(
+                    self,
+                    mut __seq: __A,
+                ) -> _serde::__private229::Result<Self::Value, __A::Error>
+                where
+                    __A: _serde::de::SeqAccess<'de>,
+                {
+                    let __field0 = match _serde::de::SeqAccess::next_element::<
+                        BTreeMap<String, String>,
+                    >(&mut __seq)?
+                    {
+                        _serde::__private229::Some(__value) => __value,
+                        _serde::__private229::None => {
+                            return _serde::__private229::Err(_serde::de::Error::invalid_length(
+                                0usize,
+                                &"tuple struct RequestBody with 1 element",
+                            ))
+                        }
+                    };
+                    _serde::__private229::Ok(RequestBody(__field0))
+                }
+            }
+            _serde::Deserializer::deserialize_newtype_struct(
+                __deserializer,
+                "RequestBody",
+                __Visitor {
+                    marker: _serde::__private229::PhantomData::<RequestBody>,
+                    lifetime: _serde::__private229::PhantomData,
+                },
+            )
+        }
+    }
+};
+#[doc = "Data in the response from the `newtype_body` API endpoint."]
+#[incoming_no_deserialize]
+pub struct Response {
+    pub content: BTreeMap<String, String>,
+}
+#[automatically_derived]
+impl ::core::fmt::Debug for Response {
+    #[inline]
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        ::core::fmt::Formatter::debug_struct_field1_finish(f, "Response", "content", &&self.content)
+    }
+}
+#[automatically_derived]
+impl ::core::clone::Clone for Response {
+    #[inline]
+    fn clone(&self) -> Response {
+        Response {
+            content: ::core::clone::Clone::clone(&self.content),
+        }
+    }
+}
+impl ruma_api::Outgoing for Response {
+    type Incoming = Self;
+}
+#[doc = r" Data in the response body."]
+struct ResponseBody(pub BTreeMap<String, String>);
+#[automatically_derived]
+impl ::core::fmt::Debug for ResponseBody {
+    #[inline]
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        ::core::fmt::Formatter::debug_tuple_field1_finish(f, "ResponseBody", &&self.0)
+    }
+}
+impl ruma_api::Outgoing for ResponseBody {
+    type Incoming = Self;
+}
+#[doc(hidden)]
+#[allow(
+    non_upper_case_globals,
+    unused_attributes,
+    unused_qualifications,
+    clippy::absolute_paths
+)]
+const _: () = {
+    use ruma_api::exports::serde as _serde;
+    #[automatically_derived]
+    impl _serde::Serialize for ResponseBody {
+        fn serialize<__S>(
+            &self,
+            __serializer: __S,
+        ) -> _serde::__private229::Result<__S::Ok, __S::Error>
+        where
+            __S: _serde::Serializer,
+        {
+            _serde::Serializer::serialize_newtype_struct(__serializer, "ResponseBody", &self.0)
+        }
+    }
+};
+#[doc(hidden)]
+#[allow(
+    non_upper_case_globals,
+    unused_attributes,
+    unused_qualifications,
+    clippy::absolute_paths
+)]
+const _: () = {
+    use ruma_api::exports::serde as _serde;
+    #[automatically_derived]
+    impl<'de> _serde::Deserialize<'de> for ResponseBody {
+        fn deserialize<__D>(__deserializer: __D) -> _serde::__private229::Result<Self, __D::Error>
+        where
+            __D: _serde::Deserializer<'de>,
+        {
+            #[doc(hidden)]
+            struct __Visitor<'de> {
+                marker: _serde::__private229::PhantomData<ResponseBody>,
+                lifetime: _serde::__private229::PhantomData<&'de ()>,
+            }
+            #[automatically_derived]
+            impl<'de> _serde::de::Visitor<'de> for __Visitor<'de> {
+                type Value = ResponseBody;
+                fn expecting(
+                    &self,
+                    __formatter: &mut _serde::__private229::Formatter,
+                ) -> _serde::__private229::fmt::Result {
+                    _serde::__private229::Formatter::write_str(
+                        __formatter,
+                        "tuple struct ResponseBody",
+                    )
+                }
+                #[inline]
+                fn visit_newtype_struct<__E>(
+                    self,
+                    __e: __E,
+                ) -> _serde::__private229::Result<Self::Value, __E::Error>
+                where
+                    __E: _serde::Deserializer<'de>,
+                {
+                    let __field0: BTreeMap<String, String> =
+                        <BTreeMap<String, String> as _serde::Deserialize>::deserialize(__e)?;
+                    _serde::__private229::Ok(ResponseBody(__field0))
+                }
+                #[inline]
+                fn visit_seq<__A>(
+                    self,
+                    mut __seq: __A,
+                ) -> _serde::__private229::Result<Self::Value, __A::Error>
+                where
+                    __A: _serde::de::SeqAccess<'de>,
+                {
+                    let __field0 = match _serde::de::SeqAccess::next_element::<
+                        BTreeMap<String, String>,
+                    >(&mut __seq)?
+                    {
+                        _serde::__private229::Some(__value) => __value,
+                        _serde::__private229::None => {
+                            return _serde::__private229::Err(_serde::de::Error::invalid_length(
+                                0usize,
+                                &"tuple struct ResponseBody with 1 element",
+                            ))
+                        }
+                    };
+                    _serde::__private229::Ok(ResponseBody(__field0))
+                }
+            }
+            _serde::Deserializer::deserialize_newtype_struct(
+                __deserializer,
+                "ResponseBody",
+                __Visitor {
+                    marker: _serde::__private229::PhantomData::<ResponseBody>,
+                    lifetime: _serde::__private229::PhantomData,
+                },
+            )
+        }
+    }
+};
+impl Request {
+    #[doc = r" Converts this request into an `http::Request`, handing it back together"]
+    #[doc = r" with the error if that fails."]
+    #[allow(clippy::useless_conversion, unused_mut, unused_variables)]
+    pub fn try_into_http_request_or_return(
+        self,
+    ) -> Result<
+        ruma_api::exports::http::Request<Vec<u8>>,
+        ruma_api::error::IntoHttpRequestError<Self>,
+    > {
+        let request = self;
+        let metadata = Request::METADATA;
+        let request_body = RequestBody(request.content);
+        let body = match ruma_api::exports::serde_json::to_vec(&request_body) {
+            Ok(body) => body,
+            Err(err) => {
+                return Err(ruma_api::error::IntoHttpRequestError::new(
+                    Request {
+                        content: request_body.0,
+                    },
+                    std::convert::From::from(err),
+                ))
+            }
+        };
+        let mut uri = String::with_capacity(45usize);
+        uri.push_str("http://invalid-host-please-change");
+        {
+            uri.push_str(metadata.path());
+        }
+        {}
+        let mut http_request = ruma_api::exports::http::Request::new(body);
+        *http_request.method_mut() = ruma_api::exports::http::Method::PUT;
+        *http_request.uri_mut() = uri.parse().unwrap();
+        {}
+        {
+            let headers = http_request.headers_mut();
+            if !headers.contains_key(ruma_api::exports::http::header::CONTENT_TYPE) {
+                headers.insert(
+                    ruma_api::exports::http::header::CONTENT_TYPE,
+                    ruma_api::exports::http::header::HeaderValue::from_static("application/json"),
+                );
+            }
+        }
+        {
+            let content_length = http_request.body().len();
+            http_request.headers_mut().insert(
+                ruma_api::exports::http::header::CONTENT_LENGTH,
+                ruma_api::exports::http::header::HeaderValue::from(content_length),
+            );
+        }
+        Ok(http_request)
+    }
+}
+impl std::convert::TryFrom<Request> for ruma_api::exports::http::Request<Vec<u8>> {
+    type Error = ruma_api::error::IntoHttpError;
+    fn try_from(request: Request) -> Result<Self, Self::Error> {
+        Ok(request.try_into_http_request_or_return()?)
+    }
+}
+impl std::convert::TryFrom<ruma_api::exports::http::Response<Vec<u8>>> for Response {
+    type Error = ruma_api::error::FromHttpResponseError;
+    #[allow(clippy::useless_conversion, unused_variables)]
+    fn try_from(response: ruma_api::exports::http::Response<Vec<u8>>) -> Result<Self, Self::Error> {
+        if response.status().as_u16() < 400 {
+            let response_body: <ResponseBody as ruma_api::Outgoing>::Incoming =
+                match ruma_api::exports::serde_json::from_slice(response.body().as_slice()) {
+                    Ok(body) => body,
+                    Err(err) => {
+                        return Err(ruma_api::error::ResponseDeserializationError::new(
+                            err, response,
+                        )
+                        .into());
+                    }
+                };
+            Ok(Self {
+                content: response_body.0,
+            })
+        } else {
+            Err(ruma_api::error::FromHttpResponseError::from_error_response(
+                response,
+            ))
+        }
+    }
+}
+impl std::convert::TryFrom<ruma_api::exports::http::Request<Vec<u8>>> for Request {
+    type Error = ruma_api::error::FromHttpRequestError;
+    #[allow(clippy::useless_conversion, unused_variables)]
+    fn try_from(request: ruma_api::exports::http::Request<Vec<u8>>) -> Result<Self, Self::Error> {
+        ruma_api::options::check_content_length(&request)?;
+        let request_body: <RequestBody as ruma_api::Outgoing>::Incoming =
+            match ruma_api::exports::serde_json::from_slice(request.body().as_slice()) {
+                Ok(body) => body,
+                Err(err) => {
+                    return Err(
+                        ruma_api::error::RequestDeserializationError::new(err, &request).into(),
+                    );
+                }
+            };
+        let incoming = Self {
+            content: request_body.0,
+        };
+        Ok(incoming)
+    }
+}
+impl Request {
+    #[doc = r" Whether the given request path matches the path of this endpoint, with any"]
+    #[doc = r" value for the path parameters."]
+    pub fn matches_path(path: &str) -> bool {
+        ruma_api::path::matches(Request::METADATA.path(), path)
+    }
+    #[doc = r" Checks the path, query string and headers of an incoming request before its"]
+    #[doc = r" body is read, e.g. to reject it early."]
+    #[doc = r""]
+    #[doc = r" The request is expected to be routed to this endpoint already, see"]
+    #[doc = r" `matches_path`. The returned `PartialIncoming` is completed with the body."]
+    #[allow(clippy::useless_conversion, unused_variables)]
+    pub fn try_from_parts(
+        parts: ruma_api::exports::http::request::Parts,
+    ) -> Result<ruma_api::conversion::PartialIncoming<Request>, ruma_api::error::FromHttpRequestError>
+    {
+        let request = ruma_api::exports::http::Request::from_parts(parts, Vec::<u8>::new());
+        Ok(ruma_api::conversion::PartialIncoming::new(
+            request.into_parts().0,
+        ))
+    }
+}
+impl std::convert::TryFrom<Response> for ruma_api::exports::http::Response<Vec<u8>> {
+    type Error = ruma_api::error::IntoHttpError;
+    #[allow(clippy::useless_conversion, unused_variables)]
+    fn try_from(response: Response) -> Result<Self, Self::Error> {
+        let builder = ruma_api::exports::http::Response::builder().header(
+            ruma_api::exports::http::header::CONTENT_TYPE,
+            "application/json",
+        );
+        let mut response = builder
+            .body(ruma_api::exports::serde_json::to_vec(&response.content)?)
+            .unwrap();
+        let content_length = response.body().len();
+        response.headers_mut().insert(
+            ruma_api::exports::http::header::CONTENT_LENGTH,
+            ruma_api::exports::http::header::HeaderValue::from(content_length),
+        );
+        Ok(response)
+    }
+}
+impl Response {
+    #[doc = r" Converts this response into an `http::Response`, writing the body to the given"]
+    #[doc = r" writer instead of a new `Vec<u8>`."]
+    #[doc = r""]
+    #[doc = r" The writer is used as the body of the returned `http::Response`. Since the"]
+    #[doc = r" length of the body isn't known, setting the `Content-Length` header is left"]
+    #[doc = r" to the transport."]
+    #[allow(clippy::useless_conversion, unused_mut, unused_variables)]
+    pub fn try_into_http_response_with_writer<W: std::io::Write>(
+        self,
+        mut writer: W,
+    ) -> Result<ruma_api::exports::http::Response<W>, ruma_api::error::IntoHttpError> {
+        let response = self;
+        let builder = ruma_api::exports::http::Response::builder().header(
+            ruma_api::exports::http::header::CONTENT_TYPE,
+            "application/json",
+        );
+        ruma_api::exports::serde_json::to_writer(&mut writer, &response.content)?;
+        Ok(builder.body(writer).unwrap())
+    }
+}
+impl ruma_api::Endpoint for Request {
+    type Response = Response;
+    #[doc = r" Metadata for the `#name` endpoint."]
+    const METADATA: ruma_api::Metadata = ruma_api::Metadata::new(
+        "Does something.",
+        &ruma_api::exports::http::Method::PUT,
+        "newtype_body",
+        "/_matrix/foo",
+    )
+    .with_rate_limited(false)
+    .with_requires_authentication(false);
+}
+impl ruma_api::StableEndpoint for Request {}
+impl ruma_api::examples::EndpointExamples for Request {
+    fn request_examples() -> Vec<ruma_api::examples::FieldExample> {
+        {
+            #[allow(unused_mut)]
+            let mut examples = Vec::new();
+            examples
+        }
+    }
+    fn response_examples() -> Vec<ruma_api::examples::FieldExample> {
+        {
+            #[allow(unused_mut)]
+            let mut examples = Vec::new();
+            examples
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,26 @@
+use std::collections::BTreeMap;
+
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: PUT,
+        name: "newtype_body",
+        path: "/_matrix/foo",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(body)]
+        pub content: BTreeMap<String, String>,
+    }
+
+    response {
+        #[ruma_api(body)]
+        pub content: BTreeMap<String, String>,
+    }
+}
+
+fn main() {}
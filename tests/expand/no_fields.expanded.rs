@@ -0,0 +1,199 @@
+use ruma_api::ruma_api;
+
+use ruma_api::exports::serde::de::Error as _;
+use ruma_api::exports::serde::Deserialize as _;
+use ruma_api::Endpoint as _;
+use std::convert::TryInto as _;
+#[doc = "Data for a request to the `no_fields` API endpoint.\n\nDoes something."]
+#[incoming_no_deserialize]
+pub struct Request;
+#[automatically_derived]
+impl ::core::fmt::Debug for Request {
+    #[inline]
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        ::core::fmt::Formatter::write_str(f, "Request")
+    }
+}
+#[automatically_derived]
+impl ::core::clone::Clone for Request {
+    #[inline]
+    fn clone(&self) -> Request {
+        Request
+    }
+}
+impl ruma_api::Outgoing for Request {
+    type Incoming = Self;
+}
+#[allow(clippy::new_without_default, clippy::too_many_arguments)]
+impl Request {
+    #[doc = r" Creates a new `Request` with the given required fields, and the default values for"]
+    #[doc = r" all other fields."]
+    pub fn new() -> Self {
+        Self
+    }
+}
+#[doc = "Data in the response from the `no_fields` API endpoint."]
+#[incoming_no_deserialize]
+pub struct Response;
+#[automatically_derived]
+impl ::core::fmt::Debug for Response {
+    #[inline]
+    fn fmt(&self, f: &mut ::core::fmt::Formatter) -> ::core::fmt::Result {
+        ::core::fmt::Formatter::write_str(f, "Response")
+    }
+}
+#[automatically_derived]
+impl ::core::clone::Clone for Response {
+    #[inline]
+    fn clone(&self) -> Response {
+        Response
+    }
+}
+impl ruma_api::Outgoing for Response {
+    type Incoming = Self;
+}
+impl Request {
+    #[doc = r" Converts this request into an `http::Request`, handing it back together"]
+    #[doc = r" with the error if that fails."]
+    #[allow(clippy::useless_conversion, unused_mut, unused_variables)]
+    pub fn try_into_http_request_or_return(
+        self,
+    ) -> Result<
+        ruma_api::exports::http::Request<Vec<u8>>,
+        ruma_api::error::IntoHttpRequestError<Self>,
+    > {
+        let request = self;
+        let metadata = Request::METADATA;
+        let mut uri = String::with_capacity(45usize);
+        uri.push_str("http://invalid-host-please-change");
+        {
+            uri.push_str(metadata.path());
+        }
+        {}
+        let mut http_request = ruma_api::exports::http::Request::new(Vec::new());
+        *http_request.method_mut() = ruma_api::exports::http::Method::GET;
+        *http_request.uri_mut() = uri.parse().unwrap();
+        {}
+        {}
+        {}
+        Ok(http_request)
+    }
+}
+impl std::convert::TryFrom<Request> for ruma_api::exports::http::Request<Vec<u8>> {
+    type Error = ruma_api::error::IntoHttpError;
+    fn try_from(request: Request) -> Result<Self, Self::Error> {
+        Ok(request.try_into_http_request_or_return()?)
+    }
+}
+impl std::convert::TryFrom<ruma_api::exports::http::Response<Vec<u8>>> for Response {
+    type Error = ruma_api::error::FromHttpResponseError;
+    #[allow(clippy::useless_conversion, unused_variables)]
+    fn try_from(response: ruma_api::exports::http::Response<Vec<u8>>) -> Result<Self, Self::Error> {
+        if response.status().as_u16() < 400 {
+            Ok(Self {})
+        } else {
+            Err(ruma_api::error::FromHttpResponseError::from_error_response(
+                response,
+            ))
+        }
+    }
+}
+impl std::convert::TryFrom<ruma_api::exports::http::Request<Vec<u8>>> for Request {
+    type Error = ruma_api::error::FromHttpRequestError;
+    #[allow(clippy::useless_conversion, unused_variables)]
+    fn try_from(request: ruma_api::exports::http::Request<Vec<u8>>) -> Result<Self, Self::Error> {
+        ruma_api::options::check_content_length(&request)?;
+        let incoming = Self {};
+        Ok(incoming)
+    }
+}
+impl Request {
+    #[doc = r" Whether the given request path matches the path of this endpoint, with any"]
+    #[doc = r" value for the path parameters."]
+    pub fn matches_path(path: &str) -> bool {
+        ruma_api::path::matches(Request::METADATA.path(), path)
+    }
+    #[doc = r" Checks the path, query string and headers of an incoming request before its"]
+    #[doc = r" body is read, e.g. to reject it early."]
+    #[doc = r""]
+    #[doc = r" The request is expected to be routed to this endpoint already, see"]
+    #[doc = r" `matches_path`. The returned `PartialIncoming` is completed with the body."]
+    #[allow(clippy::useless_conversion, unused_variables)]
+    pub fn try_from_parts(
+        parts: ruma_api::exports::http::request::Parts,
+    ) -> Result<ruma_api::conversion::PartialIncoming<Request>, ruma_api::error::FromHttpRequestError>
+    {
+        let request = ruma_api::exports::http::Request::from_parts(parts, Vec::<u8>::new());
+        Ok(ruma_api::conversion::PartialIncoming::new(
+            request.into_parts().0,
+        ))
+    }
+}
+impl std::convert::TryFrom<Response> for ruma_api::exports::http::Response<Vec<u8>> {
+    type Error = ruma_api::error::IntoHttpError;
+    #[allow(clippy::useless_conversion, unused_variables)]
+    fn try_from(response: Response) -> Result<Self, Self::Error> {
+        let builder = ruma_api::exports::http::Response::builder().header(
+            ruma_api::exports::http::header::CONTENT_TYPE,
+            "application/json",
+        );
+        let mut response = builder.body(Vec::new()).unwrap();
+        let content_length = response.body().len();
+        response.headers_mut().insert(
+            ruma_api::exports::http::header::CONTENT_LENGTH,
+            ruma_api::exports::http::header::HeaderValue::from(content_length),
+        );
+        Ok(response)
+    }
+}
+impl Response {
+    #[doc = r" Converts this response into an `http::Response`, writing the body to the given"]
+    #[doc = r" writer instead of a new `Vec<u8>`."]
+    #[doc = r""]
+    #[doc = r" The writer is used as the body of the returned `http::Response`. Since the"]
+    #[doc = r" length of the body isn't known, setting the `Content-Length` header is left"]
+    #[doc = r" to the transport."]
+    #[allow(clippy::useless_conversion, unused_mut, unused_variables)]
+    pub fn try_into_http_response_with_writer<W: std::io::Write>(
+        self,
+        mut writer: W,
+    ) -> Result<ruma_api::exports::http::Response<W>, ruma_api::error::IntoHttpError> {
+        let response = self;
+        let builder = ruma_api::exports::http::Response::builder().header(
+            ruma_api::exports::http::header::CONTENT_TYPE,
+            "application/json",
+        );
+        Ok(builder.body(writer).unwrap())
+    }
+}
+impl ruma_api::Endpoint for Request {
+    type Response = Response;
+    #[doc = r" Metadata for the `#name` endpoint."]
+    const METADATA: ruma_api::Metadata = ruma_api::Metadata::new(
+        "Does something.",
+        &ruma_api::exports::http::Method::GET,
+        "no_fields",
+        "/_matrix/foo",
+    )
+    .with_rate_limited(false)
+    .with_requires_authentication(false);
+}
+impl ruma_api::StableEndpoint for Request {}
+impl ruma_api::examples::EndpointExamples for Request {
+    fn request_examples() -> Vec<ruma_api::examples::FieldExample> {
+        {
+            #[allow(unused_mut)]
+            let mut examples = Vec::new();
+            examples
+        }
+    }
+    fn response_examples() -> Vec<ruma_api::examples::FieldExample> {
+        {
+            #[allow(unused_mut)]
+            let mut examples = Vec::new();
+            examples
+        }
+    }
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "no_fields",
+        path: "/_matrix/foo",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {}
+}
+
+fn main() {}
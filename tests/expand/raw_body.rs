@@ -0,0 +1,26 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: PUT,
+        name: "raw_body",
+        path: "/_matrix/foo",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(header = CONTENT_TYPE)]
+        pub content_type: String,
+        #[ruma_api(raw_body)]
+        pub file: Vec<u8>,
+    }
+
+    response {
+        #[ruma_api(raw_body)]
+        pub file: Vec<u8>,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,110 @@
+#![cfg(feature = "cbor")]
+
+use std::convert::TryFrom;
+
+use ruma_api::{
+    error::FromHttpRequestError,
+    exports::http::{
+        self,
+        header::{HeaderMap, HeaderValue, ACCEPT, CONTENT_LENGTH, CONTENT_TYPE, VARY},
+    },
+    format::{decode_request, decode_response, encode_request, encode_response, BodyFormat},
+    ruma_api,
+};
+use serde_json::json;
+
+ruma_api! {
+    metadata {
+        description: "Set the topic of a room.",
+        method: PUT,
+        name: "set_topic",
+        path: "/_matrix/client/r0/rooms/:room_id/topic",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+        pub topic: String,
+    }
+
+    response {
+        pub event_id: String,
+    }
+}
+
+fn accept(value: &str) -> BodyFormat {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT, HeaderValue::from_str(value).unwrap());
+    BodyFormat::accepted(&headers)
+}
+
+#[test]
+fn accepted_format() {
+    assert_eq!(BodyFormat::accepted(&HeaderMap::new()), BodyFormat::Json);
+    assert_eq!(accept("application/cbor"), BodyFormat::Cbor);
+    assert_eq!(accept("application/cbor, application/json;q=0.9"), BodyFormat::Cbor);
+    assert_eq!(accept("application/json, application/cbor"), BodyFormat::Json);
+    assert_eq!(accept("application/cbor;q=0.5, */*;q=0.8"), BodyFormat::Json);
+    assert_eq!(accept("*/*"), BodyFormat::Json);
+    assert_eq!(accept("text/html"), BodyFormat::Json);
+}
+
+#[test]
+fn cbor_round_trip() {
+    let request = Request { room_id: "!room:example.org".to_owned(), topic: "Hello".to_owned() };
+    let http_request =
+        encode_request(http::Request::<Vec<u8>>::try_from(request).unwrap(), BodyFormat::Cbor)
+            .unwrap();
+    assert_eq!(http_request.headers()[CONTENT_TYPE], "application/cbor");
+    assert_eq!(
+        http_request.headers()[CONTENT_LENGTH],
+        http_request.body().len().to_string().as_str()
+    );
+    let body: serde_json::Value = serde_cbor::from_slice(http_request.body()).unwrap();
+    assert_eq!(body, json!({ "topic": "Hello" }));
+
+    let (http_request, format) = decode_request(http_request).unwrap();
+    assert_eq!(format, BodyFormat::Cbor);
+    let request = Request::try_from(http_request).unwrap();
+    assert_eq!(request.room_id, "!room:example.org");
+    assert_eq!(request.topic, "Hello");
+
+    let response = Response { event_id: "$event".to_owned() };
+    let http_response =
+        encode_response(http::Response::<Vec<u8>>::try_from(response).unwrap(), format).unwrap();
+    assert_eq!(http_response.headers()[CONTENT_TYPE], "application/cbor");
+    assert_eq!(http_response.headers()[VARY], "Accept");
+
+    let response = Response::try_from(decode_response(http_response).unwrap()).unwrap();
+    assert_eq!(response.event_id, "$event");
+}
+
+#[test]
+fn json_is_unchanged() {
+    let request = Request { room_id: "!room:example.org".to_owned(), topic: "Hello".to_owned() };
+    let http_request =
+        encode_request(http::Request::<Vec<u8>>::try_from(request).unwrap(), BodyFormat::Json)
+            .unwrap();
+    assert_eq!(http_request.body(), br#"{"topic":"Hello"}"#);
+
+    let (http_request, format) = decode_request(http_request).unwrap();
+    assert_eq!(format, BodyFormat::Json);
+    assert_eq!(http_request.body(), br#"{"topic":"Hello"}"#);
+}
+
+#[test]
+fn invalid_cbor() {
+    let http_request = http::Request::builder()
+        .method("PUT")
+        .uri("https://example.org/_matrix/client/r0/rooms/!room:example.org/topic")
+        .header(CONTENT_TYPE, "application/cbor")
+        .body(vec![0xff, 0x00])
+        .unwrap();
+
+    match decode_request(http_request) {
+        Err(FromHttpRequestError::Deserialization(_)) => {}
+        _ => panic!("expected a deserialization error"),
+    }
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "handler")]
+
+use std::convert::TryFrom;
+
+use ruma_api::{async_trait, exports::http, ruma_api};
+
+ruma_api! {
+    metadata {
+        description: "Get a name.",
+        method: GET,
+        name: "get_name",
+        path: "/_matrix/name/:user",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub user: String,
+    }
+
+    response {
+        pub name: String,
+    }
+}
+
+struct Server;
+
+#[async_trait]
+impl Handler for Server {
+    type Error = std::convert::Infallible;
+
+    async fn handle(&self, request: Request) -> Result<Response, Self::Error> {
+        Ok(Response { name: request.user.to_uppercase() })
+    }
+}
+
+#[test]
+fn handler_trait_is_implementable() {
+    fn assert_handler<H: Handler>(_: &H) {}
+
+    assert_handler(&Server);
+
+    let http_req = http::Request::builder().uri("/_matrix/name/alice").body(Vec::new()).unwrap();
+    let request = Request::try_from(http_req).unwrap();
+    assert_eq!(request.user, "alice");
+}
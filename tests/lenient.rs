@@ -0,0 +1,91 @@
+use std::convert::TryFrom;
+
+use ruma_api::{exports::http, ruma_api};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "my_endpoint",
+        path: "/_matrix/foo",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(query)]
+        #[serde(default, deserialize_with = "ruma_api::lenient::bool")]
+        pub flag: bool,
+        #[ruma_api(query)]
+        #[serde(
+            default,
+            deserialize_with = "ruma_api::lenient::opt_bool",
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub opt_flag: Option<bool>,
+        #[ruma_api(query)]
+        #[serde(default, deserialize_with = "ruma_api::lenient::int")]
+        pub limit: u32,
+        #[ruma_api(query)]
+        #[serde(
+            default,
+            deserialize_with = "ruma_api::lenient::opt_int",
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub opt_limit: Option<u32>,
+    }
+
+    response {}
+}
+
+fn parse(query: &str) -> Result<Request, ruma_api::error::FromHttpRequestError> {
+    let http_req = http::Request::builder()
+        .uri(format!("http://localhost/_matrix/foo?{}", query))
+        .body(Vec::new())
+        .unwrap();
+    Request::try_from(http_req)
+}
+
+#[test]
+fn lenient_bool() {
+    assert!(parse("flag=true").unwrap().flag);
+    assert!(parse("flag=1").unwrap().flag);
+    assert!(!parse("flag=false").unwrap().flag);
+    assert!(!parse("flag=0").unwrap().flag);
+    assert!(!parse("flag=").unwrap().flag);
+    assert!(!parse("").unwrap().flag);
+    assert!(parse("flag=yes").is_err());
+
+    assert_eq!(parse("opt_flag=1").unwrap().opt_flag, Some(true));
+    assert_eq!(parse("opt_flag=false").unwrap().opt_flag, Some(false));
+    assert_eq!(parse("opt_flag=").unwrap().opt_flag, None);
+    assert_eq!(parse("").unwrap().opt_flag, None);
+}
+
+#[test]
+fn lenient_int() {
+    assert_eq!(parse("limit=10").unwrap().limit, 10);
+    assert_eq!(parse("limit=%2010%20").unwrap().limit, 10);
+    assert_eq!(parse("limit=").unwrap().limit, 0);
+    assert!(parse("limit=-1").is_err());
+    assert!(parse("limit=ten").is_err());
+
+    assert_eq!(parse("opt_limit=5").unwrap().opt_limit, Some(5));
+    assert_eq!(parse("opt_limit=").unwrap().opt_limit, None);
+}
+
+#[test]
+fn lenient_roundtrip() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    let req = Request { flag: true, opt_flag: None, limit: 3, opt_limit: Some(7) };
+
+    let http_req = http::Request::<Vec<u8>>::try_from(req.clone())?;
+    assert_eq!(http_req.uri().query(), Some("flag=true&limit=3&opt_limit=7"));
+
+    let req2 = Request::try_from(http_req)?;
+    assert_eq!(req.flag, req2.flag);
+    assert_eq!(req.opt_flag, req2.opt_flag);
+    assert_eq!(req.limit, req2.limit);
+    assert_eq!(req.opt_limit, req2.opt_limit);
+
+    Ok(())
+}
@@ -0,0 +1,228 @@
+#![cfg(feature = "media")]
+
+use std::convert::TryFrom;
+
+use ruma_api::{
+    exports::http,
+    media::{ByteRange, ContentDisposition, ContentRange, DispositionType},
+    ruma_api,
+};
+
+ruma_api! {
+    metadata {
+        description: "Download some media.",
+        method: GET,
+        name: "get_content",
+        path: "/_matrix/media/:media_id",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub media_id: String,
+    }
+
+    response {
+        #[ruma_api(raw_body)]
+        pub file: Vec<u8>,
+        #[ruma_api(header = CONTENT_TYPE)]
+        pub content_type: Option<String>,
+        #[ruma_api(header = CONTENT_DISPOSITION)]
+        pub content_disposition: Option<ContentDisposition>,
+    }
+}
+
+ruma_api! {
+    pub mod get_content_range;
+
+    metadata {
+        description: "Download part of some media.",
+        method: GET,
+        name: "get_content_range",
+        path: "/_matrix/media/:media_id",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub media_id: String,
+        #[ruma_api(header = RANGE)]
+        pub range: Option<ByteRange>,
+    }
+
+    response {
+        #[ruma_api(raw_body)]
+        pub file: Vec<u8>,
+        #[ruma_api(header = CONTENT_RANGE)]
+        pub content_range: Option<ContentRange>,
+    }
+}
+
+fn parse(s: &str) -> ContentDisposition {
+    ContentDisposition::try_from(s).unwrap()
+}
+
+#[test]
+fn parse_content_disposition() {
+    assert_eq!(parse("inline"), ContentDisposition::new(DispositionType::Inline));
+    assert_eq!(
+        parse("Attachment; filename=foo.html"),
+        ContentDisposition::new(DispositionType::Attachment).with_filename("foo.html")
+    );
+    assert_eq!(
+        parse(r#"attachment; filename="a \"b\"; c.txt""#).filename.unwrap(),
+        r#"a "b"; c.txt"#
+    );
+    assert_eq!(
+        parse("unknown-type; size=5; filename*=UTF-8''na%C3%AFve%20file.txt; filename=naive.txt;")
+            .filename
+            .unwrap(),
+        "naïve file.txt"
+    );
+    assert_eq!(
+        parse("attachment; filename*=iso-8859-1'en'%A3%20rates").filename.unwrap(),
+        "£ rates"
+    );
+    assert_eq!(parse("unknown-type").disposition_type, DispositionType::Attachment,);
+
+    assert!(ContentDisposition::try_from("").is_err());
+    assert!(ContentDisposition::try_from("attachment; filename").is_err());
+    assert!(ContentDisposition::try_from(r#"attachment; filename="unterminated"#).is_err());
+}
+
+#[test]
+fn display_content_disposition() {
+    let ascii = ContentDisposition::new(DispositionType::Attachment).with_filename(r#"a "b".txt"#);
+    assert_eq!(ascii.to_string(), r#"attachment; filename="a \"b\".txt""#);
+    assert_eq!(parse(&ascii.to_string()), ascii);
+
+    let unicode = ContentDisposition::new(DispositionType::Inline).with_filename("naïve file.txt");
+    assert_eq!(unicode.to_string(), "inline; filename*=utf-8''na%C3%AFve%20file.txt");
+    assert_eq!(parse(&unicode.to_string()), unicode);
+}
+
+#[test]
+fn raw_response_with_typed_headers() {
+    let response = Response {
+        file: vec![0xff, 0x00, 0xfe],
+        content_type: Some("image/png".into()),
+        content_disposition: Some(
+            ContentDisposition::new(DispositionType::Inline).with_filename("ñ.png"),
+        ),
+    };
+
+    let http_response = http::Response::<Vec<u8>>::try_from(response).unwrap();
+    assert_eq!(http_response.body(), &[0xff, 0x00, 0xfe]);
+    assert_eq!(http_response.headers().get_all(http::header::CONTENT_TYPE).iter().count(), 1);
+    assert_eq!(http_response.headers()[http::header::CONTENT_TYPE], "image/png");
+
+    let response = Response::try_from(http_response).unwrap();
+    assert_eq!(response.file, vec![0xff, 0x00, 0xfe]);
+    assert_eq!(response.content_type.unwrap(), "image/png");
+    assert_eq!(response.content_disposition.unwrap().filename.unwrap(), "ñ.png");
+}
+
+#[test]
+fn raw_response_without_optional_headers() {
+    let response = Response { file: Vec::new(), content_type: None, content_disposition: None };
+    let http_response = http::Response::<Vec<u8>>::try_from(response).unwrap();
+    assert_eq!(http_response.headers().len(), 1);
+    assert_eq!(http_response.headers()[http::header::CONTENT_LENGTH], "0");
+
+    let http_response = http::Response::builder()
+        .header(http::header::CONTENT_DISPOSITION, "attachment; filename")
+        .body(b"data".to_vec())
+        .unwrap();
+    let response = Response::try_from(http_response).unwrap();
+    assert!(response.content_type.is_none());
+    assert!(response.content_disposition.is_none());
+}
+
+#[test]
+fn parse_byte_range() {
+    assert_eq!(ByteRange::try_from("bytes=0-499"), Ok(ByteRange::FromTo(0, 499)));
+    assert_eq!(ByteRange::try_from("Bytes=9500-"), Ok(ByteRange::From(9500)));
+    assert_eq!(ByteRange::try_from("bytes=-500"), Ok(ByteRange::Last(500)));
+
+    assert!(ByteRange::try_from("bytes=500-499").is_err());
+    assert!(ByteRange::try_from("bytes=0-1,5-6").is_err());
+    assert!(ByteRange::try_from("bytes=-").is_err());
+    assert!(ByteRange::try_from("bytes=+1-2").is_err());
+    assert!(ByteRange::try_from("items=0-1").is_err());
+
+    assert_eq!(ByteRange::FromTo(0, 499).to_string(), "bytes=0-499");
+    assert_eq!(ByteRange::From(9500).to_string(), "bytes=9500-");
+    assert_eq!(ByteRange::Last(500).to_string(), "bytes=-500");
+}
+
+#[test]
+fn parse_content_range() {
+    let range = ContentRange { first: 0, last: 499, complete_length: Some(1234) };
+    assert_eq!(ContentRange::try_from("bytes 0-499/1234"), Ok(range));
+    assert_eq!(range.to_string(), "bytes 0-499/1234");
+
+    let range = ContentRange { first: 500, last: 999, complete_length: None };
+    assert_eq!(ContentRange::try_from("bytes 500-999/*"), Ok(range));
+    assert_eq!(range.to_string(), "bytes 500-999/*");
+
+    assert!(ContentRange::try_from("bytes */1234").is_err());
+    assert!(ContentRange::try_from("bytes 0-1234/1234").is_err());
+    assert!(ContentRange::try_from("bytes 5-4/10").is_err());
+}
+
+#[test]
+fn byte_range_to_content_range() {
+    let content_range = |first, last| Some(ContentRange { first, last, complete_length: Some(10) });
+
+    assert_eq!(ByteRange::FromTo(2, 5).to_content_range(10), content_range(2, 5));
+    assert_eq!(ByteRange::FromTo(2, 50).to_content_range(10), content_range(2, 9));
+    assert_eq!(ByteRange::From(7).to_content_range(10), content_range(7, 9));
+    assert_eq!(ByteRange::Last(3).to_content_range(10), content_range(7, 9));
+    assert_eq!(ByteRange::Last(30).to_content_range(10), content_range(0, 9));
+
+    assert_eq!(ByteRange::From(10).to_content_range(10), None);
+    assert_eq!(ByteRange::Last(0).to_content_range(10), None);
+    assert_eq!(ByteRange::From(0).to_content_range(0), None);
+}
+
+#[test]
+fn ranged_download() {
+    let request =
+        get_content_range::Request { media_id: "abc".into(), range: Some(ByteRange::From(2)) };
+    let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+    assert_eq!(http_request.headers()[http::header::RANGE], "bytes=2-");
+
+    let request = get_content_range::Request::try_from(http_request).unwrap();
+    let content_range = request.range.unwrap().to_content_range(5).unwrap();
+
+    let response =
+        get_content_range::Response { file: b"llo".to_vec(), content_range: Some(content_range) };
+    let http_response = http::Response::<Vec<u8>>::try_from(response).unwrap();
+    assert_eq!(http_response.status(), http::StatusCode::PARTIAL_CONTENT);
+    assert_eq!(http_response.headers()[http::header::CONTENT_RANGE], "bytes 2-4/5");
+
+    let response = get_content_range::Response::try_from(http_response).unwrap();
+    assert_eq!(response.file, b"llo");
+    assert_eq!(response.content_range, Some(content_range));
+}
+
+#[test]
+fn unranged_download() {
+    let request = get_content_range::Request::new("abc".into());
+    let http_request = http::Request::<Vec<u8>>::try_from(request).unwrap();
+    assert!(http_request.headers().get(http::header::RANGE).is_none());
+
+    let http_request = http::Request::builder()
+        .uri("/_matrix/media/abc")
+        .header(http::header::RANGE, "bytes=0-1,5-6")
+        .body(Vec::new())
+        .unwrap();
+    let request = get_content_range::Request::try_from(http_request).unwrap();
+    assert!(request.range.is_none());
+
+    let response = get_content_range::Response { file: b"hello".to_vec(), content_range: None };
+    let http_response = http::Response::<Vec<u8>>::try_from(response).unwrap();
+    assert_eq!(http_response.status(), http::StatusCode::OK);
+}
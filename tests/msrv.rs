@@ -0,0 +1,13 @@
+/// Makes sure the code generated by `ruma_api!` compiles on its own, which CI also does with the
+/// minimum supported Rust version, and that the `nightly` path check rejects invalid paths.
+#[test]
+fn generated_code() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/msrv/*.rs");
+
+    if cfg!(feature = "nightly") {
+        t.compile_fail("tests/nightly/*.rs");
+    } else {
+        t.pass("tests/nightly/*.rs");
+    }
+}
@@ -0,0 +1,34 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: PUT,
+        name: "all_field_kinds",
+        path: "/_matrix/foo/:room_id/bar/:event_type",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+        #[ruma_api(path)]
+        pub event_type: String,
+        #[ruma_api(query)]
+        pub limit: Option<u32>,
+        #[ruma_api(header = CONTENT_TYPE)]
+        pub content_type: String,
+        pub body_field: Vec<String>,
+    }
+
+    response {
+        #[ruma_api(header = CONTENT_LANGUAGE)]
+        pub language: String,
+        #[ruma_api(header = CONTENT_DISPOSITION)]
+        pub disposition: Option<String>,
+        pub body_field: bool,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Has a query string in its path.",
+        method: GET,
+        name: "invalid_path",
+        path: "/_matrix/foo?bar=baz",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {}
+}
+
+fn main() {}
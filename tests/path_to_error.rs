@@ -0,0 +1,71 @@
+#![cfg(feature = "path-to-error")]
+
+use std::{collections::BTreeMap, convert::TryFrom};
+
+use ruma_api::{error::FromHttpResponseError, exports::http, ruma_api};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct JoinedRoom {
+    pub timeline: Timeline,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Timeline {
+    pub events: Vec<Event>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Event {
+    #[serde(rename = "type")]
+    pub kind: String,
+}
+
+ruma_api! {
+    metadata {
+        description: "Get the latest events.",
+        method: GET,
+        name: "sync",
+        path: "/_matrix/client/r0/sync",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {}
+
+    response {
+        pub next_batch: String,
+        pub join: BTreeMap<String, JoinedRoom>,
+    }
+}
+
+fn deserialization_error(body: &[u8]) -> ruma_api::error::ResponseDeserializationError {
+    match Response::try_from(http::Response::builder().body(body.to_vec()).unwrap()) {
+        Err(FromHttpResponseError::Deserialization(err)) => err,
+        _ => panic!("expected a deserialization error"),
+    }
+}
+
+#[test]
+fn error_has_json_path() {
+    let body = br#"{
+        "next_batch": "s1",
+        "join": {
+            "!x:example.org": {
+                "timeline": { "events": [{ "type": "m.room.message" }, { "type": 1 }] }
+            }
+        }
+    }"#;
+
+    let err = deserialization_error(body);
+    assert_eq!(err.json_path(), Some("join.!x:example.org.timeline.events[1].type"));
+    assert!(err
+        .to_string()
+        .starts_with("join.!x:example.org.timeline.events[1].type: invalid type"));
+}
+
+#[test]
+fn no_path_for_errors_of_the_body() {
+    assert_eq!(deserialization_error(b"[]").json_path(), None);
+    assert_eq!(deserialization_error(br#"{"next_batch":"s1","join":{}} x"#).json_path(), None);
+}
@@ -0,0 +1,44 @@
+use ruma_api::{deps::http, prelude::*};
+
+ruma_api! {
+    metadata {
+        description: "Get a name.",
+        method: GET,
+        name: "get_name",
+        path: "/_matrix/name/:user",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub user: String,
+    }
+
+    response {
+        pub name: String,
+    }
+}
+
+fn endpoint_name<E>() -> &'static str
+where
+    E: Endpoint,
+    <E as Outgoing>::Incoming: TryFromHttpRequest,
+    <E::Response as Outgoing>::Incoming: TryFromHttpResponse,
+{
+    E::METADATA.name()
+}
+
+#[test]
+fn prelude_is_enough_for_conversions() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    assert_eq!(endpoint_name::<Request>(), "get_name");
+
+    let http_request: http::Request<Vec<u8>> = Request { user: "alice".into() }.try_into()?;
+    assert_eq!(http_request.uri().path(), "/_matrix/name/alice");
+
+    let http_response = http::Response::new(br#"{"name":"Alice"}"#.to_vec());
+    let response: Result<Response, FromHttpResponseError> = Response::try_from(http_response);
+    assert_eq!(response?.name, "Alice");
+
+    Ok(())
+}
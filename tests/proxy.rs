@@ -0,0 +1,64 @@
+use std::convert::TryFrom;
+
+use ruma_api::{exports::http, proxy::HeaderForwarding, ruma_api};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "my_endpoint",
+        path: "/_matrix/foo",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {
+        pub value: String,
+    }
+}
+
+fn upstream_response() -> http::Response<Vec<u8>> {
+    http::Response::builder()
+        .header(http::header::CONTENT_TYPE, "application/json; charset=utf-8")
+        .header(http::header::CACHE_CONTROL, "max-age=60")
+        .header(http::header::VARY, "Accept")
+        .header(http::header::VARY, "Authorization")
+        .header(http::header::CONNECTION, "close, etag")
+        .header(http::header::ETAG, "\"abc\"")
+        .header(http::header::TRANSFER_ENCODING, "chunked")
+        .header(http::header::SET_COOKIE, "session=1")
+        .body(br#"{"value":"hi"}"#.to_vec())
+        .unwrap()
+}
+
+#[test]
+fn forwards_allowed_headers() {
+    let upstream = upstream_response();
+    let response = Response::try_from(upstream_response()).unwrap();
+    let http_response = HeaderForwarding::default().forward_response(&upstream, response).unwrap();
+    let headers = http_response.headers();
+
+    // The generated header is kept.
+    assert_eq!(headers[http::header::CONTENT_TYPE], "application/json");
+    assert_eq!(headers[http::header::CACHE_CONTROL], "max-age=60");
+    assert_eq!(headers.get_all(http::header::VARY).iter().count(), 2);
+
+    assert!(!headers.contains_key(http::header::CONNECTION));
+    assert!(!headers.contains_key(http::header::ETAG));
+    assert!(!headers.contains_key(http::header::TRANSFER_ENCODING));
+    assert!(!headers.contains_key(http::header::SET_COOKIE));
+}
+
+#[test]
+fn custom_allowlist() {
+    let mut forwarding = HeaderForwarding::default();
+    forwarding.allowlist = vec![http::header::SET_COOKIE, http::header::TRANSFER_ENCODING];
+
+    let mut headers = http::HeaderMap::new();
+    forwarding.forward_headers(upstream_response().headers(), &mut headers);
+
+    assert_eq!(headers.len(), 1);
+    assert_eq!(headers[http::header::SET_COOKIE], "session=1");
+}
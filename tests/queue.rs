@@ -0,0 +1,265 @@
+#![cfg(feature = "queue")]
+
+mod common;
+
+use std::{
+    collections::VecDeque,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use ruma_api::{
+    client::{HttpResponseFuture, Transport},
+    exports::http,
+    options::RequestOptions,
+    queue::{Backoff, QueueError, QueuedRequest, SendOutcome, SendQueue},
+    ruma_api,
+};
+
+use self::common::block_on;
+
+ruma_api! {
+    mod send_message;
+
+    metadata {
+        description: "Send a message to a room.",
+        method: PUT,
+        name: "send_message",
+        path: "/_matrix/client/r0/rooms/:room_id/send/m.room.message/:txn_id",
+        rate_limited: true,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+        #[ruma_api(path)]
+        pub txn_id: String,
+        pub body: String,
+    }
+
+    response {}
+}
+
+ruma_api! {
+    mod set_typing;
+
+    metadata {
+        description: "Tell the server that the user is typing.",
+        method: PUT,
+        name: "set_typing",
+        path: "/_matrix/client/r0/rooms/:room_id/typing",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+        pub typing: bool,
+    }
+
+    response {}
+}
+
+/// A transport that answers requests with the given responses in order, or fails to send them
+/// once there are none left.
+struct ScriptedTransport {
+    responses: Mutex<VecDeque<http::Response<Vec<u8>>>>,
+    sent: Mutex<Vec<http::Request<Vec<u8>>>>,
+}
+
+impl ScriptedTransport {
+    fn new(responses: Vec<(u16, &str)>) -> Self {
+        let responses = responses
+            .into_iter()
+            .map(|(status, body)| {
+                http::Response::builder().status(status).body(body.as_bytes().to_vec()).unwrap()
+            })
+            .collect();
+        Self { responses: Mutex::new(responses), sent: Mutex::new(Vec::new()) }
+    }
+}
+
+impl Transport for ScriptedTransport {
+    type Error = &'static str;
+
+    fn request_options(&self) -> RequestOptions {
+        let mut options = RequestOptions::default();
+        options.access_token = Some("secret".to_owned());
+        options
+    }
+
+    fn send_http(&self, request: http::Request<Vec<u8>>) -> HttpResponseFuture<'_, Self::Error> {
+        self.sent.lock().unwrap().push(request);
+        let response = self.responses.lock().unwrap().pop_front().ok_or("offline");
+        Box::pin(async move { response })
+    }
+}
+
+fn message(txn_id: &str) -> QueuedRequest {
+    QueuedRequest::new(send_message::Request {
+        room_id: "!room:example.org".to_owned(),
+        txn_id: txn_id.to_owned(),
+        body: "hello".to_owned(),
+    })
+    .unwrap()
+}
+
+fn typing() -> QueuedRequest {
+    QueuedRequest::new(set_typing::Request {
+        room_id: "!room:example.org".to_owned(),
+        typing: true,
+    })
+    .unwrap()
+}
+
+#[test]
+fn sent_request_is_removed() {
+    let transport = ScriptedTransport::new(vec![(200, "{}")]);
+    let mut queue = SendQueue::new();
+    queue.push(message("1"));
+    let now = Instant::now();
+
+    assert_eq!(queue.next_due_in(now), Some(Duration::from_secs(0)));
+    match block_on(queue.send_next(&transport, now)) {
+        Some(SendOutcome::Sent { request, response }) => {
+            assert_eq!(request.name(), "send_message");
+            assert_eq!(response.status(), 200);
+        }
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+
+    assert!(queue.is_empty());
+    assert_eq!(queue.next_due_in(now), None);
+    assert_eq!(
+        transport.sent.lock().unwrap()[0].headers()[http::header::AUTHORIZATION],
+        "Bearer secret"
+    );
+}
+
+#[test]
+fn transient_errors_are_retried_with_backoff() {
+    let transport = ScriptedTransport::new(vec![(503, ""), (200, "{}")]);
+    let mut queue = SendQueue::new();
+    queue.push(message("1"));
+    let now = Instant::now();
+
+    match block_on(queue.send_next(&transport, now)) {
+        Some(SendOutcome::Retrying { error: QueueError::Server(_), retry_in }) => {
+            assert_eq!(retry_in, Duration::from_secs(1));
+        }
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+    assert_eq!(queue.next_due_in(now), Some(Duration::from_secs(1)));
+    assert!(block_on(queue.send_next(&transport, now)).is_none());
+
+    match block_on(queue.send_next(&transport, now + Duration::from_secs(1))) {
+        Some(SendOutcome::Sent { .. }) => {}
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+}
+
+#[test]
+fn rate_limit_delays_rate_limited_endpoints() {
+    let rate_limited = r#"{"errcode":"M_LIMIT_EXCEEDED","retry_after_ms":5000}"#;
+    let transport = ScriptedTransport::new(vec![(429, rate_limited), (200, "{}")]);
+    let mut queue = SendQueue::new();
+    queue.extend(vec![message("1"), message("2"), typing()]);
+    let now = Instant::now();
+
+    match block_on(queue.send_next(&transport, now)) {
+        Some(SendOutcome::Retrying { retry_in, .. }) => {
+            assert_eq!(retry_in, Duration::from_secs(5));
+        }
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+
+    // The second message waits as well, the typing notification doesn't.
+    match block_on(queue.send_next(&transport, now)) {
+        Some(SendOutcome::Sent { request, .. }) => assert_eq!(request.name(), "set_typing"),
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+    assert!(block_on(queue.send_next(&transport, now)).is_none());
+    assert_eq!(queue.next_due_in(now), Some(Duration::from_secs(5)));
+}
+
+#[test]
+fn huge_retry_after_is_capped() {
+    let transport = ScriptedTransport::new(Vec::new());
+    transport.responses.lock().unwrap().push_back(
+        http::Response::builder()
+            .status(429)
+            .header(http::header::RETRY_AFTER, "18446744073709551615")
+            .body(Vec::new())
+            .unwrap(),
+    );
+    let mut queue = SendQueue::new();
+    queue.push(message("1"));
+    let now = Instant::now();
+
+    match block_on(queue.send_next(&transport, now)) {
+        Some(SendOutcome::Retrying { retry_in, .. }) => {
+            assert_eq!(retry_in, Backoff::default().max);
+        }
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+    assert_eq!(queue.next_due_in(now), Some(Backoff::default().max));
+}
+
+#[test]
+fn permanent_errors_fail() {
+    let transport = ScriptedTransport::new(vec![(400, r#"{"errcode":"M_BAD_JSON"}"#)]);
+    let mut queue = SendQueue::new();
+    queue.push(message("1"));
+
+    match block_on(queue.send_next(&transport, Instant::now())) {
+        Some(SendOutcome::Failed { error: QueueError::Server(error), .. }) => {
+            assert_eq!(error.status(), 400);
+        }
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn gives_up_after_max_attempts() {
+    let transport = ScriptedTransport::new(Vec::new());
+    let mut backoff = Backoff::default();
+    backoff.max_attempts = Some(2);
+    let mut queue = SendQueue::with_backoff(backoff);
+    queue.push(message("1"));
+    let now = Instant::now();
+
+    match block_on(queue.send_next(&transport, now)) {
+        Some(SendOutcome::Retrying { error: QueueError::Transport("offline"), .. }) => {}
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+    match block_on(queue.send_next(&transport, now + Duration::from_secs(1))) {
+        Some(SendOutcome::Failed { error: QueueError::Transport("offline"), .. }) => {}
+        outcome => panic!("unexpected outcome: {:?}", outcome),
+    }
+    assert!(queue.is_empty());
+}
+
+#[test]
+fn persisted_queue_round_trip() {
+    let mut queue = SendQueue::new();
+    queue.extend(vec![message("1"), typing()]);
+
+    let json = serde_json::to_string(&queue.requests().collect::<Vec<_>>()).unwrap();
+    assert!(!json.contains("secret"));
+
+    let requests: Vec<QueuedRequest> = serde_json::from_str(&json).unwrap();
+    let restored: SendQueue = requests.into_iter().collect();
+    assert_eq!(restored.len(), 2);
+
+    let http_request = restored.requests().next().unwrap().to_http_request();
+    assert_eq!(http_request.method(), http::Method::PUT);
+    assert_eq!(
+        http_request.uri().path(),
+        "/_matrix/client/r0/rooms/!room:example.org/send/m.room.message/1"
+    );
+    assert_eq!(http_request.headers()[http::header::CONTENT_TYPE], "application/json");
+    assert_eq!(http_request.body(), br#"{"body":"hello"}"#);
+}
@@ -0,0 +1,131 @@
+use ruma_api::{
+    exports::http::{
+        self,
+        header::{
+            CONTENT_SECURITY_POLICY, CONTENT_TYPE, SET_COOKIE, X_CONTENT_TYPE_OPTIONS,
+            X_FRAME_OPTIONS,
+        },
+    },
+    options::ResponseOptions,
+    security::{SameSite, SecurityHeaders, MEDIA_CONTENT_SECURITY_POLICY},
+    Endpoint as _,
+};
+
+mod get_content {
+    use ruma_api::ruma_api;
+
+    ruma_api! {
+        metadata {
+            description: "Download some content.",
+            method: GET,
+            name: "get_content",
+            path: "/_matrix/media/r0/download/:server_name/:media_id",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            #[ruma_api(path)]
+            pub server_name: String,
+            #[ruma_api(path)]
+            pub media_id: String,
+        }
+
+        response {
+            #[ruma_api(raw_body)]
+            pub file: Vec<u8>,
+            #[ruma_api(header = CONTENT_TYPE)]
+            pub content_type: Option<String>,
+        }
+    }
+}
+
+mod login {
+    use ruma_api::ruma_api;
+
+    ruma_api! {
+        metadata {
+            description: "Log in.",
+            method: POST,
+            name: "login",
+            path: "/_matrix/client/r0/login",
+            rate_limited: false,
+            requires_authentication: false,
+        }
+
+        request {
+            pub user: String,
+        }
+
+        response {
+            #[ruma_api(cookie = "session")]
+            pub session: String,
+            pub user_id: String,
+        }
+    }
+}
+
+fn media_response() -> get_content::Response {
+    get_content::Response {
+        file: b"<html></html>".to_vec(),
+        content_type: Some("text/html".to_owned()),
+    }
+}
+
+fn login_response() -> login::Response {
+    login::Response { session: "abc".to_owned(), user_id: "@alice:example.org".to_owned() }
+}
+
+#[test]
+fn default_options_add_nothing() {
+    let http_response =
+        get_content::Request::try_into_http_response_with(media_response(), &Default::default())
+            .unwrap();
+    assert!(!http_response.headers().contains_key(X_CONTENT_TYPE_OPTIONS));
+    assert!(!http_response.headers().contains_key(CONTENT_SECURITY_POLICY));
+}
+
+#[test]
+fn media_endpoints_get_the_media_headers() {
+    let options = ResponseOptions::recommended();
+    let http_response =
+        get_content::Request::try_into_http_response_with(media_response(), &options).unwrap();
+
+    let headers = http_response.headers();
+    assert_eq!(headers[X_CONTENT_TYPE_OPTIONS], "nosniff");
+    assert_eq!(headers[CONTENT_SECURITY_POLICY], MEDIA_CONTENT_SECURITY_POLICY);
+    assert_eq!(headers[CONTENT_TYPE], "text/html");
+}
+
+#[test]
+fn other_endpoints_get_the_general_headers() {
+    let options = ResponseOptions::recommended();
+    let http_response =
+        login::Request::try_into_http_response_with(login_response(), &options).unwrap();
+
+    let headers = http_response.headers();
+    assert_eq!(headers[X_CONTENT_TYPE_OPTIONS], "nosniff");
+    assert_eq!(headers[X_FRAME_OPTIONS], "DENY");
+    assert!(!headers.contains_key(CONTENT_SECURITY_POLICY));
+    assert_eq!(headers[SET_COOKIE], "session=abc; SameSite=Lax");
+}
+
+#[test]
+fn existing_headers_are_kept() {
+    let mut security_headers = SecurityHeaders::recommended();
+    security_headers.content_security_policy = Some("default-src 'none'".to_owned());
+    security_headers.same_site = Some(SameSite::Strict);
+
+    let mut http_response = http::Response::builder()
+        .header(CONTENT_SECURITY_POLICY, "default-src 'self'")
+        .header(SET_COOKIE, "a=1; SameSite=None; Secure")
+        .header(SET_COOKIE, "b=2")
+        .body(Vec::<u8>::new())
+        .unwrap();
+    security_headers.apply(&mut http_response);
+
+    let headers = http_response.headers();
+    assert_eq!(headers[CONTENT_SECURITY_POLICY], "default-src 'self'");
+    let cookies: Vec<_> = headers.get_all(SET_COOKIE).iter().collect();
+    assert_eq!(cookies, ["a=1; SameSite=None; Secure", "b=2; SameSite=Strict"]);
+}
@@ -0,0 +1,153 @@
+#![cfg(feature = "spec-check")]
+
+use ruma_api::{
+    examples::FieldLocation,
+    exports::http,
+    ruma_api,
+    spec_check::{Drift, EndpointFields, Part, Spec},
+    Endpoint,
+};
+use serde_json::json;
+
+ruma_api! {
+    mod get_messages;
+
+    metadata {
+        description: "Get the messages of a room.",
+        method: GET,
+        name: "get_messages",
+        path: "/_matrix/client/r0/rooms/:room_id/messages",
+        rate_limited: false,
+        requires_authentication: true,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub room_id: String,
+        #[ruma_api(query)]
+        pub from: String,
+        #[ruma_api(query)]
+        pub limit: Option<u32>,
+        #[ruma_api(query)]
+        pub filter: Option<String>,
+    }
+
+    response {
+        pub start: String,
+        #[serde(rename = "end")]
+        pub end_token: String,
+        pub chunk: Vec<String>,
+    }
+}
+
+ruma_api! {
+    mod get_unknown;
+
+    metadata {
+        description: "An endpoint that isn't in the spec.",
+        method: GET,
+        name: "get_unknown",
+        path: "/_matrix/client/r0/unknown",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {}
+}
+
+fn spec() -> Spec {
+    Spec::from_openapi(&json!({
+        "swagger": "2.0",
+        "basePath": "/_matrix/client/r0",
+        "paths": {
+            "/rooms/{roomId}/messages": {
+                "get": {
+                    "parameters": [
+                        { "in": "path", "name": "roomId", "required": true, "type": "string" },
+                        { "in": "query", "name": "from", "required": true, "type": "string" },
+                        { "in": "query", "name": "dir", "required": true, "type": "string" },
+                        { "in": "query", "name": "limit", "type": "integer" },
+                        { "in": "query", "name": "filter", "required": true, "type": "string" }
+                    ],
+                    "responses": {
+                        "200": {
+                            "schema": {
+                                "type": "object",
+                                "properties": {
+                                    "start": { "type": "string" },
+                                    "end": { "type": "string" },
+                                    "chunk": { "type": "array" },
+                                    "state": { "type": "array" }
+                                },
+                                "required": ["start", "end", "chunk"]
+                            }
+                        }
+                    }
+                }
+            },
+            "/rooms/{roomId}/state": {
+                "get": { "responses": { "200": { "schema": { "type": "array" } } } }
+            }
+        }
+    }))
+    .unwrap()
+}
+
+#[test]
+fn field_infos() {
+    let request_fields = get_messages::Request::request_fields();
+    assert_eq!(request_fields.len(), 4);
+    assert_eq!(request_fields[0].location, FieldLocation::Path);
+    assert_eq!(request_fields[2].name.as_deref(), Some("limit"));
+    assert!(!request_fields[2].required);
+
+    let end = &get_messages::Request::response_fields()[1];
+    assert_eq!(end.field, "end_token");
+    assert_eq!(end.name.as_deref(), Some("end"));
+    assert_eq!(end.location, FieldLocation::Body);
+    assert!(end.required);
+}
+
+#[test]
+fn reports_drift() {
+    let drifts = spec().check::<get_messages::Request>();
+    assert_eq!(
+        drifts,
+        vec![
+            Drift::MissingFromEndpoint { part: Part::Query, name: "dir".into() },
+            Drift::RequiredMismatch {
+                part: Part::Query,
+                name: "filter".into(),
+                spec_required: true
+            },
+            Drift::MissingFromEndpoint { part: Part::ResponseBody, name: "state".into() },
+        ]
+    );
+    assert_eq!(
+        drifts[1].to_string(),
+        "the query parameter `filter` is required by the spec but optional"
+    );
+}
+
+#[test]
+fn missing_and_unimplemented_operations() {
+    let spec = spec();
+    assert_eq!(
+        spec.check::<get_unknown::Request>(),
+        vec![Drift::MissingOperation {
+            method: http::Method::GET,
+            path: "/_matrix/client/r0/unknown"
+        }]
+    );
+    assert_eq!(
+        spec.unimplemented(&[get_messages::Request::METADATA, get_unknown::Request::METADATA]),
+        vec![(http::Method::GET, "/_matrix/client/r0/rooms/{roomId}/state".to_owned())]
+    );
+}
+
+#[test]
+fn invalid_definition() {
+    assert!(Spec::from_openapi(&json!({ "swagger": "2.0" })).is_err());
+}
@@ -0,0 +1,95 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use ruma_api::{
+    exports::http,
+    ruma_api,
+    time::{self, MilliSecondsSinceUnixEpoch},
+};
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "my_endpoint",
+        path: "/_matrix/foo/:since",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(path)]
+        pub since: MilliSecondsSinceUnixEpoch,
+        #[ruma_api(query)]
+        #[serde(with = "time::duration::ms")]
+        pub timeout: Duration,
+        #[serde(
+            with = "time::opt_ms_since_unix_epoch",
+            default,
+            skip_serializing_if = "Option::is_none"
+        )]
+        pub origin_server_ts: Option<std::time::SystemTime>,
+    }
+
+    response {
+        #[serde(with = "time::duration::opt_ms", default, skip_serializing_if = "Option::is_none")]
+        pub retry_after: Option<Duration>,
+    }
+}
+
+#[test]
+fn request_ms_roundtrip() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use std::convert::TryFrom;
+
+    let req = Request {
+        since: MilliSecondsSinceUnixEpoch(1_500),
+        timeout: Duration::from_millis(30_000),
+        origin_server_ts: Some(UNIX_EPOCH + Duration::from_millis(42)),
+    };
+
+    let http_req = http::Request::<Vec<u8>>::try_from(req.clone())?;
+    assert_eq!(http_req.uri().path(), "/_matrix/foo/1500");
+    assert_eq!(http_req.uri().query(), Some("timeout=30000"));
+    assert_eq!(http_req.body(), br#"{"origin_server_ts":42}"#);
+
+    let req2 = Request::try_from(http_req)?;
+    assert_eq!(req.since, req2.since);
+    assert_eq!(req.timeout, req2.timeout);
+    assert_eq!(req.origin_server_ts, req2.origin_server_ts);
+
+    Ok(())
+}
+
+#[test]
+fn response_optional_duration() -> Result<(), Box<dyn std::error::Error + 'static>> {
+    use std::convert::TryFrom;
+
+    let http_res = http::Response::<Vec<u8>>::try_from(Response {
+        retry_after: Some(Duration::from_secs(2)),
+    })?;
+    assert_eq!(http_res.body(), br#"{"retry_after":2000}"#);
+
+    let empty = http::Response::builder().body(b"{}".to_vec()).unwrap();
+    assert_eq!(Response::try_from(empty)?.retry_after, None);
+
+    Ok(())
+}
+
+#[test]
+fn request_invalid_timestamp_path() {
+    use std::convert::TryFrom;
+
+    use ruma_api::error::{FromHttpRequestError, InvalidInteger};
+
+    let http_req = http::Request::builder()
+        .method("POST")
+        .uri("https://example.org/_matrix/foo/15x0?timeout=0")
+        .body(b"{}".to_vec())
+        .unwrap();
+
+    match Request::try_from(http_req) {
+        Err(FromHttpRequestError::Deserialization(err)) => {
+            assert_eq!(err.invalid_integer(), Some(InvalidInteger::InvalidCharacter('x')));
+        }
+        _ => panic!("expected a deserialization error"),
+    }
+}
@@ -0,0 +1,6 @@
+/// Pins the error messages of `ruma_api!` for invalid input.
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,17 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "missing_metadata_key",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {}
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "raw_body_wrong_type",
+        path: "/_matrix/foo",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(raw_body)]
+        pub file: String,
+    }
+
+    response {}
+}
+
+fn main() {}
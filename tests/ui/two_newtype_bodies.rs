@@ -0,0 +1,23 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: POST,
+        name: "two_newtype_bodies",
+        path: "/_matrix/foo",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(body)]
+        pub first: Vec<String>,
+        #[ruma_api(body)]
+        pub second: Vec<String>,
+    }
+
+    response {}
+}
+
+fn main() {}
@@ -0,0 +1,21 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "unknown_attribute",
+        path: "/_matrix/foo",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {
+        #[ruma_api(querry)]
+        pub limit: u32,
+    }
+
+    response {}
+}
+
+fn main() {}
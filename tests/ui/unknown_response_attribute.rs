@@ -0,0 +1,21 @@
+use ruma_api::ruma_api;
+
+ruma_api! {
+    metadata {
+        description: "Does something.",
+        method: GET,
+        name: "unknown_response_attribute",
+        path: "/_matrix/foo",
+        rate_limited: false,
+        requires_authentication: false,
+    }
+
+    request {}
+
+    response {
+        #[ruma_api(header_name = CONTENT_TYPE)]
+        pub content_type: String,
+    }
+}
+
+fn main() {}
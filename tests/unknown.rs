@@ -0,0 +1,51 @@
+use ruma_api::{
+    error::{ErrorKind, ServerError},
+    exports::http::{self, header, Method, StatusCode},
+    unknown::UnknownEndpoint,
+};
+
+fn unknown_endpoint() -> UnknownEndpoint {
+    http::Request::builder()
+        .method(Method::POST)
+        .uri("http://localhost/_matrix/client/unstable/org.example/thing?a=1&b=x%20y")
+        .header(header::AUTHORIZATION, "Bearer secret")
+        .body(b"{}".to_vec())
+        .unwrap()
+        .into()
+}
+
+#[test]
+fn captures_request() {
+    let unknown = unknown_endpoint();
+
+    assert_eq!(unknown.method(), Method::POST);
+    assert_eq!(unknown.path(), "/_matrix/client/unstable/org.example/thing");
+    assert_eq!(unknown.query(), Some("a=1&b=x%20y"));
+    assert_eq!(
+        unknown.query_pairs(),
+        vec![("a".to_owned(), "1".to_owned()), ("b".to_owned(), "x y".to_owned())]
+    );
+    assert_eq!(unknown.headers()[header::AUTHORIZATION], "Bearer secret");
+    assert_eq!(unknown.body(), b"{}");
+
+    let http_request = unknown.into_http_request();
+    assert_eq!(http_request.uri().query(), Some("a=1&b=x%20y"));
+}
+
+#[test]
+fn unrecognized_response() {
+    let response = unknown_endpoint().unrecognized_response();
+
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+    assert_eq!(ServerError::new(response).kind(), ErrorKind::Unrecognized);
+}
+
+#[test]
+fn method_not_allowed_response() {
+    let response = unknown_endpoint().method_not_allowed_response(&[Method::GET, Method::PUT]);
+
+    assert_eq!(response.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(response.headers()[header::ALLOW], "GET, PUT");
+    assert_eq!(ServerError::new(response).kind(), ErrorKind::Unrecognized);
+}
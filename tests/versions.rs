@@ -0,0 +1,51 @@
+use std::convert::TryFrom;
+
+use ruma_api::{
+    exports::http,
+    versions::{get_supported_versions, MatrixVersion, SupportedVersions},
+};
+
+#[test]
+fn matrix_version_strings() {
+    for version in MatrixVersion::ALL {
+        assert_eq!(MatrixVersion::try_from(version.as_str()), Ok(*version));
+    }
+    assert!(MatrixVersion::try_from("v1.1").is_err());
+    assert_eq!(MatrixVersion::R0_6_0.to_string(), "r0.6.0");
+}
+
+#[test]
+fn supported_versions_from_response() {
+    let http_response = http::Response::builder()
+        .status(200)
+        .body(
+            br#"{
+                "versions": ["r0.5.0", "r0.4.0", "r9.9.9"],
+                "unstable_features": { "m.lazy_load_members": true, "org.example.off": false }
+            }"#
+            .to_vec(),
+        )
+        .unwrap();
+
+    let response = get_supported_versions::Response::try_from(http_response).unwrap();
+    let supported = SupportedVersions::from(response);
+
+    assert!(supported.supports(MatrixVersion::R0_4_0));
+    assert!(!supported.supports(MatrixVersion::R0_6_0));
+    assert_eq!(supported.latest(), Some(MatrixVersion::R0_5_0));
+    assert_eq!(supported.unknown_versions, vec!["r9.9.9".to_owned()]);
+    assert!(supported.supports_unstable_feature("m.lazy_load_members"));
+    assert!(!supported.supports_unstable_feature("org.example.off"));
+    assert!(!supported.supports_unstable_feature("org.example.missing"));
+}
+
+#[test]
+fn unstable_features_are_optional() {
+    let http_response =
+        http::Response::builder().status(200).body(br#"{"versions":["r0.6.0"]}"#.to_vec()).unwrap();
+
+    let supported =
+        SupportedVersions::from(get_supported_versions::Response::try_from(http_response).unwrap());
+    assert_eq!(supported.latest(), Some(MatrixVersion::R0_6_0));
+    assert!(supported.unstable_features.is_empty());
+}
@@ -0,0 +1,86 @@
+mod common;
+
+use std::{future::Future, pin::Pin};
+
+use ruma_api::{
+    client::Transport,
+    exports::http,
+    well_known::{discover_homeserver, Discovery},
+};
+
+use self::common::block_on;
+
+/// A transport that answers requests for the two `.well-known` files with fixed responses.
+struct MockTransport {
+    client: (u16, &'static str),
+    server: (u16, &'static str),
+}
+
+impl Transport for MockTransport {
+    type Error = std::convert::Infallible;
+
+    fn send_http(
+        &self,
+        request: http::Request<Vec<u8>>,
+    ) -> Pin<Box<dyn Future<Output = Result<http::Response<Vec<u8>>, Self::Error>> + Send + '_>>
+    {
+        assert_eq!(request.uri().authority().unwrap(), "example.org");
+        assert_eq!(request.headers()[http::header::HOST], "example.org");
+
+        let (status, body) = match request.uri().path() {
+            "/.well-known/matrix/client" => self.client,
+            "/.well-known/matrix/server" => self.server,
+            path => panic!("unexpected path: {}", path),
+        };
+
+        let response =
+            http::Response::builder().status(status).body(body.as_bytes().to_vec()).unwrap();
+        Box::pin(async move { Ok(response) })
+    }
+}
+
+#[test]
+fn discovery_uses_well_known_files() {
+    let transport = MockTransport {
+        client: (
+            200,
+            r#"{
+                "m.homeserver": { "base_url": "https://matrix.example.org/" },
+                "m.identity_server": { "base_url": "https://identity.example.org" }
+            }"#,
+        ),
+        server: (200, r#"{ "m.server": "matrix.example.org:443" }"#),
+    };
+
+    let discovery = block_on(discover_homeserver(&transport, &"example.org".parse().unwrap()));
+    assert_eq!(
+        discovery.unwrap(),
+        Discovery {
+            homeserver_url: "https://matrix.example.org".into(),
+            identity_server_url: Some("https://identity.example.org".into()),
+            federation_server: "matrix.example.org:443".into(),
+        }
+    );
+}
+
+#[test]
+fn discovery_falls_back_to_server_name() {
+    let transport = MockTransport { client: (404, "{}"), server: (200, "not json") };
+
+    let discovery = block_on(discover_homeserver(&transport, &"example.org".parse().unwrap()));
+    assert_eq!(
+        discovery.unwrap(),
+        Discovery {
+            homeserver_url: "https://example.org".into(),
+            identity_server_url: None,
+            federation_server: "example.org:8448".into(),
+        }
+    );
+}
+
+#[test]
+fn discovery_reports_client_errors() {
+    let transport = MockTransport { client: (500, "{}"), server: (404, "{}") };
+
+    assert!(block_on(discover_homeserver(&transport, &"example.org".parse().unwrap())).is_err());
+}